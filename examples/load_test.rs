@@ -0,0 +1,135 @@
+//! Saturates the login endpoint and samples `/api/health` meanwhile. Logins
+//! hash on the blocking pool, so health latency should stay flat no matter
+//! how many are in flight.
+//!
+//! ```sh
+//! LOGIN_EMAIL=<email> LOGIN_PASSWORD=<password> cargo run --release --example load_test
+//! ```
+//!
+//! Use a registered user. `CONCURRENCY` logins are kept in flight for
+//! `DURATION_SECONDS`, after a baseline of the same length without load.
+//! Exits with an error when the p99 under load exceeds `HEALTH_P99_BUDGET_MS`.
+
+use actix_web::rt::time;
+use serde_json::json;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+// Time between two health probes
+const PROBE_INTERVAL: Duration = Duration::from_millis(20);
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+
+    let rank = ((sorted.len() - 1) as f64 * p).round() as usize;
+    sorted[rank]
+}
+
+fn report(label: &str, mut samples: Vec<Duration>) -> Duration {
+    samples.sort();
+    let p99 = percentile(&samples, 0.99);
+    println!(
+        "{:<10} health n={:<5} p50={:>7.2?} p95={:>7.2?} p99={:>7.2?} max={:>7.2?}",
+        label,
+        samples.len(),
+        percentile(&samples, 0.50),
+        percentile(&samples, 0.95),
+        p99,
+        samples.last().copied().unwrap_or_default()
+    );
+    p99
+}
+
+// Probes the health endpoint until `deadline`, returning every latency
+async fn probe_health(client: &reqwest::Client, url: &str, deadline: Instant) -> Vec<Duration> {
+    let mut samples = Vec::new();
+    while Instant::now() < deadline {
+        let started = Instant::now();
+        match client.get(url).send().await {
+            Ok(response) if response.status().is_success() => samples.push(started.elapsed()),
+            Ok(response) => eprintln!("health answered {}", response.status()),
+            Err(e) => eprintln!("health failed: {}", e),
+        }
+        time::sleep(PROBE_INTERVAL).await;
+    }
+
+    samples
+}
+
+#[actix_web::main]
+async fn main() {
+    let base_url = std::env::var("BASE_URL").unwrap_or_else(|_| "http://127.0.0.1:8080".into());
+    let email = std::env::var("LOGIN_EMAIL").expect("LOGIN_EMAIL must be set");
+    let password = std::env::var("LOGIN_PASSWORD").expect("LOGIN_PASSWORD must be set");
+    let concurrency: usize = env_or("CONCURRENCY", 32);
+    let duration = Duration::from_secs(env_or("DURATION_SECONDS", 15));
+    let budget = Duration::from_millis(env_or("HEALTH_P99_BUDGET_MS", 50));
+
+    let client = reqwest::Client::new();
+    let health_url = format!("{}/api/health", base_url);
+    let login_url = format!("{}/api/auth/login", base_url);
+
+    // Baseline without load
+    let baseline = probe_health(&client, &health_url, Instant::now() + duration).await;
+
+    // Keep `concurrency` logins in flight while probing
+    let deadline = Instant::now() + duration;
+    let logins = Rc::new(RefCell::new((0usize, 0usize)));
+    let workers: Vec<_> = (0..concurrency)
+        .map(|_| {
+            let client = client.clone();
+            let url = login_url.clone();
+            let body = json!({ "email": email, "password": password }).to_string();
+            let logins = logins.clone();
+            actix_web::rt::spawn(async move {
+                while Instant::now() < deadline {
+                    let ok = matches!(
+                        client
+                            .post(&url)
+                            .header("Content-Type", "application/json")
+                            .body(body.clone())
+                            .send()
+                            .await,
+                        Ok(response) if response.status().is_success()
+                    );
+                    let mut logins = logins.borrow_mut();
+                    if ok {
+                        logins.0 += 1;
+                    } else {
+                        logins.1 += 1;
+                    }
+                }
+            })
+        })
+        .collect();
+    let loaded = probe_health(&client, &health_url, deadline).await;
+    for worker in workers {
+        let _ = worker.await;
+    }
+
+    let (succeeded, failed) = *logins.borrow();
+    println!(
+        "logins     {} ok, {} failed, {:.1}/s with {} in flight",
+        succeeded,
+        failed,
+        succeeded as f64 / duration.as_secs_f64(),
+        concurrency
+    );
+    report("baseline", baseline);
+    let p99 = report("loaded", loaded);
+
+    if p99 > budget {
+        eprintln!("health p99 {:?} is over the {:?} budget", p99, budget);
+        std::process::exit(1);
+    }
+}
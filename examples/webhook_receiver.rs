@@ -0,0 +1,103 @@
+//! Local stand-in for an organization webhook receiver. Verifies the
+//! signature and timestamp of every delivery and prints it.
+//!
+//! ```sh
+//! WEBHOOK_SECRET=<secret> cargo run --example webhook_receiver
+//! ```
+//!
+//! Then create a webhook pointing at `http://127.0.0.1:9000/`. Set
+//! `RECEIVER_STATUS` to answer with another status, e.g. `500` to watch
+//! the retries.
+
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Deliveries older than this are rejected as replays
+const TOLERANCE_SECONDS: i64 = 5 * 60;
+
+struct Receiver {
+    secret: String,
+    status: u16,
+}
+
+fn header<'a>(req: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    req.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+}
+
+fn verify(secret: &[u8], timestamp: &str, signature: &str, body: &[u8]) -> Result<(), String> {
+    let sent_at: i64 = timestamp.parse().map_err(|_| "Invalid timestamp")?;
+    let age = chrono::Utc::now().timestamp() - sent_at;
+    if age.abs() > TOLERANCE_SECONDS {
+        return Err(format!("Timestamp is {} seconds off", age));
+    }
+
+    let signature = signature
+        .strip_prefix("sha256=")
+        .and_then(|signature| hex::decode(signature).ok())
+        .ok_or("Malformed signature")?;
+
+    let mut mac = HmacSha256::new_from_slice(secret).map_err(|e| e.to_string())?;
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    if !bool::from(mac.finalize().into_bytes().ct_eq(&signature)) {
+        return Err("Signature mismatch".into());
+    }
+
+    Ok(())
+}
+
+async fn receive(
+    req: HttpRequest,
+    body: web::Bytes,
+    receiver: web::Data<Receiver>,
+) -> HttpResponse {
+    let event = header(&req, "X-Scylla-Event").unwrap_or("-");
+    let delivery = header(&req, "X-Scylla-Delivery").unwrap_or("-");
+    let timestamp = header(&req, "X-Scylla-Timestamp").unwrap_or_default();
+    let signature = header(&req, "X-Scylla-Signature").unwrap_or_default();
+
+    if let Err(e) = verify(receiver.secret.as_bytes(), timestamp, signature, &body) {
+        println!("rejected {} {}: {}", event, delivery, e);
+        return HttpResponse::Unauthorized().body(e);
+    }
+
+    println!(
+        "received {} {}\n{}\n",
+        event,
+        delivery,
+        String::from_utf8_lossy(&body)
+    );
+
+    let status = actix_web::http::StatusCode::from_u16(receiver.status)
+        .unwrap_or(actix_web::http::StatusCode::OK);
+    HttpResponse::build(status).body("ok")
+}
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let secret = std::env::var("WEBHOOK_SECRET").expect("WEBHOOK_SECRET must be set");
+    let status = std::env::var("RECEIVER_STATUS")
+        .unwrap_or_else(|_| "200".to_string())
+        .parse()
+        .unwrap_or(200);
+    let bind = std::env::var("RECEIVER_BIND").unwrap_or_else(|_| "127.0.0.1:9000".to_string());
+
+    let receiver = web::Data::new(Receiver { secret, status });
+
+    println!("Listening on http://{}", bind);
+    HttpServer::new(move || {
+        App::new()
+            .app_data(receiver.clone())
+            .default_service(web::post().to(receive))
+    })
+    .bind(bind)?
+    .run()
+    .await
+}
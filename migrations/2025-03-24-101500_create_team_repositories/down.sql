@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_team_repositories_repository_id;
+DROP TABLE team_repositories;
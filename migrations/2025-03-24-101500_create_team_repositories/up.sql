@@ -0,0 +1,9 @@
+CREATE TABLE team_repositories (
+    team_id UUID REFERENCES teams(id) ON DELETE CASCADE,
+    repository_id UUID REFERENCES repositories(id) ON DELETE CASCADE,
+    permission VARCHAR(50) NOT NULL DEFAULT 'read'
+        CHECK (permission IN ('read', 'triage', 'write', 'maintain', 'admin')),
+    PRIMARY KEY (team_id, repository_id)
+);
+
+CREATE INDEX idx_team_repositories_repository_id ON team_repositories(repository_id);
@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS idx_organizations_deletion_batch_id;
+DROP INDEX IF EXISTS idx_teams_deletion_batch_id;
+DROP INDEX IF EXISTS idx_repositories_deletion_batch_id;
+
+ALTER TABLE organizations DROP COLUMN deletion_batch_id;
+ALTER TABLE teams DROP COLUMN deletion_batch_id;
+ALTER TABLE repositories DROP COLUMN deletion_batch_id;
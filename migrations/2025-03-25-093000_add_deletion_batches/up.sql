@@ -0,0 +1,26 @@
+ALTER TABLE organizations ADD COLUMN deletion_batch_id UUID;
+ALTER TABLE teams ADD COLUMN deletion_batch_id UUID;
+ALTER TABLE repositories ADD COLUMN deletion_batch_id UUID;
+
+CREATE INDEX idx_organizations_deletion_batch_id ON organizations(deletion_batch_id);
+CREATE INDEX idx_teams_deletion_batch_id ON teams(deletion_batch_id);
+CREATE INDEX idx_repositories_deletion_batch_id ON repositories(deletion_batch_id);
+
+-- Cascade organizations that were deleted before batches existed
+UPDATE organizations
+SET deletion_batch_id = uuid_generate_v4()
+WHERE deleted_at IS NOT NULL;
+
+UPDATE teams t
+SET deleted_at = o.deleted_at, deletion_batch_id = o.deletion_batch_id
+FROM organizations o
+WHERE t.organization_id = o.id
+  AND o.deleted_at IS NOT NULL
+  AND t.deleted_at IS NULL;
+
+UPDATE repositories r
+SET deleted_at = o.deleted_at, deletion_batch_id = o.deletion_batch_id
+FROM organizations o
+WHERE r.organization_id = o.id
+  AND o.deleted_at IS NOT NULL
+  AND r.deleted_at IS NULL;
@@ -0,0 +1,9 @@
+DROP TABLE slug_redirects;
+
+DROP INDEX IF EXISTS idx_organizations_slug;
+DROP INDEX IF EXISTS idx_teams_organization_id_slug;
+DROP INDEX IF EXISTS idx_repositories_organization_id_slug;
+
+ALTER TABLE organizations DROP COLUMN slug;
+ALTER TABLE teams DROP COLUMN slug;
+ALTER TABLE repositories DROP COLUMN slug;
@@ -0,0 +1,72 @@
+ALTER TABLE organizations ADD COLUMN slug VARCHAR(100);
+
+UPDATE organizations
+SET slug = COALESCE(
+    NULLIF(trim(BOTH '-' FROM left(regexp_replace(lower(name), '[^a-z0-9]+', '-', 'g'), 90)), ''),
+    'organization'
+);
+
+WITH ranked AS (
+    SELECT id, row_number() OVER (PARTITION BY slug ORDER BY created_at, id) AS n
+    FROM organizations
+)
+UPDATE organizations t
+SET slug = t.slug || '-' || ranked.n
+FROM ranked
+WHERE t.id = ranked.id AND ranked.n > 1;
+
+ALTER TABLE organizations ALTER COLUMN slug SET NOT NULL;
+
+ALTER TABLE teams ADD COLUMN slug VARCHAR(100);
+
+UPDATE teams
+SET slug = COALESCE(
+    NULLIF(trim(BOTH '-' FROM left(regexp_replace(lower(name), '[^a-z0-9]+', '-', 'g'), 90)), ''),
+    'team'
+);
+
+WITH ranked AS (
+    SELECT id, row_number() OVER (PARTITION BY organization_id, slug ORDER BY created_at, id) AS n
+    FROM teams
+)
+UPDATE teams t
+SET slug = t.slug || '-' || ranked.n
+FROM ranked
+WHERE t.id = ranked.id AND ranked.n > 1;
+
+ALTER TABLE teams ALTER COLUMN slug SET NOT NULL;
+
+ALTER TABLE repositories ADD COLUMN slug VARCHAR(100);
+
+UPDATE repositories
+SET slug = COALESCE(
+    NULLIF(trim(BOTH '-' FROM left(regexp_replace(lower(name), '[^a-z0-9]+', '-', 'g'), 90)), ''),
+    'repository'
+);
+
+WITH ranked AS (
+    SELECT id, row_number() OVER (PARTITION BY organization_id, slug ORDER BY created_at, id) AS n
+    FROM repositories
+)
+UPDATE repositories t
+SET slug = t.slug || '-' || ranked.n
+FROM ranked
+WHERE t.id = ranked.id AND ranked.n > 1;
+
+ALTER TABLE repositories ALTER COLUMN slug SET NOT NULL;
+
+CREATE UNIQUE INDEX idx_organizations_slug ON organizations(slug) WHERE deleted_at IS NULL;
+CREATE UNIQUE INDEX idx_teams_organization_id_slug ON teams(organization_id, slug) WHERE deleted_at IS NULL;
+CREATE UNIQUE INDEX idx_repositories_organization_id_slug ON repositories(organization_id, slug) WHERE deleted_at IS NULL;
+
+CREATE TABLE slug_redirects (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    resource_type VARCHAR(50) NOT NULL,
+    scope_id UUID,
+    old_slug VARCHAR(100) NOT NULL,
+    resource_id UUID NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_slug_redirects_lookup ON slug_redirects(resource_type, old_slug, scope_id);
+CREATE INDEX idx_slug_redirects_resource_id ON slug_redirects(resource_id);
@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS idx_repositories_organization_id_remote_key;
+
+ALTER TABLE repositories
+DROP COLUMN protocol,
+DROP COLUMN owner_path,
+DROP COLUMN remote_key;
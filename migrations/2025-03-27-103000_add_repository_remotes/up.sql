@@ -0,0 +1,8 @@
+ALTER TABLE repositories
+ADD COLUMN protocol VARCHAR(10),
+ADD COLUMN owner_path VARCHAR(255),
+ADD COLUMN remote_key VARCHAR(255);
+
+CREATE UNIQUE INDEX idx_repositories_organization_id_remote_key
+ON repositories(organization_id, remote_key)
+WHERE deleted_at IS NULL;
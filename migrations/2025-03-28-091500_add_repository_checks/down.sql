@@ -0,0 +1,6 @@
+ALTER TABLE repositories
+DROP COLUMN reachable,
+DROP COLUMN default_branch,
+DROP COLUMN ref_count,
+DROP COLUMN last_error,
+DROP COLUMN last_checked_at;
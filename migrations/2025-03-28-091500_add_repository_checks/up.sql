@@ -0,0 +1,6 @@
+ALTER TABLE repositories
+ADD COLUMN reachable BOOLEAN,
+ADD COLUMN default_branch VARCHAR(255),
+ADD COLUMN ref_count INTEGER,
+ADD COLUMN last_error TEXT,
+ADD COLUMN last_checked_at TIMESTAMPTZ;
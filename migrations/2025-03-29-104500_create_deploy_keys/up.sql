@@ -0,0 +1,15 @@
+CREATE TABLE deploy_keys (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    repository_id UUID NOT NULL REFERENCES repositories(id) ON DELETE CASCADE,
+    public_key TEXT NOT NULL,
+    fingerprint VARCHAR(100) NOT NULL,
+    encrypted_private_key BYTEA NOT NULL,
+    private_key_nonce BYTEA NOT NULL,
+    encrypted_data_key BYTEA NOT NULL,
+    data_key_nonce BYTEA NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_deploy_keys_repository_id ON deploy_keys(repository_id);
+CREATE UNIQUE INDEX idx_deploy_keys_repository_id_active ON deploy_keys(repository_id) WHERE expires_at IS NULL;
@@ -0,0 +1,15 @@
+CREATE TABLE known_hosts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    host VARCHAR(255) NOT NULL,
+    key_type VARCHAR(50) NOT NULL,
+    public_key TEXT NOT NULL,
+    fingerprint VARCHAR(100) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'trusted', 'changed')),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    confirmed_at TIMESTAMP WITH TIME ZONE,
+    confirmed_by UUID REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE UNIQUE INDEX idx_known_hosts_organization_id_host_public_key ON known_hosts(organization_id, host, public_key);
+CREATE UNIQUE INDEX idx_known_hosts_organization_id_host_key_type_trusted ON known_hosts(organization_id, host, key_type) WHERE status = 'trusted';
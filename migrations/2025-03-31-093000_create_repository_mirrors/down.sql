@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS repository_mirrors;
+
+ALTER TABLE repositories DROP COLUMN sync_interval_minutes;
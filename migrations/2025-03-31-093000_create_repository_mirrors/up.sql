@@ -0,0 +1,18 @@
+ALTER TABLE repositories ADD COLUMN sync_interval_minutes INTEGER;
+
+CREATE TABLE repository_mirrors (
+    repository_id UUID PRIMARY KEY REFERENCES repositories(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL CHECK (status IN ('syncing', 'succeeded', 'failed')),
+    path TEXT NOT NULL,
+    default_branch VARCHAR(255),
+    size_bytes BIGINT,
+    last_error TEXT,
+    sync_started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    last_synced_at TIMESTAMP WITH TIME ZONE,
+    last_duration_ms BIGINT,
+    next_sync_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_repository_mirrors_next_sync_at ON repository_mirrors(next_sync_at);
@@ -0,0 +1,26 @@
+CREATE TABLE webhook_secrets (
+    repository_id UUID PRIMARY KEY REFERENCES repositories(id) ON DELETE CASCADE,
+    encrypted_secret BYTEA NOT NULL,
+    secret_nonce BYTEA NOT NULL,
+    encrypted_data_key BYTEA NOT NULL,
+    data_key_nonce BYTEA NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE webhook_deliveries (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    repository_id UUID NOT NULL REFERENCES repositories(id) ON DELETE CASCADE,
+    provider VARCHAR(20) NOT NULL CHECK (provider IN ('github', 'gitlab', 'gitea')),
+    delivery_id VARCHAR(255),
+    event_name VARCHAR(100) NOT NULL,
+    event JSONB,
+    payload TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL CHECK (status IN ('received', 'processed', 'ignored', 'failed')),
+    error TEXT,
+    attempts INTEGER NOT NULL DEFAULT 1,
+    received_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    processed_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_webhook_deliveries_repository_id ON webhook_deliveries(repository_id, received_at);
+CREATE UNIQUE INDEX idx_webhook_deliveries_delivery_id ON webhook_deliveries(repository_id, provider, delivery_id) WHERE delivery_id IS NOT NULL;
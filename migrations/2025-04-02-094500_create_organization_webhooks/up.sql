@@ -0,0 +1,37 @@
+CREATE TABLE organization_webhooks (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    events TEXT[] NOT NULL DEFAULT '{}',
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    encrypted_secret BYTEA NOT NULL,
+    secret_nonce BYTEA NOT NULL,
+    encrypted_data_key BYTEA NOT NULL,
+    data_key_nonce BYTEA NOT NULL,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_organization_webhooks_organization_id ON organization_webhooks(organization_id);
+
+CREATE TABLE organization_webhook_deliveries (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    webhook_id UUID NOT NULL REFERENCES organization_webhooks(id) ON DELETE CASCADE,
+    event_id UUID NOT NULL,
+    event_type VARCHAR(100) NOT NULL,
+    payload TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL CHECK (status IN ('pending', 'succeeded', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP WITH TIME ZONE,
+    last_attempt_at TIMESTAMP WITH TIME ZONE,
+    request_excerpt TEXT,
+    response_status INTEGER,
+    response_excerpt TEXT,
+    error TEXT,
+    duration_ms BIGINT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_organization_webhook_deliveries_webhook_id ON organization_webhook_deliveries(webhook_id, created_at);
+CREATE INDEX idx_organization_webhook_deliveries_next_attempt_at ON organization_webhook_deliveries(next_attempt_at) WHERE status = 'pending';
@@ -0,0 +1,34 @@
+CREATE TABLE audit_events (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    -- Insertion order, used as the pagination cursor
+    seq BIGSERIAL NOT NULL UNIQUE,
+    organization_id UUID,
+    actor_id UUID,
+    action VARCHAR(100) NOT NULL,
+    target_type VARCHAR(50) NOT NULL,
+    target_id UUID,
+    changes JSONB,
+    ip VARCHAR(45),
+    user_agent TEXT,
+    request_id VARCHAR(100),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_audit_events_organization_id ON audit_events(organization_id, seq);
+CREATE INDEX idx_audit_events_actor_id ON audit_events(actor_id, seq);
+CREATE INDEX idx_audit_events_target ON audit_events(target_type, target_id);
+
+-- Events outlive the rows they describe and are never changed
+CREATE FUNCTION audit_events_append_only() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_no_update
+    BEFORE UPDATE OR DELETE ON audit_events
+    FOR EACH ROW EXECUTE FUNCTION audit_events_append_only();
+
+CREATE TRIGGER audit_events_no_truncate
+    BEFORE TRUNCATE ON audit_events
+    FOR EACH STATEMENT EXECUTE FUNCTION audit_events_append_only();
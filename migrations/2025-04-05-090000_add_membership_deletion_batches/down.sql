@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS idx_organization_users_deletion_batch_id;
+DROP INDEX IF EXISTS idx_team_users_deletion_batch_id;
+
+ALTER TABLE organization_users DROP COLUMN deleted_at;
+ALTER TABLE organization_users DROP COLUMN deletion_batch_id;
+ALTER TABLE team_users DROP COLUMN deleted_at;
+ALTER TABLE team_users DROP COLUMN deletion_batch_id;
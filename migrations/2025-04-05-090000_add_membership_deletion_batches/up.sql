@@ -0,0 +1,21 @@
+ALTER TABLE organization_users ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE organization_users ADD COLUMN deletion_batch_id UUID;
+ALTER TABLE team_users ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE team_users ADD COLUMN deletion_batch_id UUID;
+
+CREATE INDEX idx_organization_users_deletion_batch_id ON organization_users(deletion_batch_id);
+CREATE INDEX idx_team_users_deletion_batch_id ON team_users(deletion_batch_id);
+
+-- Cascade organizations that were deleted before memberships followed them
+UPDATE organization_users ou
+SET deleted_at = o.deleted_at, deletion_batch_id = o.deletion_batch_id
+FROM organizations o
+WHERE ou.organization_id = o.id
+  AND o.deleted_at IS NOT NULL;
+
+UPDATE team_users tu
+SET deleted_at = t.deleted_at, deletion_batch_id = t.deletion_batch_id
+FROM teams t
+JOIN organizations o ON o.id = t.organization_id
+WHERE tu.team_id = t.id
+  AND t.deletion_batch_id = o.deletion_batch_id;
@@ -0,0 +1,2 @@
+-- Nothing to undo, the backfilled columns already existed
+SELECT 1;
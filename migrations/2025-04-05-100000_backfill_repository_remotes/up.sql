@@ -0,0 +1,69 @@
+-- Derive the remote columns of repositories registered before URLs were
+-- parsed, and recompute every remote key now that it keeps a non-default port.
+-- Follows `GitUrl::parse`; URLs it would reject are left alone.
+CREATE TEMPORARY TABLE parsed_remotes ON COMMIT DROP AS
+SELECT
+    r.id,
+    r.organization_id,
+    r.deleted_at,
+    r.created_at,
+    CASE
+        WHEN m.url_form IS NOT NULL AND lower(m.url_form[1]) IN ('ssh', 'git+ssh', 'ssh+git') THEN 'ssh'
+        WHEN m.url_form IS NOT NULL THEN lower(m.url_form[1])
+        WHEN m.file_form IS NOT NULL THEN 'file'
+        ELSE 'ssh'
+    END AS protocol,
+    lower(COALESCE(m.url_form[2], m.scp_form[1])) AS host,
+    m.url_form[3] AS port,
+    rtrim(COALESCE(m.url_form[4], m.file_form[1], ltrim(m.scp_form[2], '/')), '/') AS path
+FROM repositories r
+CROSS JOIN LATERAL (
+    SELECT
+        regexp_match(trim(r.url), '^(https?|ssh|git\+ssh|ssh\+git)://(?:[^@/:]+@)?([^@/:]+)(?::([0-9]+))?/+([^?#]+)$', 'i') AS url_form,
+        regexp_match(trim(r.url), '^file://(/[^?#]+)$', 'i') AS file_form,
+        regexp_match(trim(r.url), '^(?:[^@/:]+@)?([^@/:]+):([^/][^?#]*)$') AS scp_form
+) m
+WHERE m.url_form IS NOT NULL OR m.file_form IS NOT NULL OR m.scp_form IS NOT NULL;
+
+-- Default ports are dropped, as in the normalized URL
+UPDATE parsed_remotes
+SET port = NULL
+WHERE (protocol = 'https' AND port = '443')
+   OR (protocol = 'http' AND port = '80')
+   OR (protocol = 'ssh' AND port = '22');
+
+DELETE FROM parsed_remotes
+WHERE regexp_replace(path, '^.*/', '') IN ('', '.git');
+
+ALTER TABLE parsed_remotes ADD COLUMN owner_path TEXT, ADD COLUMN remote_key TEXT;
+
+UPDATE parsed_remotes
+SET
+    owner_path = NULLIF(ltrim(substring(path FROM '^(.*)/[^/]*$'), '/'), ''),
+    remote_key = CASE
+        WHEN host IS NULL THEN 'file://' || regexp_replace(path, '\.git$', '')
+        ELSE host || COALESCE(':' || port, '') || '/' || regexp_replace(path, '\.git$', '')
+    END;
+
+-- Clear the keys first, the unique index is checked row by row
+UPDATE repositories r
+SET remote_key = NULL
+FROM parsed_remotes p
+WHERE r.id = p.id;
+
+-- The oldest live repository keeps a remote registered more than once
+UPDATE repositories r
+SET
+    protocol = p.protocol,
+    owner_path = left(p.owner_path, 255),
+    remote_key = CASE WHEN p.rank = 1 OR r.deleted_at IS NOT NULL THEN left(p.remote_key, 255) END
+FROM (
+    SELECT
+        *,
+        row_number() OVER (
+            PARTITION BY organization_id, remote_key, deleted_at IS NULL
+            ORDER BY created_at, id
+        ) AS rank
+    FROM parsed_remotes
+) p
+WHERE r.id = p.id;
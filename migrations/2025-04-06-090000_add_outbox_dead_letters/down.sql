@@ -0,0 +1,4 @@
+DROP INDEX idx_outbox_events_next_attempt_at;
+CREATE INDEX idx_outbox_events_next_attempt_at ON outbox_events(next_attempt_at) WHERE dispatched_at IS NULL;
+
+ALTER TABLE outbox_events DROP COLUMN dead_lettered_at;
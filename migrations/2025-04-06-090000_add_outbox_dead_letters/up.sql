@@ -0,0 +1,6 @@
+-- Events whose subscribers kept failing are set aside instead of retried
+ALTER TABLE outbox_events ADD COLUMN dead_lettered_at TIMESTAMP WITH TIME ZONE;
+
+DROP INDEX idx_outbox_events_next_attempt_at;
+CREATE INDEX idx_outbox_events_next_attempt_at ON outbox_events(next_attempt_at)
+    WHERE dispatched_at IS NULL AND dead_lettered_at IS NULL;
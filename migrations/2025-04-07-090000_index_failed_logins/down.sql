@@ -0,0 +1 @@
+DROP INDEX idx_audit_events_login_failed;
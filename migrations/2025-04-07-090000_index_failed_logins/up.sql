@@ -0,0 +1,3 @@
+-- Failed logins are counted per address before being recorded
+CREATE INDEX idx_audit_events_login_failed ON audit_events(ip, created_at)
+    WHERE action = 'auth.login_failed';
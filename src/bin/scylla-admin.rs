@@ -0,0 +1,227 @@
+//! Maintenance tasks run against the database the server is configured for,
+//! reading the same `.env` and environment variables.
+//!
+//! ```sh
+//! cargo run --bin scylla-admin -- migrate status
+//! ```
+
+use actix_poc_scylla::config::{Config, ConfigArgs, Settings};
+use actix_poc_scylla::db;
+use actix_poc_scylla::modules::admin::service::{AdminService, DEMO_PASSWORD};
+use actix_poc_scylla::modules::audit::dto::AuditContext;
+use actix_poc_scylla::modules::auth::dto::RegisterQuery;
+use clap::{Parser, Subcommand};
+use diesel::{Connection, PgConnection};
+use std::error::Error;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::process::ExitCode;
+use tracing_subscriber::EnvFilter;
+
+// Recorded as the user agent of the audit events we write
+const TOOL: &str = "scylla-admin";
+
+#[derive(Parser)]
+#[command(name = TOOL, version, about = "Scylla administration tasks")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    #[command(flatten)]
+    config: ConfigArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Apply, revert or list the database migrations built into the binary
+    Migrate {
+        #[command(subcommand)]
+        action: MigrateAction,
+    },
+    /// Create a verified platform admin
+    CreateAdmin {
+        #[arg(long)]
+        email: String,
+        #[arg(long)]
+        name: String,
+        /// Read from standard input when omitted
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Mark a user as verified without the email round trip
+    VerifyUser { email: String },
+    /// Set a new password for a user
+    ResetPassword {
+        email: String,
+        /// Read from standard input when omitted
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Delete expired verification and reset password tokens
+    PurgeTokens {
+        /// Count the expired tokens without deleting them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Create demo users, an organization, a team and a repository
+    Seed,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the effective settings, secrets hidden, and validate them
+    Check,
+}
+
+#[derive(Subcommand)]
+enum MigrateAction {
+    /// Apply every pending migration
+    Run,
+    /// Revert the most recent migrations
+    Revert {
+        #[arg(long, default_value_t = 1)]
+        steps: usize,
+    },
+    /// List every migration and whether it is applied
+    Status,
+}
+
+fn main() -> ExitCode {
+    // Output is for people, logs only report trouble
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+        )
+        .with_writer(io::stderr)
+        .init();
+
+    let cli = Cli::parse();
+    match run(cli.command, &cli.config) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command, args: &ConfigArgs) -> Result<(), Box<dyn Error>> {
+    // Checking must work without a valid configuration or a database
+    if let Command::Config {
+        action: ConfigAction::Check,
+    } = command
+    {
+        let settings = Settings::load(args);
+        print!("{}", settings.redacted());
+        settings.resolve()?;
+        eprintln!("Configuration is valid");
+        return Ok(());
+    }
+
+    let config = Config::load(args)?;
+    let mut conn = PgConnection::establish(&config.database_url)
+        .map_err(|e| format!("Failed to connect to Postgres: {}", e))?;
+    let audit = AuditContext::command_line(TOOL);
+
+    match command {
+        Command::Config { .. } => {}
+        Command::Migrate { action } => match action {
+            MigrateAction::Run => {
+                let applied = db::run_migrations(&mut conn)?;
+                if applied.is_empty() {
+                    println!("No pending migrations");
+                }
+                for version in applied {
+                    println!("Applied {}", version);
+                }
+            }
+            MigrateAction::Revert { steps } => {
+                let reverted = db::revert_migrations(&mut conn, steps)?;
+                if reverted.is_empty() {
+                    println!("No migration to revert");
+                }
+                for version in reverted {
+                    println!("Reverted {}", version);
+                }
+            }
+            MigrateAction::Status => {
+                for (name, applied) in db::migration_status(&mut conn)? {
+                    let state = if applied { "applied" } else { "pending" };
+                    println!("{:<8} {}", state, name);
+                }
+            }
+        },
+        Command::CreateAdmin {
+            email,
+            name,
+            password,
+        } => {
+            let password = password_or_stdin(password)?;
+            let user = AdminService::create_admin(
+                &mut conn,
+                &RegisterQuery {
+                    name,
+                    email,
+                    password,
+                },
+                &audit,
+            )?;
+            println!("Created admin {} ({})", user.email, user.id);
+        }
+        Command::VerifyUser { email } => {
+            if AdminService::verify_user(&mut conn, &email, &audit)? {
+                println!("Verified {}", email);
+            } else {
+                println!("{} is already verified", email);
+            }
+        }
+        Command::ResetPassword { email, password } => {
+            let password = password_or_stdin(password)?;
+            AdminService::reset_password(&mut conn, &email, &password, &audit)?;
+            println!("Reset the password of {}", email);
+        }
+        Command::PurgeTokens { dry_run } => {
+            let report =
+                AdminService::purge_expired_tokens(&mut conn, config.purge.batch_size, dry_run)?;
+            let verb = if dry_run { "Would delete" } else { "Deleted" };
+            for table in report.tables {
+                println!("{} {} from {}", verb, table.count, table.table);
+            }
+        }
+        Command::Seed => {
+            let demo = AdminService::seed_demo(&mut conn, &config, &audit)?;
+            println!(
+                "Seeded organization '{}' with team '{}' and repository '{}'",
+                demo.organization.slug, demo.team.slug, demo.repository.slug
+            );
+            for user in demo.users {
+                println!("Log in as {} with password {}", user.email, DEMO_PASSWORD);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Passwords passed as arguments end up in the shell history, so they can be
+// piped in instead. Input is echoed when typed in a terminal.
+fn password_or_stdin(password: Option<String>) -> Result<String, Box<dyn Error>> {
+    if let Some(password) = password {
+        return Ok(password);
+    }
+
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        eprint!("Password: ");
+        io::stderr().flush()?;
+    }
+
+    let mut line = String::new();
+    stdin.lock().read_line(&mut line)?;
+
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
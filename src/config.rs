@@ -1,17 +1,233 @@
+use crate::utils::cors::AllowedOrigin;
+use crate::utils::envelope::MasterKey;
+use actix_web::cookie::SameSite;
+use actix_web::http::header::HeaderName;
+use clap::Args;
 use dotenv::dotenv;
+use redis::IntoConnectionInfo;
 use serde::Deserialize;
-use std::env;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::{env, fmt, fs};
+use toml::{Table, Value};
+use tracing_subscriber::EnvFilter;
+use url::Url;
+
+/// Built-in defaults, the lowest layer. Also lists every setting.
+const DEFAULTS: &str = include_str!("../config/default.toml");
+
+/// Read when no file is given, if it exists
+const DEFAULT_FILE: &str = "scylla.toml";
+
+/// Defaults of each value of `env`, layered over the built-in defaults
+const PROFILES: &[(&str, &str)] = &[
+    ("dev", include_str!("../config/dev.toml")),
+    ("staging", include_str!("../config/staging.toml")),
+    ("prod", include_str!("../config/prod.toml")),
+];
+
+// Methods the API may be called with from a browser
+const METHODS: &[&str] = &["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"];
+
+/// Environment variables and the setting each overrides. Each can also be
+/// read from the file named by the variable suffixed with `_FILE`.
+const ENV_VARS: &[(&str, &str)] = &[
+    ("BASE_URL", "base_url"),
+    ("HOST", "host"),
+    ("PORT", "port"),
+    ("ENV", "env"),
+    ("DATABASE_URL", "database_url"),
+    ("REDIS_URL", "redis_url"),
+    ("SESSION_SECRET", "session_secret"),
+    ("CORS_ALLOWED_ORIGINS", "cors.allowed_origins"),
+    ("CORS_ALLOWED_METHODS", "cors.allowed_methods"),
+    ("CORS_ALLOWED_HEADERS", "cors.allowed_headers"),
+    ("CORS_MAX_AGE_SECONDS", "cors.max_age_seconds"),
+    ("COOKIE_NAME", "cookie.name"),
+    ("COOKIE_DOMAIN", "cookie.domain"),
+    ("COOKIE_SAME_SITE", "cookie.same_site"),
+    ("COOKIE_MAX_AGE_SECONDS", "cookie.max_age_seconds"),
+    ("COOKIE_SECURE", "cookie.secure"),
+    ("GOOGLE_CLIENT_ID", "oauth.google.client_id"),
+    ("GOOGLE_CLIENT_SECRET", "oauth.google.client_secret"),
+    ("GOOGLE_REDIRECT_URL", "oauth.google.redirect_url"),
+    ("SMTP_SERVER", "smtp.server"),
+    ("SMTP_PORT", "smtp.port"),
+    ("SMTP_USERNAME", "smtp.username"),
+    ("SMTP_PASSWORD", "smtp.password"),
+    ("SMTP_EMAIL_FROM", "smtp.email_from"),
+    ("SMTP_FRONTEND_URL", "smtp.frontend_url"),
+    ("SMTP_TLS_MODE", "smtp.tls_mode"),
+    ("PURGE_ENABLED", "purge.enabled"),
+    ("PURGE_RETENTION_DAYS", "purge.retention_days"),
+    ("PURGE_INTERVAL_MINUTES", "purge.interval_minutes"),
+    ("PURGE_BATCH_SIZE", "purge.batch_size"),
+    ("PURGE_DRY_RUN", "purge.dry_run"),
+    ("GIT_TIMEOUT_SECONDS", "git.timeout_seconds"),
+    ("GIT_ALLOW_FILE_REMOTES", "git.allow_file_remotes"),
+    ("GIT_ALLOW_HTTP_REMOTES", "git.allow_http_remotes"),
+    ("DEPLOY_KEY_MASTER_KEY", "deploy_keys.master_key"),
+    ("DEPLOY_KEY_OVERLAP_HOURS", "deploy_keys.overlap_hours"),
+    ("MIRROR_ENABLED", "mirror.enabled"),
+    ("MIRROR_STORAGE_DIR", "mirror.storage_dir"),
+    ("MIRROR_SYNC_INTERVAL_MINUTES", "mirror.interval_minutes"),
+    ("MIRROR_POLL_SECONDS", "mirror.poll_seconds"),
+    ("MIRROR_BATCH_SIZE", "mirror.batch_size"),
+    ("BROWSE_MAX_BLOB_BYTES", "browse.max_blob_bytes"),
+    ("BROWSE_MAX_TREE_ENTRIES", "browse.max_tree_entries"),
+    ("BROWSE_MAX_DIFF_FILES", "browse.max_diff_files"),
+    ("BROWSE_MAX_WALK_COMMITS", "browse.max_walk_commits"),
+    ("WEBHOOK_ENABLED", "webhooks.enabled"),
+    ("WEBHOOK_POLL_SECONDS", "webhooks.poll_seconds"),
+    ("WEBHOOK_BATCH_SIZE", "webhooks.batch_size"),
+    ("WEBHOOK_TIMEOUT_SECONDS", "webhooks.timeout_seconds"),
+    ("WEBHOOK_MAX_ATTEMPTS", "webhooks.max_attempts"),
+    ("WEBHOOK_BACKOFF_SECONDS", "webhooks.backoff_seconds"),
+    (
+        "WEBHOOK_ALLOW_PRIVATE_NETWORKS",
+        "webhooks.allow_private_networks",
+    ),
+    ("OUTBOX_ENABLED", "outbox.enabled"),
+    ("OUTBOX_POLL_SECONDS", "outbox.poll_seconds"),
+    ("OUTBOX_BATCH_SIZE", "outbox.batch_size"),
+    ("OUTBOX_BACKOFF_SECONDS", "outbox.backoff_seconds"),
+    ("OUTBOX_MAX_ATTEMPTS", "outbox.max_attempts"),
+    ("DATABASE_MAX_CONNECTIONS", "database.max_connections"),
+    ("DATABASE_MIN_CONNECTIONS", "database.min_connections"),
+    (
+        "DATABASE_CONNECT_TIMEOUT_SECONDS",
+        "database.connect_timeout_seconds",
+    ),
+    (
+        "DATABASE_IDLE_TIMEOUT_SECONDS",
+        "database.idle_timeout_seconds",
+    ),
+    (
+        "DATABASE_STATEMENT_TIMEOUT_MS",
+        "database.statement_timeout_ms",
+    ),
+    ("DATABASE_APPLICATION_NAME", "database.application_name"),
+    (
+        "REDIS_CONNECT_TIMEOUT_SECONDS",
+        "redis.connect_timeout_seconds",
+    ),
+    ("STARTUP_MAX_ATTEMPTS", "startup.max_attempts"),
+    ("STARTUP_BACKOFF_SECONDS", "startup.backoff_seconds"),
+    ("STARTUP_MAX_BACKOFF_SECONDS", "startup.max_backoff_seconds"),
+    ("SHUTDOWN_TIMEOUT_SECONDS", "shutdown.timeout_seconds"),
+    ("HEALTH_CACHE_SECONDS", "health.cache_seconds"),
+    ("HEALTH_TIMEOUT_SECONDS", "health.timeout_seconds"),
+    ("HEALTH_CHECK_SMTP", "health.check_smtp"),
+    ("METRICS_ENABLED", "metrics.enabled"),
+    ("METRICS_BIND", "metrics.bind"),
+    ("METRICS_TOKEN", "metrics.token"),
+    ("TRACING_ENABLED", "tracing.enabled"),
+    ("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT", "tracing.endpoint"),
+    ("OTEL_SERVICE_NAME", "tracing.service_name"),
+    ("TRACING_SAMPLE_RATIO", "tracing.sample_ratio"),
+    ("RUST_LOG", "log.filter"),
+    ("LOG_FORMAT", "log.format"),
+];
+
+/// Settings without a default, `None` unless set
+const OPTIONAL: &[&str] = &["database.min_connections"];
+
+/// Credentials, hidden by `config check`
+const SECRETS: &[&str] = &[
+    "session_secret",
+    "oauth.google.client_secret",
+    "smtp.password",
+    "deploy_keys.master_key",
+    "metrics.token",
+];
+
+/// URLs that may embed a password, hidden by `config check`
+const CREDENTIAL_URLS: &[&str] = &["database_url", "redis_url"];
+
+/// Command line flags selecting the file and overriding settings, shared by
+/// every binary.
+#[derive(Args, Debug, Clone, Default)]
+pub struct ConfigArgs {
+    /// TOML file layered over the defaults, `scylla.toml` when it exists
+    #[arg(
+        long,
+        short = 'c',
+        global = true,
+        env = "CONFIG_FILE",
+        value_name = "PATH"
+    )]
+    pub config: Option<PathBuf>,
+
+    /// Override a setting, e.g. `--set database.max_connections=20`
+    #[arg(long = "set", global = true, value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+}
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct Config {
     pub base_url: String,
+    /// Address the server binds to
+    pub host: String,
     pub port: u16,
+    /// Profile: `dev`, `staging` or `prod`
     pub env: String,
     pub database_url: String,
     pub redis_url: String,
     pub session_secret: String,
+    pub cors: CorsConfig,
+    pub cookie: CookieConfig,
     pub oauth: OAuthConfig,
     pub smtp: SmtpConfig,
+    pub purge: PurgeConfig,
+    pub git: GitConfig,
+    pub deploy_keys: DeployKeyConfig,
+    pub mirror: MirrorConfig,
+    pub browse: BrowseConfig,
+    pub webhooks: WebhookConfig,
+    pub outbox: OutboxConfig,
+    pub database: DatabaseConfig,
+    pub redis: RedisConfig,
+    pub startup: StartupConfig,
+    pub shutdown: ShutdownConfig,
+    pub health: HealthConfig,
+    pub metrics: MetricsConfig,
+    pub log: LogConfig,
+    pub tracing: TracingConfig,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct CorsConfig {
+    /// Exact origins, or `https://*.example.com` for any subdomain
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    /// How long browsers may cache a preflight response
+    pub max_age_seconds: u64,
+}
+
+/// The session cookie
+#[derive(Clone, Debug, Deserialize)]
+pub struct CookieConfig {
+    pub name: String,
+    /// Host-only cookie when empty
+    pub domain: String,
+    /// `strict`, `lax` or `none`
+    pub same_site: String,
+    /// Lifetime of the session, which ends with the browser when 0
+    pub max_age_seconds: u64,
+    pub secure: bool,
+}
+
+impl CookieConfig {
+    pub fn same_site(&self) -> SameSite {
+        match self.same_site.to_lowercase().as_str() {
+            "strict" => SameSite::Strict,
+            "none" => SameSite::None,
+            _ => SameSite::Lax,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -37,42 +253,767 @@ pub struct SmtpConfig {
     pub tls_mode: String,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+pub struct PurgeConfig {
+    pub enabled: bool,
+    pub retention_days: i64,
+    pub interval_minutes: u64,
+    pub batch_size: i64,
+    pub dry_run: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct GitConfig {
+    pub timeout_seconds: u64,
+    /// Whether repositories may point at paths on this server
+    pub allow_file_remotes: bool,
+    /// Whether repositories may use unencrypted `http://` remotes
+    pub allow_http_remotes: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct DeployKeyConfig {
+    pub master_key: String,
+    pub overlap_hours: i64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MirrorConfig {
+    pub enabled: bool,
+    pub storage_dir: String,
+    pub interval_minutes: i32,
+    pub poll_seconds: u64,
+    pub batch_size: i64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BrowseConfig {
+    pub max_blob_bytes: usize,
+    pub max_tree_entries: usize,
+    pub max_diff_files: usize,
+    pub max_walk_commits: usize,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebhookConfig {
+    pub enabled: bool,
+    pub poll_seconds: u64,
+    pub batch_size: i64,
+    pub timeout_seconds: u64,
+    pub max_attempts: i32,
+    pub backoff_seconds: i64,
+    /// Whether deliveries may go to loopback, private or link-local
+    /// addresses
+    pub allow_private_networks: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct OutboxConfig {
+    pub enabled: bool,
+    pub poll_seconds: u64,
+    pub batch_size: i64,
+    pub backoff_seconds: i64,
+    /// Failed attempts after which an event is dead-lettered
+    pub max_attempts: i32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct DatabaseConfig {
+    pub max_connections: u32,
+    /// Idle connections kept open, all of them when unset
+    pub min_connections: Option<u32>,
+    pub connect_timeout_seconds: u64,
+    /// Idle connections above the minimum are closed after this, never when 0
+    pub idle_timeout_seconds: u64,
+    /// Queries running longer are cancelled by Postgres, never when 0
+    pub statement_timeout_ms: u64,
+    pub application_name: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RedisConfig {
+    pub connect_timeout_seconds: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StartupConfig {
+    /// Attempts at reaching Postgres and Redis before giving up
+    pub max_attempts: u32,
+    pub backoff_seconds: u64,
+    pub max_backoff_seconds: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ShutdownConfig {
+    /// Time in-flight requests and background tasks get to finish once
+    /// shutdown begins
+    pub timeout_seconds: u64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct HealthConfig {
+    /// Readiness results are reused for this long
+    pub cache_seconds: u64,
+    /// Time each dependency gets to answer
+    pub timeout_seconds: u64,
+    pub check_smtp: bool,
+}
+
+/// `/metrics`, on its own listener when `bind` is set, else on the API
+/// listener behind `token`
+#[derive(Clone, Debug, Deserialize)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    /// Address like `127.0.0.1:9090`
+    pub bind: String,
+    /// Bearer token scrapers must send, none when empty
+    pub token: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LogConfig {
+    /// Directives like `info,actix_poc_scylla=debug`
+    pub filter: String,
+    /// `json` or `text`
+    pub format: String,
+}
+
+/// Span export over OTLP/HTTP
+#[derive(Clone, Debug, Deserialize)]
+pub struct TracingConfig {
+    pub enabled: bool,
+    /// Traces URL of the collector, e.g. `http://localhost:4318/v1/traces`
+    pub endpoint: String,
+    pub service_name: String,
+    /// Share of traces started here that are kept, between 0 and 1. Callers
+    /// decide for the traces they propagate.
+    pub sample_ratio: f64,
+}
+
 impl Config {
-    pub fn load() -> Self {
+    /// Merges every layer and checks the result, reporting all problems
+    /// at once.
+    pub fn load(args: &ConfigArgs) -> Result<Self, ConfigError> {
+        Settings::load(args).resolve()
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut require = |ok: bool, problem: String| {
+            if !ok {
+                problems.push(problem);
+            }
+        };
+
+        require(
+            !self.database_url.is_empty(),
+            format!("database_url is required ({})", env_var("database_url")),
+        );
+        match self.redis_url.as_str() {
+            "" => require(
+                false,
+                format!("redis_url is required ({})", env_var("redis_url")),
+            ),
+            url => require(
+                url.into_connection_info().is_ok(),
+                "redis_url is not a valid Redis URL".to_string(),
+            ),
+        }
+        // Shorter keys make cookie signing panic
+        require(
+            self.session_secret.len() >= 64,
+            format!(
+                "session_secret must be at least 64 bytes, got {} ({})",
+                self.session_secret.len(),
+                env_var("session_secret")
+            ),
+        );
+        require(
+            Url::parse(&self.base_url).is_ok(),
+            format!("base_url '{}' is not a valid URL", self.base_url),
+        );
+        require(self.port > 0, "port must not be 0".to_string());
+        require(
+            self.host.parse::<IpAddr>().is_ok(),
+            format!(
+                "host '{}' is not an IP address, e.g. 127.0.0.1 or 0.0.0.0",
+                self.host
+            ),
+        );
+
+        let hardened = self.env == "staging" || self.env == "prod";
+        for origin in &self.cors.allowed_origins {
+            match AllowedOrigin::parse(origin) {
+                Ok(allowed) => require(
+                    allowed.is_https() || self.env != "prod",
+                    format!("cors.allowed_origins: '{}' must use https in prod", origin),
+                ),
+                Err(e) => require(false, format!("cors.allowed_origins: {}", e)),
+            }
+        }
+        require(
+            !self.cors.allowed_methods.is_empty(),
+            "cors.allowed_methods must not be empty".to_string(),
+        );
+        for method in &self.cors.allowed_methods {
+            require(
+                METHODS.contains(&method.as_str()),
+                format!(
+                    "cors.allowed_methods: '{}' is not one of {}",
+                    method,
+                    METHODS.join(", ")
+                ),
+            );
+        }
+        for header in &self.cors.allowed_headers {
+            require(
+                HeaderName::try_from(header.as_str()).is_ok(),
+                format!("cors.allowed_headers: '{}' is not a header name", header),
+            );
+        }
+
+        require(
+            !self.cookie.name.is_empty()
+                && self
+                    .cookie
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)),
+            format!(
+                "cookie.name '{}' may only contain letters, digits, '-', '_' and '.'",
+                self.cookie.name
+            ),
+        );
+        require(
+            !self.cookie.domain.contains([':', '/']),
+            format!(
+                "cookie.domain '{}' must be a bare domain, without scheme or port",
+                self.cookie.domain
+            ),
+        );
+        require(
+            matches!(
+                self.cookie.same_site.to_lowercase().as_str(),
+                "strict" | "lax" | "none"
+            ),
+            format!(
+                "cookie.same_site must be strict, lax or none, got '{}'",
+                self.cookie.same_site
+            ),
+        );
+        // Browsers drop SameSite=None cookies that are not secure
+        require(
+            self.cookie.secure || self.cookie.same_site() != SameSite::None,
+            "cookie.same_site = none requires cookie.secure".to_string(),
+        );
+        require(
+            self.cookie.secure || !hardened,
+            format!("cookie.secure must be true in {}", self.env),
+        );
+
+        require(
+            !self.smtp.server.is_empty(),
+            "smtp.server is required".to_string(),
+        );
+        require(self.smtp.port > 0, "smtp.port must not be 0".to_string());
+        require(
+            self.smtp.username.is_empty() || !self.smtp.password.is_empty(),
+            format!(
+                "smtp.password is required with smtp.username ({})",
+                env_var("smtp.password")
+            ),
+        );
+        require(
+            self.smtp.email_from.contains('@'),
+            format!(
+                "smtp.email_from '{}' is not an email address",
+                self.smtp.email_from
+            ),
+        );
+        require(
+            Url::parse(&self.smtp.frontend_url).is_ok(),
+            format!(
+                "smtp.frontend_url '{}' is not a valid URL",
+                self.smtp.frontend_url
+            ),
+        );
+        require(
+            matches!(
+                self.smtp.tls_mode.to_lowercase().as_str(),
+                "none" | "required" | "opportunistic"
+            ),
+            format!(
+                "smtp.tls_mode must be none, required or opportunistic, got '{}'",
+                self.smtp.tls_mode
+            ),
+        );
+
+        // Optional, but unusable when malformed
+        require(
+            self.deploy_keys.master_key.is_empty()
+                || MasterKey::from_base64(&self.deploy_keys.master_key).is_ok(),
+            format!(
+                "deploy_keys.master_key must be 32 base64-encoded bytes ({})",
+                env_var("deploy_keys.master_key")
+            ),
+        );
+
+        require(
+            self.database.max_connections > 0,
+            "database.max_connections must be at least 1".to_string(),
+        );
+        if let Some(min) = self.database.min_connections {
+            require(
+                min <= self.database.max_connections,
+                format!(
+                    "database.min_connections ({}) exceeds database.max_connections ({})",
+                    min, self.database.max_connections
+                ),
+            );
+        }
+        require(
+            self.startup.max_attempts > 0,
+            "startup.max_attempts must be at least 1".to_string(),
+        );
+        require(
+            self.startup.backoff_seconds <= self.startup.max_backoff_seconds,
+            format!(
+                "startup.backoff_seconds ({}) exceeds startup.max_backoff_seconds ({})",
+                self.startup.backoff_seconds, self.startup.max_backoff_seconds
+            ),
+        );
+
+        for (key, value) in [
+            // 0 would hard-delete everything soft-deleted, leaving nothing
+            // to restore
+            ("purge.retention_days", self.purge.retention_days),
+            ("purge.interval_minutes", self.purge.interval_minutes as i64),
+            ("purge.batch_size", self.purge.batch_size),
+            ("git.timeout_seconds", self.git.timeout_seconds as i64),
+            ("mirror.poll_seconds", self.mirror.poll_seconds as i64),
+            ("mirror.batch_size", self.mirror.batch_size),
+            ("browse.max_blob_bytes", self.browse.max_blob_bytes as i64),
+            (
+                "browse.max_tree_entries",
+                self.browse.max_tree_entries as i64,
+            ),
+            ("browse.max_diff_files", self.browse.max_diff_files as i64),
+            (
+                "browse.max_walk_commits",
+                self.browse.max_walk_commits as i64,
+            ),
+            ("webhooks.poll_seconds", self.webhooks.poll_seconds as i64),
+            ("webhooks.batch_size", self.webhooks.batch_size),
+            (
+                "webhooks.timeout_seconds",
+                self.webhooks.timeout_seconds as i64,
+            ),
+            ("webhooks.max_attempts", self.webhooks.max_attempts as i64),
+            ("outbox.poll_seconds", self.outbox.poll_seconds as i64),
+            ("outbox.batch_size", self.outbox.batch_size),
+            ("outbox.max_attempts", self.outbox.max_attempts as i64),
+            ("health.timeout_seconds", self.health.timeout_seconds as i64),
+            (
+                "shutdown.timeout_seconds",
+                self.shutdown.timeout_seconds as i64,
+            ),
+        ] {
+            require(value > 0, format!("{} must be at least 1", key));
+        }
+
+        if self.metrics.enabled {
+            require(
+                self.metrics.bind.is_empty() || self.metrics.bind.parse::<SocketAddr>().is_ok(),
+                format!(
+                    "metrics.bind '{}' is not an address like 127.0.0.1:9090",
+                    self.metrics.bind
+                ),
+            );
+            // The API listener is public, metrics would leak to anyone
+            require(
+                !self.metrics.bind.is_empty() || !self.metrics.token.is_empty(),
+                format!(
+                    "metrics.token is required without metrics.bind ({})",
+                    env_var("metrics.token")
+                ),
+            );
+            require(
+                self.metrics.bind != format!("{}:{}", self.host, self.port),
+                format!("metrics.bind must differ from {}:{}", self.host, self.port),
+            );
+        }
+
+        if let Err(e) = EnvFilter::try_new(&self.log.filter) {
+            require(
+                false,
+                format!("log.filter '{}' is invalid: {}", self.log.filter, e),
+            );
+        }
+        require(
+            matches!(self.log.format.as_str(), "json" | "text"),
+            format!("log.format must be json or text, got '{}'", self.log.format),
+        );
+
+        if self.tracing.enabled {
+            require(
+                Url::parse(&self.tracing.endpoint)
+                    .is_ok_and(|url| matches!(url.scheme(), "http" | "https")),
+                format!(
+                    "tracing.endpoint '{}' is not an http(s) URL",
+                    self.tracing.endpoint
+                ),
+            );
+            require(
+                !self.tracing.service_name.is_empty(),
+                "tracing.service_name is required".to_string(),
+            );
+        }
+        require(
+            (0.0..=1.0).contains(&self.tracing.sample_ratio),
+            format!(
+                "tracing.sample_ratio must be between 0 and 1, got {}",
+                self.tracing.sample_ratio
+            ),
+        );
+
+        problems
+    }
+}
+
+/// Every problem found while loading the configuration.
+#[derive(Debug)]
+pub struct ConfigError(pub Vec<String>);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration:")?;
+        for problem in &self.0 {
+            write!(f, "\n  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Where the effective value of a setting came from.
+#[derive(Debug, Clone)]
+pub enum Source {
+    Default,
+    Profile(&'static str),
+    File(PathBuf),
+    Env(String),
+    Flag,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::Profile(name) => write!(f, "profile {}", name),
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Env(name) => write!(f, "${}", name),
+            Source::Flag => write!(f, "--set"),
+        }
+    }
+}
+
+/// Settings merged from defaults, profile, file, environment and flags,
+/// keyed by their dotted path, before they are typed and validated.
+pub struct Settings {
+    values: BTreeMap<String, (Value, Source)>,
+    problems: Vec<String>,
+}
+
+impl Settings {
+    pub fn load(args: &ConfigArgs) -> Self {
         dotenv().ok();
 
-        Config {
-            base_url: env::var("BASE_URL").unwrap_or_else(|_| "http://localhost".to_string()),
-            port: env::var("PORT")
-                .unwrap_or_else(|_| "3000".to_string())
-                .parse()
-                .unwrap_or(3000),
-            env: env::var("ENV").unwrap_or_else(|_| "dev".to_string()),
-            database_url: env::var("DATABASE_URL").unwrap_or_default(),
-            redis_url: env::var("REDIS_URL").unwrap_or_default(),
-            session_secret: env::var("SESSION_SECRET").unwrap_or_default(),
-            oauth: OAuthConfig {
-                google: ProviderConfig {
-                    client_id: env::var("GOOGLE_CLIENT_ID").unwrap_or_default(),
-                    client_secret: env::var("GOOGLE_CLIENT_SECRET").unwrap_or_default(),
-                    redirect_url: env::var("GOOGLE_REDIRECT_URL")
-                        .unwrap_or_else(|_| "".to_string()),
+        Self::from_env(args, |name| env::var(name).ok())
+    }
+
+    /// Like `load`, reading variables from `lookup` instead of the process
+    /// environment.
+    pub fn from_env(args: &ConfigArgs, lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let mut settings = Settings {
+            values: BTreeMap::new(),
+            problems: Vec::new(),
+        };
+        let defaults: Table = DEFAULTS.parse().expect("built-in defaults are valid TOML");
+        flatten("", defaults, &mut |key, value| {
+            settings.values.insert(key, (value, Source::Default));
+        });
+
+        settings.merge_file(args.config.as_deref());
+        settings.merge_env(lookup);
+        for set in &args.overrides {
+            match set.split_once('=') {
+                Some((key, raw)) => settings.set_raw(key.trim(), raw.trim(), Source::Flag),
+                None => settings
+                    .problems
+                    .push(format!("--set {}: expected KEY=VALUE", set)),
+            }
+        }
+        settings.merge_profile();
+
+        settings
+    }
+
+    /// Types and validates the merged settings. Values rejected while
+    /// merging fall back to the layer below, so the remaining checks still
+    /// run and every problem is reported together.
+    pub fn resolve(&self) -> Result<Config, ConfigError> {
+        let mut problems = self.problems.clone();
+
+        let mut tree = Table::new();
+        for (key, (value, _)) in &self.values {
+            insert(&mut tree, key, value.clone());
+        }
+
+        let config = match Config::deserialize(tree) {
+            Ok(config) => {
+                problems.extend(config.validate());
+                Some(config)
+            }
+            Err(e) => {
+                problems.push(e.to_string().trim().replace('\n', " "));
+                None
+            }
+        };
+
+        match config {
+            Some(config) if problems.is_empty() => Ok(config),
+            _ => Err(ConfigError(problems)),
+        }
+    }
+
+    /// Effective settings as TOML, with their source and credentials hidden.
+    pub fn redacted(&self) -> String {
+        self.values
+            .iter()
+            .map(|(key, (value, source))| {
+                format!("{} = {}  # {}\n", key, redact(key, value), source)
+            })
+            .collect()
+    }
+
+    fn merge_file(&mut self, path: Option<&Path>) {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => (PathBuf::from(DEFAULT_FILE), false),
+        };
+        if !required && !path.exists() {
+            return;
+        }
+
+        let table: Table = match fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| content.parse().map_err(|e: toml::de::Error| e.to_string()))
+        {
+            Ok(table) => table,
+            Err(e) => {
+                self.problems.push(format!(
+                    "{}: {}",
+                    path.display(),
+                    e.trim().replace('\n', " ")
+                ));
+                return;
+            }
+        };
+
+        let source = Source::File(path);
+        let mut entries = Vec::new();
+        flatten("", table, &mut |key, value| entries.push((key, value)));
+        for (key, value) in entries {
+            self.set(&key, value, source.clone());
+        }
+    }
+
+    // Selected by the final `env`, so it applies wherever that was set, but
+    // only replaces the built-in defaults
+    fn merge_profile(&mut self) {
+        let (name, source) = match self.values.get("env") {
+            Some((Value::String(name), source)) => (name.clone(), source.clone()),
+            _ => return,
+        };
+        let Some((name, profile)) = PROFILES.iter().find(|(profile, _)| *profile == name) else {
+            let names: Vec<&str> = PROFILES.iter().map(|(profile, _)| *profile).collect();
+            self.problems.push(format!(
+                "env ({}): unknown profile '{}', expected one of {}",
+                source,
+                name,
+                names.join(", ")
+            ));
+            return;
+        };
+
+        let profile: Table = profile.parse().expect("built-in profiles are valid TOML");
+        flatten("", profile, &mut |key, value| {
+            if let Some((current, source @ Source::Default)) = self.values.get_mut(&key) {
+                *current = value;
+                *source = Source::Profile(name);
+            }
+        });
+    }
+
+    fn merge_env(&mut self, lookup: impl Fn(&str) -> Option<String>) {
+        for (name, key) in ENV_VARS {
+            let file_var = format!("{}_FILE", name);
+            // Empty variables, as left by `.env.example`, count as unset
+            let set = |var: &str| lookup(var).filter(|value| !value.is_empty());
+            let (raw, source) = match (set(name), set(&file_var)) {
+                (Some(_), Some(_)) => {
+                    self.problems
+                        .push(format!("${} and ${} are both set", name, file_var));
+                    continue;
+                }
+                (Some(raw), None) => (raw, Source::Env(name.to_string())),
+                (None, Some(path)) => match fs::read_to_string(&path) {
+                    // Files usually end with a newline the secret does not have
+                    Ok(content) => (
+                        content.trim_end_matches(['\r', '\n']).to_string(),
+                        Source::Env(file_var),
+                    ),
+                    Err(e) => {
+                        self.problems
+                            .push(format!("${}: cannot read {}: {}", file_var, path, e));
+                        continue;
+                    }
                 },
-            },
-            smtp: SmtpConfig {
-                server: env::var("SMTP_SERVER").unwrap_or_else(|_| "sandbox.smtp.mailtrap.io".to_string()),
-                port: env::var("SMTP_PORT")
-                    .unwrap_or_else(|_| "2525".to_string())
-                    .parse()
-                    .unwrap_or(2525),
-                username: env::var("SMTP_USERNAME").unwrap_or_else(|_| "your_username".to_string()),
-                password: env::var("SMTP_PASSWORD").unwrap_or_else(|_| "your_password".to_string()),
-                email_from: env::var("SMTP_EMAIL_FROM")
-                    .unwrap_or_else(|_| "noreply@example.com".to_string()),
-                frontend_url: env::var("SMTP_FRONTEND_URL")
-                    .unwrap_or_else(|_| "http://localhost:8080".to_string()),
-                tls_mode: env::var("SMTP_TLS_MODE").unwrap_or_else(|_| "opportunistic".to_string()),
-            },
+                (None, None) => continue,
+            };
+
+            self.set_raw(key, &raw, source);
+        }
+    }
+
+    // Environment variables and flags are strings, typed after the default
+    fn set_raw(&mut self, key: &str, raw: &str, source: Source) {
+        let expected = self.values.get(key).map(|(value, _)| value.type_str());
+        let value = match expected {
+            Some("integer") => raw.parse().map(Value::Integer).ok(),
+            Some("boolean") => raw.parse().map(Value::Boolean).ok(),
+            Some("float") => raw.parse().map(Value::Float).ok(),
+            // Comma-separated
+            Some("array") => Some(Value::Array(
+                raw.split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| Value::String(item.to_string()))
+                    .collect(),
+            )),
+            Some(_) => Some(Value::String(raw.to_string())),
+            None => Some(
+                raw.parse()
+                    .map(Value::Integer)
+                    .or_else(|_| raw.parse().map(Value::Boolean))
+                    .unwrap_or_else(|_| Value::String(raw.to_string())),
+            ),
+        };
+
+        match value {
+            Some(value) => self.set(key, value, source),
+            None => self.problems.push(format!(
+                "{} ({}): expected {}, got '{}'",
+                key,
+                source,
+                expected.unwrap_or_default(),
+                raw
+            )),
         }
     }
+
+    fn set(&mut self, key: &str, value: Value, source: Source) {
+        let expected = match self.values.get(key) {
+            Some((default, _)) => Some(default.type_str()),
+            None if OPTIONAL.contains(&key) => None,
+            None => {
+                self.problems
+                    .push(format!("{} ({}): unknown setting", key, source));
+                return;
+            }
+        };
+
+        if let Some(expected) = expected {
+            if value.type_str() != expected {
+                self.problems.push(format!(
+                    "{} ({}): expected {}, got {}",
+                    key,
+                    source,
+                    expected,
+                    value.type_str()
+                ));
+                return;
+            }
+        }
+        // Every number we take is a size, count or duration
+        if value.as_integer().is_some_and(|number| number < 0) {
+            self.problems
+                .push(format!("{} ({}): must not be negative", key, source));
+            return;
+        }
+
+        self.values.insert(key.to_string(), (value, source));
+    }
+}
+
+fn flatten(prefix: &str, table: Table, visit: &mut impl FnMut(String, Value)) {
+    for (name, value) in table {
+        let key = match prefix {
+            "" => name,
+            _ => format!("{}.{}", prefix, name),
+        };
+        match value {
+            Value::Table(table) => flatten(&key, table, visit),
+            value => visit(key, value),
+        }
+    }
+}
+
+fn insert(tree: &mut Table, key: &str, value: Value) {
+    match key.split_once('.') {
+        Some((section, rest)) => {
+            let child = tree
+                .entry(section)
+                .or_insert_with(|| Value::Table(Table::new()));
+            if let Value::Table(child) = child {
+                insert(child, rest, value);
+            }
+        }
+        None => {
+            tree.insert(key.to_string(), value);
+        }
+    }
+}
+
+fn redact(key: &str, value: &Value) -> Value {
+    let text = value.as_str().unwrap_or_default();
+    if text.is_empty() {
+        return value.clone();
+    }
+
+    if SECRETS.contains(&key) {
+        return Value::String("<redacted>".to_string());
+    }
+    if CREDENTIAL_URLS.contains(&key) {
+        return Value::String(match Url::parse(text) {
+            Ok(mut url) if url.password().is_some() => {
+                let _ = url.set_password(Some("redacted"));
+                url.to_string()
+            }
+            Ok(_) => text.to_string(),
+            Err(_) => "<redacted>".to_string(),
+        });
+    }
+
+    value.clone()
+}
+
+// Names the variable setting `key`, for error messages
+fn env_var(key: &str) -> String {
+    ENV_VARS
+        .iter()
+        .find(|(_, setting)| *setting == key)
+        .map(|(name, _)| format!("${}", name))
+        .unwrap_or_default()
 }
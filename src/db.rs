@@ -1,13 +1,336 @@
+use crate::config::DatabaseConfig;
+use crate::utils::metrics::METRICS;
+use crate::utils::response::ApiError;
+use actix_web::http::StatusCode;
+use actix_web::web;
+use diesel::connection::{Instrumentation, InstrumentationEvent};
+use diesel::migration::MigrationSource;
+use diesel::pg::Pg;
+use diesel::r2d2::{CustomizeConnection, HandleEvent, PoolError};
+use diesel::sql_types::{BigInt, Text};
 use diesel::{
     pg::PgConnection,
     r2d2::{self, ConnectionManager},
 };
+use diesel::{sql_query, Connection, RunQueryDsl};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::error::Error;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::field::Empty;
+use tracing::Span;
+use utoipa::ToSchema;
 
 pub type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 
-pub fn create_connection_pool(database_url: &str) -> DbPool {
+/// Every migration under `migrations/`, built into the binary.
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+// Held while migrating, so instances starting together take turns
+const MIGRATION_LOCK: i64 = 0x5c7_11a;
+
+// Checkouts slower than this had to wait for a connection to free up
+const WAIT_THRESHOLD: Duration = Duration::from_millis(1);
+
+/// Builds the pool, opening the minimum number of connections up front.
+/// Fails when Postgres cannot be reached within the connect timeout.
+pub fn create_connection_pool(
+    database_url: &str,
+    config: &DatabaseConfig,
+    metrics: Arc<PoolMetrics>,
+) -> Result<DbPool, PoolError> {
     let manager = ConnectionManager::<PgConnection>::new(database_url);
+    let max_size = config.max_connections.max(1);
+    let idle_timeout =
+        (config.idle_timeout_seconds > 0).then(|| Duration::from_secs(config.idle_timeout_seconds));
+
     r2d2::Pool::builder()
+        .max_size(max_size)
+        .min_idle(config.min_connections.map(|min| min.min(max_size)))
+        .connection_timeout(Duration::from_secs(config.connect_timeout_seconds.max(1)))
+        .idle_timeout(idle_timeout)
+        .connection_customizer(Box::new(SessionSettings {
+            application_name: config.application_name.clone(),
+            statement_timeout_ms: config.statement_timeout_ms,
+        }))
+        .event_handler(Box::new(MetricsHandler(metrics)))
         .build(manager)
-        .expect("Failed to create database connection pool")
+}
+
+/// Applies every pending migration, each in its own transaction, and
+/// returns their versions.
+pub fn run_migrations(conn: &mut PgConnection) -> Result<Vec<String>, Box<dyn Error>> {
+    with_migration_lock(conn, |conn| {
+        let versions = conn.run_pending_migrations(MIGRATIONS)?;
+
+        Ok(versions.iter().map(|version| version.to_string()).collect())
+    })
+}
+
+/// Reverts the `steps` most recent migrations and returns their versions.
+pub fn revert_migrations(
+    conn: &mut PgConnection,
+    steps: usize,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    with_migration_lock(conn, |conn| {
+        let mut versions = Vec::new();
+        for _ in 0..steps {
+            if conn.applied_migrations()?.is_empty() {
+                break;
+            }
+            versions.push(conn.revert_last_migration(MIGRATIONS)?.to_string());
+        }
+
+        Ok(versions)
+    })
+}
+
+/// Every embedded migration in order, with whether it has been applied.
+pub fn migration_status(conn: &mut PgConnection) -> Result<Vec<(String, bool)>, Box<dyn Error>> {
+    let applied: HashSet<String> = conn
+        .applied_migrations()
+        .map_err(|e| -> Box<dyn Error> { e })?
+        .iter()
+        .map(|version| version.to_string())
+        .collect();
+    let mut migrations =
+        MigrationSource::<Pg>::migrations(&MIGRATIONS).map_err(|e| -> Box<dyn Error> { e })?;
+    migrations.sort_by_key(|migration| migration.name().version().to_string());
+
+    Ok(migrations
+        .iter()
+        .map(|migration| {
+            let name = migration.name().to_string();
+            let applied = applied.contains(&migration.name().version().to_string());
+            (name, applied)
+        })
+        .collect())
+}
+
+fn with_migration_lock<T>(
+    conn: &mut PgConnection,
+    migrate: impl FnOnce(&mut PgConnection) -> diesel::migration::Result<T>,
+) -> Result<T, Box<dyn Error>> {
+    sql_query("SELECT pg_advisory_lock($1)")
+        .bind::<BigInt, _>(MIGRATION_LOCK)
+        .execute(conn)?;
+    let result = migrate(conn);
+    sql_query("SELECT pg_advisory_unlock($1)")
+        .bind::<BigInt, _>(MIGRATION_LOCK)
+        .execute(conn)?;
+
+    result.map_err(|e| -> Box<dyn Error> { e })
+}
+
+// Applied to every new connection, so they show up under our name in
+// `pg_stat_activity` and cannot run away with a query
+#[derive(Debug)]
+struct SessionSettings {
+    application_name: String,
+    statement_timeout_ms: u64,
+}
+
+impl CustomizeConnection<PgConnection, r2d2::Error> for SessionSettings {
+    fn on_acquire(&self, conn: &mut PgConnection) -> Result<(), r2d2::Error> {
+        sql_query(
+            "SELECT set_config('application_name', $1, false), \
+             set_config('statement_timeout', $2, false)",
+        )
+        .bind::<Text, _>(&self.application_name)
+        .bind::<Text, _>(format!("{}ms", self.statement_timeout_ms))
+        .execute(conn)
+        .map_err(r2d2::Error::QueryError)?;
+
+        conn.set_instrumentation(QuerySpans::default());
+        Ok(())
+    }
+}
+
+/// Runs each query in a span, a child of whatever the connection is used
+/// for, named after its statement kind and table.
+#[derive(Default)]
+struct QuerySpans {
+    // Queries do not nest, but a failed start may leave one behind
+    open: Vec<Span>,
+}
+
+impl Instrumentation for QuerySpans {
+    fn on_connection_event(&mut self, event: InstrumentationEvent<'_>) {
+        match event {
+            InstrumentationEvent::StartQuery { query, .. } => {
+                // Binds are left out, they may hold credentials
+                let sql = query.to_string();
+                let sql = match sql.rsplit_once(" -- binds: ") {
+                    Some((statement, _)) => statement.to_string(),
+                    None => sql,
+                };
+                let (kind, table) = describe_query(&sql);
+                let name = match &table {
+                    Some(table) => format!("{} {}", kind, table),
+                    None => kind.to_string(),
+                };
+                self.open.push(tracing::info_span!(
+                    "db.query",
+                    otel.name = %name,
+                    otel.kind = "client",
+                    otel.status_code = Empty,
+                    db.system = "postgresql",
+                    db.operation = kind,
+                    db.sql.table = table,
+                    db.statement = %sql,
+                    error = Empty,
+                ));
+            }
+            InstrumentationEvent::FinishQuery { error, .. } => {
+                if let (Some(span), Some(e)) = (self.open.pop(), error) {
+                    span.record("otel.status_code", "ERROR");
+                    span.record("error", tracing::field::display(e));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Statement kind of `sql` and the table it reads or writes, as far as a
+/// glance at the SQL diesel generates tells, e.g. `("SELECT", "users")`.
+pub fn describe_query(sql: &str) -> (&'static str, Option<String>) {
+    let mut words = sql.split_whitespace();
+    let kind = match words.next().unwrap_or_default().to_uppercase().as_str() {
+        "SELECT" => "SELECT",
+        "INSERT" => "INSERT",
+        "UPDATE" => "UPDATE",
+        "DELETE" => "DELETE",
+        "BEGIN" | "COMMIT" | "ROLLBACK" | "SAVEPOINT" | "RELEASE" => "TRANSACTION",
+        _ => "OTHER",
+    };
+
+    let mut after = |keyword: &str| {
+        words
+            .by_ref()
+            .skip_while(|word| !word.eq_ignore_ascii_case(keyword))
+            .nth(1)
+    };
+    let table = match kind {
+        "SELECT" | "DELETE" => after("FROM"),
+        "INSERT" => after("INTO"),
+        "UPDATE" => words.next(),
+        _ => None,
+    };
+
+    // Subqueries have no name to give
+    let table = table
+        .map(|table| table.trim_end_matches([',', ';', ')']).replace('"', ""))
+        .filter(|table| !table.is_empty() && !table.starts_with('('));
+
+    (kind, table)
+}
+
+/// Checkout counters r2d2 does not keep itself. Shared between the pool
+/// and whatever reports on it.
+#[derive(Debug, Default)]
+pub struct PoolMetrics {
+    checkouts: AtomicU64,
+    waits: AtomicU64,
+    timeouts: AtomicU64,
+    wait_micros: AtomicU64,
+    max_wait_micros: AtomicU64,
+}
+
+impl PoolMetrics {
+    pub fn stats(&self, pool: &DbPool) -> PoolStats {
+        let state = pool.state();
+        let waits = self.waits.load(Ordering::Relaxed);
+        let wait_micros = self.wait_micros.load(Ordering::Relaxed);
+
+        PoolStats {
+            max_size: pool.max_size(),
+            min_idle: pool.min_idle(),
+            connections: state.connections,
+            in_use: state.connections - state.idle_connections,
+            idle: state.idle_connections,
+            checkouts: self.checkouts.load(Ordering::Relaxed),
+            waits,
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            average_wait_ms: if waits > 0 {
+                wait_micros as f64 / waits as f64 / 1000.0
+            } else {
+                0.0
+            },
+            max_wait_ms: self.max_wait_micros.load(Ordering::Relaxed) as f64 / 1000.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct MetricsHandler(Arc<PoolMetrics>);
+
+impl HandleEvent for MetricsHandler {
+    fn handle_checkout(&self, event: r2d2::event::CheckoutEvent) {
+        self.0.checkouts.fetch_add(1, Ordering::Relaxed);
+        METRICS.db_pool_checkouts.inc();
+
+        let waited = event.duration();
+        METRICS.db_pool_wait_duration.observe(waited.as_secs_f64());
+        if waited >= WAIT_THRESHOLD {
+            METRICS.db_pool_waits.inc();
+            let micros = u64::try_from(waited.as_micros()).unwrap_or(u64::MAX);
+            self.0.waits.fetch_add(1, Ordering::Relaxed);
+            self.0.wait_micros.fetch_add(micros, Ordering::Relaxed);
+            self.0.max_wait_micros.fetch_max(micros, Ordering::Relaxed);
+        }
+    }
+
+    fn handle_timeout(&self, _event: r2d2::event::TimeoutEvent) {
+        self.0.timeouts.fetch_add(1, Ordering::Relaxed);
+        METRICS.db_pool_timeouts.inc();
+    }
+}
+
+/// Pool usage since startup, to size `DATABASE_MAX_CONNECTIONS`. Waits are
+/// checkouts that found no idle connection, timeouts the ones that gave up.
+#[derive(Serialize, ToSchema, Debug)]
+pub struct PoolStats {
+    pub max_size: u32,
+    pub min_idle: Option<u32>,
+    pub connections: u32,
+    pub in_use: u32,
+    pub idle: u32,
+    pub checkouts: u64,
+    pub waits: u64,
+    pub timeouts: u64,
+    pub average_wait_ms: f64,
+    pub max_wait_ms: f64,
+}
+
+/// Runs `work` on the blocking thread pool with a pooled connection.
+/// Diesel queries, bcrypt and SMTP are synchronous, and would otherwise
+/// stall the actix worker along with every request it serves. Waiting for
+/// a free connection happens on the blocking pool too.
+pub async fn block<T, F>(pool: &DbPool, work: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut PgConnection) -> Result<T, ApiError> + Send + 'static,
+{
+    let pool = pool.clone();
+    // Queries become children of the caller's span, not of the worker thread
+    let span = Span::current();
+    web::block(move || {
+        let _entered = span.enter();
+
+        // Get DB connection
+        let mut conn = pool.get().map_err(|_| {
+            ApiError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Database connection error".into(),
+            )
+        })?;
+
+        work(&mut conn)
+    })
+    .await
+    .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
 }
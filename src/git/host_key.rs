@@ -0,0 +1,84 @@
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+/// Key types libssh2 can present, as named in `known_hosts`.
+pub const SUPPORTED_KEY_TYPES: &[&str] = &[
+    "ssh-ed25519",
+    "ecdsa-sha2-nistp256",
+    "ecdsa-sha2-nistp384",
+    "ecdsa-sha2-nistp521",
+    "ssh-rsa",
+    "ssh-dss",
+];
+
+/// An SSH host key, encoded as in `known_hosts`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostKey {
+    /// `host` or `[host]:port`
+    pub host: String,
+    pub key_type: String,
+    /// Base64 of the key blob
+    pub public_key: String,
+    /// `SHA256:...`, as printed by `ssh-keygen -l`
+    pub fingerprint: String,
+}
+
+impl HostKey {
+    pub fn new(host: &str, key_type: &str, blob: &[u8]) -> Self {
+        HostKey {
+            host: host.to_lowercase(),
+            key_type: key_type.to_string(),
+            public_key: STANDARD.encode(blob),
+            fingerprint: format!("SHA256:{}", STANDARD_NO_PAD.encode(Sha256::digest(blob))),
+        }
+    }
+
+    /// Parses one `known_hosts` line into a key per listed host. Blank lines
+    /// and comments hold no key.
+    pub fn parse_line(line: &str) -> Result<Vec<HostKey>, String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(Vec::new());
+        }
+        if line.starts_with('@') {
+            return Err("Markers such as @cert-authority are not supported".into());
+        }
+
+        let mut fields = line.split_whitespace();
+        let (Some(hosts), Some(key_type), Some(encoded)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            return Err("Expected `hosts key-type base64-key`".into());
+        };
+
+        if hosts.starts_with('|') {
+            return Err("Hashed host names are not supported".into());
+        }
+        if hosts.contains(['*', '?', '!']) {
+            return Err("Host patterns are not supported".into());
+        }
+        if !SUPPORTED_KEY_TYPES.contains(&key_type) {
+            return Err(format!("Unsupported key type: {}", key_type));
+        }
+
+        let blob = STANDARD
+            .decode(encoded)
+            .map_err(|_| "Key is not valid base64".to_string())?;
+
+        // The blob starts with its own key type as an SSH string
+        let embedded_type = blob
+            .get(..4)
+            .map(|len| u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize)
+            .and_then(|len| blob.get(4..4 + len));
+        if embedded_type != Some(key_type.as_bytes()) {
+            return Err(format!("Key does not match its type {}", key_type));
+        }
+
+        Ok(hosts
+            .split(',')
+            .filter(|host| !host.is_empty())
+            .map(|host| HostKey::new(host, key_type, &blob))
+            .collect())
+    }
+}
@@ -0,0 +1,86 @@
+use crate::git::remote::{callbacks, RemoteAuth, RemoteError};
+use git2::{AutotagOption, FetchOptions, FetchPrune, Repository};
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+
+// Mirror every ref, forcing updates like `git clone --mirror`
+const MIRROR_REFSPEC: &str = "+refs/*:refs/*";
+
+#[derive(Debug)]
+pub struct MirrorStats {
+    pub default_branch: Option<String>,
+    pub size_bytes: u64,
+}
+
+/// Clones `url` as a bare mirror at `path`, or fetches into the existing
+/// mirror, pruning refs deleted on the remote. The transfer is aborted once
+/// `interrupted` returns true, leaving the mirror as it was.
+pub fn sync(
+    url: &str,
+    path: &Path,
+    auth: &RemoteAuth,
+    interrupted: &dyn Fn() -> bool,
+) -> Result<MirrorStats, RemoteError> {
+    let repo = match Repository::open_bare(path) {
+        Ok(repo) => repo,
+        Err(_) => {
+            fs::create_dir_all(path)?;
+            Repository::init_bare(path)?
+        }
+    };
+
+    // Follow URL changes made since the last sync
+    match repo.find_remote("origin") {
+        Ok(remote) if remote.url() == Some(url) => {}
+        Ok(_) => repo.remote_set_url("origin", url)?,
+        Err(_) => {
+            repo.remote_with_fetch("origin", url, MIRROR_REFSPEC)?;
+            repo.config()?.set_bool("remote.origin.mirror", true)?;
+        }
+    }
+
+    let rejected_host_key = RefCell::new(None);
+    let mut remote = repo.find_remote("origin")?;
+    let mut callbacks = callbacks(url, auth, &rejected_host_key);
+    callbacks.transfer_progress(|_| !interrupted());
+    let mut options = FetchOptions::new();
+    options
+        .remote_callbacks(callbacks)
+        .prune(FetchPrune::On)
+        .download_tags(AutotagOption::All);
+
+    if let Err(e) = remote.fetch(&[MIRROR_REFSPEC], Some(&mut options), None) {
+        return Err(rejected_host_key.take().unwrap_or(e.into()));
+    }
+
+    // Point HEAD at the remote default branch
+    let default_branch = remote
+        .default_branch()
+        .ok()
+        .and_then(|head| head.as_str().map(str::to_string));
+    if let Some(head) = &default_branch {
+        repo.set_head(head)?;
+    }
+
+    Ok(MirrorStats {
+        default_branch: default_branch
+            .map(|head| head.trim_start_matches("refs/heads/").to_string()),
+        size_bytes: dir_size(path)?,
+    })
+}
+
+fn dir_size(path: &Path) -> Result<u64, std::io::Error> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+
+    Ok(size)
+}
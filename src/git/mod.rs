@@ -0,0 +1,20 @@
+pub mod host_key;
+pub mod mirror;
+pub mod remote;
+
+use crate::config::GitConfig;
+
+/// Applies the network timeouts shared by every libgit2 transport.
+pub fn init(config: &GitConfig) {
+    let timeout = i32::try_from(config.timeout_seconds.saturating_mul(1000)).unwrap_or(i32::MAX);
+
+    // Called once at startup, before any git operation can run concurrently
+    unsafe {
+        if let Err(e) = git2::opts::set_server_connect_timeout_in_milliseconds(timeout) {
+            tracing::warn!("Failed to set git connect timeout: {}", e);
+        }
+        if let Err(e) = git2::opts::set_server_timeout_in_milliseconds(timeout) {
+            tracing::warn!("Failed to set git server timeout: {}", e);
+        }
+    }
+}
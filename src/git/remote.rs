@@ -0,0 +1,215 @@
+use crate::git::host_key::HostKey;
+use crate::utils::git_url::GitUrl;
+use actix_web::web;
+use git2::{CertificateCheckStatus, Cred, CredentialType, Direction, Remote, RemoteCallbacks};
+use std::cell::RefCell;
+use std::fmt;
+use std::time::Duration;
+use zeroize::Zeroizing;
+
+// libgit2 keeps asking for credentials as long as the callback returns some
+const MAX_CREDENTIAL_ATTEMPTS: u32 = 3;
+
+/// Refs advertised by a remote, as `git ls-remote` would list them.
+#[derive(Debug)]
+pub struct RemoteRefs {
+    pub default_branch: Option<String>,
+    pub ref_count: usize,
+}
+
+/// What we present to the remote and what we expect from it over SSH.
+#[derive(Default)]
+pub struct RemoteAuth {
+    /// Deploy private keys in OpenSSH format, tried in order
+    pub private_keys: Vec<Zeroizing<String>>,
+    /// Trusted keys of the remote host
+    pub host_keys: Vec<HostKey>,
+}
+
+#[derive(Debug)]
+pub enum RemoteError {
+    /// The host presented a key nobody confirmed yet
+    UnknownHostKey(HostKey),
+    /// The host presented a key other than the trusted one of that type
+    ChangedHostKey(HostKey),
+    TimedOut(Duration),
+    Git(String),
+}
+
+impl fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemoteError::UnknownHostKey(key) => write!(
+                f,
+                "Unknown {} host key {} for {}, confirm it to connect",
+                key.key_type, key.fingerprint, key.host
+            ),
+            RemoteError::ChangedHostKey(key) => write!(
+                f,
+                "Host key for {} changed to {} {}, connection blocked",
+                key.host, key.key_type, key.fingerprint
+            ),
+            RemoteError::TimedOut(timeout) => {
+                write!(f, "Timed out after {} seconds", timeout.as_secs())
+            }
+            RemoteError::Git(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<git2::Error> for RemoteError {
+    fn from(e: git2::Error) -> Self {
+        RemoteError::Git(e.message().to_string())
+    }
+}
+
+impl From<std::io::Error> for RemoteError {
+    fn from(e: std::io::Error) -> Self {
+        RemoteError::Git(e.to_string())
+    }
+}
+
+/// Connects to `url` for fetching and reads its ref advertisement.
+/// Works over HTTP(S), SSH and `file://`. SSH hosts must present one of
+/// the trusted host keys; clients authenticate with the deploy keys in
+/// order, or the SSH agent when there are none.
+pub fn ls_remote(url: &str, auth: &RemoteAuth) -> Result<RemoteRefs, RemoteError> {
+    let rejected_host_key = RefCell::new(None);
+
+    let mut remote = Remote::create_detached(url)?;
+    let connection = match remote.connect_auth(
+        Direction::Fetch,
+        Some(callbacks(url, auth, &rejected_host_key)),
+        None,
+    ) {
+        Ok(connection) => connection,
+        Err(e) => return Err(rejected_host_key.take().unwrap_or(e.into())),
+    };
+
+    let ref_count = connection
+        .list()?
+        .iter()
+        .filter(|head| head.name() != "HEAD" && !head.name().ends_with("^{}"))
+        .count();
+
+    let default_branch = connection.default_branch().ok().and_then(|head| {
+        head.as_str()
+            .map(|name| name.trim_start_matches("refs/heads/").to_string())
+    });
+
+    Ok(RemoteRefs {
+        default_branch,
+        ref_count,
+    })
+}
+
+/// Runs [`ls_remote`] on the blocking pool and gives up after `timeout`.
+pub async fn check(
+    url: String,
+    auth: RemoteAuth,
+    timeout: Duration,
+) -> Result<RemoteRefs, RemoteError> {
+    let advertisement = web::block(move || ls_remote(&url, &auth));
+
+    // The blocking thread itself is bounded by the libgit2 server timeouts
+    match actix_web::rt::time::timeout(timeout, advertisement).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => Err(RemoteError::Git(e.to_string())),
+        Err(_) => Err(RemoteError::TimedOut(timeout)),
+    }
+}
+
+/// Accepts `seen` only if it is one of the trusted keys.
+pub fn verify_host_key(trusted: &[HostKey], seen: HostKey) -> Result<(), RemoteError> {
+    let same_type = trusted
+        .iter()
+        .filter(|key| key.host == seen.host && key.key_type == seen.key_type)
+        .collect::<Vec<_>>();
+
+    if same_type
+        .iter()
+        .any(|key| key.public_key == seen.public_key)
+    {
+        Ok(())
+    } else if same_type.is_empty() {
+        Err(RemoteError::UnknownHostKey(seen))
+    } else {
+        Err(RemoteError::ChangedHostKey(seen))
+    }
+}
+
+/// Answers a credential request of the remote, with the deploy key at
+/// `next_key` when it asks for one. Repositories without deploy keys get
+/// none: the server's own SSH agent and keys are never offered.
+pub fn credential(
+    auth: &RemoteAuth,
+    username: Option<&str>,
+    allowed: CredentialType,
+    next_key: &mut usize,
+) -> Result<Cred, git2::Error> {
+    let username = username.unwrap_or("git");
+    if allowed.contains(CredentialType::USERNAME) {
+        Cred::username(username)
+    } else if allowed.contains(CredentialType::SSH_KEY) && auth.private_keys.is_empty() {
+        Err(git2::Error::from_str("Repository has no deploy key"))
+    } else if allowed.contains(CredentialType::SSH_KEY) {
+        // Try the next deploy key after each rejection
+        let key = auth
+            .private_keys
+            .get(*next_key)
+            .ok_or_else(|| git2::Error::from_str("No deploy key was accepted"))?;
+        *next_key += 1;
+        Cred::ssh_key_from_memory(username, None, key, None)
+    } else {
+        Err(git2::Error::from_str("Remote requires credentials"))
+    }
+}
+
+/// Authenticates with `auth` and verifies SSH host keys against it. A
+/// refused host key is left in `rejected_host_key`, as libgit2 only passes
+/// a message back.
+pub(crate) fn callbacks<'a>(
+    url: &str,
+    auth: &'a RemoteAuth,
+    rejected_host_key: &'a RefCell<Option<RemoteError>>,
+) -> RemoteCallbacks<'a> {
+    let known_host = GitUrl::parse(url).ok().and_then(|url| url.known_host());
+    let mut callbacks = RemoteCallbacks::new();
+    let mut attempts = 0;
+    let mut next_key = 0;
+
+    callbacks.credentials(move |_url, username, allowed| {
+        attempts += 1;
+        if attempts > MAX_CREDENTIAL_ATTEMPTS + auth.private_keys.len() as u32 {
+            return Err(git2::Error::from_str("Authentication failed"));
+        }
+
+        credential(auth, username, allowed, &mut next_key)
+    });
+
+    callbacks.certificate_check(move |cert, _hostname| {
+        // TLS certificates go through the regular verification
+        let Some(hostkey) = cert.as_hostkey() else {
+            return Ok(CertificateCheckStatus::CertificatePassthrough);
+        };
+
+        let (Some(host), Some(key_type), Some(blob)) = (
+            known_host.as_deref(),
+            hostkey.hostkey_type(),
+            hostkey.hostkey(),
+        ) else {
+            return Err(git2::Error::from_str("Could not read the SSH host key"));
+        };
+
+        match verify_host_key(&auth.host_keys, HostKey::new(host, key_type.name(), blob)) {
+            Ok(()) => Ok(CertificateCheckStatus::CertificateOk),
+            Err(e) => {
+                let message = e.to_string();
+                rejected_host_key.replace(Some(e));
+                Err(git2::Error::from_str(&message))
+            }
+        }
+    });
+
+    callbacks
+}
@@ -0,0 +1,206 @@
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::git;
+use crate::git::remote::RemoteError;
+use crate::models::{Repo, RepositoryMirror};
+use crate::modules::mirror::service::MirrorService;
+use crate::modules::repo::service::RepoService;
+use crate::utils::envelope::MasterKey;
+use crate::utils::shutdown::Shutdown;
+use actix_web::rt::time;
+use actix_web::web;
+use diesel::PgConnection;
+use std::error::Error;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Starts the scheduled mirror syncs, unless mirroring is disabled.
+pub fn spawn(pool: DbPool, config: Config, shutdown: &Shutdown) {
+    if !config.mirror.enabled {
+        tracing::info!("Repository mirroring is disabled");
+        return;
+    }
+
+    shutdown.spawn(run(pool, config, shutdown.clone()));
+}
+
+/// Claims the mirror of `repo` and syncs it in the background. Returns
+/// `None` when a sync is already running for it. Can be called from the
+/// blocking pool.
+pub fn trigger(
+    conn: &mut PgConnection,
+    pool: &DbPool,
+    config: &Config,
+    shutdown: &Shutdown,
+    repo: &Repo,
+) -> Result<Option<RepositoryMirror>, Box<dyn Error>> {
+    let claimed = MirrorService::claim(conn, repo.id, &config.mirror.storage_dir)?;
+    if let Some(mirror) = &claimed {
+        shutdown.spawn(sync(
+            pool.clone(),
+            config.clone(),
+            shutdown.clone(),
+            repo.id,
+            mirror.path.clone(),
+        ));
+    }
+
+    Ok(claimed)
+}
+
+async fn run(pool: DbPool, config: Config, shutdown: Shutdown) {
+    let mut interval = time::interval(Duration::from_secs(config.mirror.poll_seconds.max(1)));
+
+    while shutdown.tick(&mut interval).await {
+        let claim_pool = pool.clone();
+        let mirror = config.mirror.clone();
+        let claimed = web::block(move || {
+            let mut conn = claim_pool.get().map_err(|e| e.to_string())?;
+            let due =
+                MirrorService::get_due(&mut conn, mirror.batch_size).map_err(|e| e.to_string())?;
+
+            let mut claimed = Vec::with_capacity(due.len());
+            for repo_id in due {
+                match MirrorService::claim(&mut conn, repo_id, &mirror.storage_dir) {
+                    Ok(Some(claim)) => claimed.push((repo_id, claim.path)),
+                    Ok(None) => {}
+                    Err(e) => tracing::error!("Failed to claim mirror of {}: {}", repo_id, e),
+                }
+            }
+
+            Ok::<_, String>(claimed)
+        })
+        .await;
+
+        let claimed = match claimed {
+            Ok(Ok(claimed)) => claimed,
+            Ok(Err(e)) => {
+                tracing::error!("Failed to find repositories to mirror: {}", e);
+                continue;
+            }
+            Err(e) => {
+                tracing::error!("Mirror scheduling was cancelled: {}", e);
+                continue;
+            }
+        };
+
+        // One at a time, to keep the load on remotes and disk predictable
+        let mut claimed = claimed.into_iter();
+        for (repo_id, path) in claimed.by_ref() {
+            sync(
+                pool.clone(),
+                config.clone(),
+                shutdown.clone(),
+                repo_id,
+                path,
+            )
+            .await;
+            if shutdown.has_begun() {
+                break;
+            }
+        }
+
+        // The rest of the batch is left to the next instance
+        let unstarted: Vec<Uuid> = claimed.map(|(repo_id, _)| repo_id).collect();
+        if !unstarted.is_empty() {
+            release(pool.clone(), unstarted).await;
+        }
+    }
+}
+
+async fn release(pool: DbPool, repo_ids: Vec<Uuid>) {
+    let result = web::block(move || {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        MirrorService::release(&mut conn, &repo_ids).map_err(|e| e.to_string())
+    })
+    .await;
+
+    match result {
+        Ok(Ok(count)) => tracing::info!("Released {} mirror sync(s) for shutdown", count),
+        Ok(Err(e)) => tracing::error!("Failed to release mirror syncs: {}", e),
+        Err(e) => tracing::error!("Releasing mirror syncs was cancelled: {}", e),
+    }
+}
+
+// Repository of a claimed mirror. The claim is released when it cannot be
+// loaded, e.g. deleted since, rather than left until it goes stale.
+fn claimed_repo(conn: &mut PgConnection, repo_id: Uuid) -> Result<Repo, String> {
+    RepoService::get_by_id(conn, repo_id).map_err(|e| {
+        if let Err(e) = MirrorService::release(conn, &[repo_id]) {
+            tracing::error!("Failed to release mirror sync of {}: {}", repo_id, e);
+        }
+        e.to_string()
+    })
+}
+
+async fn sync(pool: DbPool, config: Config, shutdown: Shutdown, repo_id: Uuid, path: String) {
+    let result = web::block(move || {
+        let started = Instant::now();
+
+        // Release the connection while fetching
+        let (repo, auth) = {
+            let mut conn = pool.get().map_err(|e| e.to_string())?;
+            let repo = claimed_repo(&mut conn, repo_id)?;
+            let master_key = MasterKey::from_base64(&config.deploy_keys.master_key).ok();
+            match RepoService::remote_auth(&mut conn, &repo, master_key.as_ref()) {
+                Ok(auth) => (repo, auth),
+                // Recorded as a failed sync, shown on the mirror and retried
+                // on schedule
+                Err(e) => {
+                    let outcome = Err(RemoteError::Git(format!(
+                        "Failed to load remote credentials: {}",
+                        e
+                    )));
+                    return MirrorService::record_sync(
+                        &mut conn,
+                        &repo,
+                        outcome,
+                        started.elapsed(),
+                        config.mirror.interval_minutes,
+                    )
+                    .map(Some)
+                    .map_err(|e| e.to_string());
+                }
+            }
+        };
+
+        let outcome = git::mirror::sync(&repo.url, Path::new(&path), &auth, &|| {
+            shutdown.is_overdue()
+        });
+
+        // Not a failure of the remote, the next instance starts over
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        if outcome.is_err() && shutdown.is_overdue() {
+            MirrorService::release(&mut conn, &[repo_id]).map_err(|e| e.to_string())?;
+            return Ok(None);
+        }
+
+        // The interval may have changed during the fetch
+        let repo = claimed_repo(&mut conn, repo_id)?;
+        MirrorService::record_sync(
+            &mut conn,
+            &repo,
+            outcome,
+            started.elapsed(),
+            config.mirror.interval_minutes,
+        )
+        .map(Some)
+        .map_err(|e| e.to_string())
+    })
+    .await;
+
+    match result {
+        Ok(Ok(None)) => tracing::warn!("Mirror sync of {} was interrupted by shutdown", repo_id),
+        Ok(Ok(Some(mirror))) => match &mirror.last_error {
+            Some(e) => tracing::warn!("Mirror sync of {} failed: {}", repo_id, e),
+            None => tracing::info!(
+                "Mirrored {} in {} ms",
+                repo_id,
+                mirror.last_duration_ms.unwrap_or_default()
+            ),
+        },
+        Ok(Err(e)) => tracing::error!("Mirror sync of {} failed: {}", repo_id, e),
+        Err(e) => tracing::error!("Mirror sync of {} was cancelled: {}", repo_id, e),
+    }
+}
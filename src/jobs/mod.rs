@@ -0,0 +1,4 @@
+pub mod mirror;
+pub mod outbox;
+pub mod purge;
+pub mod webhook;
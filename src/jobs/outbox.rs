@@ -0,0 +1,39 @@
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::modules::outbox::service::OutboxService;
+use crate::utils::shutdown::Shutdown;
+use actix_web::rt::time;
+use actix_web::web;
+use std::time::Duration;
+
+/// Starts dispatching outbox events to their subscribers, unless disabled.
+pub fn spawn(pool: DbPool, config: Config, shutdown: &Shutdown) {
+    if !config.outbox.enabled {
+        tracing::info!("Outbox dispatch is disabled");
+        return;
+    }
+
+    shutdown.spawn(run(pool, config, shutdown.clone()));
+}
+
+async fn run(pool: DbPool, config: Config, shutdown: Shutdown) {
+    let mut interval = time::interval(Duration::from_secs(config.outbox.poll_seconds.max(1)));
+
+    while shutdown.tick(&mut interval).await {
+        let pool = pool.clone();
+        let outbox = config.outbox.clone();
+        let smtp = config.smtp.clone();
+        let result = web::block(move || {
+            let mut conn = pool.get().map_err(|e| e.to_string())?;
+            OutboxService::dispatch(&mut conn, &outbox, &smtp).map_err(|e| e.to_string())
+        })
+        .await;
+
+        match result {
+            Ok(Ok(count)) if count > 0 => tracing::debug!("Dispatched {} outbox event(s)", count),
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => tracing::error!("Outbox dispatch failed: {}", e),
+            Err(e) => tracing::error!("Outbox dispatch was cancelled: {}", e),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::config::PurgeConfig;
+use crate::db::DbPool;
+use crate::modules::admin::dto::PurgeReport;
+use crate::modules::admin::service::AdminService;
+use crate::utils::shutdown::Shutdown;
+use actix_web::rt::time;
+use actix_web::web;
+use std::time::Duration;
+
+/// Starts the periodic hard purge of soft-deleted data, unless disabled.
+pub fn spawn(pool: DbPool, config: PurgeConfig, shutdown: &Shutdown) {
+    if !config.enabled {
+        tracing::info!("Scheduled purge is disabled");
+        return;
+    }
+
+    shutdown.spawn(run(pool, config, shutdown.clone()));
+}
+
+async fn run(pool: DbPool, config: PurgeConfig, shutdown: Shutdown) {
+    let mut interval = time::interval(Duration::from_secs(config.interval_minutes.max(1) * 60));
+
+    while shutdown.tick(&mut interval).await {
+        let pool = pool.clone();
+        let purge = config.clone();
+        let result = web::block(move || {
+            let mut conn = pool.get().map_err(|e| e.to_string())?;
+            AdminService::purge(
+                &mut conn,
+                purge.retention_days,
+                purge.batch_size,
+                purge.dry_run,
+            )
+            .map_err(|e| e.to_string())
+        })
+        .await;
+
+        match result {
+            Ok(Ok(report)) => log_report(&report),
+            Ok(Err(e)) => tracing::error!("Scheduled purge failed: {}", e),
+            Err(e) => tracing::error!("Scheduled purge was cancelled: {}", e),
+        }
+    }
+}
+
+fn log_report(report: &PurgeReport) {
+    for table in &report.tables {
+        if report.dry_run {
+            tracing::info!(
+                "Purge dry run: {} row(s) would be removed from {}: {:?}",
+                table.count,
+                table.table,
+                table.ids
+            );
+        } else if table.count > 0 {
+            tracing::info!("Purged {} row(s) from {}", table.count, table.table);
+        }
+    }
+}
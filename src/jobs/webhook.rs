@@ -0,0 +1,254 @@
+use crate::config::{Config, WebhookConfig};
+use crate::db::DbPool;
+use crate::modules::webhook::dto::{AttemptResult, DeliveryStatus, OutgoingDelivery};
+use crate::modules::webhook::service::{
+    WebhookService, DELIVERY_HEADER, EVENT_HEADER, EXCERPT_BYTES, SIGNATURE_HEADER,
+    TIMESTAMP_HEADER,
+};
+use crate::utils::envelope::MasterKey;
+use crate::utils::network::{self, PublicResolver};
+use crate::utils::shutdown::Shutdown;
+use crate::utils::telemetry;
+use actix_web::rt::time;
+use actix_web::web;
+use chrono::Utc;
+use reqwest::header::HeaderMap;
+use reqwest::redirect::Policy;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::field::Empty;
+use tracing::Span;
+use url::Url;
+
+/// Starts sending queued organization webhook deliveries, unless
+/// webhooks are disabled.
+pub fn spawn(pool: DbPool, config: Config, shutdown: &Shutdown) {
+    if !config.webhooks.enabled {
+        tracing::info!("Outbound webhooks are disabled");
+        return;
+    }
+
+    let master_key = match MasterKey::from_base64(&config.deploy_keys.master_key) {
+        Ok(master_key) => master_key,
+        Err(e) => {
+            tracing::warn!("Outbound webhooks are disabled, no master key: {}", e);
+            return;
+        }
+    };
+
+    // Receivers are only called at the address they were registered with
+    let mut builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.webhooks.timeout_seconds.max(1)))
+        .user_agent(concat!("scylla-webhooks/", env!("CARGO_PKG_VERSION")))
+        .redirect(Policy::none());
+    if !config.webhooks.allow_private_networks {
+        builder = builder.dns_resolver(Arc::new(PublicResolver));
+    }
+
+    let client = match builder.build() {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!("Failed to build webhook HTTP client: {}", e);
+            return;
+        }
+    };
+
+    shutdown.spawn(run(
+        pool,
+        config.webhooks,
+        master_key,
+        client,
+        shutdown.clone(),
+    ));
+}
+
+async fn run(
+    pool: DbPool,
+    config: WebhookConfig,
+    master_key: MasterKey,
+    client: reqwest::Client,
+    shutdown: Shutdown,
+) {
+    let master_key = std::sync::Arc::new(master_key);
+    let mut interval = time::interval(Duration::from_secs(config.poll_seconds.max(1)));
+
+    while shutdown.tick(&mut interval).await {
+        let claim_pool = pool.clone();
+        let claim_config = config.clone();
+        let claim_key = master_key.clone();
+        let claimed = web::block(move || {
+            let mut conn = claim_pool.get().map_err(|e| e.to_string())?;
+            WebhookService::claim(&mut conn, &claim_key, &claim_config).map_err(|e| e.to_string())
+        })
+        .await;
+
+        let claimed = match claimed {
+            Ok(Ok(claimed)) => claimed,
+            Ok(Err(e)) => {
+                tracing::error!("Failed to claim webhook deliveries: {}", e);
+                continue;
+            }
+            Err(e) => {
+                tracing::error!("Webhook scheduling was cancelled: {}", e);
+                continue;
+            }
+        };
+
+        // Receivers are independent, so deliveries go out concurrently.
+        // Claimed deliveries are all sent, even once shutdown has begun.
+        for delivery in claimed {
+            shutdown.spawn(send(pool.clone(), config.clone(), client.clone(), delivery));
+        }
+    }
+}
+
+#[tracing::instrument(
+    name = "webhook.send",
+    skip_all,
+    fields(delivery_id = %delivery.id, event_type = %delivery.event_type)
+)]
+async fn send(
+    pool: DbPool,
+    config: WebhookConfig,
+    client: reqwest::Client,
+    delivery: OutgoingDelivery,
+) {
+    let result = deliver(&client, &delivery, config.allow_private_networks).await;
+
+    let id = delivery.id;
+    let span = Span::current();
+    let recorded = web::block(move || {
+        let _entered = span.enter();
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        WebhookService::record_attempt(&mut conn, &delivery, result, &config)
+            .map_err(|e| e.to_string())
+    })
+    .await;
+
+    match recorded {
+        Ok(Ok(delivery)) if delivery.status == DeliveryStatus::Failed.as_str() => {
+            tracing::warn!(
+                "Webhook delivery {} failed after {} attempts",
+                delivery.id,
+                delivery.attempts
+            );
+        }
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => tracing::error!("Failed to record webhook delivery {}: {}", id, e),
+        Err(e) => tracing::error!("Recording webhook delivery {} was cancelled: {}", id, e),
+    }
+}
+
+/// Signs and sends one delivery. The status and next attempt are left to
+/// `WebhookService::record_attempt`.
+#[tracing::instrument(
+    name = "webhook.deliver",
+    skip_all,
+    fields(
+        otel.kind = "client",
+        otel.status_code = Empty,
+        http.request.method = "POST",
+        server.address = Url::parse(&delivery.url).ok().and_then(|url| url.host_str().map(str::to_string)),
+        http.response.status_code = Empty,
+    )
+)]
+async fn deliver(
+    client: &reqwest::Client,
+    delivery: &OutgoingDelivery,
+    allow_private_networks: bool,
+) -> AttemptResult {
+    let now = Utc::now();
+    let timestamp = now.timestamp();
+    let signature = WebhookService::sign(&delivery.secret, timestamp, &delivery.payload);
+
+    let headers = [
+        ("Content-Type", "application/json".to_string()),
+        (EVENT_HEADER, delivery.event_type.clone()),
+        (DELIVERY_HEADER, delivery.id.to_string()),
+        (TIMESTAMP_HEADER, timestamp.to_string()),
+        (SIGNATURE_HEADER, format!("sha256={}", signature)),
+    ];
+
+    let mut request_excerpt = format!("POST {}\n", delivery.url);
+    for (name, value) in &headers {
+        request_excerpt.push_str(&format!("{}: {}\n", name, value));
+    }
+    request_excerpt.push('\n');
+    request_excerpt.push_str(&WebhookService::excerpt(delivery.payload.as_bytes()));
+
+    // Receivers can continue the trace, it is left out of the excerpt
+    let mut trace_headers = HeaderMap::new();
+    telemetry::propagate(&mut trace_headers);
+    let mut request = client
+        .post(&delivery.url)
+        .headers(trace_headers)
+        .body(delivery.payload.clone());
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+
+    // Host names are checked by the client's resolver
+    let allowed = match allow_private_networks {
+        true => Ok(()),
+        false => network::ensure_public_url(&delivery.url),
+    };
+
+    let started = Instant::now();
+    let sent = match allowed {
+        Ok(()) => request.send().await.map_err(|e| describe(&e)),
+        Err(e) => Err(format!("Refused to call {}: {}", delivery.url, e)),
+    };
+    let (response_status, response_excerpt, error) = match sent {
+        Ok(mut response) => {
+            let status = response.status().as_u16() as i32;
+
+            // Only the start of the body is kept
+            let mut body = Vec::new();
+            let mut error = None;
+            while body.len() < EXCERPT_BYTES {
+                match response.chunk().await {
+                    Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+                    Ok(None) => break,
+                    Err(e) => {
+                        error = Some(format!("Failed to read response: {}", e));
+                        break;
+                    }
+                }
+            }
+
+            (Some(status), Some(WebhookService::excerpt(&body)), error)
+        }
+        Err(e) => (None, None, Some(e)),
+    };
+
+    let span = Span::current();
+    if let Some(status) = response_status {
+        span.record("http.response.status_code", status);
+    }
+    if error.is_some() || !response_status.is_some_and(|status| (200..300).contains(&status)) {
+        span.record("otel.status_code", "ERROR");
+    }
+
+    AttemptResult {
+        status: DeliveryStatus::Pending.to_string(),
+        next_attempt_at: None,
+        last_attempt_at: Some(now),
+        request_excerpt: Some(request_excerpt),
+        response_status,
+        response_excerpt,
+        error,
+        duration_ms: Some(started.elapsed().as_millis() as i64),
+    }
+}
+
+// Keeps the cause, such as a refused address, that reqwest wraps
+fn describe(error: &dyn std::error::Error) -> String {
+    let mut description = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        description.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+
+    description
+}
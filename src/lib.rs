@@ -0,0 +1,9 @@
+pub mod config;
+pub mod db;
+pub mod git;
+pub mod jobs;
+pub mod models;
+pub mod modules;
+pub mod routes;
+pub mod schema;
+pub mod utils;
@@ -1,91 +1,250 @@
-pub mod config;
-pub mod db;
-pub mod models;
-pub mod modules;
-pub mod routes;
-pub mod schema;
-pub mod utils;
-
-use actix_cors::Cors;
 use actix_identity::IdentityMiddleware;
+use actix_session::config::{BrowserSession, PersistentSession, SessionLifecycle};
 use actix_session::{storage::RedisSessionStore, SessionMiddleware};
 use actix_web::{
-    cookie::{Key, SameSite},
-    middleware::{Logger, NormalizePath, TrailingSlash},
+    cookie::{time::Duration as CookieDuration, Key},
+    middleware::{from_fn, NormalizePath, TrailingSlash},
+    rt::{self, time},
     web, App, HttpResponse, HttpServer,
 };
+use clap::Parser;
+use diesel::{Connection, PgConnection};
 use dotenv::dotenv;
-use env_logger::Env;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
 // use utoipa_swagger_ui::{SwaggerUi, Url};
 
-use crate::config::Config;
-use crate::db::create_connection_pool;
-use crate::routes::config_routes;
+use actix_poc_scylla::config::{Config, ConfigArgs, CookieConfig};
+use actix_poc_scylla::db::{self, create_connection_pool, PoolMetrics};
+use actix_poc_scylla::modules::health::service::ReadinessCache;
+use actix_poc_scylla::modules::metrics::routes as metrics_routes;
+use actix_poc_scylla::routes::config_routes;
+use actix_poc_scylla::utils::retry::with_backoff;
+use actix_poc_scylla::utils::shutdown::Shutdown;
+use actix_poc_scylla::utils::{cors, metrics, request_id, telemetry};
+use actix_poc_scylla::{git, jobs};
+
+#[derive(Parser)]
+#[command(version, about = "Scylla API server")]
+struct Args {
+    /// Apply pending database migrations before serving requests
+    #[arg(long, env = "MIGRATE_ON_START")]
+    migrate_on_start: bool,
+
+    #[command(flatten)]
+    config: ConfigArgs,
+}
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // Load environment variables from .env file
     dotenv().ok();
 
-    // Initialize logger
-    env_logger::init_from_env(Env::default().default_filter_or("info"));
+    let args = Args::parse();
+
+    // Load configuration, refusing to start on any problem
+    let config = match Config::load(&args.config) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
-    // Load configuration
-    let config = Config::load();
+    // Initialize logging and span export
+    let telemetry = telemetry::init(&config.log, &config.tracing)
+        .map_err(|e| io::Error::other(format!("Failed to set up telemetry: {}", e)))?;
 
-    let port = config.port;
+    let bind = (config.host.clone(), config.port);
+
+    // Set up database connection pool, waiting for Postgres to come up
+    let pool_metrics = Arc::new(PoolMetrics::default());
+    let db_pool = with_backoff("Postgres", &config.startup, || async {
+        create_connection_pool(&config.database_url, &config.database, pool_metrics.clone())
+    })
+    .await
+    .map_err(|e| io::Error::other(format!("Failed to connect to Postgres: {}", e)))?;
 
-    // Set up database connection pool
-    let db_pool = create_connection_pool(&config.database_url);
+    // Migrate on a dedicated connection, free of the pool's statement timeout
+    if args.migrate_on_start {
+        let applied = PgConnection::establish(&config.database_url)
+            .map_err(|e| e.to_string())
+            .and_then(|mut conn| db::run_migrations(&mut conn).map_err(|e| e.to_string()))
+            .map_err(|e| io::Error::other(format!("Failed to run migrations: {}", e)))?;
+        tracing::info!("Applied {} pending migrations", applied.len());
+    }
 
     // Set up redis store for sessions
-    let redis_store = RedisSessionStore::new(config.redis_url.clone())
+    let redis_timeout = Duration::from_secs(config.redis.connect_timeout_seconds);
+    let redis_store = with_backoff("Redis", &config.startup, || async {
+        match time::timeout(
+            redis_timeout,
+            RedisSessionStore::new(config.redis_url.clone()),
+        )
         .await
-        .expect("Failed to connect to Redis");
+        {
+            Ok(store) => store.map_err(|e| e.to_string()),
+            Err(_) => Err(format!("timed out after {:?}", redis_timeout)),
+        }
+    })
+    .await
+    .map_err(|e| io::Error::other(format!("Failed to connect to Redis: {}", e)))?;
+
+    // Separate client for readiness checks, the session store keeps its own
+    let redis_client = redis::Client::open(config.redis_url.as_str())
+        .map_err(|e| io::Error::other(format!("Invalid Redis URL: {}", e)))?;
+    let readiness_cache = web::Data::new(ReadinessCache::new(Duration::from_secs(
+        config.health.cache_seconds,
+    )));
+
+    // Configure git network timeouts
+    git::init(&config.git);
+
+    // Coordinates the shutdown of the server and background tasks
+    let shutdown = Shutdown::new(Duration::from_secs(config.shutdown.timeout_seconds));
+
+    // Schedule the hard purge of soft-deleted data
+    jobs::purge::spawn(db_pool.clone(), config.purge.clone(), &shutdown);
+
+    // Schedule repository mirror syncs
+    jobs::mirror::spawn(db_pool.clone(), config.clone(), &shutdown);
+
+    // Dispatch domain events to in-process subscribers
+    jobs::outbox::spawn(db_pool.clone(), config.clone(), &shutdown);
+
+    // Send queued organization webhook deliveries
+    jobs::webhook::spawn(db_pool.clone(), config.clone(), &shutdown);
+
+    // Serve metrics on their own listener, unless they share the API one
+    let metrics_server = match config.metrics.bind.as_str() {
+        bind if config.metrics.enabled && !bind.is_empty() => {
+            let db_pool = db_pool.clone();
+            let config = config.clone();
+            let server = HttpServer::new(move || {
+                App::new()
+                    .app_data(web::Data::new(db_pool.clone()))
+                    .app_data(web::Data::new(config.clone()))
+                    .configure(metrics_routes::config_routes)
+            })
+            .workers(1)
+            .disable_signals()
+            .bind(bind)?
+            .run();
+            tracing::info!("Serving metrics at http://{}/metrics", bind);
+            Some(server)
+        }
+        _ => None,
+    };
+    let metrics_on_api = config.metrics.enabled && metrics_server.is_none();
+    let metrics_handle = metrics_server.map(|server| {
+        let handle = server.handle();
+        rt::spawn(server);
+        handle
+    });
 
     // Secret key for session
     let secret_key = Key::from(config.session_secret.as_bytes());
 
-    log::info!("Starting server at http://0.0.0.0:{}", config.port);
+    tracing::info!(
+        "Starting server at http://{}:{} with the {} profile",
+        config.host,
+        config.port,
+        config.env
+    );
 
-    // Start HTTP server
-    HttpServer::new(move || {
-        let cors = Cors::default()
-            .allowed_origin("http://localhost:3000")
-            .allowed_methods(vec!["GET", "POST", "PUT", "DELETE", "OPTIONS"])
-            .allowed_headers(vec!["Authorization", "Content-Type", "Accept"])
-            .supports_credentials()
-            .max_age(3600);
+    // The server gets its own handles, the pool is closed once it stopped
+    let server_pool = db_pool.clone();
+    let server_shutdown = web::Data::new(shutdown.clone());
+    let shutdown_timeout = config.shutdown.timeout_seconds;
 
+    // Start HTTP server
+    let server = HttpServer::new(move || {
         App::new()
-            // Enable logger
-            .wrap(Logger::default())
             // Enable CORS
-            .wrap(cors)
+            .wrap(cors::cors(&config.cors))
+            // Tag and log each request, inside the identity middleware to see the user
+            .wrap(from_fn(request_id::request_id))
+            // Count requests, by route template and status
+            .wrap(from_fn(metrics::track))
             // Normalize paths
             .wrap(NormalizePath::new(TrailingSlash::Trim))
             // Identity middleware
             .wrap(IdentityMiddleware::default())
             // Session middleware
-            .wrap(
-                SessionMiddleware::builder(redis_store.clone(), secret_key.clone())
-                    .cookie_name("session_id".to_string())
-                    .cookie_secure(config.env == "prod") // Only use secure cookies in production
-                    .cookie_http_only(true)
-                    .cookie_same_site(SameSite::Lax)
-                    .build(),
-            )
+            .wrap(session(
+                redis_store.clone(),
+                secret_key.clone(),
+                &config.cookie,
+            ))
             // Share database pool
-            .app_data(web::Data::new(db_pool.clone()))
+            .app_data(web::Data::new(server_pool.clone()))
+            .app_data(web::Data::from(pool_metrics.clone()))
             // Share config
             .app_data(web::Data::new(config.clone()))
+            // Dependencies probed by the readiness check
+            .app_data(web::Data::new(redis_client.clone()))
+            .app_data(readiness_cache.clone())
+            // Fails readiness and tracks mirror syncs started by requests
+            .app_data(server_shutdown.clone())
             // Swagger
             // .service(SwaggerUi::new("/swagger-ui/{_:.*}").urls())
             // Configure API routes
             .configure(config_routes)
+            .configure(|cfg| {
+                if metrics_on_api {
+                    metrics_routes::config_routes(cfg);
+                }
+            })
             .default_service(web::route().to(|| HttpResponse::NotFound()))
     })
-    .bind(format!("0.0.0.0:{}", port))?
-    .run()
-    .await
+    .disable_signals()
+    .shutdown_timeout(shutdown_timeout)
+    .bind(bind)?
+    .run();
+
+    // On SIGTERM or Ctrl-C, stop accepting connections and let in-flight
+    // requests finish
+    shutdown.on_signal();
+    shutdown.stop_server(server.handle());
+    let served = server.await;
+
+    // Background tasks may still be busy with work the last requests started
+    shutdown.drain().await;
+    if let Some(handle) = metrics_handle {
+        handle.stop(true).await;
+    }
+
+    // Idle connections close with the last handle on the pool
+    tracing::info!(
+        "Closing {} database connection(s)",
+        db_pool.state().connections
+    );
+    drop(db_pool);
+    telemetry.shutdown();
+
+    served
+}
+
+// Session cookie according to the configured policy
+fn session(
+    store: RedisSessionStore,
+    key: Key,
+    cookie: &CookieConfig,
+) -> SessionMiddleware<RedisSessionStore> {
+    let lifecycle: SessionLifecycle = match cookie.max_age_seconds {
+        0 => BrowserSession::default().into(),
+        seconds => PersistentSession::default()
+            .session_ttl(CookieDuration::seconds(seconds as i64))
+            .into(),
+    };
+
+    SessionMiddleware::builder(store, key)
+        .cookie_name(cookie.name.clone())
+        .cookie_domain((!cookie.domain.is_empty()).then(|| cookie.domain.clone()))
+        .cookie_secure(cookie.secure)
+        .cookie_http_only(true)
+        .cookie_same_site(cookie.same_site())
+        .session_lifecycle(lifecycle)
+        .build()
 }
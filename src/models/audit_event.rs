@@ -0,0 +1,31 @@
+use crate::schema::audit_events;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// An administrative or security-relevant action. Rows are never updated
+/// nor deleted, and outlive the actor and target they refer to.
+#[derive(Queryable, Selectable, Serialize, ToSchema, Debug)]
+#[diesel(table_name = audit_events)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct AuditEvent {
+    pub id: Uuid,
+    /// Insertion order, the pagination cursor
+    #[serde(skip)]
+    pub seq: i64,
+    pub organization_id: Option<Uuid>,
+    /// Missing for anonymous and system actions
+    pub actor_id: Option<Uuid>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<Uuid>,
+    /// Changed fields as `{"field": {"before": .., "after": ..}}`
+    pub changes: Option<Value>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,32 @@
+use crate::schema::deploy_keys;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// An SSH deploy key. Only the public half is ever serialized.
+#[derive(Queryable, Selectable, Serialize, ToSchema, Debug)]
+#[diesel(table_name = deploy_keys)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DeployKey {
+    pub id: Uuid,
+    pub repository_id: Uuid,
+    pub public_key: String,
+    pub fingerprint: String,
+    #[serde(skip_serializing)]
+    #[schema(ignore)]
+    pub encrypted_private_key: Vec<u8>,
+    #[serde(skip_serializing)]
+    #[schema(ignore)]
+    pub private_key_nonce: Vec<u8>,
+    #[serde(skip_serializing)]
+    #[schema(ignore)]
+    pub encrypted_data_key: Vec<u8>,
+    #[serde(skip_serializing)]
+    #[schema(ignore)]
+    pub data_key_nonce: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+    /// Set once the key has been rotated out
+    pub expires_at: Option<DateTime<Utc>>,
+}
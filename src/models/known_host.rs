@@ -0,0 +1,22 @@
+use crate::schema::known_hosts;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Queryable, Selectable, Serialize, ToSchema, Debug)]
+#[diesel(table_name = known_hosts)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct KnownHost {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub host: String,
+    pub key_type: String,
+    pub public_key: String,
+    pub fingerprint: String,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub confirmed_by: Option<Uuid>,
+}
@@ -1,15 +1,37 @@
 mod account;
+mod audit_event;
+mod deploy_key;
+mod known_host;
 mod organization;
+mod organization_webhook;
+mod organization_webhook_delivery;
+mod outbox_event;
 mod repo;
+mod repository_mirror;
 mod reset_password_token;
+mod slug_redirect;
 mod team;
+mod team_repo;
 mod user;
 mod verification_token;
+mod webhook_delivery;
+mod webhook_secret;
 
 pub use account::Account;
+pub use audit_event::AuditEvent;
+pub use deploy_key::DeployKey;
+pub use known_host::KnownHost;
 pub use organization::Organization;
+pub use organization_webhook::OrganizationWebhook;
+pub use organization_webhook_delivery::OrganizationWebhookDelivery;
+pub use outbox_event::OutboxEvent;
 pub use repo::Repo;
+pub use repository_mirror::RepositoryMirror;
 pub use reset_password_token::ResetPasswordToken;
+pub use slug_redirect::SlugRedirect;
 pub use team::Team;
+pub use team_repo::TeamRepo;
 pub use user::User;
 pub use verification_token::VerificationToken;
+pub use webhook_delivery::WebhookDelivery;
+pub use webhook_secret::WebhookSecret;
@@ -1,11 +1,11 @@
 use crate::schema::organizations;
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Queryable, Selectable, Serialize, ToSchema, Debug)]
+#[derive(Queryable, Selectable, Serialize, Deserialize, ToSchema, Debug, Clone)]
 #[diesel(table_name = organizations)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Organization {
@@ -15,4 +15,6 @@ pub struct Organization {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    pub deletion_batch_id: Option<Uuid>,
+    pub slug: String,
 }
@@ -0,0 +1,35 @@
+use crate::schema::organization_webhooks;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// An integrator endpoint notified of changes in an organization. The
+/// signing secret is never serialized.
+#[derive(Queryable, Selectable, Serialize, ToSchema, Debug)]
+#[diesel(table_name = organization_webhooks)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OrganizationWebhook {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub url: String,
+    /// Event types or `prefix.*` patterns, every event when empty
+    pub events: Vec<String>,
+    pub active: bool,
+    #[serde(skip_serializing)]
+    #[schema(ignore)]
+    pub encrypted_secret: Vec<u8>,
+    #[serde(skip_serializing)]
+    #[schema(ignore)]
+    pub secret_nonce: Vec<u8>,
+    #[serde(skip_serializing)]
+    #[schema(ignore)]
+    pub encrypted_data_key: Vec<u8>,
+    #[serde(skip_serializing)]
+    #[schema(ignore)]
+    pub data_key_nonce: Vec<u8>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,33 @@
+use crate::schema::organization_webhook_deliveries;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// One event sent to one webhook, with the outcome of its last attempt.
+#[derive(Queryable, Selectable, Serialize, ToSchema, Debug)]
+#[diesel(table_name = organization_webhook_deliveries)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OrganizationWebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    /// Shared by every delivery of the same event
+    pub event_id: Uuid,
+    pub event_type: String,
+    #[serde(skip_serializing)]
+    #[schema(ignore)]
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    /// Signed headers and the start of the body
+    pub request_excerpt: Option<String>,
+    pub response_status: Option<i32>,
+    /// Start of the response body
+    pub response_excerpt: Option<String>,
+    pub error: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
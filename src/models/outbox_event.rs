@@ -0,0 +1,28 @@
+use crate::schema::outbox_events;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// A domain event, written in the same transaction as the change it
+/// describes and dispatched to subscribers afterwards.
+#[derive(Queryable, Selectable, Serialize, ToSchema, Debug)]
+#[diesel(table_name = outbox_events)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OutboxEvent {
+    /// Deduplication id, shared by everything the event causes
+    pub id: Uuid,
+    pub event_type: String,
+    pub organization_id: Option<Uuid>,
+    pub payload: Value,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub dispatched_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// Set once `OUTBOX_MAX_ATTEMPTS` attempts failed, the event is no
+    /// longer retried
+    pub dead_lettered_at: Option<DateTime<Utc>>,
+}
@@ -1,11 +1,11 @@
 use crate::schema::repositories;
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Queryable, Selectable, Serialize, ToSchema, Debug)]
+#[derive(Queryable, Selectable, Serialize, Deserialize, ToSchema, Debug, Clone)]
 #[diesel(table_name = repositories)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Repo {
@@ -18,4 +18,16 @@ pub struct Repo {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    pub deletion_batch_id: Option<Uuid>,
+    pub slug: String,
+    pub protocol: Option<String>,
+    pub owner_path: Option<String>,
+    pub remote_key: Option<String>,
+    pub reachable: Option<bool>,
+    pub default_branch: Option<String>,
+    pub ref_count: Option<i32>,
+    pub last_error: Option<String>,
+    pub last_checked_at: Option<DateTime<Utc>>,
+    /// Minutes between mirror syncs, `MIRROR_SYNC_INTERVAL_MINUTES` when unset
+    pub sync_interval_minutes: Option<i32>,
 }
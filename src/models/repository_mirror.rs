@@ -0,0 +1,27 @@
+use crate::schema::repository_mirrors;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Queryable, Selectable, Serialize, ToSchema, Debug)]
+#[diesel(table_name = repository_mirrors)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RepositoryMirror {
+    pub repository_id: Uuid,
+    pub status: String,
+    #[serde(skip_serializing)]
+    #[schema(ignore)]
+    pub path: String,
+    pub default_branch: Option<String>,
+    pub size_bytes: Option<i64>,
+    pub last_error: Option<String>,
+    pub sync_started_at: DateTime<Utc>,
+    /// Last successful sync
+    pub last_synced_at: Option<DateTime<Utc>>,
+    pub last_duration_ms: Option<i64>,
+    pub next_sync_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,18 @@
+use crate::schema::slug_redirects;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Queryable, Selectable, Serialize, ToSchema, Debug)]
+#[diesel(table_name = slug_redirects)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SlugRedirect {
+    pub id: Uuid,
+    pub resource_type: String,
+    pub scope_id: Option<Uuid>,
+    pub old_slug: String,
+    pub resource_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,14 @@
+use crate::schema::team_repositories;
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Queryable, Selectable, Serialize, ToSchema, Debug)]
+#[diesel(table_name = team_repositories)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TeamRepo {
+    pub team_id: Uuid,
+    pub repository_id: Uuid,
+    pub permission: String,
+}
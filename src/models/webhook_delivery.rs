@@ -0,0 +1,30 @@
+use crate::schema::webhook_deliveries;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// A verified webhook received from a git provider, kept for replay.
+#[derive(Queryable, Selectable, Serialize, ToSchema, Debug)]
+#[diesel(table_name = webhook_deliveries)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub repository_id: Uuid,
+    pub provider: String,
+    /// Delivery id sent by the provider, used to drop redeliveries
+    pub delivery_id: Option<String>,
+    /// Event name as sent by the provider
+    pub event_name: String,
+    /// Parsed event, unset for events we do not handle
+    pub event: Option<serde_json::Value>,
+    #[serde(skip_serializing)]
+    #[schema(ignore)]
+    pub payload: String,
+    pub status: String,
+    pub error: Option<String>,
+    pub attempts: i32,
+    pub received_at: DateTime<Utc>,
+    pub processed_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,17 @@
+use crate::schema::webhook_secrets;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+/// Shared secret signing the webhooks of a repository, stored encrypted.
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = webhook_secrets)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WebhookSecret {
+    pub repository_id: Uuid,
+    pub encrypted_secret: Vec<u8>,
+    pub secret_nonce: Vec<u8>,
+    pub encrypted_data_key: Vec<u8>,
+    pub data_key_nonce: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+}
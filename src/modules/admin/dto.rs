@@ -0,0 +1,42 @@
+use crate::models::{Organization, Repo, Team, User};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+#[derive(Serialize, ToSchema)]
+pub struct TrashListing {
+    pub organizations: Vec<Organization>,
+    pub teams: Vec<Team>,
+    pub repositories: Vec<Repo>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct PurgeQuery {
+    /// List what would be removed without deleting anything
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct PurgedTable {
+    pub table: String,
+    pub count: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ids: Vec<Uuid>,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct PurgeReport {
+    pub dry_run: bool,
+    pub cutoff: DateTime<Utc>,
+    pub tables: Vec<PurgedTable>,
+}
+
+/// Everything `seed_demo` created
+#[derive(Serialize, Debug)]
+pub struct DemoData {
+    pub users: Vec<User>,
+    pub organization: Organization,
+    pub team: Team,
+    pub repository: Repo,
+}
@@ -0,0 +1,113 @@
+use crate::config::Config;
+use crate::db::{self, PoolMetrics};
+use crate::modules::admin::dto::PurgeQuery;
+use crate::modules::admin::service::AdminService;
+use crate::modules::auth::handler::session_user;
+use crate::utils::response::{success, ApiError};
+use actix_identity::Identity;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use diesel::r2d2::{self, ConnectionManager};
+use diesel::PgConnection;
+use tracing::instrument;
+use uuid::Uuid;
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+// Make sure the session user is a platform admin
+pub fn require_admin(conn: &mut PgConnection, user_id: Uuid) -> Result<(), ApiError> {
+    // Check platform admin
+    match AdminService::is_admin(conn, user_id) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "Admin access required".into(),
+        )),
+        Err(e) => Err(ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to check permissions: {}", e),
+        )),
+    }
+}
+
+#[instrument(name = "admin::handler::get_trash", skip_all)]
+pub async fn get_trash(id: Identity, pool: web::Data<DbPool>) -> HttpResponse {
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Get deleted entities
+    let result = db::block(&pool, move |conn| {
+        require_admin(conn, user_id)?;
+        AdminService::get_trash(conn).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve trash: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(trash) => HttpResponse::Ok().json(success(StatusCode::OK, Some(trash))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "admin::handler::purge", skip_all)]
+pub async fn purge(
+    id: Identity,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    query: web::Query<PurgeQuery>,
+) -> HttpResponse {
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Purge soft-deleted data past retention
+    let dry_run = query.dry_run.unwrap_or(config.purge.dry_run);
+    let result = db::block(&pool, move |conn| {
+        require_admin(conn, user_id)?;
+        AdminService::purge(
+            conn,
+            config.purge.retention_days,
+            config.purge.batch_size,
+            dry_run,
+        )
+        .map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to purge deleted data: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(report) => HttpResponse::Ok().json(success(StatusCode::OK, Some(report))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "admin::handler::get_pool_stats", skip_all)]
+pub async fn get_pool_stats(
+    id: Identity,
+    pool: web::Data<DbPool>,
+    metrics: web::Data<PoolMetrics>,
+) -> HttpResponse {
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    let result = db::block(&pool, move |conn| require_admin(conn, user_id)).await;
+
+    // Read the counters after the check, which took a connection itself
+    match result {
+        Ok(()) => HttpResponse::Ok().json(success(StatusCode::OK, Some(metrics.stats(&pool)))),
+        Err(e) => e.error_response(),
+    }
+}
@@ -0,0 +1,383 @@
+use crate::models::{Organization, Repo, Team};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use std::error::Error;
+use tracing::instrument;
+use uuid::Uuid;
+
+pub struct AdminRepository;
+
+impl AdminRepository {
+    #[instrument(name = "AdminRepository::find_user_role", skip_all)]
+    pub fn find_user_role(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        use crate::schema::users::dsl::*;
+
+        let user_role = users
+            .filter(id.eq(user_id))
+            .filter(deleted_at.is_null())
+            .select(role)
+            .first::<String>(conn)
+            .optional()?;
+
+        Ok(user_role)
+    }
+
+    #[instrument(name = "AdminRepository::set_user_role", skip_all)]
+    pub fn set_user_role(
+        conn: &mut PgConnection,
+        user_id: Uuid,
+        new_role: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::schema::users::dsl::*;
+
+        diesel::update(users.filter(id.eq(user_id)))
+            .set((role.eq(new_role), updated_at.eq(Utc::now())))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    #[instrument(name = "AdminRepository::find_deleted_organizations", skip_all)]
+    pub fn find_deleted_organizations(
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Organization>, Box<dyn Error>> {
+        use crate::schema::organizations::dsl::*;
+
+        let deleted = organizations
+            .filter(deleted_at.is_not_null())
+            .order(deleted_at.desc())
+            .load::<Organization>(conn)?;
+
+        Ok(deleted)
+    }
+
+    #[instrument(name = "AdminRepository::find_deleted_teams", skip_all)]
+    pub fn find_deleted_teams(conn: &mut PgConnection) -> Result<Vec<Team>, Box<dyn Error>> {
+        use crate::schema::teams::dsl::*;
+
+        let deleted = teams
+            .filter(deleted_at.is_not_null())
+            .order(deleted_at.desc())
+            .load::<Team>(conn)?;
+
+        Ok(deleted)
+    }
+
+    #[instrument(name = "AdminRepository::find_deleted_repositories", skip_all)]
+    pub fn find_deleted_repositories(conn: &mut PgConnection) -> Result<Vec<Repo>, Box<dyn Error>> {
+        use crate::schema::repositories::dsl::*;
+
+        let deleted = repositories
+            .filter(deleted_at.is_not_null())
+            .order(deleted_at.desc())
+            .load::<Repo>(conn)?;
+
+        Ok(deleted)
+    }
+
+    #[instrument(name = "AdminRepository::find_expired_verification_tokens", skip_all)]
+    pub fn find_expired_verification_tokens(
+        conn: &mut PgConnection,
+        cutoff: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Uuid>, Box<dyn Error>> {
+        use crate::schema::verification_tokens::dsl::*;
+
+        let ids = verification_tokens
+            .filter(expires_at.lt(cutoff))
+            .select(id)
+            .limit(limit)
+            .load::<Uuid>(conn)?;
+
+        Ok(ids)
+    }
+
+    #[instrument(name = "AdminRepository::count_expired_verification_tokens", skip_all)]
+    pub fn count_expired_verification_tokens(
+        conn: &mut PgConnection,
+        cutoff: DateTime<Utc>,
+    ) -> Result<i64, Box<dyn Error>> {
+        use crate::schema::verification_tokens::dsl::*;
+
+        let count = verification_tokens
+            .filter(expires_at.lt(cutoff))
+            .count()
+            .get_result::<i64>(conn)?;
+
+        Ok(count)
+    }
+
+    #[instrument(name = "AdminRepository::delete_verification_tokens", skip_all)]
+    pub fn delete_verification_tokens(
+        conn: &mut PgConnection,
+        ids: &[Uuid],
+    ) -> Result<usize, Box<dyn Error>> {
+        use crate::schema::verification_tokens::dsl::*;
+
+        Ok(diesel::delete(verification_tokens.filter(id.eq_any(ids))).execute(conn)?)
+    }
+
+    #[instrument(name = "AdminRepository::find_expired_reset_password_tokens", skip_all)]
+    pub fn find_expired_reset_password_tokens(
+        conn: &mut PgConnection,
+        cutoff: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Uuid>, Box<dyn Error>> {
+        use crate::schema::reset_password_tokens::dsl::*;
+
+        let ids = reset_password_tokens
+            .filter(expires_at.lt(cutoff))
+            .select(id)
+            .limit(limit)
+            .load::<Uuid>(conn)?;
+
+        Ok(ids)
+    }
+
+    #[instrument(
+        name = "AdminRepository::count_expired_reset_password_tokens",
+        skip_all
+    )]
+    pub fn count_expired_reset_password_tokens(
+        conn: &mut PgConnection,
+        cutoff: DateTime<Utc>,
+    ) -> Result<i64, Box<dyn Error>> {
+        use crate::schema::reset_password_tokens::dsl::*;
+
+        let count = reset_password_tokens
+            .filter(expires_at.lt(cutoff))
+            .count()
+            .get_result::<i64>(conn)?;
+
+        Ok(count)
+    }
+
+    #[instrument(name = "AdminRepository::delete_reset_password_tokens", skip_all)]
+    pub fn delete_reset_password_tokens(
+        conn: &mut PgConnection,
+        ids: &[Uuid],
+    ) -> Result<usize, Box<dyn Error>> {
+        use crate::schema::reset_password_tokens::dsl::*;
+
+        Ok(diesel::delete(reset_password_tokens.filter(id.eq_any(ids))).execute(conn)?)
+    }
+
+    #[instrument(name = "AdminRepository::find_purgeable_repositories", skip_all)]
+    pub fn find_purgeable_repositories(
+        conn: &mut PgConnection,
+        cutoff: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Uuid>, Box<dyn Error>> {
+        use crate::schema::repositories::dsl::*;
+
+        let ids = repositories
+            .filter(deleted_at.lt(cutoff))
+            .select(id)
+            .limit(limit)
+            .load::<Uuid>(conn)?;
+
+        Ok(ids)
+    }
+
+    #[instrument(name = "AdminRepository::count_purgeable_repositories", skip_all)]
+    pub fn count_purgeable_repositories(
+        conn: &mut PgConnection,
+        cutoff: DateTime<Utc>,
+    ) -> Result<i64, Box<dyn Error>> {
+        use crate::schema::repositories::dsl::*;
+
+        let count = repositories
+            .filter(deleted_at.lt(cutoff))
+            .count()
+            .get_result::<i64>(conn)?;
+
+        Ok(count)
+    }
+
+    #[instrument(name = "AdminRepository::delete_repositories", skip_all)]
+    pub fn delete_repositories(
+        conn: &mut PgConnection,
+        ids: &[Uuid],
+    ) -> Result<usize, Box<dyn Error>> {
+        use crate::schema::repositories::dsl::*;
+
+        Ok(diesel::delete(repositories.filter(id.eq_any(ids))).execute(conn)?)
+    }
+
+    #[instrument(name = "AdminRepository::find_purgeable_teams", skip_all)]
+    pub fn find_purgeable_teams(
+        conn: &mut PgConnection,
+        cutoff: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Uuid>, Box<dyn Error>> {
+        use crate::schema::teams::dsl::*;
+
+        let ids = teams
+            .filter(deleted_at.lt(cutoff))
+            .select(id)
+            .limit(limit)
+            .load::<Uuid>(conn)?;
+
+        Ok(ids)
+    }
+
+    #[instrument(name = "AdminRepository::count_purgeable_teams", skip_all)]
+    pub fn count_purgeable_teams(
+        conn: &mut PgConnection,
+        cutoff: DateTime<Utc>,
+    ) -> Result<i64, Box<dyn Error>> {
+        use crate::schema::teams::dsl::*;
+
+        let count = teams
+            .filter(deleted_at.lt(cutoff))
+            .count()
+            .get_result::<i64>(conn)?;
+
+        Ok(count)
+    }
+
+    #[instrument(name = "AdminRepository::delete_teams", skip_all)]
+    pub fn delete_teams(conn: &mut PgConnection, ids: &[Uuid]) -> Result<usize, Box<dyn Error>> {
+        use crate::schema::teams::dsl::*;
+
+        Ok(diesel::delete(teams.filter(id.eq_any(ids))).execute(conn)?)
+    }
+
+    #[instrument(name = "AdminRepository::find_purgeable_organizations", skip_all)]
+    pub fn find_purgeable_organizations(
+        conn: &mut PgConnection,
+        cutoff: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Uuid>, Box<dyn Error>> {
+        use crate::schema::organizations::dsl::*;
+
+        let ids = organizations
+            .filter(deleted_at.lt(cutoff))
+            .select(id)
+            .limit(limit)
+            .load::<Uuid>(conn)?;
+
+        Ok(ids)
+    }
+
+    #[instrument(name = "AdminRepository::count_purgeable_organizations", skip_all)]
+    pub fn count_purgeable_organizations(
+        conn: &mut PgConnection,
+        cutoff: DateTime<Utc>,
+    ) -> Result<i64, Box<dyn Error>> {
+        use crate::schema::organizations::dsl::*;
+
+        let count = organizations
+            .filter(deleted_at.lt(cutoff))
+            .count()
+            .get_result::<i64>(conn)?;
+
+        Ok(count)
+    }
+
+    #[instrument(name = "AdminRepository::delete_organizations", skip_all)]
+    pub fn delete_organizations(
+        conn: &mut PgConnection,
+        ids: &[Uuid],
+    ) -> Result<usize, Box<dyn Error>> {
+        use crate::schema::organizations::dsl::*;
+
+        Ok(diesel::delete(organizations.filter(id.eq_any(ids))).execute(conn)?)
+    }
+
+    #[instrument(name = "AdminRepository::find_purgeable_accounts", skip_all)]
+    pub fn find_purgeable_accounts(
+        conn: &mut PgConnection,
+        cutoff: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Uuid>, Box<dyn Error>> {
+        use crate::schema::accounts::dsl::*;
+
+        let ids = accounts
+            .filter(deleted_at.lt(cutoff))
+            .select(id)
+            .limit(limit)
+            .load::<Uuid>(conn)?;
+
+        Ok(ids)
+    }
+
+    #[instrument(name = "AdminRepository::count_purgeable_accounts", skip_all)]
+    pub fn count_purgeable_accounts(
+        conn: &mut PgConnection,
+        cutoff: DateTime<Utc>,
+    ) -> Result<i64, Box<dyn Error>> {
+        use crate::schema::accounts::dsl::*;
+
+        let count = accounts
+            .filter(deleted_at.lt(cutoff))
+            .count()
+            .get_result::<i64>(conn)?;
+
+        Ok(count)
+    }
+
+    #[instrument(name = "AdminRepository::delete_accounts", skip_all)]
+    pub fn delete_accounts(conn: &mut PgConnection, ids: &[Uuid]) -> Result<usize, Box<dyn Error>> {
+        use crate::schema::accounts::dsl::*;
+
+        Ok(diesel::delete(accounts.filter(id.eq_any(ids))).execute(conn)?)
+    }
+
+    #[instrument(name = "AdminRepository::find_purgeable_users", skip_all)]
+    pub fn find_purgeable_users(
+        conn: &mut PgConnection,
+        cutoff: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Uuid>, Box<dyn Error>> {
+        use crate::schema::users::dsl::*;
+
+        let ids = users
+            .filter(deleted_at.lt(cutoff))
+            .select(id)
+            .limit(limit)
+            .load::<Uuid>(conn)?;
+
+        Ok(ids)
+    }
+
+    #[instrument(name = "AdminRepository::count_purgeable_users", skip_all)]
+    pub fn count_purgeable_users(
+        conn: &mut PgConnection,
+        cutoff: DateTime<Utc>,
+    ) -> Result<i64, Box<dyn Error>> {
+        use crate::schema::users::dsl::*;
+
+        let count = users
+            .filter(deleted_at.lt(cutoff))
+            .count()
+            .get_result::<i64>(conn)?;
+
+        Ok(count)
+    }
+
+    /// Tokens reference users without `ON DELETE CASCADE`, so they are removed
+    /// first; accounts and memberships cascade on their own.
+    #[instrument(name = "AdminRepository::delete_users", skip_all)]
+    pub fn delete_users(conn: &mut PgConnection, ids: &[Uuid]) -> Result<usize, Box<dyn Error>> {
+        use crate::schema::{reset_password_tokens, users, verification_tokens};
+
+        conn.transaction(|conn| {
+            diesel::delete(
+                verification_tokens::table.filter(verification_tokens::user_id.eq_any(ids)),
+            )
+            .execute(conn)?;
+
+            diesel::delete(
+                reset_password_tokens::table.filter(reset_password_tokens::user_id.eq_any(ids)),
+            )
+            .execute(conn)?;
+
+            Ok(diesel::delete(users::table.filter(users::id.eq_any(ids))).execute(conn)?)
+        })
+    }
+}
@@ -0,0 +1,13 @@
+use crate::modules::admin::handler::{get_pool_stats, get_trash, purge};
+use crate::modules::audit::routes as audit_routes;
+use actix_web::web;
+
+pub fn config_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin")
+            .route("/trash", web::get().to(get_trash))
+            .route("/purge", web::post().to(purge))
+            .route("/pool", web::get().to(get_pool_stats))
+            .configure(audit_routes::config_admin_routes),
+    );
+}
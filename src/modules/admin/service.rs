@@ -0,0 +1,452 @@
+use crate::config::Config;
+use crate::models::User;
+use crate::modules::admin::dto::{DemoData, PurgeReport, PurgedTable, TrashListing};
+use crate::modules::admin::repository::AdminRepository;
+use crate::modules::audit::dto::{AuditAction, AuditContext};
+use crate::modules::audit::service::AuditService;
+use crate::modules::auth::dto::RegisterQuery;
+use crate::modules::auth::repository::AuthRepository;
+use crate::modules::auth::service::AuthService;
+use crate::modules::mirror::repository::MirrorRepository;
+use crate::modules::mirror::service::MirrorService;
+use crate::modules::organization::dto::{AddUserToOrganizationQuery, OrganizationCreateQuery};
+use crate::modules::organization::repository::OrganizationRepository;
+use crate::modules::organization::service::OrganizationService;
+use crate::modules::repo::dto::{GrantTeamAccessQuery, RepoCreateQuery, RepoPermission};
+use crate::modules::repo::service::RepoService;
+use crate::modules::team::dto::{AddUserToTeamQuery, TeamCreateQuery};
+use crate::modules::team::service::TeamService;
+use chrono::{DateTime, Duration, Utc};
+use diesel::{Connection, PgConnection};
+use std::error::Error;
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+type FindPurgeable = fn(&mut PgConnection, DateTime<Utc>, i64) -> Result<Vec<Uuid>, Box<dyn Error>>;
+type CountPurgeable = fn(&mut PgConnection, DateTime<Utc>) -> Result<i64, Box<dyn Error>>;
+type DeletePurgeable = fn(&mut PgConnection, &[Uuid]) -> Result<usize, Box<dyn Error>>;
+
+// Finds, counts and deletes the rows of a table past the cutoff
+struct Purgeable {
+    find: FindPurgeable,
+    count: CountPurgeable,
+    delete: DeletePurgeable,
+}
+
+/// Shared by every user `seed_demo` creates
+pub const DEMO_PASSWORD: &str = "password123";
+
+pub struct AdminService;
+
+impl AdminService {
+    /// Platform admins are users whose `role` is `admin`.
+    #[instrument(name = "AdminService::is_admin", skip_all)]
+    pub fn is_admin(conn: &mut PgConnection, user_id: Uuid) -> Result<bool, Box<dyn Error>> {
+        let role = AdminRepository::find_user_role(conn, user_id)?;
+
+        Ok(role.as_deref() == Some("admin"))
+    }
+
+    /// Creates a verified user with the `admin` role, to bootstrap a fresh
+    /// install.
+    #[instrument(name = "AdminService::create_admin", skip_all)]
+    pub fn create_admin(
+        conn: &mut PgConnection,
+        data: &RegisterQuery,
+        audit: &AuditContext,
+    ) -> Result<User, Box<dyn Error>> {
+        data.validate()?;
+        if AuthRepository::find_user_by_email(conn, &data.email)?.is_some() {
+            return Err("User with this email already exists".into());
+        }
+
+        conn.transaction(|conn| {
+            let user = AuthRepository::create_user_account(conn, data)?;
+            AdminRepository::set_user_role(conn, user.id, "admin")?;
+            AuthRepository::verify_user(conn, &user)?;
+
+            let user = AuthRepository::find_user_by_id(conn, user.id)?.ok_or("User not found")?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::UserRegistered,
+                None,
+                Some(user.id),
+                AuditService::diff(None, Some(&user))?,
+            )?;
+
+            Ok(user)
+        })
+    }
+
+    /// Verifies the user without a token. Returns `false` when they already
+    /// were.
+    #[instrument(name = "AdminService::verify_user", skip_all)]
+    pub fn verify_user(
+        conn: &mut PgConnection,
+        email: &str,
+        audit: &AuditContext,
+    ) -> Result<bool, Box<dyn Error>> {
+        let user = AuthRepository::find_user_by_email(conn, email)?.ok_or("User not found")?;
+        if user.verified {
+            return Ok(false);
+        }
+
+        conn.transaction(|conn| {
+            AuthRepository::verify_user(conn, &user)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::UserVerified,
+                None,
+                Some(user.id),
+                None,
+            )?;
+
+            Ok(true)
+        })
+    }
+
+    /// Sets a new password without going through the reset email.
+    #[instrument(name = "AdminService::reset_password", skip_all)]
+    pub fn reset_password(
+        conn: &mut PgConnection,
+        email: &str,
+        password: &str,
+        audit: &AuditContext,
+    ) -> Result<(), Box<dyn Error>> {
+        if !(8..=100).contains(&password.chars().count()) {
+            return Err("Password must be between 8 and 100 characters".into());
+        }
+
+        let user = AuthRepository::find_user_by_email(conn, email)?.ok_or("User not found")?;
+
+        conn.transaction(|conn| {
+            AuthRepository::set_password(conn, user.id, password)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::PasswordReset,
+                None,
+                Some(user.id),
+                None,
+            )
+        })
+    }
+
+    /// Creates two verified users sharing `DEMO_PASSWORD`, a `demo`
+    /// organization they belong to, a team and a repository it can write
+    /// to. Refuses to run twice, and in staging and prod where anyone
+    /// knowing the password could log in.
+    #[instrument(name = "AdminService::seed_demo", skip_all)]
+    pub fn seed_demo(
+        conn: &mut PgConnection,
+        config: &Config,
+        audit: &AuditContext,
+    ) -> Result<DemoData, Box<dyn Error>> {
+        if config.env == "staging" || config.env == "prod" {
+            return Err(format!("Demo data cannot be seeded in {}", config.env).into());
+        }
+        if OrganizationRepository::slug_taken(conn, "demo", None)? {
+            return Err("Demo data is already seeded, organization 'demo' exists".into());
+        }
+
+        conn.transaction(|conn| {
+            let mut users = Vec::new();
+            for (name, email, role) in [
+                ("Alice Demo", "alice@example.com", "owner"),
+                ("Bob Demo", "bob@example.com", "member"),
+            ] {
+                let user = AuthService::register(
+                    conn,
+                    &RegisterQuery {
+                        name: name.to_string(),
+                        email: email.to_string(),
+                        password: DEMO_PASSWORD.to_string(),
+                    },
+                    audit,
+                )?;
+                AuthRepository::verify_user(conn, &user)?;
+                users.push((
+                    AuthRepository::find_user_by_id(conn, user.id)?.ok_or("User not found")?,
+                    role,
+                ));
+            }
+
+            let organization = OrganizationService::create(
+                conn,
+                &OrganizationCreateQuery {
+                    name: "Demo".to_string(),
+                    description: Some("Sample data created by scylla-admin".to_string()),
+                    slug: Some("demo".to_string()),
+                },
+                audit,
+            )?;
+            let team = TeamService::create(
+                conn,
+                &TeamCreateQuery {
+                    name: "Core".to_string(),
+                    description: Some("Maintainers of the demo repository".to_string()),
+                    organization_id: organization.id,
+                    slug: None,
+                },
+                audit,
+            )?;
+            for (user, role) in &users {
+                OrganizationService::add_user(
+                    conn,
+                    organization.id,
+                    &AddUserToOrganizationQuery {
+                        user_id: user.id,
+                        role: Some(role.to_string()),
+                    },
+                    audit,
+                )?;
+                TeamService::add_user(
+                    conn,
+                    team.id,
+                    &AddUserToTeamQuery {
+                        user_id: user.id,
+                        role: Some("member".to_string()),
+                    },
+                    audit,
+                )?;
+            }
+
+            let repository = RepoService::create(
+                conn,
+                &RepoCreateQuery {
+                    name: "Hello World".to_string(),
+                    url: "https://github.com/octocat/Hello-World.git".to_string(),
+                    organization_id: organization.id,
+                    slug: None,
+                    sync_interval_minutes: Some(0),
+                },
+                &config.git,
+                audit,
+            )?;
+            RepoService::grant_team(
+                conn,
+                repository.id,
+                &GrantTeamAccessQuery {
+                    team_id: team.id,
+                    permission: RepoPermission::Write,
+                },
+                audit,
+            )?;
+
+            Ok(DemoData {
+                users: users.into_iter().map(|(user, _)| user).collect(),
+                organization,
+                team,
+                repository,
+            })
+        })
+    }
+
+    #[instrument(name = "AdminService::get_trash", skip_all)]
+    pub fn get_trash(conn: &mut PgConnection) -> Result<TrashListing, Box<dyn Error>> {
+        Ok(TrashListing {
+            organizations: AdminRepository::find_deleted_organizations(conn)?,
+            teams: AdminRepository::find_deleted_teams(conn)?,
+            repositories: AdminRepository::find_deleted_repositories(conn)?,
+        })
+    }
+
+    /// Permanently deletes rows soft-deleted more than `retention_days` ago,
+    /// plus expired verification and reset password tokens.
+    ///
+    /// Tables are purged children first so no foreign key is left dangling,
+    /// `batch_size` rows at a time. In dry-run mode nothing is deleted and the
+    /// report counts the rows that would be removed, listing the ids of the
+    /// first batch.
+    #[instrument(name = "AdminService::purge", skip_all)]
+    pub fn purge(
+        conn: &mut PgConnection,
+        retention_days: i64,
+        batch_size: i64,
+        dry_run: bool,
+    ) -> Result<PurgeReport, Box<dyn Error>> {
+        let now = Utc::now();
+        let cutoff = now - Duration::days(retention_days);
+
+        let mut tables = Self::purge_tokens(conn, now, batch_size, dry_run)?;
+        tables.extend([
+            Self::purge_table(
+                conn,
+                "repositories",
+                cutoff,
+                batch_size,
+                dry_run,
+                Purgeable {
+                    find: AdminRepository::find_purgeable_repositories,
+                    count: AdminRepository::count_purgeable_repositories,
+                    delete: Self::delete_repositories,
+                },
+            )?,
+            Self::purge_table(
+                conn,
+                "teams",
+                cutoff,
+                batch_size,
+                dry_run,
+                Purgeable {
+                    find: AdminRepository::find_purgeable_teams,
+                    count: AdminRepository::count_purgeable_teams,
+                    delete: AdminRepository::delete_teams,
+                },
+            )?,
+            Self::purge_table(
+                conn,
+                "organizations",
+                cutoff,
+                batch_size,
+                dry_run,
+                Purgeable {
+                    find: AdminRepository::find_purgeable_organizations,
+                    count: AdminRepository::count_purgeable_organizations,
+                    delete: Self::delete_organizations,
+                },
+            )?,
+            Self::purge_table(
+                conn,
+                "accounts",
+                cutoff,
+                batch_size,
+                dry_run,
+                Purgeable {
+                    find: AdminRepository::find_purgeable_accounts,
+                    count: AdminRepository::count_purgeable_accounts,
+                    delete: AdminRepository::delete_accounts,
+                },
+            )?,
+            Self::purge_table(
+                conn,
+                "users",
+                cutoff,
+                batch_size,
+                dry_run,
+                Purgeable {
+                    find: AdminRepository::find_purgeable_users,
+                    count: AdminRepository::count_purgeable_users,
+                    delete: AdminRepository::delete_users,
+                },
+            )?,
+        ]);
+
+        Ok(PurgeReport {
+            dry_run,
+            cutoff,
+            tables,
+        })
+    }
+
+    /// Deletes only the expired verification and reset password tokens.
+    #[instrument(name = "AdminService::purge_expired_tokens", skip_all)]
+    pub fn purge_expired_tokens(
+        conn: &mut PgConnection,
+        batch_size: i64,
+        dry_run: bool,
+    ) -> Result<PurgeReport, Box<dyn Error>> {
+        let now = Utc::now();
+
+        Ok(PurgeReport {
+            dry_run,
+            cutoff: now,
+            tables: Self::purge_tokens(conn, now, batch_size, dry_run)?,
+        })
+    }
+
+    fn purge_tokens(
+        conn: &mut PgConnection,
+        now: DateTime<Utc>,
+        batch_size: i64,
+        dry_run: bool,
+    ) -> Result<Vec<PurgedTable>, Box<dyn Error>> {
+        Ok(vec![
+            Self::purge_table(
+                conn,
+                "verification_tokens",
+                now,
+                batch_size,
+                dry_run,
+                Purgeable {
+                    find: AdminRepository::find_expired_verification_tokens,
+                    count: AdminRepository::count_expired_verification_tokens,
+                    delete: AdminRepository::delete_verification_tokens,
+                },
+            )?,
+            Self::purge_table(
+                conn,
+                "reset_password_tokens",
+                now,
+                batch_size,
+                dry_run,
+                Purgeable {
+                    find: AdminRepository::find_expired_reset_password_tokens,
+                    count: AdminRepository::count_expired_reset_password_tokens,
+                    delete: AdminRepository::delete_reset_password_tokens,
+                },
+            )?,
+        ])
+    }
+
+    fn delete_repositories(conn: &mut PgConnection, ids: &[Uuid]) -> Result<usize, Box<dyn Error>> {
+        let mirrors = MirrorRepository::find_paths_by_repositories(conn, ids)?;
+        let count = AdminRepository::delete_repositories(conn, ids)?;
+        MirrorService::remove(&mirrors);
+
+        Ok(count)
+    }
+
+    // Repositories go with their organization
+    fn delete_organizations(
+        conn: &mut PgConnection,
+        ids: &[Uuid],
+    ) -> Result<usize, Box<dyn Error>> {
+        let mirrors = MirrorRepository::find_paths_by_organizations(conn, ids)?;
+        let count = AdminRepository::delete_organizations(conn, ids)?;
+        MirrorService::remove(&mirrors);
+
+        Ok(count)
+    }
+
+    fn purge_table(
+        conn: &mut PgConnection,
+        table: &str,
+        cutoff: DateTime<Utc>,
+        batch_size: i64,
+        dry_run: bool,
+        rows: Purgeable,
+    ) -> Result<PurgedTable, Box<dyn Error>> {
+        let batch_size = batch_size.max(1);
+        if dry_run {
+            return Ok(PurgedTable {
+                table: table.to_string(),
+                count: (rows.count)(conn, cutoff)? as usize,
+                ids: (rows.find)(conn, cutoff, batch_size)?,
+            });
+        }
+
+        let mut count = 0;
+        loop {
+            let ids = (rows.find)(conn, cutoff, batch_size)?;
+            if ids.is_empty() {
+                break;
+            }
+
+            count += (rows.delete)(conn, &ids)?;
+
+            if (ids.len() as i64) < batch_size {
+                break;
+            }
+        }
+
+        Ok(PurgedTable {
+            table: table.to_string(),
+            count,
+            ids: Vec::new(),
+        })
+    }
+}
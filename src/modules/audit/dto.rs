@@ -0,0 +1,338 @@
+use crate::models::AuditEvent;
+use crate::schema::audit_events;
+use actix_identity::IdentityExt;
+use actix_web::dev::Payload;
+use actix_web::{FromRequest, HttpRequest};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::future::{ready, Ready};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Who performed an action and from where. Extracted from every request
+/// reaching a service that writes the audit log.
+#[derive(Debug, Clone)]
+pub struct AuditContext {
+    /// Session user, missing for anonymous requests
+    pub actor_id: Option<Uuid>,
+    /// Peer address of the connection, forwarded headers are not trusted
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+impl AuditContext {
+    /// The same request acting as `actor_id`, for requests that establish
+    /// who the user is, such as a login.
+    pub fn with_actor(&self, actor_id: Uuid) -> Self {
+        AuditContext {
+            actor_id: Some(actor_id),
+            ..self.clone()
+        }
+    }
+
+    /// Actions taken by a background job, such as the outbox dispatcher.
+    /// The job's name stands in for the user agent.
+    pub fn background(job: &str) -> Self {
+        AuditContext {
+            actor_id: None,
+            ip: None,
+            user_agent: Some(job.to_string()),
+            request_id: None,
+        }
+    }
+
+    /// Actions run from a command line tool, outside of any request. The
+    /// tool's name stands in for the user agent.
+    pub fn command_line(tool: &str) -> Self {
+        AuditContext {
+            actor_id: None,
+            ip: None,
+            user_agent: Some(tool.to_string()),
+            request_id: None,
+        }
+    }
+
+    fn header(req: &HttpRequest, name: &str, max_len: usize) -> Option<String> {
+        req.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(max_len).collect())
+    }
+}
+
+impl FromRequest for AuditContext {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let actor_id = req
+            .get_identity()
+            .ok()
+            .and_then(|identity| identity.id().ok())
+            .and_then(|id| Uuid::parse_str(&id).ok());
+
+        ready(Ok(AuditContext {
+            actor_id,
+            ip: req.peer_addr().map(|addr| addr.ip().to_string()),
+            user_agent: Self::header(req, "User-Agent", 512),
+            request_id: Self::header(req, REQUEST_ID_HEADER, 100),
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditTarget {
+    User,
+    Organization,
+    Team,
+    Repository,
+}
+
+impl AuditTarget {
+    pub const ALL: &'static [AuditTarget] = &[
+        AuditTarget::User,
+        AuditTarget::Organization,
+        AuditTarget::Team,
+        AuditTarget::Repository,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditTarget::User => "user",
+            AuditTarget::Organization => "organization",
+            AuditTarget::Team => "team",
+            AuditTarget::Repository => "repository",
+        }
+    }
+}
+
+impl fmt::Display for AuditTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    UserRegistered,
+    UserUpdated,
+    UserVerified,
+    LoginSucceeded,
+    LoginFailed,
+    LoggedOut,
+    PasswordResetRequested,
+    PasswordReset,
+    OrganizationCreated,
+    OrganizationUpdated,
+    OrganizationDeleted,
+    OrganizationRestored,
+    MemberAdded,
+    TeamCreated,
+    TeamUpdated,
+    TeamDeleted,
+    TeamMemberAdded,
+    RepositoryCreated,
+    RepositoryUpdated,
+    RepositoryDeleted,
+    RepositoryTransferred,
+    TeamAccessGranted,
+    TeamAccessUpdated,
+    TeamAccessRevoked,
+    HostKeyChanged,
+}
+
+impl AuditAction {
+    pub const ALL: &'static [AuditAction] = &[
+        AuditAction::UserRegistered,
+        AuditAction::UserUpdated,
+        AuditAction::UserVerified,
+        AuditAction::LoginSucceeded,
+        AuditAction::LoginFailed,
+        AuditAction::LoggedOut,
+        AuditAction::PasswordResetRequested,
+        AuditAction::PasswordReset,
+        AuditAction::OrganizationCreated,
+        AuditAction::OrganizationUpdated,
+        AuditAction::OrganizationDeleted,
+        AuditAction::OrganizationRestored,
+        AuditAction::MemberAdded,
+        AuditAction::TeamCreated,
+        AuditAction::TeamUpdated,
+        AuditAction::TeamDeleted,
+        AuditAction::TeamMemberAdded,
+        AuditAction::RepositoryCreated,
+        AuditAction::RepositoryUpdated,
+        AuditAction::RepositoryDeleted,
+        AuditAction::RepositoryTransferred,
+        AuditAction::TeamAccessGranted,
+        AuditAction::TeamAccessUpdated,
+        AuditAction::TeamAccessRevoked,
+        AuditAction::HostKeyChanged,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::UserRegistered => "user.registered",
+            AuditAction::UserUpdated => "user.updated",
+            AuditAction::UserVerified => "user.verified",
+            AuditAction::LoginSucceeded => "auth.login",
+            AuditAction::LoginFailed => "auth.login_failed",
+            AuditAction::LoggedOut => "auth.logout",
+            AuditAction::PasswordResetRequested => "auth.password_reset_requested",
+            AuditAction::PasswordReset => "auth.password_reset",
+            AuditAction::OrganizationCreated => "organization.created",
+            AuditAction::OrganizationUpdated => "organization.updated",
+            AuditAction::OrganizationDeleted => "organization.deleted",
+            AuditAction::OrganizationRestored => "organization.restored",
+            AuditAction::MemberAdded => "organization.member_added",
+            AuditAction::TeamCreated => "team.created",
+            AuditAction::TeamUpdated => "team.updated",
+            AuditAction::TeamDeleted => "team.deleted",
+            AuditAction::TeamMemberAdded => "team.member_added",
+            AuditAction::RepositoryCreated => "repository.created",
+            AuditAction::RepositoryUpdated => "repository.updated",
+            AuditAction::RepositoryDeleted => "repository.deleted",
+            AuditAction::RepositoryTransferred => "repository.transferred",
+            AuditAction::TeamAccessGranted => "repository.team_granted",
+            AuditAction::TeamAccessUpdated => "repository.team_updated",
+            AuditAction::TeamAccessRevoked => "repository.team_revoked",
+            AuditAction::HostKeyChanged => "organization.host_key_changed",
+        }
+    }
+
+    /// Kind of entity the action applies to. Membership changes target the
+    /// user, team grants the repository.
+    pub fn target(&self) -> AuditTarget {
+        match self {
+            AuditAction::UserRegistered
+            | AuditAction::UserUpdated
+            | AuditAction::UserVerified
+            | AuditAction::LoginSucceeded
+            | AuditAction::LoginFailed
+            | AuditAction::LoggedOut
+            | AuditAction::PasswordResetRequested
+            | AuditAction::PasswordReset
+            | AuditAction::MemberAdded
+            | AuditAction::TeamMemberAdded => AuditTarget::User,
+            AuditAction::OrganizationCreated
+            | AuditAction::OrganizationUpdated
+            | AuditAction::OrganizationDeleted
+            | AuditAction::OrganizationRestored
+            | AuditAction::HostKeyChanged => AuditTarget::Organization,
+            AuditAction::TeamCreated | AuditAction::TeamUpdated | AuditAction::TeamDeleted => {
+                AuditTarget::Team
+            }
+            AuditAction::RepositoryCreated
+            | AuditAction::RepositoryUpdated
+            | AuditAction::RepositoryDeleted
+            | AuditAction::RepositoryTransferred
+            | AuditAction::TeamAccessGranted
+            | AuditAction::TeamAccessUpdated
+            | AuditAction::TeamAccessRevoked => AuditTarget::Repository,
+        }
+    }
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = audit_events)]
+pub struct NewAuditEvent {
+    pub organization_id: Option<Uuid>,
+    pub actor_id: Option<Uuid>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<Uuid>,
+    pub changes: Option<Value>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditLogFormat {
+    #[default]
+    Json,
+    Csv,
+    Ndjson,
+}
+
+// A known action, or every action of a kind such as `repository.*`
+fn validate_action(action: &str) -> Result<(), ValidationError> {
+    let known = match action.strip_suffix(".*") {
+        Some(prefix) => AuditAction::ALL
+            .iter()
+            .any(|known| known.as_str().split('.').next() == Some(prefix)),
+        None => AuditAction::ALL
+            .iter()
+            .any(|known| known.as_str() == action),
+    };
+
+    if !known {
+        return Err(ValidationError::new("unknown_action"));
+    }
+
+    Ok(())
+}
+
+fn validate_target_type(target_type: &str) -> Result<(), ValidationError> {
+    if !AuditTarget::ALL
+        .iter()
+        .any(|known| known.as_str() == target_type)
+    {
+        return Err(ValidationError::new("unknown_target_type"));
+    }
+
+    Ok(())
+}
+
+fn validate_cursor(cursor: &str) -> Result<(), ValidationError> {
+    if cursor.parse::<i64>().is_err() {
+        return Err(ValidationError::new("invalid_cursor"));
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize, IntoParams, Validate)]
+pub struct AuditLogQuery {
+    /// Only on the platform-wide log, organization logs are scoped by path
+    pub organization_id: Option<Uuid>,
+    pub actor_id: Option<Uuid>,
+    /// An action such as `organization.deleted`, or `organization.*`
+    #[validate(custom(function = "validate_action"))]
+    pub action: Option<String>,
+    /// `user`, `organization`, `team` or `repository`
+    #[validate(custom(function = "validate_target_type"))]
+    pub target_type: Option<String>,
+    pub target_id: Option<Uuid>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// `next_cursor` of the previous page
+    #[validate(custom(function = "validate_cursor"))]
+    pub cursor: Option<String>,
+    /// Page size for JSON, exports are sent in chunks of up to 10000 events
+    #[validate(range(min = 1, max = 500))]
+    pub limit: Option<i64>,
+    /// `json`, `csv` or `ndjson`
+    pub format: Option<AuditLogFormat>,
+}
+
+/// Events newest first. `next_cursor` is missing on the last page.
+#[derive(Serialize, ToSchema, Debug)]
+pub struct AuditLogPage {
+    pub events: Vec<AuditEvent>,
+    pub next_cursor: Option<String>,
+}
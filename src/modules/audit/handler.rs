@@ -0,0 +1,156 @@
+use crate::db;
+use crate::modules::admin::handler::require_admin;
+use crate::modules::audit::dto::{AuditLogFormat, AuditLogPage, AuditLogQuery};
+use crate::modules::audit::service::{AuditService, DEFAULT_PAGE_SIZE, EXPORT_CHUNK_SIZE};
+use crate::modules::auth::handler::session_user;
+use crate::modules::organization::handler::require_manager;
+use crate::utils::response::{error, success, ApiError};
+use actix_identity::Identity;
+use actix_web::http::header::{self, ContentDisposition, DispositionParam, DispositionType};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use diesel::r2d2::{self, ConnectionManager};
+use diesel::PgConnection;
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+pub const NEXT_CURSOR_HEADER: &str = "X-Next-Cursor";
+
+fn page_size(query: &AuditLogQuery) -> i64 {
+    match query.format.unwrap_or_default() {
+        AuditLogFormat::Json => query.limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        AuditLogFormat::Csv | AuditLogFormat::Ndjson => EXPORT_CHUNK_SIZE,
+    }
+}
+
+fn search(
+    conn: &mut PgConnection,
+    organization_id: Option<Uuid>,
+    query: &AuditLogQuery,
+) -> Result<AuditLogPage, ApiError> {
+    AuditService::search(conn, organization_id, query, page_size(query)).map_err(|e| {
+        ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to retrieve audit log: {}", e),
+        )
+    })
+}
+
+// JSON pages are wrapped like any other response, exports are sent as a
+// file with the cursor of the next chunk in a header
+fn respond(page: AuditLogPage, format: AuditLogFormat) -> HttpResponse {
+    let (body, content_type, extension) = match format {
+        AuditLogFormat::Json => {
+            return HttpResponse::Ok().json(success(StatusCode::OK, Some(page)));
+        }
+        AuditLogFormat::Csv => (AuditService::to_csv(&page.events), "text/csv", "csv"),
+        AuditLogFormat::Ndjson => (
+            AuditService::to_ndjson(&page.events),
+            "application/x-ndjson",
+            "ndjson",
+        ),
+    };
+
+    let body = match body {
+        Ok(body) => body,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to export audit log: {}", e),
+            ));
+        }
+    };
+
+    let mut response = HttpResponse::Ok();
+    response
+        .content_type(content_type)
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!(
+                "audit-log.{}",
+                extension
+            ))],
+        });
+    if let Some(cursor) = &page.next_cursor {
+        response.insert_header((NEXT_CURSOR_HEADER, cursor.as_str()));
+    }
+    response.insert_header((header::CACHE_CONTROL, "no-store"));
+
+    response.body(body)
+}
+
+#[instrument(name = "audit::handler::get_organization_log", skip_all)]
+pub async fn get_organization_log(
+    id: Identity,
+    path: web::Path<Uuid>,
+    pool: web::Data<DbPool>,
+    query: web::Query<AuditLogQuery>,
+) -> HttpResponse {
+    let org_id = path.into_inner();
+
+    // Validate filters
+    if let Err(errors) = query.validate() {
+        return HttpResponse::BadRequest().json(error(
+            StatusCode::BAD_REQUEST,
+            format!("Validation error: {:?}", errors),
+        ));
+    }
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Get a page of the organization's log
+    let query = query.into_inner();
+    let format = query.format.unwrap_or_default();
+    let result = db::block(&pool, move |conn| {
+        // Members can see the organization, only owners, admins and
+        // platform admins its audit log
+        require_manager(conn, user_id, org_id)?;
+        search(conn, Some(org_id), &query)
+    })
+    .await;
+
+    match result {
+        Ok(page) => respond(page, format),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "audit::handler::get_platform_log", skip_all)]
+pub async fn get_platform_log(
+    id: Identity,
+    pool: web::Data<DbPool>,
+    query: web::Query<AuditLogQuery>,
+) -> HttpResponse {
+    // Validate filters
+    if let Err(errors) = query.validate() {
+        return HttpResponse::BadRequest().json(error(
+            StatusCode::BAD_REQUEST,
+            format!("Validation error: {:?}", errors),
+        ));
+    }
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Get a page of the platform-wide log
+    let query = query.into_inner();
+    let format = query.format.unwrap_or_default();
+    let result = db::block(&pool, move |conn| {
+        require_admin(conn, user_id)?;
+        search(conn, query.organization_id, &query)
+    })
+    .await;
+
+    match result {
+        Ok(page) => respond(page, format),
+        Err(e) => e.error_response(),
+    }
+}
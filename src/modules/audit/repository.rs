@@ -0,0 +1,95 @@
+use crate::models::AuditEvent;
+use crate::modules::audit::dto::{AuditLogQuery, NewAuditEvent};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use std::error::Error;
+use tracing::instrument;
+use uuid::Uuid;
+
+pub struct AuditRepository;
+
+impl AuditRepository {
+    #[instrument(name = "AuditRepository::insert", skip_all)]
+    pub fn insert(conn: &mut PgConnection, event: &NewAuditEvent) -> Result<(), Box<dyn Error>> {
+        use crate::schema::audit_events::dsl::*;
+
+        diesel::insert_into(audit_events)
+            .values(event)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Number of `name` events recorded from `address` since `since`.
+    /// Anonymous requests without an address are counted together.
+    #[instrument(name = "AuditRepository::count_recent", skip_all)]
+    pub fn count_recent(
+        conn: &mut PgConnection,
+        name: &str,
+        address: Option<&str>,
+        since: DateTime<Utc>,
+    ) -> Result<i64, Box<dyn Error>> {
+        use crate::schema::audit_events::dsl::*;
+
+        let mut query = audit_events
+            .filter(action.eq(name))
+            .filter(created_at.ge(since))
+            .into_boxed();
+        query = match address {
+            Some(address) => query.filter(ip.eq(address)),
+            None => query.filter(ip.is_null()),
+        };
+
+        Ok(query.count().get_result(conn)?)
+    }
+
+    /// Events matching the query filters, newest first, starting before
+    /// `before_seq` when given.
+    #[instrument(name = "AuditRepository::find", skip_all)]
+    pub fn find(
+        conn: &mut PgConnection,
+        org_id: Option<Uuid>,
+        filters: &AuditLogQuery,
+        before_seq: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<AuditEvent>, Box<dyn Error>> {
+        use crate::schema::audit_events::dsl::*;
+
+        let mut query = audit_events.into_boxed();
+        if let Some(org_id) = org_id {
+            query = query.filter(organization_id.eq(org_id));
+        }
+        if let Some(actor) = filters.actor_id {
+            query = query.filter(actor_id.eq(actor));
+        }
+        if let Some(name) = &filters.action {
+            query = match name.strip_suffix(".*") {
+                Some(prefix) => query.filter(action.like(format!("{}.%", prefix))),
+                None => query.filter(action.eq(name)),
+            };
+        }
+        if let Some(kind) = &filters.target_type {
+            query = query.filter(target_type.eq(kind));
+        }
+        if let Some(target) = filters.target_id {
+            query = query.filter(target_id.eq(target));
+        }
+        if let Some(since) = filters.since {
+            query = query.filter(created_at.ge(since));
+        }
+        if let Some(until) = filters.until {
+            query = query.filter(created_at.lt(until));
+        }
+        if let Some(before_seq) = before_seq {
+            query = query.filter(seq.lt(before_seq));
+        }
+
+        let events = query
+            .order(seq.desc())
+            .limit(limit)
+            .load::<AuditEvent>(conn)?;
+
+        Ok(events)
+    }
+}
@@ -0,0 +1,12 @@
+use crate::modules::audit::handler::{get_organization_log, get_platform_log};
+use actix_web::web;
+
+// Mounted inside the `/organizations` scope
+pub fn config_organization_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/{id}/audit-log", web::get().to(get_organization_log));
+}
+
+// Mounted inside the `/admin` scope
+pub fn config_admin_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/audit-log", web::get().to(get_platform_log));
+}
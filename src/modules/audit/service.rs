@@ -0,0 +1,229 @@
+use crate::models::AuditEvent;
+use crate::modules::audit::dto::{
+    AuditAction, AuditContext, AuditLogPage, AuditLogQuery, NewAuditEvent,
+};
+use crate::modules::audit::repository::AuditRepository;
+use crate::modules::outbox::dto::DomainEvent;
+use chrono::{DateTime, Duration, Utc};
+use diesel::PgConnection;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::borrow::Cow;
+use std::error::Error;
+use tracing::{instrument, warn};
+use uuid::Uuid;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+pub const EXPORT_CHUNK_SIZE: i64 = 10_000;
+
+// Failed logins recorded per address and window, so that guessing
+// passwords cannot flood the append-only log
+pub const MAX_FAILED_LOGINS: i64 = 20;
+pub const FAILED_LOGIN_WINDOW_MINUTES: i64 = 10;
+
+// Spreadsheets evaluate cells starting with these as formulas
+const FORMULA_PREFIXES: &[char] = &['=', '+', '-', '@', '\t', '\r'];
+
+// Bumped on every write, they would show up in every diff
+const IGNORED_FIELDS: &[&str] = &["created_at", "updated_at"];
+
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    id: Uuid,
+    created_at: DateTime<Utc>,
+    organization_id: Option<Uuid>,
+    actor_id: Option<Uuid>,
+    action: Cow<'a, str>,
+    target_type: Cow<'a, str>,
+    target_id: Option<Uuid>,
+    changes: Option<String>,
+    ip: Option<Cow<'a, str>>,
+    user_agent: Option<Cow<'a, str>>,
+    request_id: Option<Cow<'a, str>>,
+}
+
+pub struct AuditService;
+
+impl AuditService {
+    /// Appends an event to the audit log. Call it inside the transaction
+    /// making the change, so the event is stored if and only if the change
+    /// is.
+    #[instrument(name = "AuditService::record", skip_all)]
+    pub fn record(
+        conn: &mut PgConnection,
+        context: &AuditContext,
+        action: AuditAction,
+        organization_id: Option<Uuid>,
+        target_id: Option<Uuid>,
+        changes: Option<Value>,
+    ) -> Result<(), Box<dyn Error>> {
+        let event = NewAuditEvent {
+            organization_id,
+            actor_id: context.actor_id,
+            action: action.as_str().to_string(),
+            target_type: action.target().as_str().to_string(),
+            target_id,
+            changes,
+            ip: context.ip.clone(),
+            user_agent: context.user_agent.clone(),
+            request_id: context.request_id.clone(),
+        };
+
+        AuditRepository::insert(conn, &event)
+    }
+
+    /// Records a failed login attempt, unless the address already made
+    /// `MAX_FAILED_LOGINS` of them within the last
+    /// `FAILED_LOGIN_WINDOW_MINUTES`.
+    #[instrument(name = "AuditService::record_failed_login", skip_all)]
+    pub fn record_failed_login(
+        conn: &mut PgConnection,
+        context: &AuditContext,
+        target_id: Option<Uuid>,
+    ) -> Result<(), Box<dyn Error>> {
+        let action = AuditAction::LoginFailed;
+        let since = Utc::now() - Duration::minutes(FAILED_LOGIN_WINDOW_MINUTES);
+        let recent =
+            AuditRepository::count_recent(conn, action.as_str(), context.ip.as_deref(), since)?;
+        if recent >= MAX_FAILED_LOGINS {
+            warn!(ip = ?context.ip, "Too many failed logins, not recording this one");
+            return Ok(());
+        }
+
+        Self::record(conn, context, action, None, target_id, None)
+    }
+
+    /// Records domain events raised outside of any request. Changes made
+    /// through the API are recorded in their own transaction instead, with
+    /// the request that made them.
+    #[instrument(name = "AuditService::publish", skip_all)]
+    pub fn publish(conn: &mut PgConnection, event: &DomainEvent) -> Result<(), Box<dyn Error>> {
+        match event {
+            DomainEvent::HostKeyChanged {
+                organization_id,
+                host,
+                key_type,
+                fingerprint,
+            } => Self::record(
+                conn,
+                &AuditContext::background("outbox"),
+                AuditAction::HostKeyChanged,
+                Some(*organization_id),
+                Some(*organization_id),
+                Some(json!({
+                    "host": host,
+                    "key_type": key_type,
+                    "fingerprint": fingerprint,
+                })),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fields that differ between two states of an entity, as
+    /// `{"field": {"before": .., "after": ..}}`. A missing state lists
+    /// every field of the other one. `None` when nothing changed.
+    #[instrument(name = "AuditService::diff", skip_all)]
+    pub fn diff<T: Serialize>(
+        before: Option<&T>,
+        after: Option<&T>,
+    ) -> Result<Option<Value>, serde_json::Error> {
+        let before = Self::fields(before)?;
+        let after = Self::fields(after)?;
+
+        let mut changes = Map::new();
+        for key in before.keys().chain(after.keys()) {
+            if IGNORED_FIELDS.contains(&key.as_str()) || changes.contains_key(key) {
+                continue;
+            }
+
+            let old = before.get(key).unwrap_or(&Value::Null);
+            let new = after.get(key).unwrap_or(&Value::Null);
+            if old != new {
+                changes.insert(key.clone(), json!({ "before": old, "after": new }));
+            }
+        }
+
+        Ok((!changes.is_empty()).then_some(Value::Object(changes)))
+    }
+
+    fn fields<T: Serialize>(state: Option<&T>) -> Result<Map<String, Value>, serde_json::Error> {
+        match state.map(serde_json::to_value).transpose()? {
+            Some(Value::Object(fields)) => Ok(fields),
+            Some(value) => Ok(Map::from_iter([("value".to_string(), value)])),
+            None => Ok(Map::new()),
+        }
+    }
+
+    /// A page of the log, the whole platform's when `organization_id` is
+    /// `None`.
+    #[instrument(name = "AuditService::search", skip_all)]
+    pub fn search(
+        conn: &mut PgConnection,
+        organization_id: Option<Uuid>,
+        query: &AuditLogQuery,
+        limit: i64,
+    ) -> Result<AuditLogPage, Box<dyn Error>> {
+        let before_seq = query.cursor.as_deref().map(str::parse::<i64>).transpose()?;
+
+        // One extra row tells whether there is a next page
+        let mut events =
+            AuditRepository::find(conn, organization_id, query, before_seq, limit + 1)?;
+        let next_cursor = if events.len() as i64 > limit {
+            events.truncate(limit as usize);
+            events.last().map(|event| event.seq.to_string())
+        } else {
+            None
+        };
+
+        Ok(AuditLogPage {
+            events,
+            next_cursor,
+        })
+    }
+
+    #[instrument(name = "AuditService::to_csv", skip_all)]
+    pub fn to_csv(events: &[AuditEvent]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        for event in events {
+            writer.serialize(CsvRow {
+                id: event.id,
+                created_at: event.created_at,
+                organization_id: event.organization_id,
+                actor_id: event.actor_id,
+                action: Self::csv_cell(&event.action),
+                target_type: Self::csv_cell(&event.target_type),
+                target_id: event.target_id,
+                changes: event
+                    .changes
+                    .as_ref()
+                    .map(|changes| Self::csv_cell(&changes.to_string()).into_owned()),
+                ip: event.ip.as_deref().map(Self::csv_cell),
+                user_agent: event.user_agent.as_deref().map(Self::csv_cell),
+                request_id: event.request_id.as_deref().map(Self::csv_cell),
+            })?;
+        }
+
+        writer.into_inner().map_err(|e| e.to_string().into())
+    }
+
+    /// Prefixes values a spreadsheet would run as a formula with `'`, so
+    /// that they are displayed as text.
+    pub fn csv_cell(value: &str) -> Cow<'_, str> {
+        match value.starts_with(FORMULA_PREFIXES) {
+            true => Cow::Owned(format!("'{}", value)),
+            false => Cow::Borrowed(value),
+        }
+    }
+
+    #[instrument(name = "AuditService::to_ndjson", skip_all)]
+    pub fn to_ndjson(events: &[AuditEvent]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut body = Vec::new();
+        for event in events {
+            serde_json::to_writer(&mut body, event)?;
+            body.push(b'\n');
+        }
+
+        Ok(body)
+    }
+}
@@ -1,211 +1,214 @@
 use crate::config::Config;
+use crate::db;
+use crate::modules::audit::dto::AuditContext;
 use crate::modules::auth::dto::{LoginQuery, RegisterQuery, ResetPasswordQuery, VerifyQuery};
 use crate::modules::auth::service::AuthService;
-use crate::utils::response::{error, success};
+use crate::utils::metrics::METRICS;
+use crate::utils::response::{error, success, ApiError};
 use actix_identity::Identity;
 use actix_web::http::StatusCode;
-use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
 use diesel::r2d2::{self, ConnectionManager};
 use diesel::PgConnection;
+use tracing::instrument;
 use uuid::Uuid;
 use validator::Validate;
 
 type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 
+// Resolve the session user, before handing work to the blocking pool
+pub fn session_user(id: &Identity) -> Result<Uuid, ApiError> {
+    // Get user ID from session
+    let user_id = id
+        .id()
+        .map_err(|_| ApiError::new(StatusCode::UNAUTHORIZED, "Not authenticated".into()))?;
+
+    // Parse UUID
+    Uuid::parse_str(&user_id)
+        .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "Invalid user ID format".into()))
+}
+
+#[instrument(name = "auth::handler::register", skip_all)]
 pub async fn register(
     pool: web::Data<DbPool>,
     user_data: web::Json<RegisterQuery>,
+    audit: AuditContext,
 ) -> HttpResponse {
     // Validate user data
     if let Err(errors) = user_data.validate() {
+        METRICS.registrations.with_label_values(&["invalid"]).inc();
         return HttpResponse::BadRequest().json(error(
             StatusCode::BAD_REQUEST,
             format!("Validation error: {:?}", errors),
         ));
     }
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
+    // Register user, hashing the password off the worker
+    let user_data = user_data.into_inner();
+    let result = db::block(&pool, move |conn| {
+        AuthService::register(conn, &user_data, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Registration failed: {}", e),
+            )
+        })
+    })
+    .await;
+
+    let outcome = if result.is_ok() { "success" } else { "failure" };
+    METRICS.registrations.with_label_values(&[outcome]).inc();
 
-    // Register user
-    match AuthService::register(&mut conn, &user_data) {
+    match result {
         Ok(user) => HttpResponse::Created().json(success(StatusCode::CREATED, Some(user))),
-        Err(e) => HttpResponse::BadRequest().json(error(
-            StatusCode::BAD_REQUEST,
-            format!("Registration failed: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
+#[instrument(name = "auth::handler::login", skip_all)]
 pub async fn login(
     req: HttpRequest,
     pool: web::Data<DbPool>,
     login_data: web::Json<LoginQuery>,
+    audit: AuditContext,
 ) -> HttpResponse {
     // Validate login data
     if let Err(errors) = login_data.validate() {
+        METRICS.logins.with_label_values(&["invalid"]).inc();
         return HttpResponse::BadRequest().json(error(
             StatusCode::BAD_REQUEST,
             format!("Validation error: {:?}", errors),
         ));
     }
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
+    // Check credentials, verifying the password off the worker
+    let login_data = login_data.into_inner();
+    let result = db::block(&pool, move |conn| {
+        AuthService::login(conn, &login_data, &audit)
+            .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, format!("Login failed: {}", e)))
+    })
+    .await;
+
+    let outcome = if result.is_ok() { "success" } else { "failure" };
+    METRICS.logins.with_label_values(&[outcome]).inc();
+
+    match result {
+        Ok(user) => {
+            // Password verified, log the user in
+            let _ = Identity::login(&req.extensions(), user.id.to_string());
+            HttpResponse::Ok().json(success(StatusCode::OK, Some(user)))
         }
-    };
-
-    // Login user
-    match AuthService::login(&req, &mut conn, &login_data) {
-        Ok(user) => HttpResponse::Ok().json(success(StatusCode::OK, Some(user))),
-        Err(e) => HttpResponse::BadRequest().json(error(
-            StatusCode::BAD_REQUEST,
-            format!("Login failed: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
-pub async fn logout(id: Identity) -> HttpResponse {
-    match AuthService::logout(id) {
-        Ok(_) => HttpResponse::Ok().json(success::<()>(StatusCode::OK, None)),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Logout failed: {}", e),
-        )),
+#[instrument(name = "auth::handler::logout", skip_all)]
+pub async fn logout(id: Identity, pool: web::Data<DbPool>, audit: AuditContext) -> HttpResponse {
+    // Record logout
+    let result = db::block(&pool, move |conn| {
+        AuthService::logout(conn, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Logout failed: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(_) => {
+            id.logout();
+            HttpResponse::Ok().json(success::<()>(StatusCode::OK, None))
+        }
+        Err(e) => e.error_response(),
     }
 }
 
+#[instrument(name = "auth::handler::request_verification", skip_all)]
 pub async fn request_verification(
     id: Identity,
     pool: web::Data<DbPool>,
     config: web::Data<Config>,
 ) -> HttpResponse {
-    // Get user ID from session
-    let user_id = match id.id() {
-        Ok(id) => id,
-        Err(_) => {
-            return HttpResponse::Unauthorized()
-                .json(error(StatusCode::UNAUTHORIZED, "Not authenticated".into()));
-        }
-    };
-
-    // Parse UUID
-    let uuid = match Uuid::parse_str(&user_id) {
-        Ok(id) => id,
-        Err(_) => {
-            return HttpResponse::BadRequest().json(error(
-                StatusCode::BAD_REQUEST,
-                "Invalid user ID format".into(),
-            ));
-        }
-    };
-
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
+    let uuid = match session_user(&id) {
+        Ok(uuid) => uuid,
+        Err(e) => return e.error_response(),
     };
 
-    // Request email verification
-    match AuthService::request_verification(&mut conn, uuid, &config) {
+    // Request email verification, sending the email off the worker
+    let result = db::block(&pool, move |conn| {
+        AuthService::request_verification(conn, uuid, &config).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to send verification email: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(_) => HttpResponse::Ok().json(success::<()>(StatusCode::OK, None)),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to send verification email: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
-pub async fn verify(pool: web::Data<DbPool>, token_data: web::Json<VerifyQuery>) -> HttpResponse {
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
+#[instrument(name = "auth::handler::verify", skip_all)]
+pub async fn verify(
+    pool: web::Data<DbPool>,
+    token_data: web::Json<VerifyQuery>,
+    audit: AuditContext,
+) -> HttpResponse {
     // Verify email
-    match AuthService::verify(&mut conn, &token_data) {
+    let token_data = token_data.into_inner();
+    let result = db::block(&pool, move |conn| {
+        AuthService::verify(conn, &token_data, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Email verification failed: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(_) => HttpResponse::Ok().json(success::<()>(StatusCode::OK, None)),
-        Err(e) => HttpResponse::BadRequest().json(error(
-            StatusCode::BAD_REQUEST,
-            format!("Email verification failed: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
+#[instrument(name = "auth::handler::forgot_password", skip_all)]
 pub async fn forgot_password(
     id: Identity,
     pool: web::Data<DbPool>,
     config: web::Data<Config>,
+    audit: AuditContext,
 ) -> HttpResponse {
-    // Get user ID from session
-    let user_id = match id.id() {
-        Ok(id) => id,
-        Err(_) => {
-            return HttpResponse::Unauthorized()
-                .json(error(StatusCode::UNAUTHORIZED, "Not authenticated".into()));
-        }
+    let uuid = match session_user(&id) {
+        Ok(uuid) => uuid,
+        Err(e) => return e.error_response(),
     };
 
-    // Parse UUID
-    let uuid = match Uuid::parse_str(&user_id) {
-        Ok(id) => id,
-        Err(_) => {
-            return HttpResponse::BadRequest().json(error(
-                StatusCode::BAD_REQUEST,
-                "Invalid user ID format".into(),
-            ));
-        }
-    };
-
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
-    // Send password reset email
-    match AuthService::forgot_password(&mut conn, uuid, &config) {
+    // Send password reset email off the worker
+    let result = db::block(&pool, move |conn| {
+        AuthService::forgot_password(conn, uuid, &config, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to send verification email: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(_) => HttpResponse::Ok().json(success::<()>(StatusCode::OK, None)),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to send verification email: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
+#[instrument(name = "auth::handler::reset_password", skip_all)]
 pub async fn reset_password(
     pool: web::Data<DbPool>,
     reset_data: web::Json<ResetPasswordQuery>,
+    audit: AuditContext,
 ) -> HttpResponse {
     // Validate reset data
     if let Err(errors) = reset_data.validate() {
@@ -223,26 +226,23 @@ pub async fn reset_password(
         ));
     }
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
+    // Reset password, hashing it off the worker
+    let reset_data = reset_data.into_inner();
+    let result = db::block(&pool, move |conn| {
+        AuthService::reset_password(conn, &reset_data, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Password reset failed: {}", e),
+            )
+        })
+    })
+    .await;
 
-    // Reset password
-    match AuthService::reset_password(&mut conn, &reset_data) {
+    match result {
         Ok(_) => HttpResponse::Ok().json(success::<String>(
             StatusCode::OK,
             Some("Password has been reset successfully".into()),
         )),
-        Err(e) => HttpResponse::BadRequest().json(error(
-            StatusCode::BAD_REQUEST,
-            format!("Password reset failed: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
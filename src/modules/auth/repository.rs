@@ -3,15 +3,19 @@ use crate::models::ResetPasswordToken;
 use crate::models::User;
 use crate::models::VerificationToken;
 use crate::modules::auth::dto::RegisterQuery;
+use crate::modules::outbox::dto::DomainEvent;
+use crate::modules::outbox::repository::OutboxRepository;
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use diesel::PgConnection;
 use std::error::Error;
+use tracing::instrument;
 use uuid::Uuid;
 
 pub struct AuthRepository;
 
 impl AuthRepository {
+    #[instrument(name = "AuthRepository::find_user_by_id", skip_all)]
     pub fn find_user_by_id(
         conn: &mut PgConnection,
         user_id: Uuid,
@@ -27,6 +31,7 @@ impl AuthRepository {
         Ok(user)
     }
 
+    #[instrument(name = "AuthRepository::find_user_by_email", skip_all)]
     pub fn find_user_by_email(
         conn: &mut PgConnection,
         user_email: &str,
@@ -42,6 +47,7 @@ impl AuthRepository {
         Ok(user)
     }
 
+    #[instrument(name = "AuthRepository::create_user_account", skip_all)]
     pub fn create_user_account(
         conn: &mut PgConnection,
         new_user: &RegisterQuery,
@@ -71,6 +77,7 @@ impl AuthRepository {
         })
     }
 
+    #[instrument(name = "AuthRepository::create_verification_token", skip_all)]
     pub fn create_verification_token(
         conn: &mut PgConnection,
         new_user_id: Uuid,
@@ -90,6 +97,7 @@ impl AuthRepository {
         Ok(())
     }
 
+    #[instrument(name = "AuthRepository::find_verification_token", skip_all)]
     pub fn find_verification_token(
         conn: &mut PgConnection,
         token_to_find: &str,
@@ -104,6 +112,7 @@ impl AuthRepository {
         Ok(verification_token)
     }
 
+    #[instrument(name = "AuthRepository::use_verification_token", skip_all)]
     pub fn use_verification_token(
         conn: &mut PgConnection,
         token: &VerificationToken,
@@ -119,9 +128,7 @@ impl AuthRepository {
                 .first::<User>(conn)?;
 
             // Update the user's verified status
-            diesel::update(users::table.filter(users::id.eq(user.id)))
-                .set(users::verified.eq(true))
-                .execute(conn)?;
+            Self::verify_user(conn, &user)?;
 
             // Delete the verification token
             diesel::update(verification_tokens::table.filter(verification_tokens::id.eq(token.id)))
@@ -132,6 +139,27 @@ impl AuthRepository {
         })
     }
 
+    /// Marks the user as verified and records the `UserVerified` event.
+    #[instrument(name = "AuthRepository::verify_user", skip_all)]
+    pub fn verify_user(conn: &mut PgConnection, user: &User) -> Result<(), Box<dyn Error>> {
+        use crate::schema::users;
+
+        diesel::update(users::table.filter(users::id.eq(user.id)))
+            .set(users::verified.eq(true))
+            .execute(conn)?;
+
+        OutboxRepository::record(
+            conn,
+            &DomainEvent::UserVerified {
+                user_id: user.id,
+                email: user.email.clone(),
+            },
+        )?;
+
+        Ok(())
+    }
+
+    #[instrument(name = "AuthRepository::create_reset_password_token", skip_all)]
     pub fn create_reset_password_token(
         conn: &mut PgConnection,
         new_user_id: Uuid,
@@ -151,6 +179,7 @@ impl AuthRepository {
         Ok(())
     }
 
+    #[instrument(name = "AuthRepository::find_reset_password_token", skip_all)]
     pub fn find_reset_password_token(
         conn: &mut PgConnection,
         token_to_find: &str,
@@ -165,29 +194,17 @@ impl AuthRepository {
         Ok(verification_token)
     }
 
+    #[instrument(name = "AuthRepository::use_reset_password_token", skip_all)]
     pub fn use_reset_password_token(
         conn: &mut PgConnection,
         token: &ResetPasswordToken,
         new_password: &str,
     ) -> Result<(), Box<dyn Error>> {
         // find the user's account, then update the password, then set the token as used, everything in a transaction
-        use crate::schema::accounts;
         use crate::schema::reset_password_tokens;
 
         conn.transaction(|conn| {
-            // Find the associated account
-            let account = accounts::table
-                .filter(accounts::user_id.eq(token.user_id))
-                .filter(accounts::account_type.eq("credentials"))
-                .first::<Account>(conn)?;
-
-            // Hash the new password
-            let hashed_password = crate::utils::password::hash_password(new_password)?;
-
-            // Update the account's password
-            diesel::update(accounts::table.filter(accounts::id.eq(account.id)))
-                .set(accounts::password.eq(&hashed_password))
-                .execute(conn)?;
+            Self::set_password(conn, token.user_id, new_password)?;
 
             // Delete the reset password token
             diesel::update(
@@ -199,4 +216,30 @@ impl AuthRepository {
             Ok(())
         })
     }
+
+    /// Replaces the password of the user's credentials account.
+    #[instrument(name = "AuthRepository::set_password", skip_all)]
+    pub fn set_password(
+        conn: &mut PgConnection,
+        account_user_id: Uuid,
+        new_password: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::schema::accounts;
+
+        // Find the associated account
+        let account = accounts::table
+            .filter(accounts::user_id.eq(account_user_id))
+            .filter(accounts::account_type.eq("credentials"))
+            .first::<Account>(conn)?;
+
+        // Hash the new password
+        let hashed_password = crate::utils::password::hash_password(new_password)?;
+
+        // Update the account's password
+        diesel::update(accounts::table.filter(accounts::id.eq(account.id)))
+            .set(accounts::password.eq(&hashed_password))
+            .execute(conn)?;
+
+        Ok(())
+    }
 }
@@ -1,51 +1,94 @@
 use crate::config::Config;
 use crate::models::User;
+use crate::modules::audit::dto::{AuditAction, AuditContext};
+use crate::modules::audit::service::AuditService;
 use crate::modules::auth::dto::{LoginQuery, RegisterQuery, ResetPasswordQuery, VerifyQuery};
 use crate::modules::auth::repository::AuthRepository;
-use actix_identity::Identity;
-use actix_web::{web, HttpMessage, HttpRequest};
+use crate::utils::mail;
+use actix_web::web;
 use chrono::{Duration, Utc};
 use diesel::prelude::*;
 use diesel::PgConnection;
 use std::error::Error;
+use tracing::instrument;
 use uuid::Uuid;
 
 pub struct AuthService;
 
 impl AuthService {
+    #[instrument(name = "AuthService::register", skip_all)]
     pub fn register(
         conn: &mut PgConnection,
         user_data: &RegisterQuery,
+        audit: &AuditContext,
     ) -> Result<User, Box<dyn Error>> {
         // Check if user already exists
         if let Some(_) = AuthRepository::find_user_by_email(conn, &user_data.email)? {
             return Err("User with this email already exists".into());
         }
 
-        // Create user and associated account
-        let user = AuthRepository::create_user_account(conn, user_data)?;
-
-        Ok(user)
+        conn.transaction(|conn| {
+            // Create user and associated account
+            let user = AuthRepository::create_user_account(conn, user_data)?;
+            AuditService::record(
+                conn,
+                &audit.with_actor(user.id),
+                AuditAction::UserRegistered,
+                None,
+                Some(user.id),
+                AuditService::diff(None, Some(&user))?,
+            )?;
+
+            Ok(user)
+        })
     }
 
+    /// Checks the credentials. The caller logs the user in, since the
+    /// session lives on the request.
+    #[instrument(name = "AuthService::login", skip_all)]
     pub fn login(
-        req: &HttpRequest,
         conn: &mut PgConnection,
         login_data: &LoginQuery,
+        audit: &AuditContext,
     ) -> Result<User, Box<dyn Error>> {
-        use crate::models::Account;
-        use crate::schema::accounts::dsl::*;
-        use crate::utils::password::verify_password;
-
         // Find user by email
         let user = match AuthRepository::find_user_by_email(conn, &login_data.email)? {
             Some(user) => user,
-            None => return Err("User not found".into()),
+            None => {
+                AuditService::record_failed_login(conn, audit, None)?;
+                return Err("User not found".into());
+            }
         };
 
+        if let Err(e) = Self::check_password(conn, user.id, &login_data.password) {
+            AuditService::record_failed_login(conn, audit, Some(user.id))?;
+            return Err(e);
+        }
+
+        AuditService::record(
+            conn,
+            &audit.with_actor(user.id),
+            AuditAction::LoginSucceeded,
+            None,
+            Some(user.id),
+            None,
+        )?;
+
+        Ok(user)
+    }
+
+    fn check_password(
+        conn: &mut PgConnection,
+        account_user_id: Uuid,
+        candidate: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::models::Account;
+        use crate::schema::accounts::dsl::*;
+        use crate::utils::password::verify_password;
+
         // Find the associated account with credentials
         let account = accounts
-            .filter(user_id.eq(user.id))
+            .filter(user_id.eq(account_user_id))
             .filter(account_type.eq("credentials"))
             .filter(deleted_at.is_null())
             .first::<Account>(conn)
@@ -54,29 +97,34 @@ impl AuthService {
 
         // Get the stored password hash and verify
         let stored_hash = account.password.ok_or("No password set for this account")?;
-        if !verify_password(&login_data.password, &stored_hash)? {
+        if !verify_password(candidate, &stored_hash)? {
             return Err("Invalid password".into());
         }
 
-        // Password verified, log the user in
-        let _ = Identity::login(&req.extensions(), user.id.to_string());
-
-        Ok(user)
+        Ok(())
     }
 
-    pub fn logout(id: Identity) -> Result<(), Box<dyn Error>> {
-        id.logout();
-        Ok(())
+    /// Records the logout. The caller ends the session, which lives on the
+    /// request.
+    #[instrument(name = "AuthService::logout", skip_all)]
+    pub fn logout(conn: &mut PgConnection, audit: &AuditContext) -> Result<(), Box<dyn Error>> {
+        AuditService::record(
+            conn,
+            audit,
+            AuditAction::LoggedOut,
+            None,
+            audit.actor_id,
+            None,
+        )
     }
 
+    #[instrument(name = "AuthService::request_verification", skip_all)]
     pub fn request_verification(
         conn: &mut PgConnection,
         user_id: Uuid,
         config: &web::Data<Config>,
     ) -> Result<(), Box<dyn Error>> {
-        use lettre::transport::smtp::authentication::Credentials;
-        use lettre::transport::smtp::client::{Tls, TlsParameters};
-        use lettre::{Message, SmtpTransport, Transport};
+        use lettre::Message;
         use rand::{distributions::Alphanumeric, Rng};
 
         // Récupérer le user pour son mail
@@ -119,46 +167,16 @@ impl AuthService {
                 user.name, config.smtp.frontend_url, token
             ))?;
 
-        let creds = Credentials::new(config.smtp.username.clone(), config.smtp.password.clone());
-
-        // Build different transports based on TLS mode
-        let mailer = match config.smtp.tls_mode.to_lowercase().as_str() {
-            "none" => SmtpTransport::builder_dangerous(&config.smtp.server)
-                .credentials(creds)
-                .port(config.smtp.port)
-                .build(),
-            "required" => {
-                let tls_parameters = TlsParameters::new(config.smtp.server.clone())
-                    .map_err(|e| format!("TLS error: {}", e))?;
-
-                SmtpTransport::builder_dangerous(&config.smtp.server)
-                    .credentials(creds)
-                    .port(config.smtp.port)
-                    .tls(Tls::Required(tls_parameters))
-                    .build()
-            }
-            _ => {
-                // "opportunistic" - default
-                SmtpTransport::builder_dangerous(&config.smtp.server)
-                    .credentials(creds)
-                    .port(config.smtp.port)
-                    .tls(Tls::Opportunistic(
-                        TlsParameters::new(config.smtp.server.clone())
-                            .map_err(|e| format!("TLS error: {}", e))?,
-                    ))
-                    .build()
-            }
-        };
-
-        // Send the email with detailed error handling
-        mailer.send(&email)?;
+        mail::send(&config.smtp, "verification", &email)?;
 
         Ok(())
     }
 
+    #[instrument(name = "AuthService::verify", skip_all)]
     pub fn verify(
         conn: &mut PgConnection,
         verify_data: &VerifyQuery,
+        audit: &AuditContext,
     ) -> Result<(), Box<dyn Error>> {
         // retrieve the verification token
         let token = match AuthRepository::find_verification_token(conn, &verify_data.token)? {
@@ -175,20 +193,28 @@ impl AuthService {
             return Err("Token has already been used".into());
         }
 
-        // set the token as used
-        AuthRepository::use_verification_token(conn, &token)?;
-
-        Ok(())
+        conn.transaction(|conn| {
+            // set the token as used
+            AuthRepository::use_verification_token(conn, &token)?;
+            AuditService::record(
+                conn,
+                &audit.with_actor(token.user_id),
+                AuditAction::UserVerified,
+                None,
+                Some(token.user_id),
+                None,
+            )
+        })
     }
 
+    #[instrument(name = "AuthService::forgot_password", skip_all)]
     pub fn forgot_password(
         conn: &mut PgConnection,
         user_id: Uuid,
         config: &web::Data<Config>,
+        audit: &AuditContext,
     ) -> Result<(), Box<dyn Error>> {
-        use lettre::transport::smtp::authentication::Credentials;
-        use lettre::transport::smtp::client::{Tls, TlsParameters};
-        use lettre::{Message, SmtpTransport, Transport};
+        use lettre::Message;
         use rand::{distributions::Alphanumeric, Rng};
 
         // Récupérer le user pour son mail
@@ -207,7 +233,17 @@ impl AuthService {
             .collect();
 
         let expiration = Utc::now() + Duration::minutes(10);
-        AuthRepository::create_reset_password_token(conn, user_id, &token, expiration)?;
+        conn.transaction(|conn| {
+            AuthRepository::create_reset_password_token(conn, user_id, &token, expiration)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::PasswordResetRequested,
+                None,
+                Some(user_id),
+                None,
+            )
+        })?;
 
         let email = Message::builder()
             .from(
@@ -231,46 +267,16 @@ impl AuthService {
                 user.name, config.smtp.frontend_url, token
             ))?;
 
-        let creds = Credentials::new(config.smtp.username.clone(), config.smtp.password.clone());
-
-        // Build different transports based on TLS mode
-        let mailer = match config.smtp.tls_mode.to_lowercase().as_str() {
-            "none" => SmtpTransport::builder_dangerous(&config.smtp.server)
-                .credentials(creds)
-                .port(config.smtp.port)
-                .build(),
-            "required" => {
-                let tls_parameters = TlsParameters::new(config.smtp.server.clone())
-                    .map_err(|e| format!("TLS error: {}", e))?;
-
-                SmtpTransport::builder_dangerous(&config.smtp.server)
-                    .credentials(creds)
-                    .port(config.smtp.port)
-                    .tls(Tls::Required(tls_parameters))
-                    .build()
-            }
-            _ => {
-                // "opportunistic" - default
-                SmtpTransport::builder_dangerous(&config.smtp.server)
-                    .credentials(creds)
-                    .port(config.smtp.port)
-                    .tls(Tls::Opportunistic(
-                        TlsParameters::new(config.smtp.server.clone())
-                            .map_err(|e| format!("TLS error: {}", e))?,
-                    ))
-                    .build()
-            }
-        };
-
-        // Send the email with detailed error handling
-        mailer.send(&email)?;
+        mail::send(&config.smtp, "password_reset", &email)?;
 
         Ok(())
     }
 
+    #[instrument(name = "AuthService::reset_password", skip_all)]
     pub fn reset_password(
         conn: &mut PgConnection,
         reset_data: &ResetPasswordQuery,
+        audit: &AuditContext,
     ) -> Result<(), Box<dyn Error>> {
         // verify matching passwords
         if reset_data.password != reset_data.password_confirm {
@@ -292,9 +298,17 @@ impl AuthService {
             return Err("Token has already been used".into());
         }
 
-        // set the token as used
-        AuthRepository::use_reset_password_token(conn, &token, &reset_data.password)?;
-
-        Ok(())
+        conn.transaction(|conn| {
+            // set the token as used
+            AuthRepository::use_reset_password_token(conn, &token, &reset_data.password)?;
+            AuditService::record(
+                conn,
+                &audit.with_actor(token.user_id),
+                AuditAction::PasswordReset,
+                None,
+                Some(token.user_id),
+                None,
+            )
+        })
     }
 }
@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+#[derive(Debug)]
+pub enum BrowseError {
+    /// The ref, commit or path does not exist in the mirror
+    NotFound(String),
+    /// The blob is larger than `BROWSE_MAX_BLOB_BYTES`
+    TooLarge {
+        size: usize,
+        limit: usize,
+    },
+    Git(String),
+}
+
+impl fmt::Display for BrowseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BrowseError::NotFound(what) => write!(f, "{} not found", what),
+            BrowseError::TooLarge { size, limit } => write!(
+                f,
+                "File is {} bytes, larger than the {} bytes limit",
+                size, limit
+            ),
+            BrowseError::Git(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<git2::Error> for BrowseError {
+    fn from(e: git2::Error) -> Self {
+        BrowseError::Git(e.message().to_string())
+    }
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct Branch {
+    pub name: String,
+    pub sha: String,
+    pub is_default: bool,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct Tag {
+    pub name: String,
+    /// Commit the tag points to, through annotated tags
+    pub sha: String,
+    pub annotated: bool,
+    pub message: Option<String>,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct Signature {
+    pub name: String,
+    pub email: String,
+    pub date: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct CommitSummary {
+    pub sha: String,
+    pub parents: Vec<String>,
+    pub author: Signature,
+    pub committer: Signature,
+    pub summary: String,
+    pub message: String,
+}
+
+#[derive(Deserialize, IntoParams, Validate)]
+pub struct CommitListQuery {
+    /// Branch, tag, full ref name or full commit SHA to list from, defaults
+    /// to the default branch
+    #[serde(rename = "ref")]
+    pub rev: Option<String>,
+    /// Only list commits touching this file or directory
+    pub path: Option<String>,
+    #[validate(range(min = 1))]
+    pub page: Option<usize>,
+    #[validate(range(min = 1, max = 100))]
+    pub per_page: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct CommitPage {
+    pub commits: Vec<CommitSummary>,
+    pub page: usize,
+    pub per_page: usize,
+    pub has_more: bool,
+    /// The walk stopped after `BROWSE_MAX_WALK_COMMITS` commits, older
+    /// matching commits are not listed
+    pub truncated: bool,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct FileStat {
+    pub path: String,
+    /// Previous path of a renamed file
+    pub old_path: Option<String>,
+    /// `added`, `deleted`, `modified`, `renamed`, ...
+    pub status: String,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub binary: bool,
+}
+
+/// A commit with its changes against the first parent.
+#[derive(Serialize, ToSchema, Debug)]
+pub struct CommitDetails {
+    #[serde(flatten)]
+    pub commit: CommitSummary,
+    pub files_changed: usize,
+    /// Lines added in the listed files
+    pub insertions: usize,
+    /// Lines removed from the listed files
+    pub deletions: usize,
+    /// At most `BROWSE_MAX_DIFF_FILES` of the changed files
+    pub files: Vec<FileStat>,
+    pub truncated: bool,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct TreeEntry {
+    pub name: String,
+    pub path: String,
+    /// `blob`, `tree` or `commit` for submodules
+    pub kind: String,
+    pub mode: String,
+    pub sha: String,
+    /// Size of blobs, in bytes
+    pub size: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct Tree {
+    pub sha: String,
+    pub path: String,
+    pub entries: Vec<TreeEntry>,
+    pub truncated: bool,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct Blob {
+    pub sha: String,
+    pub path: String,
+    pub size: usize,
+    pub binary: bool,
+    /// `utf-8` for text, `base64` for binary content
+    pub encoding: String,
+    pub content: String,
+}
@@ -0,0 +1,177 @@
+use crate::config::Config;
+use crate::db;
+use crate::modules::auth::handler::session_user;
+use crate::modules::browse::dto::{BrowseError, CommitListQuery};
+use crate::modules::browse::service::BrowseService;
+use crate::modules::mirror::service::MirrorService;
+use crate::modules::organization::handler::require_member;
+use crate::modules::repo::service::RepoService;
+use crate::utils::response::{error, success, ApiError};
+use actix_identity::Identity;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use diesel::r2d2::{self, ConnectionManager};
+use diesel::PgConnection;
+use git2::Repository;
+use serde::Serialize;
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+const DEFAULT_PER_PAGE: usize = 30;
+
+#[instrument(name = "browse::handler::get_branches", skip_all)]
+pub async fn get_branches(
+    id: Identity,
+    path: web::Path<Uuid>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    browse(&id, path.into_inner(), &pool, BrowseService::branches).await
+}
+
+#[instrument(name = "browse::handler::get_tags", skip_all)]
+pub async fn get_tags(
+    id: Identity,
+    path: web::Path<Uuid>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    browse(&id, path.into_inner(), &pool, BrowseService::tags).await
+}
+
+#[instrument(name = "browse::handler::get_commits", skip_all)]
+pub async fn get_commits(
+    id: Identity,
+    path: web::Path<Uuid>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    query: web::Query<CommitListQuery>,
+) -> HttpResponse {
+    if let Err(errors) = query.validate() {
+        return HttpResponse::BadRequest().json(error(
+            StatusCode::BAD_REQUEST,
+            format!("Validation error: {:?}", errors),
+        ));
+    }
+
+    let query = query.into_inner();
+    let max_walk = config.browse.max_walk_commits;
+    browse(&id, path.into_inner(), &pool, move |git| {
+        BrowseService::commits(
+            git,
+            query.rev.as_deref(),
+            query.path.as_deref(),
+            query.page.unwrap_or(1),
+            query.per_page.unwrap_or(DEFAULT_PER_PAGE),
+            max_walk,
+        )
+    })
+    .await
+}
+
+#[instrument(name = "browse::handler::get_commit", skip_all)]
+pub async fn get_commit(
+    id: Identity,
+    path: web::Path<(Uuid, String)>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    let (repo_id, sha) = path.into_inner();
+    let max_files = config.browse.max_diff_files;
+    let max_file_bytes = config.browse.max_blob_bytes;
+
+    browse(&id, repo_id, &pool, move |git| {
+        BrowseService::commit(git, &sha, max_files, max_file_bytes)
+    })
+    .await
+}
+
+#[instrument(name = "browse::handler::get_tree", skip_all)]
+pub async fn get_tree(
+    id: Identity,
+    path: web::Path<(Uuid, String)>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    let (repo_id, spec) = path.into_inner();
+    let max_entries = config.browse.max_tree_entries;
+
+    browse(&id, repo_id, &pool, move |git| {
+        BrowseService::tree(git, &spec, max_entries)
+    })
+    .await
+}
+
+#[instrument(name = "browse::handler::get_blob", skip_all)]
+pub async fn get_blob(
+    id: Identity,
+    path: web::Path<(Uuid, String)>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    let (repo_id, spec) = path.into_inner();
+    let max_bytes = config.browse.max_blob_bytes;
+
+    browse(&id, repo_id, &pool, move |git| {
+        BrowseService::blob(git, &spec, max_bytes)
+    })
+    .await
+}
+
+/// Runs `read` on the mirror of the repo, once the user is known to be a
+/// member of its organization.
+async fn browse<T, F>(id: &Identity, repo_id: Uuid, pool: &DbPool, read: F) -> HttpResponse
+where
+    T: Serialize + Send + 'static,
+    F: FnOnce(&Repository) -> Result<T, BrowseError> + Send + 'static,
+{
+    let user_id = match session_user(id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Get mirror path, without holding a connection while reading it
+    let result = db::block(pool, move |conn| {
+        let repo = RepoService::get_by_id(conn, repo_id)
+            .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, format!("Repo not found: {}", e)))?;
+
+        require_member(conn, user_id, repo.organization_id)?;
+
+        match MirrorService::get_by_repository(conn, repo_id) {
+            Ok(mirror) if mirror.last_synced_at.is_some() => Ok(mirror.path),
+            Ok(_) => Err(ApiError::new(
+                StatusCode::NOT_FOUND,
+                "Repository has not been mirrored yet".into(),
+            )),
+            Err(e) => Err(ApiError::new(
+                StatusCode::NOT_FOUND,
+                format!("Failed to retrieve mirror: {}", e),
+            )),
+        }
+    })
+    .await;
+
+    let mirror_path = match result {
+        Ok(mirror_path) => mirror_path,
+        Err(e) => return e.error_response(),
+    };
+
+    // Read the mirror on the blocking pool
+    match web::block(move || BrowseService::open(&mirror_path).and_then(|git| read(&git))).await {
+        Ok(Ok(result)) => HttpResponse::Ok().json(success(StatusCode::OK, Some(result))),
+        Ok(Err(e @ BrowseError::NotFound(_))) => {
+            HttpResponse::NotFound().json(error(StatusCode::NOT_FOUND, e.to_string()))
+        }
+        Ok(Err(e @ BrowseError::TooLarge { .. })) => HttpResponse::PayloadTooLarge()
+            .json(error(StatusCode::PAYLOAD_TOO_LARGE, e.to_string())),
+        Ok(Err(e)) => HttpResponse::InternalServerError().json(error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read repository: {}", e),
+        )),
+        Err(e) => HttpResponse::InternalServerError().json(error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read repository: {}", e),
+        )),
+    }
+}
@@ -0,0 +1,4 @@
+pub mod dto;
+pub mod handler;
+pub mod routes;
+pub mod service;
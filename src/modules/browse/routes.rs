@@ -0,0 +1,14 @@
+use crate::modules::browse::handler::{
+    get_blob, get_branches, get_commit, get_commits, get_tags, get_tree,
+};
+use actix_web::web;
+
+// Mounted inside the `/repositories` scope
+pub fn config_repository_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/{id}/branches", web::get().to(get_branches))
+        .route("/{id}/tags", web::get().to(get_tags))
+        .route("/{id}/commits", web::get().to(get_commits))
+        .route("/{id}/commits/{sha}", web::get().to(get_commit))
+        .route("/{id}/tree/{spec:.*}", web::get().to(get_tree))
+        .route("/{id}/blob/{spec:.*}", web::get().to(get_blob));
+}
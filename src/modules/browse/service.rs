@@ -0,0 +1,397 @@
+use crate::modules::browse::dto::{
+    Blob, Branch, BrowseError, CommitDetails, CommitPage, CommitSummary, FileStat, Signature, Tag,
+    Tree, TreeEntry,
+};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use git2::{
+    BranchType, Commit, Delta, DiffFindOptions, DiffOptions, ObjectType, Oid, Patch, Reference,
+    Repository, Sort,
+};
+use std::path::Path;
+use tracing::instrument;
+
+// Length of a full hexadecimal SHA-1
+const SHA_LENGTH: usize = 40;
+
+/// Read-only access to the local mirror of a repository.
+pub struct BrowseService;
+
+impl BrowseService {
+    #[instrument(name = "BrowseService::open", skip_all)]
+    pub fn open(path: &str) -> Result<Repository, BrowseError> {
+        Repository::open_bare(path).map_err(|_| BrowseError::NotFound("Mirror".into()))
+    }
+
+    #[instrument(name = "BrowseService::branches", skip_all)]
+    pub fn branches(git: &Repository) -> Result<Vec<Branch>, BrowseError> {
+        let default_branch = git
+            .head()
+            .ok()
+            .and_then(|head| head.shorthand().map(String::from));
+
+        let mut branches = Vec::new();
+        for branch in git.branches(Some(BranchType::Local))? {
+            let (branch, _) = branch?;
+            let (Some(name), Ok(commit)) = (branch.name()?, branch.get().peel_to_commit()) else {
+                continue;
+            };
+
+            branches.push(Branch {
+                is_default: default_branch.as_deref() == Some(name),
+                name: name.to_string(),
+                sha: commit.id().to_string(),
+            });
+        }
+
+        branches.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(branches)
+    }
+
+    #[instrument(name = "BrowseService::tags", skip_all)]
+    pub fn tags(git: &Repository) -> Result<Vec<Tag>, BrowseError> {
+        let mut tags = Vec::new();
+        for reference in git.references_glob("refs/tags/*")? {
+            let reference = reference?;
+            let Some(name) = reference.shorthand() else {
+                continue;
+            };
+
+            let annotation = reference.peel_to_tag().ok();
+            tags.push(Tag {
+                name: name.to_string(),
+                sha: reference.peel(ObjectType::Any)?.id().to_string(),
+                annotated: annotation.is_some(),
+                message: annotation.and_then(|tag| tag.message().map(String::from)),
+            });
+        }
+
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(tags)
+    }
+
+    /// Lists commits reachable from `rev`, newest first, like `git log`.
+    /// With a `path`, only commits changing it are listed. At most
+    /// `max_walk` commits are visited, the page is truncated past them.
+    #[instrument(name = "BrowseService::commits", skip_all)]
+    pub fn commits(
+        git: &Repository,
+        rev: Option<&str>,
+        path: Option<&str>,
+        page: usize,
+        per_page: usize,
+        max_walk: usize,
+    ) -> Result<CommitPage, BrowseError> {
+        let start = Self::resolve(git, rev.unwrap_or("HEAD"))?;
+        let path = path
+            .map(|path| path.trim_matches('/'))
+            .filter(|path| !path.is_empty())
+            .map(Path::new);
+
+        let mut walk = git.revwalk()?;
+        walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+        walk.push(start.id())?;
+
+        let skip = (page - 1).saturating_mul(per_page);
+        let mut matched = 0;
+        let mut commits = Vec::with_capacity(per_page);
+        let mut has_more = false;
+        let mut truncated = false;
+        for (visited, oid) in walk.enumerate() {
+            if visited == max_walk {
+                truncated = true;
+                break;
+            }
+
+            let commit = git.find_commit(oid?)?;
+            if let Some(path) = path {
+                if !Self::touches(&commit, path)? {
+                    continue;
+                }
+            }
+
+            matched += 1;
+            if matched <= skip {
+                continue;
+            }
+            if commits.len() == per_page {
+                has_more = true;
+                break;
+            }
+            commits.push(Self::summary(&commit));
+        }
+
+        Ok(CommitPage {
+            commits,
+            page,
+            per_page,
+            has_more,
+            truncated,
+        })
+    }
+
+    /// Returns `rev` with the files it changes against its first parent.
+    /// Only the first `max_files` files are diffed, and files larger than
+    /// `max_file_bytes` are counted as binary.
+    #[instrument(name = "BrowseService::commit", skip_all)]
+    pub fn commit(
+        git: &Repository,
+        rev: &str,
+        max_files: usize,
+        max_file_bytes: usize,
+    ) -> Result<CommitDetails, BrowseError> {
+        let commit = Self::resolve(git, rev)?;
+        let parent_tree = match commit.parents().next() {
+            Some(parent) => Some(parent.tree()?),
+            None => None,
+        };
+
+        let mut options = DiffOptions::new();
+        options.max_size(i64::try_from(max_file_bytes).unwrap_or(i64::MAX));
+        let mut diff = git.diff_tree_to_tree(
+            parent_tree.as_ref(),
+            Some(&commit.tree()?),
+            Some(&mut options),
+        )?;
+        diff.find_similar(Some(
+            DiffFindOptions::new().renames(true).rename_limit(max_files),
+        ))?;
+        let files_changed = diff.deltas().len();
+
+        let mut files = Vec::new();
+        for index in 0..files_changed.min(max_files) {
+            let Some(delta) = diff.get_delta(index) else {
+                continue;
+            };
+            let (insertions, deletions, binary) = match Patch::from_diff(&diff, index)? {
+                Some(patch) => {
+                    let (_, insertions, deletions) = patch.line_stats()?;
+                    (insertions, deletions, patch.delta().flags().is_binary())
+                }
+                None => (0, 0, true),
+            };
+
+            let new_path = delta.new_file().path().or(delta.old_file().path());
+            let old_path = delta.old_file().path();
+            files.push(FileStat {
+                path: new_path
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                old_path: old_path
+                    .filter(|_| matches!(delta.status(), Delta::Renamed | Delta::Copied))
+                    .map(|path| path.to_string_lossy().into_owned()),
+                status: Self::status(delta.status()).to_string(),
+                insertions,
+                deletions,
+                binary,
+            });
+        }
+
+        Ok(CommitDetails {
+            commit: Self::summary(&commit),
+            files_changed,
+            insertions: files.iter().map(|file| file.insertions).sum(),
+            deletions: files.iter().map(|file| file.deletions).sum(),
+            truncated: files_changed > files.len(),
+            files,
+        })
+    }
+
+    /// Lists a directory. `spec` is a ref followed by the path, as in
+    /// `main/src`; the root directory is listed when the path is empty.
+    #[instrument(name = "BrowseService::tree", skip_all)]
+    pub fn tree(git: &Repository, spec: &str, max_entries: usize) -> Result<Tree, BrowseError> {
+        let (commit, path) = Self::split_spec(git, spec)?;
+        let root = commit.tree()?;
+        let tree = if path.is_empty() {
+            root
+        } else {
+            root.get_path(Path::new(path))
+                .and_then(|entry| entry.to_object(git))
+                .ok()
+                .and_then(|object| object.into_tree().ok())
+                .ok_or_else(|| BrowseError::NotFound(format!("Directory {}", path)))?
+        };
+
+        let odb = git.odb()?;
+        let mut entries = Vec::new();
+        for entry in tree.iter().take(max_entries) {
+            let name = String::from_utf8_lossy(entry.name_bytes()).into_owned();
+            let kind = entry.kind().unwrap_or(ObjectType::Any);
+            let size = match kind {
+                ObjectType::Blob => Some(odb.read_header(entry.id())?.0),
+                _ => None,
+            };
+
+            entries.push(TreeEntry {
+                path: Self::join(path, &name),
+                name,
+                kind: kind.str().to_string(),
+                mode: format!("{:06o}", entry.filemode()),
+                sha: entry.id().to_string(),
+                size,
+            });
+        }
+
+        Ok(Tree {
+            sha: tree.id().to_string(),
+            path: path.to_string(),
+            truncated: tree.len() > entries.len(),
+            entries,
+        })
+    }
+
+    /// Reads a file, given as a ref followed by its path. Text is returned
+    /// as is, anything else base64-encoded.
+    #[instrument(name = "BrowseService::blob", skip_all)]
+    pub fn blob(git: &Repository, spec: &str, max_bytes: usize) -> Result<Blob, BrowseError> {
+        let (commit, path) = Self::split_spec(git, spec)?;
+        let not_found = || BrowseError::NotFound(format!("File {}", path));
+
+        let entry = commit
+            .tree()?
+            .get_path(Path::new(path))
+            .map_err(|_| not_found())?;
+        if entry.kind() != Some(ObjectType::Blob) {
+            return Err(not_found());
+        }
+
+        // Check the size before loading the content
+        let (size, _) = git.odb()?.read_header(entry.id())?;
+        if size > max_bytes {
+            return Err(BrowseError::TooLarge {
+                size,
+                limit: max_bytes,
+            });
+        }
+
+        let blob = git.find_blob(entry.id())?;
+        let text = match blob.is_binary() {
+            true => None,
+            false => std::str::from_utf8(blob.content()).ok(),
+        };
+
+        Ok(Blob {
+            sha: blob.id().to_string(),
+            path: path.to_string(),
+            size,
+            binary: text.is_none(),
+            encoding: if text.is_some() { "utf-8" } else { "base64" }.to_string(),
+            content: match text {
+                Some(text) => text.to_string(),
+                None => STANDARD.encode(blob.content()),
+            },
+        })
+    }
+
+    /// Resolves `HEAD`, a branch, a tag, a full ref name or a full commit
+    /// SHA. Revision expressions such as `main~2` or `@{1}` are refused.
+    fn resolve<'r>(git: &'r Repository, rev: &str) -> Result<Commit<'r>, BrowseError> {
+        let not_found = || BrowseError::NotFound(format!("Ref {}", rev));
+
+        if rev.len() == SHA_LENGTH && rev.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            let oid = Oid::from_str(rev).map_err(|_| not_found())?;
+            return git.find_commit(oid).map_err(|_| not_found());
+        }
+
+        let reference = if rev == "HEAD" {
+            git.head().ok()
+        } else {
+            [
+                format!("refs/heads/{}", rev),
+                format!("refs/tags/{}", rev),
+                rev.to_string(),
+            ]
+            .iter()
+            .filter(|name| name.starts_with("refs/") && Reference::is_valid_name(name))
+            .find_map(|name| git.find_reference(name).ok())
+        };
+
+        reference
+            .and_then(|reference| reference.peel_to_commit().ok())
+            .ok_or_else(not_found)
+    }
+
+    /// Splits `ref/path` where the ref may itself contain slashes. The
+    /// longest prefix naming a commit wins.
+    fn split_spec<'r, 's>(
+        git: &'r Repository,
+        spec: &'s str,
+    ) -> Result<(Commit<'r>, &'s str), BrowseError> {
+        let spec = spec.trim_matches('/');
+        let mut ends = spec
+            .match_indices('/')
+            .map(|(end, _)| end)
+            .collect::<Vec<_>>();
+        ends.push(spec.len());
+
+        for &end in ends.iter().rev() {
+            if let Ok(commit) = Self::resolve(git, &spec[..end]) {
+                return Ok((commit, spec[end..].trim_start_matches('/')));
+            }
+        }
+
+        Err(BrowseError::NotFound(format!("Ref in {}", spec)))
+    }
+
+    /// Whether `commit` changes `path` compared to each of its parents, so
+    /// merges taking one side as is are skipped like in `git log -- path`.
+    fn touches(commit: &Commit, path: &Path) -> Result<bool, BrowseError> {
+        let entry_at = |commit: &Commit| -> Result<Option<Oid>, BrowseError> {
+            Ok(commit.tree()?.get_path(path).ok().map(|entry| entry.id()))
+        };
+
+        let own = entry_at(commit)?;
+        if commit.parent_count() == 0 {
+            return Ok(own.is_some());
+        }
+        for parent in commit.parents() {
+            if entry_at(&parent)? == own {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn summary(commit: &Commit) -> CommitSummary {
+        CommitSummary {
+            sha: commit.id().to_string(),
+            parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+            author: Self::signature(&commit.author()),
+            committer: Self::signature(&commit.committer()),
+            summary: String::from_utf8_lossy(commit.summary_bytes().unwrap_or_default())
+                .into_owned(),
+            message: String::from_utf8_lossy(commit.message_bytes()).into_owned(),
+        }
+    }
+
+    fn signature(signature: &git2::Signature) -> Signature {
+        Signature {
+            name: String::from_utf8_lossy(signature.name_bytes()).into_owned(),
+            email: String::from_utf8_lossy(signature.email_bytes()).into_owned(),
+            date: DateTime::<Utc>::from_timestamp(signature.when().seconds(), 0)
+                .unwrap_or_default(),
+        }
+    }
+
+    fn status(status: Delta) -> &'static str {
+        match status {
+            Delta::Added => "added",
+            Delta::Deleted => "deleted",
+            Delta::Modified => "modified",
+            Delta::Renamed => "renamed",
+            Delta::Copied => "copied",
+            Delta::Typechange => "typechange",
+            _ => "unmodified",
+        }
+    }
+
+    fn join(dir: &str, name: &str) -> String {
+        match dir {
+            "" => name.to_string(),
+            _ => format!("{}/{}", dir, name),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use crate::schema::deploy_keys;
+use crate::utils::envelope::Envelope;
+use diesel::prelude::*;
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Insertable)]
+#[diesel(table_name = deploy_keys)]
+pub struct NewDeployKey {
+    pub repository_id: Uuid,
+    pub public_key: String,
+    pub fingerprint: String,
+    pub encrypted_private_key: Vec<u8>,
+    pub private_key_nonce: Vec<u8>,
+    pub encrypted_data_key: Vec<u8>,
+    pub data_key_nonce: Vec<u8>,
+}
+
+impl NewDeployKey {
+    pub fn new(
+        repository_id: Uuid,
+        public_key: String,
+        fingerprint: String,
+        envelope: Envelope,
+    ) -> Self {
+        NewDeployKey {
+            repository_id,
+            public_key,
+            fingerprint,
+            encrypted_private_key: envelope.ciphertext,
+            private_key_nonce: envelope.nonce,
+            encrypted_data_key: envelope.encrypted_data_key,
+            data_key_nonce: envelope.data_key_nonce,
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams, Validate)]
+pub struct RotateDeployKeyQuery {
+    /// Hours the current key keeps working, defaults to `DEPLOY_KEY_OVERLAP_HOURS`
+    #[validate(range(min = 0, max = 720))]
+    pub overlap_hours: Option<i64>,
+}
@@ -0,0 +1,187 @@
+use crate::config::Config;
+use crate::db;
+use crate::models::Repo;
+use crate::modules::auth::handler::session_user;
+use crate::modules::deploy_key::dto::RotateDeployKeyQuery;
+use crate::modules::deploy_key::service::DeployKeyService;
+use crate::modules::organization::handler::require_member;
+use crate::modules::repo::service::RepoService;
+use crate::utils::envelope::MasterKey;
+use crate::utils::response::{error, success, ApiError};
+use actix_identity::Identity;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use diesel::r2d2::{self, ConnectionManager};
+use diesel::PgConnection;
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+// Repo the caller is a member of
+fn load_repo(conn: &mut PgConnection, user_id: Uuid, repo_id: Uuid) -> Result<Repo, ApiError> {
+    let repo = RepoService::get_by_id(conn, repo_id)
+        .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, format!("Repo not found: {}", e)))?;
+
+    require_member(conn, user_id, repo.organization_id)?;
+
+    Ok(repo)
+}
+
+pub fn master_key(config: &Config) -> Result<MasterKey, HttpResponse> {
+    MasterKey::from_base64(&config.deploy_keys.master_key).map_err(|e| {
+        HttpResponse::ServiceUnavailable().json(error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("Deploy keys are not configured: {}", e),
+        ))
+    })
+}
+
+#[instrument(name = "deploy_key::handler::get_by_repository", skip_all)]
+pub async fn get_by_repository(
+    id: Identity,
+    path: web::Path<Uuid>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    let repo_id = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Get deploy keys
+    let result = db::block(&pool, move |conn| {
+        load_repo(conn, user_id, repo_id)?;
+        DeployKeyService::get_by_repository(conn, repo_id).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve deploy keys: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(keys) => HttpResponse::Ok().json(success(StatusCode::OK, Some(keys))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "deploy_key::handler::generate", skip_all)]
+pub async fn generate(
+    id: Identity,
+    path: web::Path<Uuid>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    let repo_id = path.into_inner();
+
+    let master_key = match master_key(&config) {
+        Ok(master_key) => master_key,
+        Err(response) => return response,
+    };
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Generate deploy key
+    let result = db::block(&pool, move |conn| {
+        let repo = load_repo(conn, user_id, repo_id)?;
+        DeployKeyService::generate(conn, &repo, &master_key).map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Failed to generate deploy key: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(key) => HttpResponse::Created().json(success(StatusCode::CREATED, Some(key))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "deploy_key::handler::rotate", skip_all)]
+pub async fn rotate(
+    id: Identity,
+    path: web::Path<Uuid>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    query: web::Query<RotateDeployKeyQuery>,
+) -> HttpResponse {
+    let repo_id = path.into_inner();
+
+    // Validate rotation data
+    if let Err(errors) = query.validate() {
+        return HttpResponse::BadRequest().json(error(
+            StatusCode::BAD_REQUEST,
+            format!("Validation error: {:?}", errors),
+        ));
+    }
+
+    let master_key = match master_key(&config) {
+        Ok(master_key) => master_key,
+        Err(response) => return response,
+    };
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Rotate deploy key
+    let overlap_hours = query
+        .overlap_hours
+        .unwrap_or(config.deploy_keys.overlap_hours);
+    let result = db::block(&pool, move |conn| {
+        let repo = load_repo(conn, user_id, repo_id)?;
+        DeployKeyService::rotate(conn, &repo, &master_key, overlap_hours).map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Failed to rotate deploy key: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(key) => HttpResponse::Created().json(success(StatusCode::CREATED, Some(key))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "deploy_key::handler::revoke", skip_all)]
+pub async fn revoke(
+    id: Identity,
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    let (repo_id, key_id) = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Revoke deploy key
+    let result = db::block(&pool, move |conn| {
+        load_repo(conn, user_id, repo_id)?;
+        DeployKeyService::revoke(conn, repo_id, key_id).map_err(|e| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                format!("Failed to revoke deploy key: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(success::<()>(StatusCode::OK, None)),
+        Err(e) => e.error_response(),
+    }
+}
@@ -0,0 +1,105 @@
+use crate::models::DeployKey;
+use crate::modules::deploy_key::dto::NewDeployKey;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use std::error::Error;
+use tracing::instrument;
+use uuid::Uuid;
+
+pub struct DeployKeyRepository;
+
+impl DeployKeyRepository {
+    /// Keys still accepted for the repository, the active one first.
+    #[instrument(name = "DeployKeyRepository::find_usable", skip_all)]
+    pub fn find_usable(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+    ) -> Result<Vec<DeployKey>, Box<dyn Error>> {
+        use crate::schema::deploy_keys::dsl::*;
+
+        let keys = deploy_keys
+            .filter(repository_id.eq(repo_id))
+            .filter(expires_at.is_null().or(expires_at.gt(Utc::now())))
+            .order((expires_at.desc().nulls_first(), created_at.desc()))
+            .load::<DeployKey>(conn)?;
+
+        Ok(keys)
+    }
+
+    #[instrument(name = "DeployKeyRepository::find_active", skip_all)]
+    pub fn find_active(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+    ) -> Result<Option<DeployKey>, Box<dyn Error>> {
+        use crate::schema::deploy_keys::dsl::*;
+
+        let key = deploy_keys
+            .filter(repository_id.eq(repo_id))
+            .filter(expires_at.is_null())
+            .first::<DeployKey>(conn)
+            .optional()?;
+
+        Ok(key)
+    }
+
+    #[instrument(name = "DeployKeyRepository::create", skip_all)]
+    pub fn create(
+        conn: &mut PgConnection,
+        new_key: &NewDeployKey,
+    ) -> Result<DeployKey, Box<dyn Error>> {
+        use crate::schema::deploy_keys::dsl::*;
+
+        diesel::insert_into(deploy_keys)
+            .values(new_key)
+            .get_result::<DeployKey>(conn)
+            .map_err(|e| e.into())
+    }
+
+    /// Schedules the active key to expire and makes `new_key` the active one.
+    #[instrument(name = "DeployKeyRepository::rotate", skip_all)]
+    pub fn rotate(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        new_key: &NewDeployKey,
+        expiry: DateTime<Utc>,
+    ) -> Result<DeployKey, Box<dyn Error>> {
+        use crate::schema::deploy_keys::dsl::*;
+
+        conn.transaction(|conn| {
+            // Drop keys whose overlap window is over
+            diesel::delete(deploy_keys)
+                .filter(repository_id.eq(repo_id))
+                .filter(expires_at.le(Utc::now()))
+                .execute(conn)?;
+
+            diesel::update(deploy_keys)
+                .filter(repository_id.eq(repo_id))
+                .filter(expires_at.is_null())
+                .set(expires_at.eq(expiry))
+                .execute(conn)?;
+
+            Self::create(conn, new_key)
+        })
+    }
+
+    #[instrument(name = "DeployKeyRepository::delete", skip_all)]
+    pub fn delete(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        key_id: Uuid,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::schema::deploy_keys::dsl::*;
+
+        let deleted = diesel::delete(deploy_keys)
+            .filter(id.eq(key_id))
+            .filter(repository_id.eq(repo_id))
+            .execute(conn)?;
+
+        if deleted == 0 {
+            return Err("Deploy key not found".into());
+        }
+
+        Ok(())
+    }
+}
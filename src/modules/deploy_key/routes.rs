@@ -0,0 +1,10 @@
+use crate::modules::deploy_key::handler::{generate, get_by_repository, revoke, rotate};
+use actix_web::web;
+
+// Mounted inside the `/repositories` scope
+pub fn config_repository_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/{id}/deploy-keys", web::get().to(get_by_repository))
+        .route("/{id}/deploy-keys", web::post().to(generate))
+        .route("/{id}/deploy-keys/rotate", web::post().to(rotate))
+        .route("/{id}/deploy-keys/{key_id}", web::delete().to(revoke));
+}
@@ -0,0 +1,109 @@
+use crate::models::{DeployKey, Repo};
+use crate::modules::deploy_key::dto::NewDeployKey;
+use crate::modules::deploy_key::repository::DeployKeyRepository;
+use crate::utils::envelope::{Envelope, MasterKey};
+use chrono::{Duration, Utc};
+use diesel::PgConnection;
+use rand::rngs::OsRng;
+use ssh_key::{Algorithm, HashAlg, LineEnding, PrivateKey};
+use std::error::Error;
+use tracing::instrument;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+pub struct DeployKeyService;
+
+impl DeployKeyService {
+    #[instrument(name = "DeployKeyService::get_by_repository", skip_all)]
+    pub fn get_by_repository(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+    ) -> Result<Vec<DeployKey>, Box<dyn Error>> {
+        DeployKeyRepository::find_usable(conn, repo_id)
+    }
+
+    #[instrument(name = "DeployKeyService::generate", skip_all)]
+    pub fn generate(
+        conn: &mut PgConnection,
+        repo: &Repo,
+        master_key: &MasterKey,
+    ) -> Result<DeployKey, Box<dyn Error>> {
+        if DeployKeyRepository::find_active(conn, repo.id)?.is_some() {
+            return Err("Repository already has a deploy key, rotate it instead".into());
+        }
+
+        let new_key = Self::new_key(repo, master_key)?;
+        DeployKeyRepository::create(conn, &new_key)
+    }
+
+    /// Replaces the active key. The previous one keeps working for
+    /// `overlap_hours` so the new public key can be rolled out on the remote.
+    #[instrument(name = "DeployKeyService::rotate", skip_all)]
+    pub fn rotate(
+        conn: &mut PgConnection,
+        repo: &Repo,
+        master_key: &MasterKey,
+        overlap_hours: i64,
+    ) -> Result<DeployKey, Box<dyn Error>> {
+        if DeployKeyRepository::find_active(conn, repo.id)?.is_none() {
+            return Err("Repository has no deploy key to rotate".into());
+        }
+
+        let new_key = Self::new_key(repo, master_key)?;
+        let expiry = Utc::now() + Duration::hours(overlap_hours);
+        DeployKeyRepository::rotate(conn, repo.id, &new_key, expiry)
+    }
+
+    #[instrument(name = "DeployKeyService::revoke", skip_all)]
+    pub fn revoke(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        key_id: Uuid,
+    ) -> Result<(), Box<dyn Error>> {
+        DeployKeyRepository::delete(conn, repo_id, key_id)
+    }
+
+    /// Decrypted OpenSSH private keys usable for the repository, active first.
+    #[instrument(name = "DeployKeyService::private_keys", skip_all)]
+    pub fn private_keys(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        master_key: &MasterKey,
+    ) -> Result<Vec<Zeroizing<String>>, Box<dyn Error>> {
+        DeployKeyRepository::find_usable(conn, repo_id)?
+            .into_iter()
+            .map(|key| {
+                let plaintext = master_key.open(
+                    &Envelope {
+                        ciphertext: key.encrypted_private_key,
+                        nonce: key.private_key_nonce,
+                        encrypted_data_key: key.encrypted_data_key,
+                        data_key_nonce: key.data_key_nonce,
+                    },
+                    repo_id.as_bytes(),
+                )?;
+                let pem = String::from_utf8(plaintext.to_vec())
+                    .map_err(|_| "Deploy key is not valid UTF-8")?;
+
+                Ok(Zeroizing::new(pem))
+            })
+            .collect()
+    }
+
+    fn new_key(repo: &Repo, master_key: &MasterKey) -> Result<NewDeployKey, Box<dyn Error>> {
+        let mut private_key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519)?;
+        private_key.set_comment(format!("deploy@{}", repo.slug));
+
+        let public_key = private_key.public_key().to_openssh()?;
+        let fingerprint = private_key.fingerprint(HashAlg::Sha256).to_string();
+        let pem = private_key.to_openssh(LineEnding::LF)?;
+        let envelope = master_key.seal(pem.as_bytes(), repo.id.as_bytes())?;
+
+        Ok(NewDeployKey::new(
+            repo.id,
+            public_key,
+            fingerprint,
+            envelope,
+        ))
+    }
+}
@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Up,
+    Down,
+    /// Not configured, or depends on a check that failed
+    Skipped,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone)]
+pub struct CheckResult {
+    pub status: CheckStatus,
+    pub latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CheckResult {
+    pub fn up(latency: Duration) -> Self {
+        CheckResult {
+            status: CheckStatus::Up,
+            latency_ms: latency.as_secs_f64() * 1000.0,
+            error: None,
+        }
+    }
+
+    pub fn down(latency: Duration, error: String) -> Self {
+        CheckResult {
+            status: CheckStatus::Down,
+            latency_ms: latency.as_secs_f64() * 1000.0,
+            error: Some(error),
+        }
+    }
+
+    pub fn skipped() -> Self {
+        CheckResult {
+            status: CheckStatus::Skipped,
+            latency_ms: 0.0,
+            error: None,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone)]
+pub struct ReadinessChecks {
+    /// `SELECT 1` on a pooled connection
+    pub database: CheckResult,
+    /// Down while embedded migrations have not been applied
+    pub migrations: CheckResult,
+    pub redis: CheckResult,
+    /// Skipped unless `HEALTH_CHECK_SMTP` is set
+    pub smtp: CheckResult,
+}
+
+/// Down as soon as one check is down. Results may be up to
+/// `HEALTH_CACHE_SECONDS` old, see `checked_at`.
+#[derive(Serialize, ToSchema, Debug, Clone)]
+pub struct Readiness {
+    pub status: CheckStatus,
+    pub checked_at: DateTime<Utc>,
+    pub checks: ReadinessChecks,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct Liveness {
+    pub status: CheckStatus,
+}
@@ -0,0 +1,68 @@
+use crate::config::Config;
+use crate::modules::health::dto::{CheckStatus, Liveness};
+use crate::modules::health::service::{HealthService, ReadinessCache};
+use crate::utils::response::{error, success};
+use crate::utils::shutdown::Shutdown;
+use actix_web::http::header;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, Responder};
+use diesel::r2d2::{self, ConnectionManager};
+use diesel::PgConnection;
+use tracing::instrument;
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+#[instrument(name = "health::handler::health_check", skip_all)]
+pub async fn health_check() -> impl Responder {
+    HttpResponse::Ok().body("OK")
+}
+
+/// The process is up and serving requests, whatever its dependencies do.
+#[instrument(name = "health::handler::live", skip_all)]
+pub async fn live() -> HttpResponse {
+    HttpResponse::Ok().json(success(
+        StatusCode::OK,
+        Some(Liveness {
+            status: CheckStatus::Up,
+        }),
+    ))
+}
+
+/// Whether the instance can serve traffic. Answers 503 with the failing
+/// checks when it cannot, and as soon as shutdown begins.
+#[instrument(name = "health::handler::ready", skip_all)]
+pub async fn ready(
+    pool: web::Data<DbPool>,
+    redis: web::Data<redis::Client>,
+    config: web::Data<Config>,
+    cache: web::Data<ReadinessCache>,
+    shutdown: web::Data<Shutdown>,
+) -> HttpResponse {
+    // Load balancers stop routing here while in-flight requests drain
+    if shutdown.has_begun() {
+        return HttpResponse::ServiceUnavailable()
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .json(error(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Shutting down".into(),
+            ));
+    }
+
+    let readiness = match cache.get() {
+        Some(readiness) => readiness,
+        None => {
+            let readiness = HealthService::readiness(&pool, &redis, &config).await;
+            cache.put(&readiness);
+            readiness
+        }
+    };
+
+    let status = match readiness.status {
+        CheckStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+        CheckStatus::Up | CheckStatus::Skipped => StatusCode::OK,
+    };
+
+    HttpResponse::build(status)
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .json(success(status, Some(readiness)))
+}
@@ -0,0 +1,8 @@
+use crate::modules::health::handler::{health_check, live, ready};
+use actix_web::web;
+
+pub fn config_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/health").route(web::get().to(health_check)))
+        .service(web::resource("/health/live").route(web::get().to(live)))
+        .service(web::resource("/health/ready").route(web::get().to(ready)));
+}
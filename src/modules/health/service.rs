@@ -0,0 +1,186 @@
+use crate::config::{Config, SmtpConfig};
+use crate::db::{DbPool, MIGRATIONS};
+use crate::modules::health::dto::{CheckResult, CheckStatus, Readiness, ReadinessChecks};
+use crate::utils::mail;
+use actix_web::rt::{self, time};
+use actix_web::web;
+use chrono::Utc;
+use diesel::{sql_query, RunQueryDsl};
+use diesel_migrations::MigrationHarness;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::instrument;
+
+/// Last readiness result, so frequent probes do not reach the
+/// dependencies every time.
+pub struct ReadinessCache {
+    ttl: Duration,
+    last: Mutex<Option<(Instant, Readiness)>>,
+}
+
+impl ReadinessCache {
+    #[instrument(name = "ReadinessCache::new", skip_all)]
+    pub fn new(ttl: Duration) -> Self {
+        ReadinessCache {
+            ttl,
+            last: Mutex::new(None),
+        }
+    }
+
+    #[instrument(name = "ReadinessCache::get", skip_all)]
+    pub fn get(&self) -> Option<Readiness> {
+        let last = self.last.lock().ok()?;
+        last.as_ref()
+            .filter(|(checked, _)| checked.elapsed() < self.ttl)
+            .map(|(_, readiness)| readiness.clone())
+    }
+
+    #[instrument(name = "ReadinessCache::put", skip_all)]
+    pub fn put(&self, readiness: &Readiness) {
+        if let Ok(mut last) = self.last.lock() {
+            *last = Some((Instant::now(), readiness.clone()));
+        }
+    }
+}
+
+pub struct HealthService;
+
+impl HealthService {
+    /// Checks every dependency concurrently, each within
+    /// `HEALTH_TIMEOUT_SECONDS`.
+    #[instrument(name = "HealthService::readiness", skip_all)]
+    pub async fn readiness(pool: &DbPool, redis: &redis::Client, config: &Config) -> Readiness {
+        let timeout = Duration::from_secs(config.health.timeout_seconds.max(1));
+
+        // Blocking checks run on the blocking pool meanwhile
+        let db_pool = pool.clone();
+        let database = rt::spawn(web::block(move || Self::check_database(&db_pool, timeout)));
+        let smtp = config.health.check_smtp.then(|| {
+            let smtp = config.smtp.clone();
+            rt::spawn(web::block(move || Self::check_smtp(&smtp)))
+        });
+
+        let redis = Self::check_redis(redis, timeout).await;
+
+        let (database, migrations) = match database.await {
+            Ok(Ok(checks)) => checks,
+            Ok(Err(e)) => (Self::failed(e.to_string()), CheckResult::skipped()),
+            Err(e) => (Self::failed(e.to_string()), CheckResult::skipped()),
+        };
+
+        let smtp = match smtp {
+            Some(smtp) => {
+                let started = Instant::now();
+                match time::timeout(timeout, smtp).await {
+                    Ok(Ok(Ok(check))) => check,
+                    Ok(Ok(Err(e))) => Self::failed(e.to_string()),
+                    Ok(Err(e)) => Self::failed(e.to_string()),
+                    Err(_) => CheckResult::down(
+                        started.elapsed(),
+                        format!("No answer within {:?}", timeout),
+                    ),
+                }
+            }
+            None => CheckResult::skipped(),
+        };
+
+        let checks = ReadinessChecks {
+            database,
+            migrations,
+            redis,
+            smtp,
+        };
+        let down = [
+            &checks.database,
+            &checks.migrations,
+            &checks.redis,
+            &checks.smtp,
+        ]
+        .iter()
+        .any(|check| check.status == CheckStatus::Down);
+
+        Readiness {
+            status: if down {
+                CheckStatus::Down
+            } else {
+                CheckStatus::Up
+            },
+            checked_at: Utc::now(),
+            checks,
+        }
+    }
+
+    fn failed(error: String) -> CheckResult {
+        CheckResult::down(Duration::ZERO, error)
+    }
+
+    // Migrations are checked on the same connection, and skipped without one
+    fn check_database(pool: &DbPool, timeout: Duration) -> (CheckResult, CheckResult) {
+        let started = Instant::now();
+        let mut conn = match pool.get_timeout(timeout) {
+            Ok(conn) => conn,
+            Err(e) => {
+                return (
+                    CheckResult::down(started.elapsed(), e.to_string()),
+                    CheckResult::skipped(),
+                );
+            }
+        };
+
+        let database = match sql_query("SELECT 1").execute(&mut conn) {
+            Ok(_) => CheckResult::up(started.elapsed()),
+            Err(e) => {
+                return (
+                    CheckResult::down(started.elapsed(), e.to_string()),
+                    CheckResult::skipped(),
+                )
+            }
+        };
+
+        let started = Instant::now();
+        let migrations = match conn.pending_migrations(MIGRATIONS) {
+            Ok(pending) if pending.is_empty() => CheckResult::up(started.elapsed()),
+            Ok(pending) => {
+                let names: Vec<String> = pending.iter().map(|m| m.name().to_string()).collect();
+                CheckResult::down(
+                    started.elapsed(),
+                    format!("{} pending: {}", names.len(), names.join(", ")),
+                )
+            }
+            Err(e) => CheckResult::down(started.elapsed(), e.to_string()),
+        };
+
+        (database, migrations)
+    }
+
+    async fn check_redis(client: &redis::Client, timeout: Duration) -> CheckResult {
+        let started = Instant::now();
+        let ping = async {
+            let mut conn = client.get_multiplexed_async_connection().await?;
+            redis::cmd("PING").query_async::<String>(&mut conn).await
+        };
+
+        match time::timeout(timeout, ping).await {
+            Ok(Ok(_)) => CheckResult::up(started.elapsed()),
+            Ok(Err(e)) => CheckResult::down(started.elapsed(), e.to_string()),
+            Err(_) => {
+                CheckResult::down(started.elapsed(), format!("No answer within {:?}", timeout))
+            }
+        }
+    }
+
+    // Connects and says hello, without sending anything
+    fn check_smtp(smtp: &SmtpConfig) -> CheckResult {
+        let started = Instant::now();
+        let reachable = mail::mailer(smtp).and_then(|mailer| Ok(mailer.test_connection()?));
+
+        match reachable {
+            Ok(true) => CheckResult::up(started.elapsed()),
+            Ok(false) => CheckResult::down(
+                started.elapsed(),
+                "Server did not accept the connection".into(),
+            ),
+            Err(e) => CheckResult::down(started.elapsed(), e.to_string()),
+        }
+    }
+}
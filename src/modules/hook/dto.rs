@@ -0,0 +1,203 @@
+use crate::schema::{webhook_deliveries, webhook_secrets};
+use crate::utils::envelope::Envelope;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    GitHub,
+    GitLab,
+    Gitea,
+}
+
+impl Provider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Provider::GitHub => "github",
+            Provider::GitLab => "gitlab",
+            Provider::Gitea => "gitea",
+        }
+    }
+
+    /// Header naming the event
+    pub fn event_header(&self) -> &'static str {
+        match self {
+            Provider::GitHub => "X-GitHub-Event",
+            Provider::GitLab => "X-Gitlab-Event",
+            Provider::Gitea => "X-Gitea-Event",
+        }
+    }
+
+    /// Header holding the provider's id of the delivery
+    pub fn delivery_header(&self) -> &'static str {
+        match self {
+            Provider::GitHub => "X-GitHub-Delivery",
+            Provider::GitLab => "X-Gitlab-Event-UUID",
+            Provider::Gitea => "X-Gitea-Delivery",
+        }
+    }
+
+    /// Header holding the signature, or the secret itself for GitLab
+    pub fn signature_header(&self) -> &'static str {
+        match self {
+            Provider::GitHub => "X-Hub-Signature-256",
+            Provider::GitLab => "X-Gitlab-Token",
+            Provider::Gitea => "X-Gitea-Signature",
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Provider {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "github" => Ok(Provider::GitHub),
+            "gitlab" => Ok(Provider::GitLab),
+            "gitea" => Ok(Provider::Gitea),
+            _ => Err(format!("Unsupported provider: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    /// Stored, not handled yet
+    Received,
+    Processed,
+    /// Verified, but not an event we react to
+    Ignored,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Received => "received",
+            DeliveryStatus::Processed => "processed",
+            DeliveryStatus::Ignored => "ignored",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+impl fmt::Display for DeliveryStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RefAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// Provider-independent form of the events we react to.
+#[derive(Serialize, Deserialize, ToSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RepoEvent {
+    Push {
+        branch: String,
+        action: RefAction,
+        before: String,
+        after: String,
+        commit_count: usize,
+        pusher: Option<String>,
+    },
+    Tag {
+        name: String,
+        action: RefAction,
+        sha: String,
+    },
+    PullRequest {
+        number: i64,
+        /// `opened`, `updated`, `closed`, `merged`, `reopened`, or as sent
+        action: String,
+        title: String,
+        source_branch: String,
+        target_branch: String,
+        head_sha: String,
+    },
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = webhook_secrets)]
+pub struct NewWebhookSecret {
+    pub repository_id: Uuid,
+    pub encrypted_secret: Vec<u8>,
+    pub secret_nonce: Vec<u8>,
+    pub encrypted_data_key: Vec<u8>,
+    pub data_key_nonce: Vec<u8>,
+}
+
+impl NewWebhookSecret {
+    pub fn new(repository_id: Uuid, envelope: Envelope) -> Self {
+        NewWebhookSecret {
+            repository_id,
+            encrypted_secret: envelope.ciphertext,
+            secret_nonce: envelope.nonce,
+            encrypted_data_key: envelope.encrypted_data_key,
+            data_key_nonce: envelope.data_key_nonce,
+        }
+    }
+}
+
+/// Returned once, when the secret is generated.
+#[derive(Serialize, ToSchema, Debug)]
+pub struct WebhookSetup {
+    pub secret: String,
+    /// Receiver URL to register with each provider
+    pub urls: Vec<WebhookUrl>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct WebhookUrl {
+    pub provider: String,
+    pub url: String,
+}
+
+/// A delivery whose signature checked out.
+pub struct IncomingDelivery {
+    pub provider: Provider,
+    pub event_name: String,
+    pub delivery_id: Option<String>,
+    pub payload: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = webhook_deliveries)]
+pub struct NewWebhookDelivery {
+    pub repository_id: Uuid,
+    pub provider: String,
+    pub delivery_id: Option<String>,
+    pub event_name: String,
+    pub event: Option<serde_json::Value>,
+    pub payload: String,
+    pub status: String,
+}
+
+/// Outcome of handling a delivery, on receipt or on replay.
+#[derive(AsChangeset)]
+#[diesel(table_name = webhook_deliveries)]
+#[diesel(treat_none_as_null = true)]
+pub struct DeliveryResult {
+    pub event: Option<serde_json::Value>,
+    pub status: String,
+    pub error: Option<String>,
+    pub processed_at: Option<DateTime<Utc>>,
+}
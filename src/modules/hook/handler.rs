@@ -0,0 +1,268 @@
+use crate::config::Config;
+use crate::db;
+use crate::models::Repo;
+use crate::modules::auth::handler::session_user;
+use crate::modules::hook::dto::{IncomingDelivery, Provider};
+use crate::modules::hook::service::HookService;
+use crate::modules::organization::handler::require_member;
+use crate::modules::repo::service::RepoService;
+use crate::utils::envelope::MasterKey;
+use crate::utils::response::{error, success, ApiError};
+use crate::utils::shutdown::Shutdown;
+use actix_identity::Identity;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
+use diesel::r2d2::{self, ConnectionManager};
+use diesel::PgConnection;
+use tracing::instrument;
+use uuid::Uuid;
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+// Repo the caller is a member of
+fn load_repo(conn: &mut PgConnection, user_id: Uuid, repo_id: Uuid) -> Result<Repo, ApiError> {
+    let repo = RepoService::get_by_id(conn, repo_id)
+        .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, format!("Repo not found: {}", e)))?;
+
+    require_member(conn, user_id, repo.organization_id)?;
+
+    Ok(repo)
+}
+
+// Webhook secrets are encrypted with the deploy key master key
+fn master_key(config: &Config) -> Result<MasterKey, HttpResponse> {
+    MasterKey::from_base64(&config.deploy_keys.master_key).map_err(|e| {
+        HttpResponse::ServiceUnavailable().json(error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("Webhook secrets are not configured: {}", e),
+        ))
+    })
+}
+
+fn header<'a>(req: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    req.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// Receiver registered with the git provider, authenticated by signature.
+#[instrument(name = "hook::handler::receive", skip_all)]
+pub async fn receive(
+    req: HttpRequest,
+    path: web::Path<(String, Uuid)>,
+    body: web::Bytes,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    shutdown: web::Data<Shutdown>,
+) -> HttpResponse {
+    let (provider, repo_id) = path.into_inner();
+
+    let provider = match provider.parse::<Provider>() {
+        Ok(provider) => provider,
+        Err(e) => return HttpResponse::NotFound().json(error(StatusCode::NOT_FOUND, e)),
+    };
+
+    let master_key = match master_key(&config) {
+        Ok(master_key) => master_key,
+        Err(response) => return response,
+    };
+
+    let signature = header(&req, provider.signature_header()).map(String::from);
+    let event_name = header(&req, provider.event_header()).map(String::from);
+    let delivery_id = header(&req, provider.delivery_header()).map(String::from);
+
+    let hook_pool = pool.clone();
+    let result = db::block(&pool, move |conn| {
+        let repo = RepoService::get_by_id(conn, repo_id)
+            .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, format!("Repo not found: {}", e)))?;
+
+        let secret = match HookService::secret(conn, repo.id, &master_key) {
+            Ok(Some(secret)) => secret,
+            Ok(None) => {
+                return Err(ApiError::new(
+                    StatusCode::NOT_FOUND,
+                    "Webhooks are not set up for this repo".into(),
+                ));
+            }
+            Err(e) => {
+                return Err(ApiError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to load webhook secret: {}", e),
+                ));
+            }
+        };
+
+        // Verify signature before looking at the payload
+        if !HookService::verify(provider, signature.as_deref(), &secret, &body) {
+            return Err(ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "Invalid webhook signature".into(),
+            ));
+        }
+
+        let Some(event_name) = event_name else {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Missing {} header", provider.event_header()),
+            ));
+        };
+
+        let payload = String::from_utf8(body.to_vec()).map_err(|_| {
+            ApiError::new(StatusCode::BAD_REQUEST, "Payload is not valid UTF-8".into())
+        })?;
+
+        let incoming = IncomingDelivery {
+            provider,
+            event_name,
+            delivery_id,
+            payload,
+        };
+
+        // Store and handle the delivery
+        HookService::receive(conn, &hook_pool, &config, &shutdown, &repo, incoming).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to store delivery: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(delivery) => HttpResponse::Ok().json(success(StatusCode::OK, Some(delivery))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "hook::handler::create_secret", skip_all)]
+pub async fn create_secret(
+    id: Identity,
+    path: web::Path<Uuid>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    let repo_id = path.into_inner();
+
+    let master_key = match master_key(&config) {
+        Ok(master_key) => master_key,
+        Err(response) => return response,
+    };
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Generate a new secret, replacing the current one
+    let result = db::block(&pool, move |conn| {
+        let repo = load_repo(conn, user_id, repo_id)?;
+        HookService::generate_secret(conn, &repo, &master_key, &config.base_url).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to generate webhook secret: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(setup) => HttpResponse::Created().json(success(StatusCode::CREATED, Some(setup))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "hook::handler::delete_secret", skip_all)]
+pub async fn delete_secret(
+    id: Identity,
+    path: web::Path<Uuid>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    let repo_id = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Delete secret, which turns webhooks off
+    let result = db::block(&pool, move |conn| {
+        load_repo(conn, user_id, repo_id)?;
+        HookService::delete_secret(conn, repo_id).map_err(|e| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                format!("Failed to delete webhook secret: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(success::<()>(StatusCode::OK, None)),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "hook::handler::get_deliveries", skip_all)]
+pub async fn get_deliveries(
+    id: Identity,
+    path: web::Path<Uuid>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    let repo_id = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Get latest deliveries
+    let result = db::block(&pool, move |conn| {
+        load_repo(conn, user_id, repo_id)?;
+        HookService::get_deliveries(conn, repo_id).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve deliveries: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(deliveries) => HttpResponse::Ok().json(success(StatusCode::OK, Some(deliveries))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "hook::handler::replay", skip_all)]
+pub async fn replay(
+    id: Identity,
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    shutdown: web::Data<Shutdown>,
+) -> HttpResponse {
+    let (repo_id, delivery_id) = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Handle the stored delivery again
+    let hook_pool = pool.clone();
+    let result = db::block(&pool, move |conn| {
+        let repo = load_repo(conn, user_id, repo_id)?;
+        HookService::replay(conn, &hook_pool, &config, &shutdown, &repo, delivery_id).map_err(|e| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                format!("Failed to replay delivery: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(delivery) => HttpResponse::Ok().json(success(StatusCode::OK, Some(delivery))),
+        Err(e) => e.error_response(),
+    }
+}
@@ -0,0 +1,155 @@
+use crate::models::{WebhookDelivery, WebhookSecret};
+use crate::modules::hook::dto::{DeliveryResult, NewWebhookDelivery, NewWebhookSecret};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use std::error::Error;
+use tracing::instrument;
+use uuid::Uuid;
+
+pub struct HookRepository;
+
+impl HookRepository {
+    #[instrument(name = "HookRepository::find_secret", skip_all)]
+    pub fn find_secret(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+    ) -> Result<Option<WebhookSecret>, Box<dyn Error>> {
+        use crate::schema::webhook_secrets::dsl::*;
+
+        let secret = webhook_secrets
+            .filter(repository_id.eq(repo_id))
+            .first::<WebhookSecret>(conn)
+            .optional()?;
+
+        Ok(secret)
+    }
+
+    /// Stores the secret of a repository, replacing the previous one.
+    #[instrument(name = "HookRepository::replace_secret", skip_all)]
+    pub fn replace_secret(
+        conn: &mut PgConnection,
+        new_secret: &NewWebhookSecret,
+    ) -> Result<WebhookSecret, Box<dyn Error>> {
+        use crate::schema::webhook_secrets::dsl::*;
+
+        conn.transaction(|conn| {
+            diesel::delete(webhook_secrets.filter(repository_id.eq(new_secret.repository_id)))
+                .execute(conn)?;
+
+            diesel::insert_into(webhook_secrets)
+                .values(new_secret)
+                .get_result::<WebhookSecret>(conn)
+        })
+        .map_err(|e| e.into())
+    }
+
+    #[instrument(name = "HookRepository::delete_secret", skip_all)]
+    pub fn delete_secret(conn: &mut PgConnection, repo_id: Uuid) -> Result<(), Box<dyn Error>> {
+        use crate::schema::webhook_secrets::dsl::*;
+
+        let deleted =
+            diesel::delete(webhook_secrets.filter(repository_id.eq(repo_id))).execute(conn)?;
+        if deleted == 0 {
+            return Err("Repository has no webhook secret".into());
+        }
+
+        Ok(())
+    }
+
+    #[instrument(name = "HookRepository::find_deliveries", skip_all)]
+    pub fn find_deliveries(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>, Box<dyn Error>> {
+        use crate::schema::webhook_deliveries::dsl::*;
+
+        let deliveries = webhook_deliveries
+            .filter(repository_id.eq(repo_id))
+            .order(received_at.desc())
+            .limit(limit)
+            .load::<WebhookDelivery>(conn)?;
+
+        Ok(deliveries)
+    }
+
+    #[instrument(name = "HookRepository::find_delivery", skip_all)]
+    pub fn find_delivery(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        delivery_uuid: Uuid,
+    ) -> Result<WebhookDelivery, Box<dyn Error>> {
+        use crate::schema::webhook_deliveries::dsl::*;
+
+        webhook_deliveries
+            .filter(id.eq(delivery_uuid))
+            .filter(repository_id.eq(repo_id))
+            .first::<WebhookDelivery>(conn)
+            .optional()?
+            .ok_or_else(|| "Delivery not found".into())
+    }
+
+    #[instrument(name = "HookRepository::find_by_delivery_id", skip_all)]
+    pub fn find_by_delivery_id(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        provider_name: &str,
+        provider_delivery_id: &str,
+    ) -> Result<Option<WebhookDelivery>, Box<dyn Error>> {
+        use crate::schema::webhook_deliveries::dsl::*;
+
+        let delivery = webhook_deliveries
+            .filter(repository_id.eq(repo_id))
+            .filter(provider.eq(provider_name))
+            .filter(delivery_id.eq(provider_delivery_id))
+            .first::<WebhookDelivery>(conn)
+            .optional()?;
+
+        Ok(delivery)
+    }
+
+    /// Stores a delivery. Returns `None` when the provider already sent it.
+    #[instrument(name = "HookRepository::record", skip_all)]
+    pub fn record(
+        conn: &mut PgConnection,
+        new_delivery: &NewWebhookDelivery,
+    ) -> Result<Option<WebhookDelivery>, Box<dyn Error>> {
+        use crate::schema::webhook_deliveries::dsl::*;
+
+        let delivery = diesel::insert_into(webhook_deliveries)
+            .values(new_delivery)
+            .on_conflict_do_nothing()
+            .get_result::<WebhookDelivery>(conn)
+            .optional()?;
+
+        Ok(delivery)
+    }
+
+    #[instrument(name = "HookRepository::finish", skip_all)]
+    pub fn finish(
+        conn: &mut PgConnection,
+        delivery_uuid: Uuid,
+        result: &DeliveryResult,
+    ) -> Result<WebhookDelivery, Box<dyn Error>> {
+        use crate::schema::webhook_deliveries::dsl::*;
+
+        diesel::update(webhook_deliveries.filter(id.eq(delivery_uuid)))
+            .set(result)
+            .get_result::<WebhookDelivery>(conn)
+            .map_err(|e| e.into())
+    }
+
+    #[instrument(name = "HookRepository::finish_replay", skip_all)]
+    pub fn finish_replay(
+        conn: &mut PgConnection,
+        delivery_uuid: Uuid,
+        result: &DeliveryResult,
+    ) -> Result<WebhookDelivery, Box<dyn Error>> {
+        use crate::schema::webhook_deliveries::dsl::*;
+
+        diesel::update(webhook_deliveries.filter(id.eq(delivery_uuid)))
+            .set((result, attempts.eq(attempts + 1)))
+            .get_result::<WebhookDelivery>(conn)
+            .map_err(|e| e.into())
+    }
+}
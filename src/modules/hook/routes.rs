@@ -0,0 +1,26 @@
+use crate::modules::hook::handler::{
+    create_secret, delete_secret, get_deliveries, receive, replay,
+};
+use actix_web::web;
+
+// Providers cap payloads at 25 MB, well above the default body limit
+const MAX_PAYLOAD_BYTES: usize = 25 * 1024 * 1024;
+
+pub fn config_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/hooks")
+            .app_data(web::PayloadConfig::new(MAX_PAYLOAD_BYTES))
+            .route("/{provider}/{repo_id}", web::post().to(receive)),
+    );
+}
+
+// Mounted inside the `/repositories` scope
+pub fn config_repository_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/{id}/hooks/secret", web::post().to(create_secret))
+        .route("/{id}/hooks/secret", web::delete().to(delete_secret))
+        .route("/{id}/hooks/deliveries", web::get().to(get_deliveries))
+        .route(
+            "/{id}/hooks/deliveries/{delivery_id}/replay",
+            web::post().to(replay),
+        );
+}
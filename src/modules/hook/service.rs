@@ -0,0 +1,384 @@
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::jobs;
+use crate::models::{Repo, WebhookDelivery};
+use crate::modules::hook::dto::{
+    DeliveryResult, DeliveryStatus, IncomingDelivery, NewWebhookDelivery, NewWebhookSecret,
+    Provider, RefAction, RepoEvent, WebhookSetup, WebhookUrl,
+};
+use crate::modules::hook::repository::HookRepository;
+use crate::utils::envelope::{Envelope, MasterKey};
+use crate::utils::shutdown::Shutdown;
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use chrono::Utc;
+use diesel::PgConnection;
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use std::error::Error;
+use subtle::ConstantTimeEq;
+use tracing::instrument;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const SECRET_BYTES: usize = 32;
+const DELIVERY_LIST_LIMIT: i64 = 100;
+
+pub struct HookService;
+
+impl HookService {
+    /// Generates a new secret for the repository, replacing the previous
+    /// one. The secret is only ever returned here.
+    #[instrument(name = "HookService::generate_secret", skip_all)]
+    pub fn generate_secret(
+        conn: &mut PgConnection,
+        repo: &Repo,
+        master_key: &MasterKey,
+        base_url: &str,
+    ) -> Result<WebhookSetup, Box<dyn Error>> {
+        let mut bytes = Zeroizing::new([0u8; SECRET_BYTES]);
+        OsRng.fill_bytes(bytes.as_mut_slice());
+        let secret = hex::encode(bytes.as_slice());
+
+        let envelope = master_key.seal(secret.as_bytes(), repo.id.as_bytes())?;
+        let stored =
+            HookRepository::replace_secret(conn, &NewWebhookSecret::new(repo.id, envelope))?;
+
+        let urls = [Provider::GitHub, Provider::GitLab, Provider::Gitea]
+            .into_iter()
+            .map(|provider| WebhookUrl {
+                provider: provider.to_string(),
+                url: format!(
+                    "{}/api/hooks/{}/{}",
+                    base_url.trim_end_matches('/'),
+                    provider,
+                    repo.id
+                ),
+            })
+            .collect();
+
+        Ok(WebhookSetup {
+            secret,
+            urls,
+            created_at: stored.created_at,
+        })
+    }
+
+    #[instrument(name = "HookService::delete_secret", skip_all)]
+    pub fn delete_secret(conn: &mut PgConnection, repo_id: Uuid) -> Result<(), Box<dyn Error>> {
+        HookRepository::delete_secret(conn, repo_id)
+    }
+
+    /// Decrypted secret of the repository, if webhooks are set up for it.
+    #[instrument(name = "HookService::secret", skip_all)]
+    pub fn secret(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        master_key: &MasterKey,
+    ) -> Result<Option<Zeroizing<Vec<u8>>>, Box<dyn Error>> {
+        let Some(stored) = HookRepository::find_secret(conn, repo_id)? else {
+            return Ok(None);
+        };
+
+        let secret = master_key.open(
+            &Envelope {
+                ciphertext: stored.encrypted_secret,
+                nonce: stored.secret_nonce,
+                encrypted_data_key: stored.encrypted_data_key,
+                data_key_nonce: stored.data_key_nonce,
+            },
+            repo_id.as_bytes(),
+        )?;
+
+        Ok(Some(secret))
+    }
+
+    /// Checks the value of the provider's signature header. GitHub and
+    /// Gitea sign the payload with HMAC-SHA256, GitLab sends the secret.
+    #[instrument(name = "HookService::verify", skip_all)]
+    pub fn verify(
+        provider: Provider,
+        signature: Option<&str>,
+        secret: &[u8],
+        payload: &[u8],
+    ) -> bool {
+        let Some(signature) = signature else {
+            return false;
+        };
+
+        match provider {
+            Provider::GitHub => signature
+                .strip_prefix("sha256=")
+                .is_some_and(|signature| Self::verify_hmac(secret, payload, signature)),
+            Provider::GitLab => signature.as_bytes().ct_eq(secret).into(),
+            Provider::Gitea => Self::verify_hmac(secret, payload, signature),
+        }
+    }
+
+    #[instrument(name = "HookService::get_deliveries", skip_all)]
+    pub fn get_deliveries(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+    ) -> Result<Vec<WebhookDelivery>, Box<dyn Error>> {
+        HookRepository::find_deliveries(conn, repo_id, DELIVERY_LIST_LIMIT)
+    }
+
+    /// Stores a verified delivery and handles it. A delivery the provider
+    /// sends again is returned as first handled.
+    #[instrument(name = "HookService::receive", skip_all)]
+    pub fn receive(
+        conn: &mut PgConnection,
+        pool: &DbPool,
+        config: &Config,
+        shutdown: &Shutdown,
+        repo: &Repo,
+        incoming: IncomingDelivery,
+    ) -> Result<WebhookDelivery, Box<dyn Error>> {
+        let provider = incoming.provider;
+        let new_delivery = NewWebhookDelivery {
+            repository_id: repo.id,
+            provider: provider.to_string(),
+            delivery_id: incoming.delivery_id,
+            event_name: incoming.event_name,
+            event: None,
+            payload: incoming.payload,
+            status: DeliveryStatus::Received.to_string(),
+        };
+
+        let Some(delivery) = HookRepository::record(conn, &new_delivery)? else {
+            return HookRepository::find_by_delivery_id(
+                conn,
+                repo.id,
+                provider.as_str(),
+                new_delivery.delivery_id.as_deref().unwrap_or_default(),
+            )?
+            .ok_or_else(|| "Delivery not found".into());
+        };
+
+        let result = Self::handle(conn, pool, config, shutdown, repo, provider, &delivery);
+        HookRepository::finish(conn, delivery.id, &result)
+    }
+
+    /// Handles a stored delivery again, as if it was just received.
+    #[instrument(name = "HookService::replay", skip_all)]
+    pub fn replay(
+        conn: &mut PgConnection,
+        pool: &DbPool,
+        config: &Config,
+        shutdown: &Shutdown,
+        repo: &Repo,
+        delivery_id: Uuid,
+    ) -> Result<WebhookDelivery, Box<dyn Error>> {
+        let delivery = HookRepository::find_delivery(conn, repo.id, delivery_id)?;
+        let provider = delivery.provider.parse::<Provider>()?;
+
+        let result = Self::handle(conn, pool, config, shutdown, repo, provider, &delivery);
+        HookRepository::finish_replay(conn, delivery.id, &result)
+    }
+
+    /// Turns a payload into the common event type. Events we do not react
+    /// to give `None`.
+    #[instrument(name = "HookService::parse", skip_all)]
+    pub fn parse(
+        provider: Provider,
+        event_name: &str,
+        payload: &str,
+    ) -> Result<Option<RepoEvent>, Box<dyn Error>> {
+        let body: Value = serde_json::from_str(payload)?;
+
+        let event = match (provider, event_name) {
+            (Provider::GitHub | Provider::Gitea, "push")
+            | (Provider::GitLab, "Push Hook" | "Tag Push Hook") => {
+                Some(Self::parse_push(provider, &body)?)
+            }
+            (Provider::GitHub | Provider::Gitea, "pull_request") => {
+                Some(Self::parse_pull_request(&body)?)
+            }
+            (Provider::GitLab, "Merge Request Hook") => Some(Self::parse_merge_request(&body)?),
+            _ => None,
+        };
+
+        Ok(event)
+    }
+
+    fn handle(
+        conn: &mut PgConnection,
+        pool: &DbPool,
+        config: &Config,
+        shutdown: &Shutdown,
+        repo: &Repo,
+        provider: Provider,
+        delivery: &WebhookDelivery,
+    ) -> DeliveryResult {
+        let result = |event, status: DeliveryStatus, error| DeliveryResult {
+            event,
+            status: status.to_string(),
+            error,
+            processed_at: Some(Utc::now()),
+        };
+
+        let event = match Self::parse(provider, &delivery.event_name, &delivery.payload) {
+            Ok(Some(event)) => event,
+            Ok(None) => return result(None, DeliveryStatus::Ignored, None),
+            Err(e) => {
+                return result(
+                    None,
+                    DeliveryStatus::Failed,
+                    Some(format!("Invalid payload: {}", e)),
+                )
+            }
+        };
+
+        let value = serde_json::to_value(&event).ok();
+        match Self::dispatch(conn, pool, config, shutdown, repo, &event) {
+            Ok(()) => result(value, DeliveryStatus::Processed, None),
+            Err(e) => result(value, DeliveryStatus::Failed, Some(e.to_string())),
+        }
+    }
+
+    /// Hands the event to whatever reacts to it. New commits and tags
+    /// refresh the mirror.
+    fn dispatch(
+        conn: &mut PgConnection,
+        pool: &DbPool,
+        config: &Config,
+        shutdown: &Shutdown,
+        repo: &Repo,
+        event: &RepoEvent,
+    ) -> Result<(), Box<dyn Error>> {
+        let refreshes_mirror = matches!(event, RepoEvent::Push { .. } | RepoEvent::Tag { .. });
+        if refreshes_mirror
+            && config.mirror.enabled
+            && jobs::mirror::trigger(conn, pool, config, shutdown, repo)?.is_none()
+        {
+            tracing::info!("Mirror of repo {} is already syncing", repo.id);
+        }
+
+        Ok(())
+    }
+
+    fn verify_hmac(secret: &[u8], payload: &[u8], signature: &str) -> bool {
+        let (Ok(signature), Ok(mut mac)) = (
+            hex::decode(signature.trim()),
+            HmacSha256::new_from_slice(secret),
+        ) else {
+            return false;
+        };
+
+        mac.update(payload);
+        mac.verify_slice(&signature).is_ok()
+    }
+
+    // GitHub and Gitea pushes share their shape, GitLab names a few fields
+    // differently. Tags arrive as pushes of `refs/tags/*`.
+    fn parse_push(provider: Provider, body: &Value) -> Result<RepoEvent, Box<dyn Error>> {
+        let reference = Self::field(body, "/ref")?;
+        let before = Self::field(body, "/before")?.to_string();
+        let after = Self::field(body, "/after")?.to_string();
+
+        let action = if Self::is_zero_sha(&before) {
+            RefAction::Created
+        } else if Self::is_zero_sha(&after) {
+            RefAction::Deleted
+        } else {
+            RefAction::Updated
+        };
+
+        if let Some(name) = reference.strip_prefix("refs/tags/") {
+            return Ok(RepoEvent::Tag {
+                name: name.to_string(),
+                action,
+                sha: after,
+            });
+        }
+        let branch = reference
+            .strip_prefix("refs/heads/")
+            .ok_or_else(|| format!("Unsupported ref {}", reference))?;
+
+        let commit_count = match provider {
+            Provider::GitLab => body
+                .pointer("/total_commits_count")
+                .and_then(Value::as_u64)
+                .unwrap_or_default() as usize,
+            _ => body
+                .pointer("/commits")
+                .and_then(Value::as_array)
+                .map_or(0, Vec::len),
+        };
+        let pusher = match provider {
+            Provider::GitHub => body.pointer("/pusher/name"),
+            Provider::GitLab => body.pointer("/user_username"),
+            Provider::Gitea => body.pointer("/pusher/login"),
+        };
+
+        Ok(RepoEvent::Push {
+            branch: branch.to_string(),
+            action,
+            before,
+            after,
+            commit_count,
+            pusher: pusher.and_then(Value::as_str).map(String::from),
+        })
+    }
+
+    // GitHub and Gitea
+    fn parse_pull_request(body: &Value) -> Result<RepoEvent, Box<dyn Error>> {
+        let merged = body
+            .pointer("/pull_request/merged")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let action = match Self::field(body, "/action")? {
+            "closed" if merged => "merged",
+            "synchronize" | "synchronized" => "updated",
+            action => action,
+        };
+
+        Ok(RepoEvent::PullRequest {
+            number: body
+                .pointer("/number")
+                .and_then(Value::as_i64)
+                .ok_or("Missing field /number")?,
+            action: action.to_string(),
+            title: Self::field(body, "/pull_request/title")?.to_string(),
+            source_branch: Self::field(body, "/pull_request/head/ref")?.to_string(),
+            target_branch: Self::field(body, "/pull_request/base/ref")?.to_string(),
+            head_sha: Self::field(body, "/pull_request/head/sha")?.to_string(),
+        })
+    }
+
+    fn parse_merge_request(body: &Value) -> Result<RepoEvent, Box<dyn Error>> {
+        let action = match Self::field(body, "/object_attributes/action")? {
+            "open" => "opened",
+            "update" => "updated",
+            "close" => "closed",
+            "merge" => "merged",
+            "reopen" => "reopened",
+            action => action,
+        };
+
+        Ok(RepoEvent::PullRequest {
+            number: body
+                .pointer("/object_attributes/iid")
+                .and_then(Value::as_i64)
+                .ok_or("Missing field /object_attributes/iid")?,
+            action: action.to_string(),
+            title: Self::field(body, "/object_attributes/title")?.to_string(),
+            source_branch: Self::field(body, "/object_attributes/source_branch")?.to_string(),
+            target_branch: Self::field(body, "/object_attributes/target_branch")?.to_string(),
+            head_sha: Self::field(body, "/object_attributes/last_commit/id")?.to_string(),
+        })
+    }
+
+    fn field<'a>(body: &'a Value, pointer: &str) -> Result<&'a str, Box<dyn Error>> {
+        body.pointer(pointer)
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("Missing field {}", pointer).into())
+    }
+
+    fn is_zero_sha(sha: &str) -> bool {
+        !sha.is_empty() && sha.bytes().all(|b| b == b'0')
+    }
+}
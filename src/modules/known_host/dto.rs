@@ -0,0 +1,110 @@
+use crate::git::host_key::HostKey;
+use crate::models::KnownHost;
+use crate::schema::known_hosts;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KnownHostStatus {
+    /// Seen on first connection, waiting for confirmation
+    Pending,
+    Trusted,
+    /// Differs from the trusted key of the same type
+    Changed,
+}
+
+impl KnownHostStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KnownHostStatus::Pending => "pending",
+            KnownHostStatus::Trusted => "trusted",
+            KnownHostStatus::Changed => "changed",
+        }
+    }
+}
+
+impl fmt::Display for KnownHostStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = known_hosts)]
+#[diesel(treat_none_as_null = true)]
+pub struct NewKnownHost {
+    pub organization_id: Uuid,
+    pub host: String,
+    pub key_type: String,
+    pub public_key: String,
+    pub fingerprint: String,
+    pub status: String,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub confirmed_by: Option<Uuid>,
+}
+
+impl NewKnownHost {
+    pub fn new(
+        organization_id: Uuid,
+        key: &HostKey,
+        status: KnownHostStatus,
+        confirmed_by: Option<Uuid>,
+    ) -> Self {
+        NewKnownHost {
+            organization_id,
+            host: key.host.clone(),
+            key_type: key.key_type.clone(),
+            public_key: key.public_key.clone(),
+            fingerprint: key.fingerprint.clone(),
+            status: status.as_str().to_string(),
+            confirmed_at: confirmed_by.map(|_| Utc::now()),
+            confirmed_by,
+        }
+    }
+}
+
+impl From<KnownHost> for HostKey {
+    fn from(known_host: KnownHost) -> Self {
+        HostKey {
+            host: known_host.host,
+            key_type: known_host.key_type,
+            public_key: known_host.public_key,
+            fingerprint: known_host.fingerprint,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ImportKnownHostsQuery {
+    /// Content in `known_hosts` format, one key per line
+    #[validate(length(min = 1, max = 65536))]
+    #[schema(
+        example = "github.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl"
+    )]
+    pub lines: String,
+}
+
+/// The fingerprint the caller checked out of band, guarding against
+/// confirming a different key than the one they reviewed.
+#[derive(Deserialize, ToSchema)]
+pub struct ConfirmKnownHostQuery {
+    #[schema(example = "SHA256:+DiY3wvvV6TuJJhbpZisF/zLDA0zPMSvHdkr4UvCOqU")]
+    pub fingerprint: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SkippedLine {
+    pub line: usize,
+    pub reason: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct KnownHostImport {
+    pub imported: Vec<KnownHost>,
+    pub skipped: Vec<SkippedLine>,
+}
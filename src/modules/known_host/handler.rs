@@ -0,0 +1,157 @@
+use crate::db;
+use crate::modules::auth::handler::session_user;
+use crate::modules::known_host::dto::{ConfirmKnownHostQuery, ImportKnownHostsQuery};
+use crate::modules::known_host::service::KnownHostService;
+use crate::modules::organization::handler::{require_manager, require_member};
+use crate::utils::response::{error, success, ApiError};
+use actix_identity::Identity;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use diesel::r2d2::{self, ConnectionManager};
+use diesel::PgConnection;
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+#[instrument(name = "known_host::handler::get_by_organization", skip_all)]
+pub async fn get_by_organization(
+    id: Identity,
+    path: web::Path<Uuid>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    let organization_id = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Get organization known hosts
+    let result = db::block(&pool, move |conn| {
+        require_member(conn, user_id, organization_id)?;
+        KnownHostService::get_by_organization(conn, organization_id).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve known hosts: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(keys) => HttpResponse::Ok().json(success(StatusCode::OK, Some(keys))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "known_host::handler::import", skip_all)]
+pub async fn import(
+    id: Identity,
+    path: web::Path<Uuid>,
+    pool: web::Data<DbPool>,
+    import_data: web::Json<ImportKnownHostsQuery>,
+) -> HttpResponse {
+    let organization_id = path.into_inner();
+
+    // Validate import data
+    if let Err(errors) = import_data.validate() {
+        return HttpResponse::BadRequest().json(error(
+            StatusCode::BAD_REQUEST,
+            format!("Validation error: {:?}", errors),
+        ));
+    }
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Import known hosts lines
+    let result = db::block(&pool, move |conn| {
+        require_manager(conn, user_id, organization_id)?;
+        KnownHostService::import(conn, organization_id, &import_data, user_id).map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Failed to import known hosts: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(report) => HttpResponse::Ok().json(success(StatusCode::OK, Some(report))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "known_host::handler::confirm", skip_all)]
+pub async fn confirm(
+    id: Identity,
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<DbPool>,
+    confirm_data: web::Json<ConfirmKnownHostQuery>,
+) -> HttpResponse {
+    let (organization_id, key_id) = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Trust the host key
+    let result = db::block(&pool, move |conn| {
+        require_manager(conn, user_id, organization_id)?;
+        KnownHostService::confirm(
+            conn,
+            organization_id,
+            key_id,
+            &confirm_data.fingerprint,
+            user_id,
+        )
+        .map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Failed to confirm host key: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(key) => HttpResponse::Ok().json(success(StatusCode::OK, Some(key))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "known_host::handler::delete", skip_all)]
+pub async fn delete(
+    id: Identity,
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    let (organization_id, key_id) = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Delete known host
+    let result = db::block(&pool, move |conn| {
+        require_manager(conn, user_id, organization_id)?;
+        KnownHostService::delete(conn, organization_id, key_id).map_err(|e| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                format!("Failed to delete known host: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(success::<()>(StatusCode::OK, None)),
+        Err(e) => e.error_response(),
+    }
+}
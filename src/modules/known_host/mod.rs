@@ -0,0 +1,5 @@
+pub mod dto;
+pub mod handler;
+pub mod repository;
+pub mod routes;
+pub mod service;
@@ -0,0 +1,137 @@
+use crate::models::KnownHost;
+use crate::modules::known_host::dto::{KnownHostStatus, NewKnownHost};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use std::error::Error;
+use tracing::instrument;
+use uuid::Uuid;
+
+pub struct KnownHostRepository;
+
+impl KnownHostRepository {
+    #[instrument(name = "KnownHostRepository::find_by_organization", skip_all)]
+    pub fn find_by_organization(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+    ) -> Result<Vec<KnownHost>, Box<dyn Error>> {
+        use crate::schema::known_hosts::dsl::*;
+
+        let keys = known_hosts
+            .filter(organization_id.eq(org_id))
+            .order((host.asc(), key_type.asc(), created_at.asc()))
+            .load::<KnownHost>(conn)?;
+
+        Ok(keys)
+    }
+
+    #[instrument(name = "KnownHostRepository::find_by_host", skip_all)]
+    pub fn find_by_host(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        host_name: &str,
+        key_status: Option<KnownHostStatus>,
+    ) -> Result<Vec<KnownHost>, Box<dyn Error>> {
+        use crate::schema::known_hosts::dsl::*;
+
+        let mut query = known_hosts
+            .filter(organization_id.eq(org_id))
+            .filter(host.eq(host_name))
+            .into_boxed();
+        if let Some(key_status) = key_status {
+            query = query.filter(status.eq(key_status.as_str()));
+        }
+
+        let keys = query
+            .order((key_type.asc(), created_at.asc()))
+            .load::<KnownHost>(conn)?;
+
+        Ok(keys)
+    }
+
+    #[instrument(name = "KnownHostRepository::find_by_id", skip_all)]
+    pub fn find_by_id(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        key_id: Uuid,
+    ) -> Result<KnownHost, Box<dyn Error>> {
+        use crate::schema::known_hosts::dsl::*;
+
+        known_hosts
+            .filter(id.eq(key_id))
+            .filter(organization_id.eq(org_id))
+            .first::<KnownHost>(conn)
+            .optional()?
+            .ok_or_else(|| "Known host not found".into())
+    }
+
+    /// Stores a key seen on connection, once per host and key.
+    #[instrument(name = "KnownHostRepository::record", skip_all)]
+    pub fn record(
+        conn: &mut PgConnection,
+        new_key: &NewKnownHost,
+    ) -> Result<KnownHost, Box<dyn Error>> {
+        use crate::schema::known_hosts::dsl::*;
+
+        diesel::insert_into(known_hosts)
+            .values(new_key)
+            .on_conflict((organization_id, host, public_key))
+            .do_update()
+            .set(status.eq(&new_key.status))
+            .get_result::<KnownHost>(conn)
+            .map_err(|e| e.into())
+    }
+
+    /// Trusts the given keys, replacing any trusted key of the same type
+    /// for their host.
+    #[instrument(name = "KnownHostRepository::trust", skip_all)]
+    pub fn trust(
+        conn: &mut PgConnection,
+        new_keys: &[NewKnownHost],
+    ) -> Result<Vec<KnownHost>, Box<dyn Error>> {
+        use crate::schema::known_hosts::dsl::*;
+
+        conn.transaction(|conn| {
+            let mut trusted = Vec::with_capacity(new_keys.len());
+
+            for new_key in new_keys {
+                diesel::delete(known_hosts)
+                    .filter(organization_id.eq(new_key.organization_id))
+                    .filter(host.eq(&new_key.host))
+                    .filter(key_type.eq(&new_key.key_type))
+                    .filter(status.eq(KnownHostStatus::Trusted.as_str()))
+                    .filter(public_key.ne(&new_key.public_key))
+                    .execute(conn)?;
+
+                let key = diesel::insert_into(known_hosts)
+                    .values(new_key)
+                    .on_conflict((organization_id, host, public_key))
+                    .do_update()
+                    .set(new_key)
+                    .get_result::<KnownHost>(conn)?;
+                trusted.push(key);
+            }
+
+            Ok(trusted)
+        })
+    }
+
+    #[instrument(name = "KnownHostRepository::delete", skip_all)]
+    pub fn delete(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        key_id: Uuid,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::schema::known_hosts::dsl::*;
+
+        let deleted = diesel::delete(known_hosts)
+            .filter(id.eq(key_id))
+            .filter(organization_id.eq(org_id))
+            .execute(conn)?;
+
+        if deleted == 0 {
+            return Err("Known host not found".into());
+        }
+
+        Ok(())
+    }
+}
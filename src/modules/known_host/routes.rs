@@ -0,0 +1,13 @@
+use crate::modules::known_host::handler::{confirm, delete, get_by_organization, import};
+use actix_web::web;
+
+// Mounted inside the `/organizations` scope
+pub fn config_organization_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/{org_id}/known-hosts", web::get().to(get_by_organization))
+        .route("/{org_id}/known-hosts", web::post().to(import))
+        .route(
+            "/{org_id}/known-hosts/{key_id}/confirm",
+            web::post().to(confirm),
+        )
+        .route("/{org_id}/known-hosts/{key_id}", web::delete().to(delete));
+}
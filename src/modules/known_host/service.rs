@@ -0,0 +1,175 @@
+use crate::git::host_key::HostKey;
+use crate::git::remote::RemoteError;
+use crate::models::KnownHost;
+use crate::modules::known_host::dto::{
+    ImportKnownHostsQuery, KnownHostImport, KnownHostStatus, NewKnownHost, SkippedLine,
+};
+use crate::modules::known_host::repository::KnownHostRepository;
+use crate::modules::outbox::dto::DomainEvent;
+use crate::modules::outbox::service::OutboxService;
+use diesel::{Connection, PgConnection};
+use std::error::Error;
+use tracing::instrument;
+use uuid::Uuid;
+
+pub struct KnownHostService;
+
+impl KnownHostService {
+    #[instrument(name = "KnownHostService::get_by_organization", skip_all)]
+    pub fn get_by_organization(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+    ) -> Result<Vec<KnownHost>, Box<dyn Error>> {
+        KnownHostRepository::find_by_organization(conn, organization_id)
+    }
+
+    /// Every key recorded for `host`, whatever its status.
+    #[instrument(name = "KnownHostService::get_by_host", skip_all)]
+    pub fn get_by_host(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+        host: &str,
+    ) -> Result<Vec<KnownHost>, Box<dyn Error>> {
+        KnownHostRepository::find_by_host(conn, organization_id, host, None)
+    }
+
+    /// Keys a connection to `host` is allowed to see.
+    #[instrument(name = "KnownHostService::get_trusted", skip_all)]
+    pub fn get_trusted(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+        host: &str,
+    ) -> Result<Vec<HostKey>, Box<dyn Error>> {
+        let keys = KnownHostRepository::find_by_host(
+            conn,
+            organization_id,
+            host,
+            Some(KnownHostStatus::Trusted),
+        )?;
+
+        Ok(keys.into_iter().map(HostKey::from).collect())
+    }
+
+    /// Imports `known_hosts` lines as trusted keys. Lines that cannot be
+    /// used are reported rather than failing the whole import.
+    #[instrument(name = "KnownHostService::import", skip_all)]
+    pub fn import(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+        data: &ImportKnownHostsQuery,
+        user_id: Uuid,
+    ) -> Result<KnownHostImport, Box<dyn Error>> {
+        let mut new_keys = Vec::new();
+        let mut skipped = Vec::new();
+
+        for (index, line) in data.lines.lines().enumerate() {
+            match HostKey::parse_line(line) {
+                Ok(keys) => new_keys.extend(keys.iter().map(|key| {
+                    NewKnownHost::new(
+                        organization_id,
+                        key,
+                        KnownHostStatus::Trusted,
+                        Some(user_id),
+                    )
+                })),
+                Err(reason) => skipped.push(SkippedLine {
+                    line: index + 1,
+                    reason,
+                }),
+            }
+        }
+
+        let imported = KnownHostRepository::trust(conn, &new_keys)?;
+
+        Ok(KnownHostImport { imported, skipped })
+    }
+
+    /// Trusts a pending or changed key. A changed key replaces the
+    /// previously trusted one.
+    #[instrument(name = "KnownHostService::confirm", skip_all)]
+    pub fn confirm(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+        key_id: Uuid,
+        fingerprint: &str,
+        user_id: Uuid,
+    ) -> Result<KnownHost, Box<dyn Error>> {
+        let known_host = KnownHostRepository::find_by_id(conn, organization_id, key_id)?;
+
+        if known_host.status == KnownHostStatus::Trusted.as_str() {
+            return Err("Host key is already trusted".into());
+        }
+        if known_host.fingerprint != fingerprint.trim() {
+            return Err("Fingerprint does not match the recorded host key".into());
+        }
+
+        let key = HostKey::from(known_host);
+        let new_key = NewKnownHost::new(
+            organization_id,
+            &key,
+            KnownHostStatus::Trusted,
+            Some(user_id),
+        );
+
+        KnownHostRepository::trust(conn, &[new_key])?
+            .pop()
+            .ok_or_else(|| "Failed to trust host key".into())
+    }
+
+    #[instrument(name = "KnownHostService::delete", skip_all)]
+    pub fn delete(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+        key_id: Uuid,
+    ) -> Result<(), Box<dyn Error>> {
+        KnownHostRepository::delete(conn, organization_id, key_id)
+    }
+
+    /// Keeps the host key a connection was refused for, so it can be
+    /// reviewed and confirmed. The first refusal of a changed key raises a
+    /// `host_key.changed` event for the organization.
+    #[instrument(name = "KnownHostService::record_rejection", skip_all)]
+    pub fn record_rejection(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+        rejection: &RemoteError,
+    ) -> Result<(), Box<dyn Error>> {
+        let (key, status) = match rejection {
+            RemoteError::UnknownHostKey(key) => (key, KnownHostStatus::Pending),
+            RemoteError::ChangedHostKey(key) => (key, KnownHostStatus::Changed),
+            _ => return Ok(()),
+        };
+
+        // Syncs keep hitting the same key until someone acts, alert once
+        let alert = status == KnownHostStatus::Changed
+            && !KnownHostRepository::find_by_host(conn, organization_id, &key.host, Some(status))?
+                .iter()
+                .any(|known| known.public_key == key.public_key);
+
+        conn.transaction(|conn| {
+            let new_key = NewKnownHost::new(organization_id, key, status, None);
+            KnownHostRepository::record(conn, &new_key)?;
+
+            if alert {
+                tracing::error!(
+                    "SSH host key for {} changed in organization {}: now {} {}",
+                    key.host,
+                    organization_id,
+                    key.key_type,
+                    key.fingerprint
+                );
+                OutboxService::record(
+                    conn,
+                    &DomainEvent::HostKeyChanged {
+                        organization_id,
+                        host: key.host.clone(),
+                        key_type: key.key_type.clone(),
+                        fingerprint: key.fingerprint.clone(),
+                    },
+                )?;
+            }
+
+            Ok(())
+        })
+    }
+}
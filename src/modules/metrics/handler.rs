@@ -0,0 +1,50 @@
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::modules::metrics::service::MetricsService;
+use crate::utils::response::error;
+use actix_web::http::header;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse};
+use subtle::ConstantTimeEq;
+use tracing::instrument;
+
+/// Every metric in the Prometheus text format. Requires
+/// `Authorization: Bearer <metrics.token>` when a token is configured.
+#[instrument(name = "metrics::handler::metrics", skip_all)]
+pub async fn metrics(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    if !authorized(&req, &config.metrics.token) {
+        return HttpResponse::Unauthorized()
+            .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+            .json(error(
+                StatusCode::UNAUTHORIZED,
+                "Invalid metrics token".into(),
+            ));
+    }
+
+    match MetricsService::scrape(&pool).await {
+        Ok(body) => HttpResponse::Ok()
+            .content_type(prometheus::TEXT_FORMAT)
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .body(body),
+        Err(e) => HttpResponse::InternalServerError().json(error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to render metrics: {}", e),
+        )),
+    }
+}
+
+fn authorized(req: &HttpRequest, token: &str) -> bool {
+    if token.is_empty() {
+        return true;
+    }
+
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| given.as_bytes().ct_eq(token.as_bytes()).into())
+}
@@ -0,0 +1,3 @@
+pub mod handler;
+pub mod routes;
+pub mod service;
@@ -0,0 +1,7 @@
+use crate::modules::metrics::handler::metrics;
+use actix_web::web;
+
+/// Mounted at the root, next to `/api`, on whichever server exposes it.
+pub fn config_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/metrics").route(web::get().to(metrics)));
+}
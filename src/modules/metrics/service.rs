@@ -0,0 +1,56 @@
+use crate::db::DbPool;
+use crate::modules::outbox::repository::OutboxRepository;
+use crate::modules::webhook::repository::WebhookRepository;
+use crate::utils::metrics::METRICS;
+use actix_web::web;
+use tracing::instrument;
+
+pub struct MetricsService;
+
+impl MetricsService {
+    /// Refreshes the gauges sampled at scrape time, then renders every
+    /// metric.
+    #[instrument(name = "MetricsService::scrape", skip_all)]
+    pub async fn scrape(pool: &DbPool) -> Result<String, String> {
+        let state = pool.state();
+        METRICS.db_pool_max_connections.set(pool.max_size().into());
+        METRICS
+            .db_pool_connections
+            .with_label_values(&["idle"])
+            .set(state.idle_connections.into());
+        METRICS
+            .db_pool_connections
+            .with_label_values(&["in_use"])
+            .set((state.connections - state.idle_connections).into());
+
+        // Stale depths beat a failed scrape when the database is in trouble
+        let db_pool = pool.clone();
+        match web::block(move || Self::refresh_queue_depths(&db_pool)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => tracing::warn!("Failed to count queued jobs: {}", e),
+            Err(e) => tracing::warn!("Counting queued jobs was cancelled: {}", e),
+        }
+
+        METRICS.render().map_err(|e| e.to_string())
+    }
+
+    // Skipped while no connection is idle, so scrapes never hold up requests
+    fn refresh_queue_depths(pool: &DbPool) -> Result<(), String> {
+        let Some(mut conn) = pool.try_get() else {
+            return Ok(());
+        };
+
+        let outbox = OutboxRepository::count_pending(&mut conn).map_err(|e| e.to_string())?;
+        let webhooks = WebhookRepository::count_pending(&mut conn).map_err(|e| e.to_string())?;
+        METRICS
+            .queue_depth
+            .with_label_values(&["outbox"])
+            .set(outbox);
+        METRICS
+            .queue_depth
+            .with_label_values(&["webhooks"])
+            .set(webhooks);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use crate::schema::repository_mirrors;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MirrorStatus {
+    Syncing,
+    Succeeded,
+    Failed,
+}
+
+impl MirrorStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MirrorStatus::Syncing => "syncing",
+            MirrorStatus::Succeeded => "succeeded",
+            MirrorStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Marks a repository as syncing, creating its mirror row on first sync.
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = repository_mirrors)]
+pub struct MirrorClaim {
+    pub repository_id: Uuid,
+    pub status: String,
+    pub path: String,
+    pub sync_started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Outcome of a sync. A failed sync keeps the last known branch and size.
+#[derive(AsChangeset)]
+#[diesel(table_name = repository_mirrors)]
+#[diesel(treat_none_as_null = true)]
+pub struct MirrorResult {
+    pub status: String,
+    pub default_branch: Option<String>,
+    pub size_bytes: Option<i64>,
+    pub last_error: Option<String>,
+    pub last_synced_at: Option<DateTime<Utc>>,
+    pub last_duration_ms: Option<i64>,
+    pub next_sync_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
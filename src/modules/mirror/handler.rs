@@ -0,0 +1,107 @@
+use crate::config::Config;
+use crate::db;
+use crate::jobs;
+use crate::models::Repo;
+use crate::modules::auth::handler::session_user;
+use crate::modules::mirror::service::MirrorService;
+use crate::modules::organization::handler::require_member;
+use crate::modules::repo::service::RepoService;
+use crate::utils::response::{error, success, ApiError};
+use crate::utils::shutdown::Shutdown;
+use actix_identity::Identity;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use diesel::r2d2::{self, ConnectionManager};
+use diesel::PgConnection;
+use tracing::instrument;
+use uuid::Uuid;
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+// Repo the caller is a member of
+fn load_repo(conn: &mut PgConnection, user_id: Uuid, repo_id: Uuid) -> Result<Repo, ApiError> {
+    let repo = RepoService::get_by_id(conn, repo_id)
+        .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, format!("Repo not found: {}", e)))?;
+
+    require_member(conn, user_id, repo.organization_id)?;
+
+    Ok(repo)
+}
+
+#[instrument(name = "mirror::handler::get_by_repository", skip_all)]
+pub async fn get_by_repository(
+    id: Identity,
+    path: web::Path<Uuid>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    let repo_id = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Get mirror status
+    let result = db::block(&pool, move |conn| {
+        load_repo(conn, user_id, repo_id)?;
+        MirrorService::get_by_repository(conn, repo_id).map_err(|e| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                format!("Failed to retrieve mirror: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(mirror) => HttpResponse::Ok().json(success(StatusCode::OK, Some(mirror))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "mirror::handler::sync", skip_all)]
+pub async fn sync(
+    id: Identity,
+    path: web::Path<Uuid>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    shutdown: web::Data<Shutdown>,
+) -> HttpResponse {
+    let repo_id = path.into_inner();
+
+    if !config.mirror.enabled {
+        return HttpResponse::ServiceUnavailable().json(error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Repository mirroring is disabled".into(),
+        ));
+    }
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Start the sync in the background
+    let sync_pool = pool.clone();
+    let result = db::block(&pool, move |conn| {
+        let repo = load_repo(conn, user_id, repo_id)?;
+        jobs::mirror::trigger(conn, &sync_pool, &config, &shutdown, &repo).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to start repo sync: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(Some(mirror)) => {
+            HttpResponse::Accepted().json(success(StatusCode::ACCEPTED, Some(mirror)))
+        }
+        Ok(None) => HttpResponse::Conflict().json(error(
+            StatusCode::CONFLICT,
+            "A sync is already running for this repo".into(),
+        )),
+        Err(e) => e.error_response(),
+    }
+}
@@ -0,0 +1,168 @@
+use crate::models::RepositoryMirror;
+use crate::modules::mirror::dto::{MirrorClaim, MirrorResult, MirrorStatus};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use std::error::Error;
+use tracing::instrument;
+use uuid::Uuid;
+
+pub struct MirrorRepository;
+
+impl MirrorRepository {
+    #[instrument(name = "MirrorRepository::find_by_repository", skip_all)]
+    pub fn find_by_repository(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+    ) -> Result<Option<RepositoryMirror>, Box<dyn Error>> {
+        use crate::schema::repository_mirrors::dsl::*;
+
+        let mirror = repository_mirrors
+            .filter(repository_id.eq(repo_id))
+            .first::<RepositoryMirror>(conn)
+            .optional()?;
+
+        Ok(mirror)
+    }
+
+    /// Live repositories never mirrored, due for a sync, or whose sync
+    /// started before `stale_before` and never finished.
+    #[instrument(name = "MirrorRepository::find_due", skip_all)]
+    pub fn find_due(
+        conn: &mut PgConnection,
+        stale_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Uuid>, Box<dyn Error>> {
+        use crate::schema::{repositories, repository_mirrors};
+
+        let syncing = MirrorStatus::Syncing.as_str();
+        let ids = repositories::table
+            .left_join(repository_mirrors::table)
+            .filter(repositories::deleted_at.is_null())
+            .filter(
+                repository_mirrors::repository_id
+                    .is_null()
+                    .or(repository_mirrors::status
+                        .ne(syncing)
+                        .and(repository_mirrors::next_sync_at.le(Utc::now())))
+                    .or(repository_mirrors::status
+                        .eq(syncing)
+                        .and(repository_mirrors::sync_started_at.lt(stale_before))),
+            )
+            .order(repository_mirrors::next_sync_at.asc().nulls_first())
+            .select(repositories::id)
+            .limit(limit)
+            .load::<Uuid>(conn)?;
+
+        Ok(ids)
+    }
+
+    /// Marks the mirror as syncing unless a sync newer than `stale_before`
+    /// is still running, in which case nothing is returned.
+    #[instrument(name = "MirrorRepository::claim", skip_all)]
+    pub fn claim(
+        conn: &mut PgConnection,
+        claim: &MirrorClaim,
+        stale_before: DateTime<Utc>,
+    ) -> Result<Option<RepositoryMirror>, Box<dyn Error>> {
+        use crate::schema::repository_mirrors::dsl::*;
+        use diesel::query_dsl::methods::FilterDsl;
+
+        let upsert = diesel::insert_into(repository_mirrors)
+            .values(claim)
+            .on_conflict(repository_id)
+            .do_update()
+            .set(claim);
+
+        // `ON CONFLICT DO UPDATE ... WHERE`, skipped while a sync is running
+        let mirror = FilterDsl::filter(
+            upsert,
+            status
+                .ne(MirrorStatus::Syncing.as_str())
+                .or(sync_started_at.lt(stale_before)),
+        )
+        .get_result::<RepositoryMirror>(conn)
+        .optional()?;
+
+        Ok(mirror)
+    }
+
+    #[instrument(name = "MirrorRepository::finish", skip_all)]
+    pub fn finish(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        result: &MirrorResult,
+    ) -> Result<RepositoryMirror, Box<dyn Error>> {
+        use crate::schema::repository_mirrors::dsl::*;
+
+        diesel::update(repository_mirrors)
+            .filter(repository_id.eq(repo_id))
+            .set(result)
+            .get_result::<RepositoryMirror>(conn)
+            .map_err(|e| e.into())
+    }
+
+    #[instrument(name = "MirrorRepository::reschedule", skip_all)]
+    pub fn reschedule(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        next: Option<DateTime<Utc>>,
+    ) -> Result<usize, Box<dyn Error>> {
+        use crate::schema::repository_mirrors::dsl::*;
+
+        diesel::update(repository_mirrors)
+            .filter(repository_id.eq(repo_id))
+            .set((next_sync_at.eq(next), updated_at.eq(Utc::now())))
+            .execute(conn)
+            .map_err(|e| e.into())
+    }
+
+    #[instrument(name = "MirrorRepository::find_paths_by_repositories", skip_all)]
+    pub fn find_paths_by_repositories(
+        conn: &mut PgConnection,
+        repo_ids: &[Uuid],
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        use crate::schema::repository_mirrors::dsl::*;
+
+        let paths = repository_mirrors
+            .filter(repository_id.eq_any(repo_ids))
+            .select(path)
+            .load::<String>(conn)?;
+
+        Ok(paths)
+    }
+
+    #[instrument(name = "MirrorRepository::find_paths_by_organizations", skip_all)]
+    pub fn find_paths_by_organizations(
+        conn: &mut PgConnection,
+        org_ids: &[Uuid],
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        use crate::schema::{repositories, repository_mirrors};
+
+        let paths = repository_mirrors::table
+            .inner_join(repositories::table)
+            .filter(repositories::organization_id.eq_any(org_ids))
+            .select(repository_mirrors::path)
+            .load::<String>(conn)?;
+
+        Ok(paths)
+    }
+
+    /// Backdates running syncs of these repositories to `stale_before`, so
+    /// they can be claimed again.
+    #[instrument(name = "MirrorRepository::release", skip_all)]
+    pub fn release(
+        conn: &mut PgConnection,
+        repo_ids: &[Uuid],
+        stale_before: DateTime<Utc>,
+    ) -> Result<usize, Box<dyn Error>> {
+        use crate::schema::repository_mirrors::dsl::*;
+
+        diesel::update(repository_mirrors)
+            .filter(repository_id.eq_any(repo_ids))
+            .filter(status.eq(MirrorStatus::Syncing.as_str()))
+            .set(sync_started_at.eq(stale_before))
+            .execute(conn)
+            .map_err(|e| e.into())
+    }
+}
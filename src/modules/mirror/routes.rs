@@ -0,0 +1,8 @@
+use crate::modules::mirror::handler::{get_by_repository, sync};
+use actix_web::web;
+
+// Mounted inside the `/repositories` scope
+pub fn config_repository_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/{id}/mirror", web::get().to(get_by_repository))
+        .route("/{id}/sync", web::post().to(sync));
+}
@@ -0,0 +1,159 @@
+use crate::git::mirror::MirrorStats;
+use crate::git::remote::RemoteError;
+use crate::models::{Repo, RepositoryMirror};
+use crate::modules::known_host::service::KnownHostService;
+use crate::modules::mirror::dto::{MirrorClaim, MirrorResult, MirrorStatus};
+use crate::modules::mirror::repository::MirrorRepository;
+use chrono::{DateTime, Duration, Utc};
+use diesel::PgConnection;
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tracing::instrument;
+use uuid::Uuid;
+
+// A sync still marked as running after this long is assumed dead
+const STALE_SYNC_MINUTES: i64 = 60;
+
+pub struct MirrorService;
+
+impl MirrorService {
+    #[instrument(name = "MirrorService::get_by_repository", skip_all)]
+    pub fn get_by_repository(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+    ) -> Result<RepositoryMirror, Box<dyn Error>> {
+        MirrorRepository::find_by_repository(conn, repo_id)?
+            .ok_or_else(|| "Repository has not been mirrored yet".into())
+    }
+
+    #[instrument(name = "MirrorService::get_due", skip_all)]
+    pub fn get_due(conn: &mut PgConnection, limit: i64) -> Result<Vec<Uuid>, Box<dyn Error>> {
+        MirrorRepository::find_due(conn, Self::stale_before(), limit)
+    }
+
+    /// Mirrors are keyed by repository id so renames and transfers keep them.
+    #[instrument(name = "MirrorService::mirror_path", skip_all)]
+    pub fn mirror_path(storage_dir: &str, repo_id: Uuid) -> PathBuf {
+        Path::new(storage_dir).join(format!("{}.git", repo_id))
+    }
+
+    /// Marks the repository as syncing. Returns `None` when a sync is
+    /// already running for it.
+    #[instrument(name = "MirrorService::claim", skip_all)]
+    pub fn claim(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        storage_dir: &str,
+    ) -> Result<Option<RepositoryMirror>, Box<dyn Error>> {
+        let now = Utc::now();
+        let claim = MirrorClaim {
+            repository_id: repo_id,
+            status: MirrorStatus::Syncing.as_str().to_string(),
+            path: Self::mirror_path(storage_dir, repo_id)
+                .to_string_lossy()
+                .into_owned(),
+            sync_started_at: now,
+            updated_at: now,
+        };
+
+        MirrorRepository::claim(conn, &claim, Self::stale_before())
+    }
+
+    /// Gives up claims whose sync did not run or was interrupted, so the
+    /// next poll, possibly on another instance, picks them up right away.
+    #[instrument(name = "MirrorService::release", skip_all)]
+    pub fn release(conn: &mut PgConnection, repo_ids: &[Uuid]) -> Result<usize, Box<dyn Error>> {
+        MirrorRepository::release(conn, repo_ids, Self::stale_before())
+    }
+
+    /// Stores the outcome of a sync and schedules the next one, unless the
+    /// repository interval is 0.
+    #[instrument(name = "MirrorService::record_sync", skip_all)]
+    pub fn record_sync(
+        conn: &mut PgConnection,
+        repo: &Repo,
+        outcome: Result<MirrorStats, RemoteError>,
+        duration: std::time::Duration,
+        default_interval_minutes: i32,
+    ) -> Result<RepositoryMirror, Box<dyn Error>> {
+        let mirror = Self::get_by_repository(conn, repo.id)?;
+
+        let now = Utc::now();
+        let interval = repo
+            .sync_interval_minutes
+            .unwrap_or(default_interval_minutes);
+        let next_sync_at = Self::next_sync_at(now, interval);
+        let last_duration_ms = Some(i64::try_from(duration.as_millis()).unwrap_or(i64::MAX));
+
+        let result = match outcome {
+            Ok(stats) => MirrorResult {
+                status: MirrorStatus::Succeeded.as_str().to_string(),
+                default_branch: stats.default_branch,
+                size_bytes: Some(i64::try_from(stats.size_bytes).unwrap_or(i64::MAX)),
+                last_error: None,
+                last_synced_at: Some(now),
+                last_duration_ms,
+                next_sync_at,
+                updated_at: now,
+            },
+            Err(e) => {
+                KnownHostService::record_rejection(conn, repo.organization_id, &e)?;
+
+                MirrorResult {
+                    status: MirrorStatus::Failed.as_str().to_string(),
+                    default_branch: mirror.default_branch,
+                    size_bytes: mirror.size_bytes,
+                    last_error: Some(e.to_string()),
+                    last_synced_at: mirror.last_synced_at,
+                    last_duration_ms,
+                    next_sync_at,
+                    updated_at: now,
+                }
+            }
+        };
+
+        MirrorRepository::finish(conn, repo.id, &result)
+    }
+
+    /// Moves the next sync of an existing mirror to match a new interval,
+    /// counted from its last successful sync.
+    #[instrument(name = "MirrorService::reschedule", skip_all)]
+    pub fn reschedule(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        interval_minutes: i32,
+    ) -> Result<(), Box<dyn Error>> {
+        let Some(mirror) = MirrorRepository::find_by_repository(conn, repo_id)? else {
+            return Ok(());
+        };
+
+        let from = mirror.last_synced_at.unwrap_or_else(Utc::now);
+        MirrorRepository::reschedule(conn, repo_id, Self::next_sync_at(from, interval_minutes))?;
+
+        Ok(())
+    }
+
+    /// Deletes mirror directories from disk. Failures are only logged, the
+    /// rows they belonged to are already gone.
+    #[instrument(name = "MirrorService::remove", skip_all)]
+    pub fn remove(paths: &[String]) {
+        for path in paths {
+            match fs::remove_dir_all(path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => tracing::warn!("Failed to remove mirror at {}: {}", path, e),
+            }
+        }
+    }
+
+    /// Mirrors with an interval of 0 are only synced on demand.
+    fn next_sync_at(from: DateTime<Utc>, interval_minutes: i32) -> Option<DateTime<Utc>> {
+        (interval_minutes > 0).then(|| from + Duration::minutes(i64::from(interval_minutes)))
+    }
+
+    fn stale_before() -> DateTime<Utc> {
+        Utc::now() - Duration::minutes(STALE_SYNC_MINUTES)
+    }
+}
@@ -2,4 +2,17 @@ pub mod auth;
 pub mod user;
 pub mod organization;
 pub mod team;
-pub mod repo;
\ No newline at end of file
+pub mod repo;
+pub mod admin;
+pub mod slug;
+pub mod deploy_key;
+pub mod known_host;
+pub mod mirror;
+pub mod browse;
+pub mod hook;
+pub mod webhook;
+pub mod outbox;
+pub mod notification;
+pub mod audit;
+pub mod health;
+pub mod metrics;
@@ -0,0 +1,91 @@
+use crate::config::SmtpConfig;
+use crate::modules::organization::repository::OrganizationRepository;
+use crate::modules::outbox::dto::DomainEvent;
+use crate::utils::mail;
+use diesel::PgConnection;
+use lettre::message::Mailbox;
+use lettre::Message;
+use std::error::Error;
+use tracing::instrument;
+
+/// Emails users about domain events that need their attention.
+pub struct NotificationService;
+
+impl NotificationService {
+    /// Sends the emails an event calls for. Owners and admins of an
+    /// organization are told when one of its SSH host keys changed.
+    ///
+    /// Managers whose address is invalid or refused are skipped. The event
+    /// only fails, to be retried, when nobody could be emailed, so a retry
+    /// never emails anyone twice.
+    #[instrument(name = "NotificationService::publish", skip_all)]
+    pub fn publish(
+        conn: &mut PgConnection,
+        smtp: &SmtpConfig,
+        event: &DomainEvent,
+    ) -> Result<(), Box<dyn Error>> {
+        let DomainEvent::HostKeyChanged {
+            organization_id,
+            host,
+            key_type,
+            fingerprint,
+        } = event
+        else {
+            return Ok(());
+        };
+
+        let organization = OrganizationRepository::find_by_id(conn, *organization_id)?;
+        let from: Mailbox = smtp
+            .email_from
+            .parse()
+            .map_err(|e| format!("Invalid from email: {}", e))?;
+
+        let mut sent = 0;
+        let mut failures = Vec::new();
+        for manager in OrganizationRepository::find_managers(conn, *organization_id)? {
+            let to: Mailbox = match manager.email.parse() {
+                Ok(to) => to,
+                Err(e) => {
+                    tracing::warn!(
+                        "Skipped host key alert to user {}, invalid email: {}",
+                        manager.id,
+                        e
+                    );
+                    continue;
+                }
+            };
+
+            let email = Message::builder()
+                .from(from.clone())
+                .to(to)
+                .subject(format!("Clé d'hôte SSH modifiée pour {}", host))
+                .body(format!(
+                    "Bonjour {},\n\n\
+                    La clé d'hôte SSH de {} a changé dans l'organisation {}. \
+                    Elle est maintenant {} {}.\n\n\
+                    Les connexions à cet hôte sont refusées tant que la nouvelle clé \
+                    n'est pas confirmée. Vérifiez-la auprès de l'hébergeur avant de lui faire confiance.\n\n\
+                    L'équipe Scylla",
+                    manager.name, host, organization.name, key_type, fingerprint
+                ))?;
+
+            match mail::send(smtp, "host_key_changed", &email) {
+                Ok(()) => sent += 1,
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to send host key alert to user {}: {}",
+                        manager.id,
+                        e
+                    );
+                    failures.push(e.to_string());
+                }
+            }
+        }
+
+        if sent == 0 && !failures.is_empty() {
+            return Err(failures.join("; ").into());
+        }
+
+        Ok(())
+    }
+}
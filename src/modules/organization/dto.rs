@@ -14,6 +14,11 @@ pub struct OrganizationCreateQuery {
 
     #[schema(example = "A leading provider of innovative solutions")]
     pub description: Option<String>,
+
+    /// Generated from `name` when omitted
+    #[schema(example = "acme-corporation")]
+    #[diesel(skip_insertion)]
+    pub slug: Option<String>,
 }
 
 #[derive(AsChangeset, Deserialize, Validate, ToSchema)]
@@ -25,6 +30,9 @@ pub struct OrganizationUpdateQuery {
 
     #[schema(example = "A leading provider of innovative solutions worldwide")]
     pub description: Option<String>,
+
+    #[schema(example = "acme-corp")]
+    pub slug: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, ToSchema)]
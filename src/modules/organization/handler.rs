@@ -1,65 +1,126 @@
+use crate::db;
+use crate::modules::admin::service::AdminService;
+use crate::modules::audit::dto::AuditContext;
 use crate::modules::organization::dto::{
     AddUserToOrganizationQuery, OrganizationCreateQuery, OrganizationUpdateQuery,
 };
 use crate::modules::organization::service::OrganizationService;
-use crate::utils::response::{error, success};
+use crate::utils::response::{error, success, ApiError};
 use actix_web::http::StatusCode;
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpResponse, ResponseError};
 use diesel::r2d2::{self, ConnectionManager};
 use diesel::PgConnection;
+use tracing::instrument;
+use uuid::Uuid;
 use validator::Validate;
 
 type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 
-pub async fn get_all(pool: web::Data<DbPool>) -> HttpResponse {
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
+// Make sure the organization exists and the user belongs to it. Platform
+// admins can act on any organization.
+pub fn require_member(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    organization_id: Uuid,
+) -> Result<(), ApiError> {
+    // Check the organization is live
+    if let Err(e) = OrganizationService::get_by_id(conn, organization_id) {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("Organization not found: {}", e),
+        ));
+    }
 
-    // Get all organizations
-    match OrganizationService::get_all(&mut conn) {
-        Ok(organizations) => HttpResponse::Ok().json(success(StatusCode::OK, Some(organizations))),
-        Err(e) => HttpResponse::InternalServerError().json(error(
+    // Check membership
+    let allowed = OrganizationService::is_member(conn, organization_id, user_id)
+        .and_then(|member| Ok(member || AdminService::is_admin(conn, user_id)?));
+
+    match allowed {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "Not a member of this organization".into(),
+        )),
+        Err(e) => Err(ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to check membership: {}", e),
+        )),
+    }
+}
+
+// Same as `require_member`, and the user must also be an owner or admin of
+// the organization, or a platform admin
+pub fn require_manager(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    organization_id: Uuid,
+) -> Result<(), ApiError> {
+    require_member(conn, user_id, organization_id)?;
+
+    // Check role
+    let allowed = OrganizationService::get_role(conn, organization_id, user_id).and_then(|role| {
+        Ok(matches!(role.as_deref(), Some("owner" | "admin"))
+            || AdminService::is_admin(conn, user_id)?)
+    });
+
+    match allowed {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "Organization owner or admin access required".into(),
+        )),
+        Err(e) => Err(ApiError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to retrieve organizations: {}", e),
+            format!("Failed to check permissions: {}", e),
         )),
     }
 }
 
+#[instrument(name = "organization::handler::get_all", skip_all)]
+pub async fn get_all(pool: web::Data<DbPool>) -> HttpResponse {
+    // Get all organizations
+    let result = db::block(&pool, |conn| {
+        OrganizationService::get_all(conn).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve organizations: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(organizations) => HttpResponse::Ok().json(success(StatusCode::OK, Some(organizations))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "organization::handler::get_by_id", skip_all)]
 pub async fn get_by_id(path: web::Path<uuid::Uuid>, pool: web::Data<DbPool>) -> HttpResponse {
     let id = path.into_inner();
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
     // Get organization by ID
-    match OrganizationService::get_by_id(&mut conn, id) {
+    let result = db::block(&pool, move |conn| {
+        OrganizationService::get_by_id(conn, id).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve organization: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(organization) => HttpResponse::Ok().json(success(StatusCode::OK, Some(organization))),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to retrieve organization: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
+#[instrument(name = "organization::handler::create", skip_all)]
 pub async fn create(
     pool: web::Data<DbPool>,
     organization_data: web::Json<OrganizationCreateQuery>,
+    audit: AuditContext,
 ) -> HttpResponse {
     // Validate organization data
     if let Err(errors) = organization_data.validate() {
@@ -69,33 +130,32 @@ pub async fn create(
         ));
     }
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
     // Create organization
-    match OrganizationService::create(&mut conn, &organization_data) {
+    let organization_data = organization_data.into_inner();
+    let result = db::block(&pool, move |conn| {
+        OrganizationService::create(conn, &organization_data, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Failed to create organization: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(organization) => {
             HttpResponse::Created().json(success(StatusCode::CREATED, Some(organization)))
         }
-        Err(e) => HttpResponse::BadRequest().json(error(
-            StatusCode::BAD_REQUEST,
-            format!("Failed to create organization: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
+#[instrument(name = "organization::handler::update", skip_all)]
 pub async fn update(
     path: web::Path<uuid::Uuid>,
     pool: web::Data<DbPool>,
     organization_data: web::Json<OrganizationUpdateQuery>,
+    audit: AuditContext,
 ) -> HttpResponse {
     let id = path.into_inner();
 
@@ -107,75 +167,113 @@ pub async fn update(
         ));
     }
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
     // Update organization
-    match OrganizationService::update(&mut conn, id, &organization_data) {
+    let organization_data = organization_data.into_inner();
+    let result = db::block(&pool, move |conn| {
+        OrganizationService::update(conn, id, &organization_data, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to update organization: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(organization) => HttpResponse::Ok().json(success(StatusCode::OK, Some(organization))),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to update organization: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
-pub async fn delete(path: web::Path<uuid::Uuid>, pool: web::Data<DbPool>) -> HttpResponse {
+#[instrument(name = "organization::handler::delete", skip_all)]
+pub async fn delete(
+    path: web::Path<uuid::Uuid>,
+    pool: web::Data<DbPool>,
+    audit: AuditContext,
+) -> HttpResponse {
     let id = path.into_inner();
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
     // Delete organization
-    match OrganizationService::delete(&mut conn, id) {
+    let result = db::block(&pool, move |conn| {
+        OrganizationService::delete(conn, id, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to delete organization: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(_) => HttpResponse::Ok().json(success::<()>(StatusCode::OK, None)),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to delete organization: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
-pub async fn add_user(
+#[instrument(name = "organization::handler::restore", skip_all)]
+pub async fn restore(
     path: web::Path<uuid::Uuid>,
     pool: web::Data<DbPool>,
-    user_data: web::Json<AddUserToOrganizationQuery>,
+    audit: AuditContext,
 ) -> HttpResponse {
     let id = path.into_inner();
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
+    // Restore organization, unless its slug went to another one meanwhile
+    let result = db::block(&pool, move |conn| {
+        match OrganizationService::restore_conflict(conn, id) {
+            Ok(None) => {}
+            Ok(Some(slug)) => {
+                return Err(ApiError::new(
+                    StatusCode::CONFLICT,
+                    format!("Slug '{}' is now used by another organization", slug),
+                ))
+            }
+            Err(e) => {
+                return Err(ApiError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to restore organization: {}", e),
+                ))
+            }
         }
-    };
+
+        OrganizationService::restore(conn, id, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Failed to restore organization: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(organization) => HttpResponse::Ok().json(success(StatusCode::OK, Some(organization))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "organization::handler::add_user", skip_all)]
+pub async fn add_user(
+    path: web::Path<uuid::Uuid>,
+    pool: web::Data<DbPool>,
+    user_data: web::Json<AddUserToOrganizationQuery>,
+    audit: AuditContext,
+) -> HttpResponse {
+    let id = path.into_inner();
 
     // Add user to organization
-    match OrganizationService::add_user(&mut conn, id, &user_data) {
+    let user_data = user_data.into_inner();
+    let result = db::block(&pool, move |conn| {
+        OrganizationService::add_user(conn, id, &user_data, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to add user to organization: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(_) => HttpResponse::Ok().json(success::<()>(StatusCode::OK, None)),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to add user to organization: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
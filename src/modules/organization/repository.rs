@@ -1,9 +1,12 @@
-use crate::models::Organization;
+use crate::models::{Organization, User};
 use crate::modules::organization::dto::{OrganizationCreateQuery, OrganizationUpdateQuery};
-use chrono::Utc;
+use crate::modules::slug::dto::SlugResource;
+use crate::modules::slug::repository::SlugRepository;
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use diesel::PgConnection;
 use std::error::Error;
+use tracing::instrument;
 use uuid::Uuid;
 
 use super::dto::AddUserToOrganizationQuery;
@@ -11,6 +14,7 @@ use super::dto::AddUserToOrganizationQuery;
 pub struct OrganizationRepository;
 
 impl OrganizationRepository {
+    #[instrument(name = "OrganizationRepository::find_by_id", skip_all)]
     pub fn find_by_id(
         conn: &mut PgConnection,
         organization_id: Uuid,
@@ -25,6 +29,45 @@ impl OrganizationRepository {
         Ok(organization)
     }
 
+    #[instrument(name = "OrganizationRepository::find_by_slug", skip_all)]
+    pub fn find_by_slug(
+        conn: &mut PgConnection,
+        organization_slug: &str,
+    ) -> Result<Option<Organization>, Box<dyn Error>> {
+        use crate::schema::organizations::dsl::*;
+
+        let organization = organizations
+            .filter(slug.eq(organization_slug))
+            .filter(deleted_at.is_null())
+            .first::<Organization>(conn)
+            .optional()?;
+
+        Ok(organization)
+    }
+
+    #[instrument(name = "OrganizationRepository::slug_taken", skip_all)]
+    pub fn slug_taken(
+        conn: &mut PgConnection,
+        organization_slug: &str,
+        exclude_id: Option<Uuid>,
+    ) -> Result<bool, Box<dyn Error>> {
+        use crate::schema::organizations::dsl::*;
+
+        let mut query = organizations
+            .filter(slug.eq(organization_slug))
+            .filter(deleted_at.is_null())
+            .into_boxed();
+
+        if let Some(exclude_id) = exclude_id {
+            query = query.filter(id.ne(exclude_id));
+        }
+
+        let taken = diesel::select(diesel::dsl::exists(query)).get_result::<bool>(conn)?;
+
+        Ok(taken)
+    }
+
+    #[instrument(name = "OrganizationRepository::find_all", skip_all)]
     pub fn find_all(conn: &mut PgConnection) -> Result<Vec<Organization>, Box<dyn Error>> {
         use crate::schema::organizations::dsl::*;
 
@@ -35,18 +78,21 @@ impl OrganizationRepository {
         Ok(all_organizations)
     }
 
+    #[instrument(name = "OrganizationRepository::create", skip_all)]
     pub fn create(
         conn: &mut PgConnection,
         new_organization: &OrganizationCreateQuery,
+        new_slug: &str,
     ) -> Result<Organization, Box<dyn Error>> {
         use crate::schema::organizations::dsl::*;
 
         diesel::insert_into(organizations)
-            .values(new_organization)
+            .values((new_organization, slug.eq(new_slug)))
             .get_result::<Organization>(conn)
             .map_err(|e| e.into())
     }
 
+    #[instrument(name = "OrganizationRepository::update", skip_all)]
     pub fn update(
         conn: &mut PgConnection,
         organization_id: Uuid,
@@ -54,26 +100,170 @@ impl OrganizationRepository {
     ) -> Result<Organization, Box<dyn Error>> {
         use crate::schema::organizations::dsl::*;
 
-        diesel::update(organizations)
-            .filter(id.eq(organization_id))
-            .filter(deleted_at.is_null())
-            .set(update_data)
-            .get_result::<Organization>(conn)
-            .map_err(|e| e.into())
+        conn.transaction(|conn| {
+            let current = Self::find_by_id(conn, organization_id)?;
+
+            let organization = diesel::update(organizations)
+                .filter(id.eq(organization_id))
+                .filter(deleted_at.is_null())
+                .set(update_data)
+                .get_result::<Organization>(conn)?;
+
+            // Keep the previous slug resolving to this organization
+            if organization.slug != current.slug {
+                SlugRepository::record_redirect(
+                    conn,
+                    SlugResource::Organization,
+                    None,
+                    &current.slug,
+                    organization.id,
+                )?;
+            }
+
+            Ok(organization)
+        })
     }
 
+    /// Soft-deletes the organization together with its live teams,
+    /// repositories and memberships, tagging every row with the same deletion
+    /// batch id so the whole cascade can be restored later.
+    #[instrument(name = "OrganizationRepository::delete", skip_all)]
     pub fn delete(conn: &mut PgConnection, organization_id: Uuid) -> Result<(), Box<dyn Error>> {
+        use crate::schema::{organization_users, organizations, repositories, team_users, teams};
+
+        let batch_id = Uuid::new_v4();
+        let now = Utc::now();
+
+        conn.transaction(|conn| {
+            let deleted = diesel::update(organizations::table)
+                .filter(organizations::id.eq(organization_id))
+                .filter(organizations::deleted_at.is_null())
+                .set((
+                    organizations::deleted_at.eq(now),
+                    organizations::deletion_batch_id.eq(batch_id),
+                ))
+                .execute(conn)?;
+
+            if deleted == 0 {
+                return Err("Organization not found".into());
+            }
+
+            diesel::update(teams::table)
+                .filter(teams::organization_id.eq(organization_id))
+                .filter(teams::deleted_at.is_null())
+                .set((teams::deleted_at.eq(now), teams::deletion_batch_id.eq(batch_id)))
+                .execute(conn)?;
+
+            diesel::update(repositories::table)
+                .filter(repositories::organization_id.eq(organization_id))
+                .filter(repositories::deleted_at.is_null())
+                .set((
+                    repositories::deleted_at.eq(now),
+                    repositories::deletion_batch_id.eq(batch_id),
+                ))
+                .execute(conn)?;
+
+            diesel::update(organization_users::table)
+                .filter(organization_users::organization_id.eq(organization_id))
+                .filter(organization_users::deleted_at.is_null())
+                .set((
+                    organization_users::deleted_at.eq(now),
+                    organization_users::deletion_batch_id.eq(batch_id),
+                ))
+                .execute(conn)?;
+
+            // Members of the teams deleted just above
+            let batch_teams = teams::table
+                .filter(teams::deletion_batch_id.eq(batch_id))
+                .select(teams::id);
+            diesel::update(team_users::table)
+                .filter(team_users::team_id.eq_any(batch_teams))
+                .filter(team_users::deleted_at.is_null())
+                .set((
+                    team_users::deleted_at.eq(now),
+                    team_users::deletion_batch_id.eq(batch_id),
+                ))
+                .execute(conn)?;
+
+            Ok(())
+        })
+    }
+
+    #[instrument(name = "OrganizationRepository::find_deleted_by_id", skip_all)]
+    pub fn find_deleted_by_id(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+    ) -> Result<Option<Organization>, Box<dyn Error>> {
         use crate::schema::organizations::dsl::*;
 
-        diesel::update(organizations)
+        let organization = organizations
             .filter(id.eq(organization_id))
-            .filter(deleted_at.is_null())
-            .set(deleted_at.eq(Utc::now()))
-            .execute(conn)?;
+            .filter(deleted_at.is_not_null())
+            .first::<Organization>(conn)
+            .optional()?;
 
-        Ok(())
+        Ok(organization)
+    }
+
+    /// Undoes the deletion batch of a soft-deleted organization. Teams,
+    /// repositories and memberships deleted on their own, before or after the
+    /// organization, stay deleted.
+    #[instrument(name = "OrganizationRepository::restore", skip_all)]
+    pub fn restore(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+    ) -> Result<Organization, Box<dyn Error>> {
+        use crate::schema::{organization_users, organizations, repositories, team_users, teams};
+
+        conn.transaction(|conn| {
+            let organization = Self::find_deleted_by_id(conn, organization_id)?
+                .ok_or("Organization is not deleted")?;
+
+            if let Some(batch_id) = organization.deletion_batch_id {
+                diesel::update(organization_users::table)
+                    .filter(organization_users::deletion_batch_id.eq(batch_id))
+                    .set((
+                        organization_users::deleted_at.eq(None::<DateTime<Utc>>),
+                        organization_users::deletion_batch_id.eq(None::<Uuid>),
+                    ))
+                    .execute(conn)?;
+
+                diesel::update(team_users::table)
+                    .filter(team_users::deletion_batch_id.eq(batch_id))
+                    .set((
+                        team_users::deleted_at.eq(None::<DateTime<Utc>>),
+                        team_users::deletion_batch_id.eq(None::<Uuid>),
+                    ))
+                    .execute(conn)?;
+
+                diesel::update(teams::table)
+                    .filter(teams::deletion_batch_id.eq(batch_id))
+                    .set((
+                        teams::deleted_at.eq(None::<DateTime<Utc>>),
+                        teams::deletion_batch_id.eq(None::<Uuid>),
+                    ))
+                    .execute(conn)?;
+
+                diesel::update(repositories::table)
+                    .filter(repositories::deletion_batch_id.eq(batch_id))
+                    .set((
+                        repositories::deleted_at.eq(None::<DateTime<Utc>>),
+                        repositories::deletion_batch_id.eq(None::<Uuid>),
+                    ))
+                    .execute(conn)?;
+            }
+
+            diesel::update(organizations::table.filter(organizations::id.eq(organization.id)))
+                .set((
+                    organizations::deleted_at.eq(None::<DateTime<Utc>>),
+                    organizations::deletion_batch_id.eq(None::<Uuid>),
+                ))
+                .get_result::<Organization>(conn)
+                .map_err(|e| e.into())
+        })
     }
 
+    #[instrument(name = "OrganizationRepository::add_user", skip_all)]
     pub fn add_user(
         conn: &mut PgConnection,
         organization_id: Uuid,
@@ -91,4 +281,63 @@ impl OrganizationRepository {
 
         Ok(())
     }
+
+    #[instrument(name = "OrganizationRepository::is_member", skip_all)]
+    pub fn is_member(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<bool, Box<dyn Error>> {
+        use crate::schema::organization_users;
+
+        let member = diesel::select(diesel::dsl::exists(
+            organization_users::table
+                .filter(organization_users::organization_id.eq(organization_id))
+                .filter(organization_users::user_id.eq(user_id))
+                .filter(organization_users::deleted_at.is_null()),
+        ))
+        .get_result::<bool>(conn)?;
+
+        Ok(member)
+    }
+
+    /// Role of the user in the organization, `None` when not a member.
+    #[instrument(name = "OrganizationRepository::find_role", skip_all)]
+    pub fn find_role(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        use crate::schema::organization_users;
+
+        let role = organization_users::table
+            .filter(organization_users::organization_id.eq(organization_id))
+            .filter(organization_users::user_id.eq(user_id))
+            .filter(organization_users::deleted_at.is_null())
+            .select(organization_users::role)
+            .first::<Option<String>>(conn)
+            .optional()?;
+
+        Ok(role.flatten())
+    }
+
+    /// Live users who are owners or admins of the organization.
+    #[instrument(name = "OrganizationRepository::find_managers", skip_all)]
+    pub fn find_managers(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+    ) -> Result<Vec<User>, Box<dyn Error>> {
+        use crate::schema::{organization_users, users};
+
+        let managers = organization_users::table
+            .inner_join(users::table)
+            .filter(organization_users::organization_id.eq(organization_id))
+            .filter(organization_users::role.eq_any(["owner", "admin"]))
+            .filter(organization_users::deleted_at.is_null())
+            .filter(users::deleted_at.is_null())
+            .select(User::as_select())
+            .load::<User>(conn)?;
+
+        Ok(managers)
+    }
 }
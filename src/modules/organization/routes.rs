@@ -1,4 +1,11 @@
-use crate::modules::organization::handler::{add_user, create, delete, get_all, get_by_id, update};
+use crate::modules::audit::routes as audit_routes;
+use crate::modules::known_host::routes as known_host_routes;
+use crate::modules::organization::handler::{
+    add_user, create, delete, get_all, get_by_id, restore, update,
+};
+use crate::modules::repo::routes as repo_routes;
+use crate::modules::team::routes as team_routes;
+use crate::modules::webhook::routes as webhook_routes;
 use actix_web::web;
 
 pub fn config_routes(cfg: &mut web::ServiceConfig) {
@@ -9,6 +16,12 @@ pub fn config_routes(cfg: &mut web::ServiceConfig) {
             .route("", web::post().to(create))
             .route("/{id}", web::put().to(update))
             .route("/{id}", web::delete().to(delete))
-            .route("/{id}/users", web::post().to(add_user)),
+            .route("/{id}/restore", web::post().to(restore))
+            .route("/{id}/users", web::post().to(add_user))
+            .configure(team_routes::config_organization_routes)
+            .configure(repo_routes::config_organization_routes)
+            .configure(known_host_routes::config_organization_routes)
+            .configure(webhook_routes::config_organization_routes)
+            .configure(audit_routes::config_organization_routes),
     );
 }
@@ -1,19 +1,28 @@
 use crate::models::Organization;
+use crate::modules::audit::dto::{AuditAction, AuditContext};
+use crate::modules::audit::service::AuditService;
 use crate::modules::organization::dto::{
     AddUserToOrganizationQuery, OrganizationCreateQuery, OrganizationUpdateQuery,
 };
 use crate::modules::organization::repository::OrganizationRepository;
-use diesel::PgConnection;
+use crate::modules::outbox::dto::DomainEvent;
+use crate::modules::outbox::service::OutboxService;
+use crate::utils::slug::{unique_slug, validate_slug};
+use diesel::{Connection, PgConnection};
+use serde_json::json;
 use std::error::Error;
+use tracing::instrument;
 use uuid::Uuid;
 
 pub struct OrganizationService;
 
 impl OrganizationService {
+    #[instrument(name = "OrganizationService::get_all", skip_all)]
     pub fn get_all(conn: &mut PgConnection) -> Result<Vec<Organization>, Box<dyn Error>> {
         OrganizationRepository::find_all(conn)
     }
 
+    #[instrument(name = "OrganizationService::get_by_id", skip_all)]
     pub fn get_by_id(
         conn: &mut PgConnection,
         organization_id: Uuid,
@@ -21,30 +30,195 @@ impl OrganizationService {
         OrganizationRepository::find_by_id(conn, organization_id)
     }
 
+    #[instrument(name = "OrganizationService::create", skip_all)]
     pub fn create(
         conn: &mut PgConnection,
         data: &OrganizationCreateQuery,
+        audit: &AuditContext,
     ) -> Result<Organization, Box<dyn Error>> {
-        OrganizationRepository::create(conn, data)
+        let slug = match &data.slug {
+            Some(slug) => {
+                validate_slug(slug)?;
+                if OrganizationRepository::slug_taken(conn, slug, None)? {
+                    return Err(format!("Slug '{}' is already taken", slug).into());
+                }
+                slug.clone()
+            }
+            None => unique_slug(&data.name, "organization", |candidate| {
+                OrganizationRepository::slug_taken(conn, candidate, None)
+            })?,
+        };
+
+        conn.transaction(|conn| {
+            let organization = OrganizationRepository::create(conn, data, &slug)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::OrganizationCreated,
+                Some(organization.id),
+                Some(organization.id),
+                AuditService::diff(None, Some(&organization))?,
+            )?;
+            OutboxService::record(
+                conn,
+                &DomainEvent::OrganizationCreated {
+                    organization: organization.clone(),
+                },
+            )?;
+
+            Ok(organization)
+        })
     }
 
+    #[instrument(name = "OrganizationService::update", skip_all)]
     pub fn update(
         conn: &mut PgConnection,
         organization_id: Uuid,
         data: &OrganizationUpdateQuery,
+        audit: &AuditContext,
     ) -> Result<Organization, Box<dyn Error>> {
-        OrganizationRepository::update(conn, organization_id, data)
+        if let Some(slug) = &data.slug {
+            validate_slug(slug)?;
+            if OrganizationRepository::slug_taken(conn, slug, Some(organization_id))? {
+                return Err(format!("Slug '{}' is already taken", slug).into());
+            }
+        }
+
+        conn.transaction(|conn| {
+            let before = OrganizationRepository::find_by_id(conn, organization_id)?;
+            let organization = OrganizationRepository::update(conn, organization_id, data)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::OrganizationUpdated,
+                Some(organization.id),
+                Some(organization.id),
+                AuditService::diff(Some(&before), Some(&organization))?,
+            )?;
+            OutboxService::record(
+                conn,
+                &DomainEvent::OrganizationUpdated {
+                    organization: organization.clone(),
+                },
+            )?;
+
+            Ok(organization)
+        })
     }
 
-    pub fn delete(conn: &mut PgConnection, organization_id: Uuid) -> Result<(), Box<dyn Error>> {
-        OrganizationRepository::delete(conn, organization_id)
+    #[instrument(name = "OrganizationService::delete", skip_all)]
+    pub fn delete(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+        audit: &AuditContext,
+    ) -> Result<(), Box<dyn Error>> {
+        conn.transaction(|conn| {
+            let organization = OrganizationRepository::find_by_id(conn, organization_id)?;
+            OrganizationRepository::delete(conn, organization_id)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::OrganizationDeleted,
+                Some(organization.id),
+                Some(organization.id),
+                AuditService::diff(Some(&organization), None)?,
+            )?;
+            OutboxService::record(conn, &DomainEvent::OrganizationDeleted { organization })?;
+
+            Ok(())
+        })
     }
 
+    /// Slug of the deleted organization when a live organization has taken
+    /// it since, which blocks the restore.
+    #[instrument(name = "OrganizationService::restore_conflict", skip_all)]
+    pub fn restore_conflict(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        let Some(organization) = OrganizationRepository::find_deleted_by_id(conn, organization_id)?
+        else {
+            return Ok(None);
+        };
+
+        let taken =
+            OrganizationRepository::slug_taken(conn, &organization.slug, Some(organization.id))?;
+
+        Ok(taken.then_some(organization.slug))
+    }
+
+    #[instrument(name = "OrganizationService::restore", skip_all)]
+    pub fn restore(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+        audit: &AuditContext,
+    ) -> Result<Organization, Box<dyn Error>> {
+        conn.transaction(|conn| {
+            let organization = OrganizationRepository::restore(conn, organization_id)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::OrganizationRestored,
+                Some(organization.id),
+                Some(organization.id),
+                None,
+            )?;
+            OutboxService::record(
+                conn,
+                &DomainEvent::OrganizationRestored {
+                    organization: organization.clone(),
+                },
+            )?;
+
+            Ok(organization)
+        })
+    }
+
+    #[instrument(name = "OrganizationService::add_user", skip_all)]
     pub fn add_user(
         conn: &mut PgConnection,
         organization_id: Uuid,
         user_data: &AddUserToOrganizationQuery,
+        audit: &AuditContext,
     ) -> Result<(), Box<dyn Error>> {
-        OrganizationRepository::add_user(conn, organization_id, user_data)
+        conn.transaction(|conn| {
+            OrganizationRepository::add_user(conn, organization_id, user_data)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::MemberAdded,
+                Some(organization_id),
+                Some(user_data.user_id),
+                AuditService::diff(None, Some(&json!({ "role": user_data.role })))?,
+            )?;
+            OutboxService::record(
+                conn,
+                &DomainEvent::MemberAdded {
+                    organization_id,
+                    user_id: user_data.user_id,
+                    role: user_data.role.clone(),
+                },
+            )?;
+
+            Ok(())
+        })
+    }
+
+    #[instrument(name = "OrganizationService::is_member", skip_all)]
+    pub fn is_member(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<bool, Box<dyn Error>> {
+        OrganizationRepository::is_member(conn, organization_id, user_id)
+    }
+
+    #[instrument(name = "OrganizationService::get_role", skip_all)]
+    pub fn get_role(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        OrganizationRepository::find_role(conn, organization_id, user_id)
     }
 }
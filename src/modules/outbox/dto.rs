@@ -0,0 +1,177 @@
+use crate::models::{Organization, Repo, Team};
+use crate::schema::outbox_events;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use uuid::Uuid;
+
+/// A change to the platform. Recorded in the outbox by the transaction
+/// making the change, then dispatched to every subscriber.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum DomainEvent {
+    #[serde(rename = "organization.created")]
+    OrganizationCreated { organization: Organization },
+    #[serde(rename = "organization.updated")]
+    OrganizationUpdated { organization: Organization },
+    #[serde(rename = "organization.deleted")]
+    OrganizationDeleted { organization: Organization },
+    #[serde(rename = "organization.restored")]
+    OrganizationRestored { organization: Organization },
+    #[serde(rename = "member.added")]
+    MemberAdded {
+        organization_id: Uuid,
+        user_id: Uuid,
+        role: Option<String>,
+    },
+    #[serde(rename = "team.created")]
+    TeamCreated { team: Team },
+    #[serde(rename = "team.updated")]
+    TeamUpdated { team: Team },
+    #[serde(rename = "team.deleted")]
+    TeamDeleted { team: Team },
+    #[serde(rename = "team.member_added")]
+    TeamMemberAdded {
+        organization_id: Uuid,
+        team_id: Uuid,
+        user_id: Uuid,
+        role: Option<String>,
+    },
+    #[serde(rename = "repository.created")]
+    RepositoryCreated { repository: Repo },
+    #[serde(rename = "repository.updated")]
+    RepositoryUpdated { repository: Repo },
+    #[serde(rename = "repository.deleted")]
+    RepositoryDeleted { repository: Repo },
+    #[serde(rename = "repository.transferred")]
+    RepositoryTransferred {
+        repository: Repo,
+        previous_organization_id: Uuid,
+    },
+    #[serde(rename = "host_key.changed")]
+    HostKeyChanged {
+        organization_id: Uuid,
+        host: String,
+        key_type: String,
+        fingerprint: String,
+    },
+    #[serde(rename = "user.verified")]
+    UserVerified { user_id: Uuid, email: String },
+}
+
+impl DomainEvent {
+    /// Same as the serialized `type`.
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            DomainEvent::OrganizationCreated { .. } => "organization.created",
+            DomainEvent::OrganizationUpdated { .. } => "organization.updated",
+            DomainEvent::OrganizationDeleted { .. } => "organization.deleted",
+            DomainEvent::OrganizationRestored { .. } => "organization.restored",
+            DomainEvent::MemberAdded { .. } => "member.added",
+            DomainEvent::TeamCreated { .. } => "team.created",
+            DomainEvent::TeamUpdated { .. } => "team.updated",
+            DomainEvent::TeamDeleted { .. } => "team.deleted",
+            DomainEvent::TeamMemberAdded { .. } => "team.member_added",
+            DomainEvent::RepositoryCreated { .. } => "repository.created",
+            DomainEvent::RepositoryUpdated { .. } => "repository.updated",
+            DomainEvent::RepositoryDeleted { .. } => "repository.deleted",
+            DomainEvent::RepositoryTransferred { .. } => "repository.transferred",
+            DomainEvent::HostKeyChanged { .. } => "host_key.changed",
+            DomainEvent::UserVerified { .. } => "user.verified",
+        }
+    }
+
+    /// Organization the event belongs to, the new one for a transfer.
+    pub fn organization_id(&self) -> Option<Uuid> {
+        match self {
+            DomainEvent::OrganizationCreated { organization }
+            | DomainEvent::OrganizationUpdated { organization }
+            | DomainEvent::OrganizationDeleted { organization }
+            | DomainEvent::OrganizationRestored { organization } => Some(organization.id),
+            DomainEvent::MemberAdded {
+                organization_id, ..
+            }
+            | DomainEvent::TeamMemberAdded {
+                organization_id, ..
+            }
+            | DomainEvent::HostKeyChanged {
+                organization_id, ..
+            } => Some(*organization_id),
+            DomainEvent::TeamCreated { team }
+            | DomainEvent::TeamUpdated { team }
+            | DomainEvent::TeamDeleted { team } => Some(team.organization_id),
+            DomainEvent::RepositoryCreated { repository }
+            | DomainEvent::RepositoryUpdated { repository }
+            | DomainEvent::RepositoryDeleted { repository }
+            | DomainEvent::RepositoryTransferred { repository, .. } => {
+                Some(repository.organization_id)
+            }
+            DomainEvent::UserVerified { .. } => None,
+        }
+    }
+
+    /// The fields of the event, without its type.
+    pub fn data(&self) -> Result<Value, serde_json::Error> {
+        let mut value = serde_json::to_value(self)?;
+        Ok(value["data"].take())
+    }
+}
+
+impl fmt::Display for DomainEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.event_type())
+    }
+}
+
+/// In-process consumers of domain events. Each one handles an event at
+/// most once, its receipt being stored in the same transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subscriber {
+    Webhooks,
+    /// Events raised outside of requests, the others are audited as they
+    /// happen
+    Audit,
+    /// Emails to the users concerned
+    Notifications,
+}
+
+impl Subscriber {
+    pub const ALL: &'static [Subscriber] = &[
+        Subscriber::Webhooks,
+        Subscriber::Audit,
+        Subscriber::Notifications,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Subscriber::Webhooks => "webhooks",
+            Subscriber::Audit => "audit",
+            Subscriber::Notifications => "notifications",
+        }
+    }
+}
+
+impl fmt::Display for Subscriber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = outbox_events)]
+pub struct NewOutboxEvent {
+    pub event_type: String,
+    pub organization_id: Option<Uuid>,
+    pub payload: Value,
+}
+
+impl NewOutboxEvent {
+    pub fn new(event: &DomainEvent) -> Result<Self, serde_json::Error> {
+        Ok(NewOutboxEvent {
+            event_type: event.event_type().to_string(),
+            organization_id: event.organization_id(),
+            payload: serde_json::to_value(event)?,
+        })
+    }
+}
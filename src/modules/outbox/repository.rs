@@ -0,0 +1,170 @@
+use crate::models::OutboxEvent;
+use crate::modules::outbox::dto::{DomainEvent, NewOutboxEvent, Subscriber};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use std::error::Error;
+use tracing::instrument;
+use uuid::Uuid;
+
+pub struct OutboxRepository;
+
+impl OutboxRepository {
+    /// Writes an event to the outbox. Call it inside the transaction making
+    /// the change, so the event is stored if and only if the change is.
+    #[instrument(name = "OutboxRepository::record", skip_all)]
+    pub fn record(conn: &mut PgConnection, event: &DomainEvent) -> Result<Uuid, Box<dyn Error>> {
+        use crate::schema::outbox_events::dsl::*;
+
+        let new_event = NewOutboxEvent::new(event)?;
+        let event_id = diesel::insert_into(outbox_events)
+            .values(&new_event)
+            .returning(id)
+            .get_result::<Uuid>(conn)?;
+
+        Ok(event_id)
+    }
+
+    /// Events not dispatched to every subscriber yet, due or not. Dead
+    /// letters are left out.
+    #[instrument(name = "OutboxRepository::count_pending", skip_all)]
+    pub fn count_pending(conn: &mut PgConnection) -> Result<i64, Box<dyn Error>> {
+        use crate::schema::outbox_events::dsl::*;
+
+        outbox_events
+            .filter(dispatched_at.is_null())
+            .filter(dead_lettered_at.is_null())
+            .count()
+            .get_result(conn)
+            .map_err(|e| e.into())
+    }
+
+    /// Takes up to `limit` undispatched events that are due and pushes
+    /// their next attempt to `lease_until`, so no other dispatcher picks
+    /// them up meanwhile. Returned oldest first.
+    #[instrument(name = "OutboxRepository::claim_due", skip_all)]
+    pub fn claim_due(
+        conn: &mut PgConnection,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<OutboxEvent>, Box<dyn Error>> {
+        use crate::schema::outbox_events::dsl::*;
+
+        conn.transaction(|conn| {
+            let due = outbox_events
+                .filter(dispatched_at.is_null())
+                .filter(dead_lettered_at.is_null())
+                .filter(next_attempt_at.le(now))
+                .order((next_attempt_at.asc(), created_at.asc()))
+                .limit(limit)
+                .select(id)
+                .for_update()
+                .skip_locked()
+                .load::<Uuid>(conn)?;
+
+            diesel::update(outbox_events.filter(id.eq_any(&due)))
+                .set(next_attempt_at.eq(lease_until))
+                .get_results::<OutboxEvent>(conn)
+        })
+        .map(|mut events| {
+            events.sort_by_key(|event| event.created_at);
+            events
+        })
+        .map_err(|e| e.into())
+    }
+
+    /// Subscribers that already handled the event.
+    #[instrument(name = "OutboxRepository::find_receipts", skip_all)]
+    pub fn find_receipts(
+        conn: &mut PgConnection,
+        outbox_event_id: Uuid,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        use crate::schema::outbox_receipts::dsl::*;
+
+        let subscribers = outbox_receipts
+            .filter(event_id.eq(outbox_event_id))
+            .select(subscriber)
+            .load::<String>(conn)?;
+
+        Ok(subscribers)
+    }
+
+    #[instrument(name = "OutboxRepository::add_receipt", skip_all)]
+    pub fn add_receipt(
+        conn: &mut PgConnection,
+        outbox_event_id: Uuid,
+        handled_by: Subscriber,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::schema::outbox_receipts::dsl::*;
+
+        diesel::insert_into(outbox_receipts)
+            .values((
+                event_id.eq(outbox_event_id),
+                subscriber.eq(handled_by.as_str()),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    #[instrument(name = "OutboxRepository::mark_dispatched", skip_all)]
+    pub fn mark_dispatched(
+        conn: &mut PgConnection,
+        event_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::schema::outbox_events::dsl::*;
+
+        diesel::update(outbox_events.filter(id.eq(event_id)))
+            .set((
+                dispatched_at.eq(now),
+                attempts.eq(attempts + 1),
+                last_error.eq(None::<String>),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    #[instrument(name = "OutboxRepository::mark_failed", skip_all)]
+    pub fn mark_failed(
+        conn: &mut PgConnection,
+        event_id: Uuid,
+        error: &str,
+        retry_at: DateTime<Utc>,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::schema::outbox_events::dsl::*;
+
+        diesel::update(outbox_events.filter(id.eq(event_id)))
+            .set((
+                attempts.eq(attempts + 1),
+                last_error.eq(error),
+                next_attempt_at.eq(retry_at),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Records the last failed attempt and stops retrying the event.
+    #[instrument(name = "OutboxRepository::mark_dead_lettered", skip_all)]
+    pub fn mark_dead_lettered(
+        conn: &mut PgConnection,
+        event_id: Uuid,
+        error: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::schema::outbox_events::dsl::*;
+
+        diesel::update(outbox_events.filter(id.eq(event_id)))
+            .set((
+                attempts.eq(attempts + 1),
+                last_error.eq(error),
+                dead_lettered_at.eq(now),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use crate::config::{OutboxConfig, SmtpConfig};
+use crate::models::OutboxEvent;
+use crate::modules::audit::service::AuditService;
+use crate::modules::notification::service::NotificationService;
+use crate::modules::outbox::dto::{DomainEvent, Subscriber};
+use crate::modules::outbox::repository::OutboxRepository;
+use crate::modules::webhook::service::WebhookService;
+use chrono::{DateTime, Duration, Utc};
+use diesel::{Connection, PgConnection};
+use std::error::Error;
+use tracing::instrument;
+use uuid::Uuid;
+
+// Long enough for a batch to be handled before it can be claimed again
+const LEASE_SECONDS: i64 = 5 * 60;
+const MAX_BACKOFF_SECONDS: i64 = 60 * 60;
+
+pub struct OutboxService;
+
+impl OutboxService {
+    /// Writes an event to the outbox. Call it inside the transaction making
+    /// the change.
+    #[instrument(name = "OutboxService::record", skip_all)]
+    pub fn record(conn: &mut PgConnection, event: &DomainEvent) -> Result<Uuid, Box<dyn Error>> {
+        OutboxRepository::record(conn, event)
+    }
+
+    /// Hands a batch of due events to every subscriber that has not handled
+    /// them yet. Delivery is at least once: an event whose subscriber fails
+    /// is retried with backoff, skipping the subscribers done with it, and
+    /// dead-lettered after `max_attempts` attempts. Returns the number of
+    /// events fully dispatched.
+    #[instrument(name = "OutboxService::dispatch", skip_all)]
+    pub fn dispatch(
+        conn: &mut PgConnection,
+        config: &OutboxConfig,
+        smtp: &SmtpConfig,
+    ) -> Result<usize, Box<dyn Error>> {
+        let now = Utc::now();
+        let lease_until = now + Duration::seconds(LEASE_SECONDS);
+        let events = OutboxRepository::claim_due(conn, now, lease_until, config.batch_size)?;
+
+        let mut dispatched = 0;
+        for event in events {
+            match Self::dispatch_event(conn, smtp, &event) {
+                Ok(()) => {
+                    OutboxRepository::mark_dispatched(conn, event.id, Utc::now())?;
+                    dispatched += 1;
+                }
+                Err(e) if event.attempts + 1 >= config.max_attempts => {
+                    tracing::error!(
+                        "Dead-lettered {} event {} after {} attempts: {}",
+                        event.event_type,
+                        event.id,
+                        event.attempts + 1,
+                        e
+                    );
+                    OutboxRepository::mark_dead_lettered(
+                        conn,
+                        event.id,
+                        &e.to_string(),
+                        Utc::now(),
+                    )?;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to dispatch {} event {}: {}",
+                        event.event_type,
+                        event.id,
+                        e
+                    );
+                    let retry_at = Self::retry_at(config.backoff_seconds, event.attempts + 1);
+                    OutboxRepository::mark_failed(conn, event.id, &e.to_string(), retry_at)?;
+                }
+            }
+        }
+
+        Ok(dispatched)
+    }
+
+    fn dispatch_event(
+        conn: &mut PgConnection,
+        smtp: &SmtpConfig,
+        event: &OutboxEvent,
+    ) -> Result<(), Box<dyn Error>> {
+        let domain_event: DomainEvent = serde_json::from_value(event.payload.clone())?;
+        let handled = OutboxRepository::find_receipts(conn, event.id)?;
+
+        let mut failures = Vec::new();
+        for subscriber in Subscriber::ALL {
+            if handled.iter().any(|name| name == subscriber.as_str()) {
+                continue;
+            }
+
+            // The receipt commits with whatever the subscriber wrote
+            let result = conn.transaction::<_, Box<dyn Error>, _>(|conn| {
+                Self::deliver(conn, smtp, *subscriber, event, &domain_event)?;
+                OutboxRepository::add_receipt(conn, event.id, *subscriber)
+            });
+
+            if let Err(e) = result {
+                failures.push(format!("{}: {}", subscriber, e));
+            }
+        }
+
+        if !failures.is_empty() {
+            return Err(failures.join("; ").into());
+        }
+
+        Ok(())
+    }
+
+    fn deliver(
+        conn: &mut PgConnection,
+        smtp: &SmtpConfig,
+        subscriber: Subscriber,
+        event: &OutboxEvent,
+        domain_event: &DomainEvent,
+    ) -> Result<(), Box<dyn Error>> {
+        match subscriber {
+            Subscriber::Webhooks => WebhookService::publish(conn, event, domain_event),
+            Subscriber::Audit => AuditService::publish(conn, domain_event),
+            Subscriber::Notifications => NotificationService::publish(conn, smtp, domain_event),
+        }
+    }
+
+    fn retry_at(backoff_seconds: i64, attempt: i32) -> DateTime<Utc> {
+        let factor = 1i64 << (attempt - 1).clamp(0, 20);
+        let delay = backoff_seconds
+            .saturating_mul(factor)
+            .min(MAX_BACKOFF_SECONDS);
+        Utc::now() + Duration::seconds(delay)
+    }
+}
@@ -1,6 +1,11 @@
+use crate::models::{KnownHost, Repo};
 use crate::schema::repositories;
+use crate::utils::git_url::GitUrl;
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::Validate;
@@ -12,17 +17,55 @@ pub struct RepoCreateQuery {
     #[validate(length(min = 3, max = 100))]
     pub name: String,
 
+    /// `https://`, `ssh://`, `file://` or scp-style `git@host:owner/repo.git`
     #[schema(example = "https://git.acme.com/acme/acme-corporation.git")]
     pub url: String,
 
-    #[schema(example = "git.acme.com")]
-    pub host_name: Option<String>,
-
-    #[schema(example = "git")]
-    pub user_name: Option<String>,
-
     #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
     pub organization_id: Uuid,
+
+    /// Generated from `name` when omitted
+    #[schema(example = "acme-corporation")]
+    #[diesel(skip_insertion)]
+    pub slug: Option<String>,
+
+    /// Minutes between mirror syncs, 0 disables scheduled syncs
+    #[schema(example = 60)]
+    #[validate(range(min = 0, max = 10080))]
+    pub sync_interval_minutes: Option<i32>,
+}
+
+/// Body of `POST /organizations/{org_id}/repositories`, the organization comes from the path.
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct OrganizationRepoCreateQuery {
+    #[schema(example = "Acme Corporation")]
+    #[validate(length(min = 3, max = 100))]
+    pub name: String,
+
+    /// `https://`, `ssh://`, `file://` or scp-style `git@host:owner/repo.git`
+    #[schema(example = "https://git.acme.com/acme/acme-corporation.git")]
+    pub url: String,
+
+    /// Generated from `name` when omitted
+    #[schema(example = "acme-corporation")]
+    pub slug: Option<String>,
+
+    /// Minutes between mirror syncs, 0 disables scheduled syncs
+    #[schema(example = 60)]
+    #[validate(range(min = 0, max = 10080))]
+    pub sync_interval_minutes: Option<i32>,
+}
+
+impl OrganizationRepoCreateQuery {
+    pub fn into_create_query(self, organization_id: Uuid) -> RepoCreateQuery {
+        RepoCreateQuery {
+            name: self.name,
+            url: self.url,
+            organization_id,
+            slug: self.slug,
+            sync_interval_minutes: self.sync_interval_minutes,
+        }
+    }
 }
 
 #[derive(AsChangeset, Deserialize, Validate, ToSchema)]
@@ -34,14 +77,154 @@ pub struct RepoUpdateQuery {
 
     #[schema(example = "https://git.acme.com/acme/acme-corporation.git")]
     pub url: Option<String>,
-    
-    #[schema(example = "git.acme.com")]
+
+    #[schema(example = "acme-corporation-api")]
+    pub slug: Option<String>,
+
+    /// Minutes between mirror syncs, 0 disables scheduled syncs
+    #[schema(example = 60)]
+    #[validate(range(min = 0, max = 10080))]
+    pub sync_interval_minutes: Option<i32>,
+}
+
+/// Columns derived from the repository URL, never taken from the request.
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = repositories)]
+#[diesel(treat_none_as_null = true)]
+pub struct RepoRemote {
+    pub protocol: Option<String>,
     pub host_name: Option<String>,
-    
-    #[schema(example = "git")]
     pub user_name: Option<String>,
+    pub owner_path: Option<String>,
+    pub remote_key: Option<String>,
+}
 
+impl From<&GitUrl> for RepoRemote {
+    fn from(git_url: &GitUrl) -> Self {
+        RepoRemote {
+            protocol: Some(git_url.protocol.as_str().to_string()),
+            host_name: git_url.host.clone(),
+            user_name: git_url.user.clone(),
+            owner_path: git_url.owner.clone(),
+            remote_key: Some(git_url.remote_key()),
+        }
+    }
+}
+
+/// A repository with the keys recorded for its SSH host.
+#[derive(Serialize, ToSchema)]
+pub struct RepoDetails {
+    #[serde(flatten)]
+    pub repo: Repo,
+    pub host_keys: Vec<KnownHost>,
+}
+
+/// Outcome of the last connectivity check against the remote.
+#[derive(AsChangeset)]
+#[diesel(table_name = repositories)]
+#[diesel(treat_none_as_null = true)]
+pub struct RepoCheck {
+    pub reachable: Option<bool>,
+    pub default_branch: Option<String>,
+    pub ref_count: Option<i32>,
+    pub last_error: Option<String>,
+    pub last_checked_at: Option<DateTime<Utc>>,
+}
+
+/// Moves a repository to another organization.
+#[derive(Deserialize, ToSchema)]
+pub struct RepoTransferQuery {
+    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
+    pub organization_id: Uuid,
+}
+
+/// Access level a team can be granted on a repository, ordered from the
+/// weakest to the strongest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RepoPermission {
+    Read,
+    Triage,
+    Write,
+    Maintain,
+    Admin,
+}
+
+impl RepoPermission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RepoPermission::Read => "read",
+            RepoPermission::Triage => "triage",
+            RepoPermission::Write => "write",
+            RepoPermission::Maintain => "maintain",
+            RepoPermission::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for RepoPermission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RepoPermission {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(RepoPermission::Read),
+            "triage" => Ok(RepoPermission::Triage),
+            "write" => Ok(RepoPermission::Write),
+            "maintain" => Ok(RepoPermission::Maintain),
+            "admin" => Ok(RepoPermission::Admin),
+            other => Err(format!("Unknown repository permission: {}", other)),
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct GrantTeamAccessQuery {
     #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
-    pub organization_id: Option<Uuid>,
+    pub team_id: Uuid,
+
+    #[schema(example = "write")]
+    pub permission: RepoPermission,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateTeamAccessQuery {
+    #[schema(example = "maintain")]
+    pub permission: RepoPermission,
 }
 
+#[derive(Serialize, ToSchema)]
+pub struct TeamAccess {
+    pub team_id: Uuid,
+    pub team_name: String,
+    pub permission: RepoPermission,
+}
+
+/// Where a user's access to a repository comes from.
+#[derive(Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AccessSource {
+    Organization {
+        role: String,
+        permission: RepoPermission,
+    },
+    Team {
+        team_id: Uuid,
+        team_name: String,
+        permission: RepoPermission,
+    },
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UserAccess {
+    pub user_id: Uuid,
+    pub name: String,
+    pub email: String,
+    pub permission: RepoPermission,
+    pub sources: Vec<AccessSource>,
+}
@@ -1,63 +1,96 @@
-use crate::modules::repo::dto::{RepoCreateQuery, RepoUpdateQuery};
+use crate::config::Config;
+use crate::db;
+use crate::git::remote;
+use crate::jobs;
+use crate::models::Repo;
+use crate::modules::audit::dto::AuditContext;
+use crate::modules::auth::handler::session_user;
+use crate::modules::deploy_key::handler::master_key;
+use crate::modules::organization::handler::{require_manager, require_member};
+use crate::modules::repo::dto::{
+    GrantTeamAccessQuery, OrganizationRepoCreateQuery, RepoCreateQuery, RepoTransferQuery,
+    RepoUpdateQuery, UpdateTeamAccessQuery,
+};
 use crate::modules::repo::service::RepoService;
-use crate::utils::response::{error, success};
+use crate::modules::team::service::TeamService;
+use crate::utils::response::{error, success, ApiError};
+use crate::utils::shutdown::Shutdown;
+use actix_identity::Identity;
 use actix_web::http::StatusCode;
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpResponse, ResponseError};
 use diesel::r2d2::{self, ConnectionManager};
 use diesel::PgConnection;
+use std::time::Duration;
+use tracing::instrument;
 use validator::Validate;
 
 type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 
+#[instrument(name = "repo::handler::get_all", skip_all)]
 pub async fn get_all(pool: web::Data<DbPool>) -> HttpResponse {
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
     // Get all repos
-    match RepoService::get_all(&mut conn) {
+    let result = db::block(&pool, |conn| {
+        RepoService::get_all(conn).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve repos: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(repos) => HttpResponse::Ok().json(success(StatusCode::OK, Some(repos))),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to retrieve repos: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
+#[instrument(name = "repo::handler::get_by_id", skip_all)]
 pub async fn get_by_id(path: web::Path<uuid::Uuid>, pool: web::Data<DbPool>) -> HttpResponse {
     let id = path.into_inner();
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
     // Get repo by ID
-    match RepoService::get_by_id(&mut conn, id) {
+    let result = db::block(&pool, move |conn| {
+        RepoService::get_details(conn, id).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve repo: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(repo) => HttpResponse::Ok().json(success(StatusCode::OK, Some(repo))),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to retrieve repo: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
+// Newly registered repos are cloned right away rather than on the next poll
+fn clone_mirror(
+    conn: &mut PgConnection,
+    pool: &DbPool,
+    config: &Config,
+    shutdown: &Shutdown,
+    repo: &Repo,
+) {
+    if !config.mirror.enabled {
+        return;
+    }
+
+    if let Err(e) = jobs::mirror::trigger(conn, pool, config, shutdown, repo) {
+        tracing::error!("Failed to start mirror of repo {}: {}", repo.id, e);
+    }
+}
+
+#[instrument(name = "repo::handler::create", skip_all)]
 pub async fn create(
+    id: Identity,
     pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    shutdown: web::Data<Shutdown>,
     repo_data: web::Json<RepoCreateQuery>,
+    audit: AuditContext,
 ) -> HttpResponse {
     // Validate repo data
     if let Err(errors) = repo_data.validate() {
@@ -67,31 +100,117 @@ pub async fn create(
         ));
     }
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
     };
 
     // Create repo
-    match RepoService::create(&mut conn, &repo_data) {
+    let mirror_pool = pool.clone();
+    let result = db::block(&pool, move |conn| {
+        require_member(conn, user_id, repo_data.organization_id)?;
+        let repo = RepoService::create(conn, &repo_data, &config.git, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Failed to create repo: {}", e),
+            )
+        })?;
+        clone_mirror(conn, &mirror_pool, &config, &shutdown, &repo);
+        Ok(repo)
+    })
+    .await;
+
+    match result {
         Ok(repo) => HttpResponse::Created().json(success(StatusCode::CREATED, Some(repo))),
-        Err(e) => HttpResponse::BadRequest().json(error(
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "repo::handler::get_by_organization", skip_all)]
+pub async fn get_by_organization(
+    id: Identity,
+    path: web::Path<uuid::Uuid>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    let organization_id = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Get organization repos
+    let result = db::block(&pool, move |conn| {
+        require_member(conn, user_id, organization_id)?;
+        RepoService::get_by_organization(conn, organization_id).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve repos: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(repos) => HttpResponse::Ok().json(success(StatusCode::OK, Some(repos))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "repo::handler::create_in_organization", skip_all)]
+pub async fn create_in_organization(
+    id: Identity,
+    path: web::Path<uuid::Uuid>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    shutdown: web::Data<Shutdown>,
+    repo_data: web::Json<OrganizationRepoCreateQuery>,
+    audit: AuditContext,
+) -> HttpResponse {
+    let organization_id = path.into_inner();
+
+    // Validate repo data
+    if let Err(errors) = repo_data.validate() {
+        return HttpResponse::BadRequest().json(error(
             StatusCode::BAD_REQUEST,
-            format!("Failed to create repo: {}", e),
-        )),
+            format!("Validation error: {:?}", errors),
+        ));
+    }
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Create repo in the organization
+    let repo_data = repo_data.into_inner().into_create_query(organization_id);
+    let mirror_pool = pool.clone();
+    let result = db::block(&pool, move |conn| {
+        require_member(conn, user_id, organization_id)?;
+        let repo = RepoService::create(conn, &repo_data, &config.git, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Failed to create repo: {}", e),
+            )
+        })?;
+        clone_mirror(conn, &mirror_pool, &config, &shutdown, &repo);
+        Ok(repo)
+    })
+    .await;
+
+    match result {
+        Ok(repo) => HttpResponse::Created().json(success(StatusCode::CREATED, Some(repo))),
+        Err(e) => e.error_response(),
     }
 }
 
+#[instrument(name = "repo::handler::update", skip_all)]
 pub async fn update(
     path: web::Path<uuid::Uuid>,
     pool: web::Data<DbPool>,
+    config: web::Data<Config>,
     repo_data: web::Json<RepoUpdateQuery>,
+    audit: AuditContext,
 ) -> HttpResponse {
     let id = path.into_inner();
 
@@ -103,47 +222,350 @@ pub async fn update(
         ));
     }
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
+    // Update repo
+    let result = db::block(&pool, move |conn| {
+        RepoService::update(conn, id, &repo_data, &config.git, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to update repo: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(repo) => HttpResponse::Ok().json(success(StatusCode::OK, Some(repo))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "repo::handler::transfer", skip_all)]
+pub async fn transfer(
+    id: Identity,
+    path: web::Path<uuid::Uuid>,
+    pool: web::Data<DbPool>,
+    transfer_data: web::Json<RepoTransferQuery>,
+    audit: AuditContext,
+) -> HttpResponse {
+    let repo_id = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    let result = db::block(&pool, move |conn| {
+        // Get repo to transfer
+        let repo = RepoService::get_by_id(conn, repo_id)
+            .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, format!("Repo not found: {}", e)))?;
+
+        // Caller must belong to both organizations
+        for organization_id in [repo.organization_id, transfer_data.organization_id] {
+            require_member(conn, user_id, organization_id)?;
         }
+
+        // Transfer repo
+        RepoService::transfer(conn, repo_id, &transfer_data, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Failed to transfer repo: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(repo) => HttpResponse::Ok().json(success(StatusCode::OK, Some(repo))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "repo::handler::check", skip_all)]
+pub async fn check(
+    id: Identity,
+    path: web::Path<uuid::Uuid>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+) -> HttpResponse {
+    let repo_id = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
     };
 
-    // Update repo
-    match RepoService::update(&mut conn, id, &repo_data) {
+    // Get repo to check, without holding a connection during the check
+    let master_key = master_key(&config).ok();
+    let result = db::block(&pool, move |conn| {
+        let repo = RepoService::get_by_id(conn, repo_id)
+            .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, format!("Repo not found: {}", e)))?;
+
+        require_member(conn, user_id, repo.organization_id)?;
+
+        // Deploy keys are only used once encryption is configured
+        let auth = RepoService::remote_auth(conn, &repo, master_key.as_ref()).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to load remote credentials: {}", e),
+            )
+        })?;
+
+        Ok((repo, auth))
+    })
+    .await;
+
+    let (repo, auth) = match result {
+        Ok(found) => found,
+        Err(e) => return e.error_response(),
+    };
+
+    // List the remote refs
+    let timeout = Duration::from_secs(config.git.timeout_seconds);
+    let outcome = remote::check(repo.url.clone(), auth, timeout).await;
+
+    // Record the outcome on the repo
+    let result = db::block(&pool, move |conn| {
+        RepoService::record_check(conn, &repo, outcome).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to record repo check: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(repo) => HttpResponse::Ok().json(success(StatusCode::OK, Some(repo))),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to update repo: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
-pub async fn delete(path: web::Path<uuid::Uuid>, pool: web::Data<DbPool>) -> HttpResponse {
+#[instrument(name = "repo::handler::delete", skip_all)]
+pub async fn delete(
+    path: web::Path<uuid::Uuid>,
+    pool: web::Data<DbPool>,
+    audit: AuditContext,
+) -> HttpResponse {
     let id = path.into_inner();
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
+    // Delete repo
+    let result = db::block(&pool, move |conn| {
+        RepoService::delete(conn, id, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to delete repo: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(success::<()>(StatusCode::OK, None)),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "repo::handler::get_teams", skip_all)]
+pub async fn get_teams(path: web::Path<uuid::Uuid>, pool: web::Data<DbPool>) -> HttpResponse {
+    let id = path.into_inner();
+
+    // Get teams with access to the repo
+    let result = db::block(&pool, move |conn| {
+        RepoService::get_teams(conn, id).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve repo teams: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(teams) => HttpResponse::Ok().json(success(StatusCode::OK, Some(teams))),
+        Err(e) => e.error_response(),
+    }
+}
+
+// Repo whose team grants the caller may change, as an owner or admin of
+// its organization
+fn load_managed_repo(
+    conn: &mut PgConnection,
+    user_id: uuid::Uuid,
+    repo_id: uuid::Uuid,
+) -> Result<Repo, ApiError> {
+    let repo = RepoService::get_by_id(conn, repo_id)
+        .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, format!("Repo not found: {}", e)))?;
+
+    require_manager(conn, user_id, repo.organization_id)?;
+
+    Ok(repo)
+}
+
+#[instrument(name = "repo::handler::grant_team", skip_all)]
+pub async fn grant_team(
+    id: Identity,
+    path: web::Path<uuid::Uuid>,
+    pool: web::Data<DbPool>,
+    grant_data: web::Json<GrantTeamAccessQuery>,
+    audit: AuditContext,
+) -> HttpResponse {
+    let repo_id = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Grant team access to the repo
+    let result = db::block(&pool, move |conn| {
+        let repo = load_managed_repo(conn, user_id, repo_id)?;
+
+        let team = TeamService::get_by_id(conn, grant_data.team_id)
+            .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, format!("Team not found: {}", e)))?;
+        if team.organization_id != repo.organization_id {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "Team does not belong to the repository's organization".into(),
             ));
         }
+
+        match RepoService::find_grant(conn, repo.id, team.id) {
+            Ok(None) => {}
+            Ok(Some(_)) => {
+                return Err(ApiError::new(
+                    StatusCode::CONFLICT,
+                    "Team already has access to this repo".into(),
+                ))
+            }
+            Err(e) => {
+                return Err(ApiError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to grant team access: {}", e),
+                ))
+            }
+        }
+
+        RepoService::grant_team(conn, repo.id, &grant_data, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to grant team access: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(grant) => HttpResponse::Created().json(success(StatusCode::CREATED, Some(grant))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "repo::handler::update_team", skip_all)]
+pub async fn update_team(
+    id: Identity,
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    pool: web::Data<DbPool>,
+    grant_data: web::Json<UpdateTeamAccessQuery>,
+    audit: AuditContext,
+) -> HttpResponse {
+    let (repo_id, team_id) = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
     };
 
-    // Delete repo
-    match RepoService::delete(&mut conn, id) {
-        Ok(_) => HttpResponse::Ok().json(success::<()>(StatusCode::OK, None)),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to delete repo: {}", e),
+    // Change team permission on the repo
+    let result = db::block(&pool, move |conn| {
+        let repo = load_managed_repo(conn, user_id, repo_id)?;
+
+        RepoService::update_team(conn, repo.id, team_id, &grant_data, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to update team access: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(Some(grant)) => HttpResponse::Ok().json(success(StatusCode::OK, Some(grant))),
+        Ok(None) => HttpResponse::NotFound().json(error(
+            StatusCode::NOT_FOUND,
+            "Team has no access to this repo".into(),
+        )),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "repo::handler::revoke_team", skip_all)]
+pub async fn revoke_team(
+    id: Identity,
+    path: web::Path<(uuid::Uuid, uuid::Uuid)>,
+    pool: web::Data<DbPool>,
+    audit: AuditContext,
+) -> HttpResponse {
+    let (repo_id, team_id) = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Revoke team access to the repo
+    let result = db::block(&pool, move |conn| {
+        let repo = load_managed_repo(conn, user_id, repo_id)?;
+
+        RepoService::revoke_team(conn, repo.id, team_id, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to revoke team access: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(true) => HttpResponse::Ok().json(success::<()>(StatusCode::OK, None)),
+        Ok(false) => HttpResponse::NotFound().json(error(
+            StatusCode::NOT_FOUND,
+            "Team has no access to this repo".into(),
         )),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "repo::handler::get_access", skip_all)]
+pub async fn get_access(
+    id: Identity,
+    path: web::Path<uuid::Uuid>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    let repo_id = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Resolve effective permissions on the repo
+    let result = db::block(&pool, move |conn| {
+        let repo = RepoService::get_by_id(conn, repo_id)
+            .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, format!("Repo not found: {}", e)))?;
+
+        require_member(conn, user_id, repo.organization_id)?;
+
+        RepoService::get_access(conn, repo.id).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to resolve repo access: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(access) => HttpResponse::Ok().json(success(StatusCode::OK, Some(access))),
+        Err(e) => e.error_response(),
     }
 }
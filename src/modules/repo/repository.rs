@@ -1,14 +1,26 @@
-use crate::models::Repo;
-use crate::modules::repo::dto::{RepoCreateQuery, RepoUpdateQuery};
+use crate::models::{Repo, TeamRepo};
+use crate::modules::repo::dto::{
+    RepoCheck, RepoCreateQuery, RepoPermission, RepoRemote, RepoUpdateQuery,
+};
+use crate::modules::slug::dto::SlugResource;
+use crate::modules::slug::repository::SlugRepository;
 use chrono::Utc;
 use diesel::prelude::*;
 use diesel::PgConnection;
 use std::error::Error;
+use tracing::instrument;
 use uuid::Uuid;
 
+/// `(user_id, name, email, team_id, team_name, permission)`
+pub type TeamMemberAccessRow = (Uuid, String, String, Uuid, String, String);
+
+/// `(user_id, name, email, role)`
+pub type OrganizationMemberAccessRow = (Uuid, String, String, Option<String>);
+
 pub struct RepoRepository;
 
 impl RepoRepository {
+    #[instrument(name = "RepoRepository::find_by_id", skip_all)]
     pub fn find_by_id(conn: &mut PgConnection, repo_id: Uuid) -> Result<Repo, Box<dyn Error>> {
         use crate::schema::repositories::dsl::*;
 
@@ -20,6 +32,73 @@ impl RepoRepository {
         Ok(repo)
     }
 
+    #[instrument(name = "RepoRepository::find_by_slug", skip_all)]
+    pub fn find_by_slug(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        repo_slug: &str,
+    ) -> Result<Option<Repo>, Box<dyn Error>> {
+        use crate::schema::repositories::dsl::*;
+
+        let repo = repositories
+            .filter(organization_id.eq(org_id))
+            .filter(slug.eq(repo_slug))
+            .filter(deleted_at.is_null())
+            .first::<Repo>(conn)
+            .optional()?;
+
+        Ok(repo)
+    }
+
+    #[instrument(name = "RepoRepository::slug_taken", skip_all)]
+    pub fn slug_taken(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        repo_slug: &str,
+        exclude_id: Option<Uuid>,
+    ) -> Result<bool, Box<dyn Error>> {
+        use crate::schema::repositories::dsl::*;
+
+        let mut query = repositories
+            .filter(organization_id.eq(org_id))
+            .filter(slug.eq(repo_slug))
+            .filter(deleted_at.is_null())
+            .into_boxed();
+
+        if let Some(exclude_id) = exclude_id {
+            query = query.filter(id.ne(exclude_id));
+        }
+
+        let taken = diesel::select(diesel::dsl::exists(query)).get_result::<bool>(conn)?;
+
+        Ok(taken)
+    }
+
+    #[instrument(name = "RepoRepository::remote_taken", skip_all)]
+    pub fn remote_taken(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        key: &str,
+        exclude_id: Option<Uuid>,
+    ) -> Result<bool, Box<dyn Error>> {
+        use crate::schema::repositories::dsl::*;
+
+        let mut query = repositories
+            .filter(organization_id.eq(org_id))
+            .filter(remote_key.eq(key))
+            .filter(deleted_at.is_null())
+            .into_boxed();
+
+        if let Some(exclude_id) = exclude_id {
+            query = query.filter(id.ne(exclude_id));
+        }
+
+        let taken = diesel::select(diesel::dsl::exists(query)).get_result::<bool>(conn)?;
+
+        Ok(taken)
+    }
+
+    #[instrument(name = "RepoRepository::find_all", skip_all)]
     pub fn find_all(conn: &mut PgConnection) -> Result<Vec<Repo>, Box<dyn Error>> {
         use crate::schema::repositories::dsl::*;
 
@@ -28,33 +107,119 @@ impl RepoRepository {
         Ok(all_repos)
     }
 
+    #[instrument(name = "RepoRepository::find_by_organization", skip_all)]
+    pub fn find_by_organization(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+    ) -> Result<Vec<Repo>, Box<dyn Error>> {
+        use crate::schema::repositories::dsl::*;
+
+        let organization_repos = repositories
+            .filter(organization_id.eq(org_id))
+            .filter(deleted_at.is_null())
+            .load::<Repo>(conn)?;
+
+        Ok(organization_repos)
+    }
+
+    #[instrument(name = "RepoRepository::create", skip_all)]
     pub fn create(
         conn: &mut PgConnection,
         new_repo: &RepoCreateQuery,
+        new_slug: &str,
+        remote: &RepoRemote,
     ) -> Result<Repo, Box<dyn Error>> {
         use crate::schema::repositories::dsl::*;
 
         diesel::insert_into(repositories)
-            .values(new_repo)
+            .values((new_repo, slug.eq(new_slug), remote))
             .get_result::<Repo>(conn)
             .map_err(|e| e.into())
     }
 
+    #[instrument(name = "RepoRepository::update", skip_all)]
     pub fn update(
         conn: &mut PgConnection,
         repo_id: Uuid,
         update_data: &RepoUpdateQuery,
+        remote: Option<&RepoRemote>,
+    ) -> Result<Repo, Box<dyn Error>> {
+        use crate::schema::repositories::dsl::*;
+
+        conn.transaction(|conn| {
+            let current = Self::find_by_id(conn, repo_id)?;
+
+            let repo = diesel::update(repositories)
+                .filter(id.eq(repo_id))
+                .filter(deleted_at.is_null())
+                .set((update_data, remote))
+                .get_result::<Repo>(conn)?;
+
+            // Keep the previous slug resolving to this repository
+            if repo.slug != current.slug {
+                SlugRepository::record_redirect(
+                    conn,
+                    SlugResource::Repository,
+                    Some(current.organization_id),
+                    &current.slug,
+                    repo.id,
+                )?;
+            }
+
+            Ok(repo)
+        })
+    }
+
+    #[instrument(name = "RepoRepository::record_check", skip_all)]
+    pub fn record_check(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        check: &RepoCheck,
     ) -> Result<Repo, Box<dyn Error>> {
         use crate::schema::repositories::dsl::*;
 
         diesel::update(repositories)
             .filter(id.eq(repo_id))
             .filter(deleted_at.is_null())
-            .set(update_data)
+            .set(check)
             .get_result::<Repo>(conn)
             .map_err(|e| e.into())
     }
 
+    #[instrument(name = "RepoRepository::transfer", skip_all)]
+    pub fn transfer(
+        conn: &mut PgConnection,
+        repo: &Repo,
+        target_organization_id: Uuid,
+    ) -> Result<Repo, Box<dyn Error>> {
+        use crate::schema::{repositories, team_repositories};
+
+        conn.transaction(|conn| {
+            let transferred = diesel::update(repositories::table)
+                .filter(repositories::id.eq(repo.id))
+                .filter(repositories::deleted_at.is_null())
+                .set(repositories::organization_id.eq(target_organization_id))
+                .get_result::<Repo>(conn)?;
+
+            // Teams of the previous organization lose their access
+            diesel::delete(team_repositories::table)
+                .filter(team_repositories::repository_id.eq(repo.id))
+                .execute(conn)?;
+
+            // Old organization paths keep resolving to the repository
+            SlugRepository::record_redirect(
+                conn,
+                SlugResource::Repository,
+                Some(repo.organization_id),
+                &repo.slug,
+                repo.id,
+            )?;
+
+            Ok(transferred)
+        })
+    }
+
+    #[instrument(name = "RepoRepository::delete", skip_all)]
     pub fn delete(conn: &mut PgConnection, repo_id: Uuid) -> Result<(), Box<dyn Error>> {
         use crate::schema::repositories::dsl::*;
 
@@ -66,4 +231,131 @@ impl RepoRepository {
 
         Ok(())
     }
+
+    #[instrument(name = "RepoRepository::find_team_grants", skip_all)]
+    pub fn find_team_grants(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+    ) -> Result<Vec<(TeamRepo, String)>, Box<dyn Error>> {
+        use crate::schema::{team_repositories, teams};
+
+        let grants = team_repositories::table
+            .inner_join(teams::table)
+            .filter(team_repositories::repository_id.eq(repo_id))
+            .filter(teams::deleted_at.is_null())
+            .select((TeamRepo::as_select(), teams::name))
+            .order(teams::name.asc())
+            .load::<(TeamRepo, String)>(conn)?;
+
+        Ok(grants)
+    }
+
+    #[instrument(name = "RepoRepository::grant_team", skip_all)]
+    pub fn grant_team(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        grant_team_id: Uuid,
+        grant_permission: RepoPermission,
+    ) -> Result<TeamRepo, Box<dyn Error>> {
+        use crate::schema::team_repositories::dsl::*;
+
+        diesel::insert_into(team_repositories)
+            .values((
+                team_id.eq(grant_team_id),
+                repository_id.eq(repo_id),
+                permission.eq(grant_permission.as_str()),
+            ))
+            .get_result::<TeamRepo>(conn)
+            .map_err(|e| e.into())
+    }
+
+    #[instrument(name = "RepoRepository::update_team_grant", skip_all)]
+    pub fn update_team_grant(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        grant_team_id: Uuid,
+        grant_permission: RepoPermission,
+    ) -> Result<Option<TeamRepo>, Box<dyn Error>> {
+        use crate::schema::team_repositories::dsl::*;
+
+        let grant = diesel::update(team_repositories)
+            .filter(team_id.eq(grant_team_id))
+            .filter(repository_id.eq(repo_id))
+            .set(permission.eq(grant_permission.as_str()))
+            .get_result::<TeamRepo>(conn)
+            .optional()?;
+
+        Ok(grant)
+    }
+
+    #[instrument(name = "RepoRepository::revoke_team", skip_all)]
+    pub fn revoke_team(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        grant_team_id: Uuid,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::schema::team_repositories::dsl::*;
+
+        let deleted = diesel::delete(team_repositories)
+            .filter(team_id.eq(grant_team_id))
+            .filter(repository_id.eq(repo_id))
+            .execute(conn)?;
+
+        if deleted == 0 {
+            return Err("Team has no access to this repository".into());
+        }
+
+        Ok(())
+    }
+
+    /// Members of live teams holding a grant on the repository, one row per
+    /// (user, team) pair.
+    #[instrument(name = "RepoRepository::find_team_members_access", skip_all)]
+    pub fn find_team_members_access(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+    ) -> Result<Vec<TeamMemberAccessRow>, Box<dyn Error>> {
+        use crate::schema::{team_repositories, team_users, teams, users};
+
+        let rows = team_users::table
+            .inner_join(teams::table)
+            .inner_join(users::table)
+            .inner_join(
+                team_repositories::table.on(team_repositories::team_id.eq(team_users::team_id)),
+            )
+            .filter(team_repositories::repository_id.eq(repo_id))
+            .filter(teams::deleted_at.is_null())
+            .filter(team_users::deleted_at.is_null())
+            .filter(users::deleted_at.is_null())
+            .select((
+                users::id,
+                users::name,
+                users::email,
+                teams::id,
+                teams::name,
+                team_repositories::permission,
+            ))
+            .load(conn)?;
+
+        Ok(rows)
+    }
+
+    /// Members of the repository's organization with their org role.
+    #[instrument(name = "RepoRepository::find_organization_members_access", skip_all)]
+    pub fn find_organization_members_access(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+    ) -> Result<Vec<OrganizationMemberAccessRow>, Box<dyn Error>> {
+        use crate::schema::{organization_users, users};
+
+        let rows = organization_users::table
+            .inner_join(users::table)
+            .filter(organization_users::organization_id.eq(org_id))
+            .filter(organization_users::deleted_at.is_null())
+            .filter(users::deleted_at.is_null())
+            .select((users::id, users::name, users::email, organization_users::role))
+            .load(conn)?;
+
+        Ok(rows)
+    }
 }
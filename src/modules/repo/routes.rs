@@ -1,4 +1,11 @@
-use crate::modules::repo::handler::{create, delete, get_all, get_by_id, update};
+use crate::modules::browse::routes as browse_routes;
+use crate::modules::deploy_key::routes as deploy_key_routes;
+use crate::modules::hook::routes as hook_routes;
+use crate::modules::mirror::routes as mirror_routes;
+use crate::modules::repo::handler::{
+    check, create, create_in_organization, delete, get_access, get_all, get_by_id,
+    get_by_organization, get_teams, grant_team, revoke_team, transfer, update, update_team,
+};
 use actix_web::web;
 
 pub fn config_routes(cfg: &mut web::ServiceConfig) {
@@ -9,5 +16,25 @@ pub fn config_routes(cfg: &mut web::ServiceConfig) {
             .route("", web::post().to(create))
             .route("/{id}", web::put().to(update))
             .route("/{id}", web::delete().to(delete))
+            .route("/{id}/transfer", web::post().to(transfer))
+            .route("/{id}/check", web::post().to(check))
+            .route("/{id}/access", web::get().to(get_access))
+            .route("/{id}/teams", web::get().to(get_teams))
+            .route("/{id}/teams", web::post().to(grant_team))
+            .route("/{id}/teams/{team_id}", web::put().to(update_team))
+            .route("/{id}/teams/{team_id}", web::delete().to(revoke_team))
+            .configure(deploy_key_routes::config_repository_routes)
+            .configure(mirror_routes::config_repository_routes)
+            .configure(browse_routes::config_repository_routes)
+            .configure(hook_routes::config_repository_routes),
     );
 }
+
+// Mounted inside the `/organizations` scope
+pub fn config_organization_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/{org_id}/repositories", web::get().to(get_by_organization))
+        .route(
+            "/{org_id}/repositories",
+            web::post().to(create_in_organization),
+        );
+}
@@ -1,34 +1,555 @@
-use crate::models::Repo;
-use crate::modules::repo::dto::{RepoCreateQuery, RepoUpdateQuery};
+use crate::config::GitConfig;
+use crate::git::remote::{RemoteAuth, RemoteError, RemoteRefs};
+use crate::models::{Repo, TeamRepo};
+use crate::modules::audit::dto::{AuditAction, AuditContext};
+use crate::modules::audit::service::AuditService;
+use crate::modules::deploy_key::service::DeployKeyService;
+use crate::modules::known_host::service::KnownHostService;
+use crate::modules::mirror::service::MirrorService;
+use crate::modules::organization::repository::OrganizationRepository;
+use crate::modules::outbox::dto::DomainEvent;
+use crate::modules::outbox::service::OutboxService;
+use crate::modules::repo::dto::{
+    AccessSource, GrantTeamAccessQuery, RepoCheck, RepoCreateQuery, RepoDetails, RepoPermission,
+    RepoRemote, RepoTransferQuery, RepoUpdateQuery, TeamAccess, UpdateTeamAccessQuery, UserAccess,
+};
 use crate::modules::repo::repository::RepoRepository;
-use diesel::PgConnection;
+use crate::modules::team::repository::TeamRepository;
+use crate::utils::envelope::MasterKey;
+use crate::utils::git_url::{GitProtocol, GitUrl};
+use crate::utils::slug::{unique_slug, validate_slug};
+use chrono::Utc;
+use diesel::{Connection, PgConnection};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
 use std::error::Error;
+use tracing::instrument;
 use uuid::Uuid;
 
 pub struct RepoService;
 
 impl RepoService {
+    #[instrument(name = "RepoService::get_all", skip_all)]
     pub fn get_all(conn: &mut PgConnection) -> Result<Vec<Repo>, Box<dyn Error>> {
         RepoRepository::find_all(conn)
     }
 
+    #[instrument(name = "RepoService::get_by_id", skip_all)]
     pub fn get_by_id(conn: &mut PgConnection, repo_id: Uuid) -> Result<Repo, Box<dyn Error>> {
         RepoRepository::find_by_id(conn, repo_id)
     }
 
-    pub fn create(conn: &mut PgConnection, data: &RepoCreateQuery) -> Result<Repo, Box<dyn Error>> {
-        RepoRepository::create(conn, data)
+    /// The repo with the recorded keys of its SSH host.
+    #[instrument(name = "RepoService::get_details", skip_all)]
+    pub fn get_details(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+    ) -> Result<RepoDetails, Box<dyn Error>> {
+        let repo = RepoRepository::find_by_id(conn, repo_id)?;
+        let host_keys = match GitUrl::parse(&repo.url)
+            .ok()
+            .and_then(|url| url.known_host())
+        {
+            Some(host) => KnownHostService::get_by_host(conn, repo.organization_id, &host)?,
+            None => Vec::new(),
+        };
+
+        Ok(RepoDetails { repo, host_keys })
+    }
+
+    #[instrument(name = "RepoService::get_by_organization", skip_all)]
+    pub fn get_by_organization(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+    ) -> Result<Vec<Repo>, Box<dyn Error>> {
+        RepoRepository::find_by_organization(conn, organization_id)
+    }
+
+    #[instrument(name = "RepoService::create", skip_all)]
+    pub fn create(
+        conn: &mut PgConnection,
+        data: &RepoCreateQuery,
+        git_config: &GitConfig,
+        audit: &AuditContext,
+    ) -> Result<Repo, Box<dyn Error>> {
+        // The owning organization must exist and not be deleted
+        OrganizationRepository::find_by_id(conn, data.organization_id)
+            .map_err(|_| "Organization not found")?;
+
+        let slug = match &data.slug {
+            Some(slug) => {
+                validate_slug(slug)?;
+                if RepoRepository::slug_taken(conn, data.organization_id, slug, None)? {
+                    return Err(format!("Slug '{}' is already taken", slug).into());
+                }
+                slug.clone()
+            }
+            None => unique_slug(&data.name, "repository", |candidate| {
+                RepoRepository::slug_taken(conn, data.organization_id, candidate, None)
+            })?,
+        };
+
+        // Normalize the remote and derive its host, user and owner
+        let git_url = GitUrl::parse(&data.url)?;
+        Self::ensure_protocol_allowed(&git_url, git_config)?;
+        let remote = RepoRemote::from(&git_url);
+        Self::ensure_remote_available(conn, data.organization_id, &git_url, None)?;
+
+        let data = RepoCreateQuery {
+            name: data.name.clone(),
+            url: git_url.to_string(),
+            organization_id: data.organization_id,
+            slug: None,
+            sync_interval_minutes: data.sync_interval_minutes,
+        };
+
+        conn.transaction(|conn| {
+            let repo = RepoRepository::create(conn, &data, &slug, &remote)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::RepositoryCreated,
+                Some(repo.organization_id),
+                Some(repo.id),
+                AuditService::diff(None, Some(&repo))?,
+            )?;
+            OutboxService::record(
+                conn,
+                &DomainEvent::RepositoryCreated {
+                    repository: repo.clone(),
+                },
+            )?;
+
+            Ok(repo)
+        })
     }
 
+    #[instrument(name = "RepoService::update", skip_all)]
     pub fn update(
         conn: &mut PgConnection,
         repo_id: Uuid,
         data: &RepoUpdateQuery,
+        git_config: &GitConfig,
+        audit: &AuditContext,
     ) -> Result<Repo, Box<dyn Error>> {
-        RepoRepository::update(conn, repo_id, data)
+        let current = RepoRepository::find_by_id(conn, repo_id)?;
+
+        if let Some(slug) = &data.slug {
+            validate_slug(slug)?;
+            if RepoRepository::slug_taken(conn, current.organization_id, slug, Some(repo_id))? {
+                return Err(format!("Slug '{}' is already taken", slug).into());
+            }
+        }
+
+        // Normalize the new remote and derive its host, user and owner
+        let git_url = data.url.as_deref().map(GitUrl::parse).transpose()?;
+        if let Some(git_url) = &git_url {
+            Self::ensure_protocol_allowed(git_url, git_config)?;
+            Self::ensure_remote_available(conn, current.organization_id, git_url, Some(repo_id))?;
+        }
+        let remote = git_url.as_ref().map(RepoRemote::from);
+
+        let data = RepoUpdateQuery {
+            name: data.name.clone(),
+            url: git_url.as_ref().map(GitUrl::to_string),
+            slug: data.slug.clone(),
+            sync_interval_minutes: data.sync_interval_minutes,
+        };
+
+        conn.transaction(|conn| {
+            let repo = RepoRepository::update(conn, repo_id, &data, remote.as_ref())?;
+            if let Some(interval) = data.sync_interval_minutes {
+                if current.sync_interval_minutes != Some(interval) {
+                    MirrorService::reschedule(conn, repo.id, interval)?;
+                }
+            }
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::RepositoryUpdated,
+                Some(repo.organization_id),
+                Some(repo.id),
+                AuditService::diff(Some(&current), Some(&repo))?,
+            )?;
+            OutboxService::record(
+                conn,
+                &DomainEvent::RepositoryUpdated {
+                    repository: repo.clone(),
+                },
+            )?;
+
+            Ok(repo)
+        })
+    }
+
+    /// Moves a repository to another organization. Team grants are dropped
+    /// since they belong to teams of the previous organization.
+    #[instrument(name = "RepoService::transfer", skip_all)]
+    pub fn transfer(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        data: &RepoTransferQuery,
+        audit: &AuditContext,
+    ) -> Result<Repo, Box<dyn Error>> {
+        let repo = RepoRepository::find_by_id(conn, repo_id)?;
+
+        if repo.organization_id == data.organization_id {
+            return Err("Repository already belongs to this organization".into());
+        }
+
+        OrganizationRepository::find_by_id(conn, data.organization_id)
+            .map_err(|_| "Organization not found")?;
+
+        if RepoRepository::slug_taken(conn, data.organization_id, &repo.slug, None)? {
+            return Err(format!(
+                "Slug '{}' is already taken in the target organization",
+                repo.slug
+            )
+            .into());
+        }
+
+        if let Some(key) = &repo.remote_key {
+            if RepoRepository::remote_taken(conn, data.organization_id, key, None)? {
+                return Err("This remote is already registered in the target organization".into());
+            }
+        }
+
+        conn.transaction(|conn| {
+            let transferred = RepoRepository::transfer(conn, &repo, data.organization_id)?;
+
+            // Both organizations keep a trace of the transfer
+            let changes = AuditService::diff(Some(&repo), Some(&transferred))?;
+            for organization_id in [repo.organization_id, transferred.organization_id] {
+                AuditService::record(
+                    conn,
+                    audit,
+                    AuditAction::RepositoryTransferred,
+                    Some(organization_id),
+                    Some(repo.id),
+                    changes.clone(),
+                )?;
+            }
+            OutboxService::record(
+                conn,
+                &DomainEvent::RepositoryTransferred {
+                    repository: transferred.clone(),
+                    previous_organization_id: repo.organization_id,
+                },
+            )?;
+
+            Ok(transferred)
+        })
+    }
+
+    /// Deploy keys and trusted host keys to connect to the remote with.
+    /// Without a master key no deploy key can be decrypted.
+    #[instrument(name = "RepoService::remote_auth", skip_all)]
+    pub fn remote_auth(
+        conn: &mut PgConnection,
+        repo: &Repo,
+        master_key: Option<&MasterKey>,
+    ) -> Result<RemoteAuth, Box<dyn Error>> {
+        let private_keys = match master_key {
+            Some(master_key) => DeployKeyService::private_keys(conn, repo.id, master_key)?,
+            None => Vec::new(),
+        };
+        let host_keys = match GitUrl::parse(&repo.url)
+            .ok()
+            .and_then(|url| url.known_host())
+        {
+            Some(host) => KnownHostService::get_trusted(conn, repo.organization_id, &host)?,
+            None => Vec::new(),
+        };
+
+        Ok(RemoteAuth {
+            private_keys,
+            host_keys,
+        })
+    }
+
+    /// Stores the result of a connectivity check. A failed check keeps the
+    /// last known default branch and ref count.
+    #[instrument(name = "RepoService::record_check", skip_all)]
+    pub fn record_check(
+        conn: &mut PgConnection,
+        repo: &Repo,
+        outcome: Result<RemoteRefs, RemoteError>,
+    ) -> Result<Repo, Box<dyn Error>> {
+        let check = match outcome {
+            Ok(refs) => RepoCheck {
+                reachable: Some(true),
+                default_branch: refs.default_branch,
+                ref_count: Some(i32::try_from(refs.ref_count).unwrap_or(i32::MAX)),
+                last_error: None,
+                last_checked_at: Some(Utc::now()),
+            },
+            Err(e) => {
+                KnownHostService::record_rejection(conn, repo.organization_id, &e)?;
+
+                RepoCheck {
+                    reachable: Some(false),
+                    default_branch: repo.default_branch.clone(),
+                    ref_count: repo.ref_count,
+                    last_error: Some(e.to_string()),
+                    last_checked_at: Some(Utc::now()),
+                }
+            }
+        };
+
+        RepoRepository::record_check(conn, repo.id, &check)
+    }
+
+    // Local paths and plain HTTP are opt-in for the operator
+    fn ensure_protocol_allowed(git_url: &GitUrl, config: &GitConfig) -> Result<(), Box<dyn Error>> {
+        let allowed = match git_url.protocol {
+            GitProtocol::File => config.allow_file_remotes,
+            GitProtocol::Http => config.allow_http_remotes,
+            GitProtocol::Https | GitProtocol::Ssh => true,
+        };
+
+        if !allowed {
+            return Err(format!(
+                "{}:// remotes are not allowed on this server",
+                git_url.protocol.as_str()
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    // The same remote can only be registered once per organization
+    fn ensure_remote_available(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+        git_url: &GitUrl,
+        exclude_id: Option<Uuid>,
+    ) -> Result<(), Box<dyn Error>> {
+        if RepoRepository::remote_taken(conn, organization_id, &git_url.remote_key(), exclude_id)? {
+            return Err(format!(
+                "Remote {} is already registered in this organization",
+                git_url
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    #[instrument(name = "RepoService::delete", skip_all)]
+    pub fn delete(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        audit: &AuditContext,
+    ) -> Result<(), Box<dyn Error>> {
+        conn.transaction(|conn| {
+            let repository = RepoRepository::find_by_id(conn, repo_id)?;
+            RepoRepository::delete(conn, repo_id)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::RepositoryDeleted,
+                Some(repository.organization_id),
+                Some(repository.id),
+                AuditService::diff(Some(&repository), None)?,
+            )?;
+            OutboxService::record(conn, &DomainEvent::RepositoryDeleted { repository })?;
+
+            Ok(())
+        })
+    }
+
+    #[instrument(name = "RepoService::get_teams", skip_all)]
+    pub fn get_teams(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+    ) -> Result<Vec<TeamAccess>, Box<dyn Error>> {
+        RepoRepository::find_by_id(conn, repo_id)?;
+
+        RepoRepository::find_team_grants(conn, repo_id)?
+            .into_iter()
+            .map(|(grant, team_name)| {
+                Ok(TeamAccess {
+                    team_id: grant.team_id,
+                    team_name,
+                    permission: grant.permission.parse()?,
+                })
+            })
+            .collect()
+    }
+
+    #[instrument(name = "RepoService::grant_team", skip_all)]
+    pub fn grant_team(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        data: &GrantTeamAccessQuery,
+        audit: &AuditContext,
+    ) -> Result<TeamRepo, Box<dyn Error>> {
+        let repo = RepoRepository::find_by_id(conn, repo_id)?;
+        let team = TeamRepository::find_by_id(conn, data.team_id)?;
+
+        // Grants never cross organization boundaries
+        if team.organization_id != repo.organization_id {
+            return Err("Team does not belong to the repository's organization".into());
+        }
+
+        conn.transaction(|conn| {
+            let grant = RepoRepository::grant_team(conn, repo_id, team.id, data.permission)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::TeamAccessGranted,
+                Some(repo.organization_id),
+                Some(repo.id),
+                AuditService::diff(None, Some(&Self::grant_state(&grant)))?,
+            )?;
+
+            Ok(grant)
+        })
+    }
+
+    /// Changes the permission of an existing grant, `None` when the team
+    /// has no access to the repository.
+    #[instrument(name = "RepoService::update_team", skip_all)]
+    pub fn update_team(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        team_id: Uuid,
+        data: &UpdateTeamAccessQuery,
+        audit: &AuditContext,
+    ) -> Result<Option<TeamRepo>, Box<dyn Error>> {
+        let repo = RepoRepository::find_by_id(conn, repo_id)?;
+        let Some(before) = Self::find_grant(conn, repo_id, team_id)? else {
+            return Ok(None);
+        };
+
+        conn.transaction(|conn| {
+            let Some(grant) =
+                RepoRepository::update_team_grant(conn, repo_id, team_id, data.permission)?
+            else {
+                return Ok(None);
+            };
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::TeamAccessUpdated,
+                Some(repo.organization_id),
+                Some(repo.id),
+                AuditService::diff(
+                    Some(&Self::grant_state(&before)),
+                    Some(&Self::grant_state(&grant)),
+                )?,
+            )?;
+
+            Ok(Some(grant))
+        })
+    }
+
+    /// Removes the grant of a team, `false` when it had none.
+    #[instrument(name = "RepoService::revoke_team", skip_all)]
+    pub fn revoke_team(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        team_id: Uuid,
+        audit: &AuditContext,
+    ) -> Result<bool, Box<dyn Error>> {
+        let repo = RepoRepository::find_by_id(conn, repo_id)?;
+        let Some(before) = Self::find_grant(conn, repo_id, team_id)? else {
+            return Ok(false);
+        };
+
+        conn.transaction(|conn| {
+            RepoRepository::revoke_team(conn, repo_id, team_id)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::TeamAccessRevoked,
+                Some(repo.organization_id),
+                Some(repo.id),
+                AuditService::diff(Some(&Self::grant_state(&before)), None)?,
+            )?;
+
+            Ok(true)
+        })
+    }
+
+    /// The grant of a team on a repository, if it has one.
+    #[instrument(name = "RepoService::find_grant", skip_all)]
+    pub fn find_grant(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+        team_id: Uuid,
+    ) -> Result<Option<TeamRepo>, Box<dyn Error>> {
+        Ok(RepoRepository::find_team_grants(conn, repo_id)?
+            .into_iter()
+            .map(|(grant, _)| grant)
+            .find(|grant| grant.team_id == team_id))
+    }
+
+    // Keyed by team so the audit diff names the team whose grant changed
+    fn grant_state(grant: &TeamRepo) -> Value {
+        json!({ "teams": { grant.team_id.to_string(): grant.permission } })
+    }
+
+    /// Resolves the effective permission of every user on a repository.
+    ///
+    /// Organization owners and admins get `admin`, other organization members
+    /// get `read` as a base permission, and team grants raise that to the
+    /// strongest permission of any team the user belongs to.
+    #[instrument(name = "RepoService::get_access", skip_all)]
+    pub fn get_access(
+        conn: &mut PgConnection,
+        repo_id: Uuid,
+    ) -> Result<Vec<UserAccess>, Box<dyn Error>> {
+        let repo = RepoRepository::find_by_id(conn, repo_id)?;
+        let mut access: BTreeMap<Uuid, UserAccess> = BTreeMap::new();
+
+        for (user_id, name, email, role) in
+            RepoRepository::find_organization_members_access(conn, repo.organization_id)?
+        {
+            let role = role.unwrap_or_else(|| "member".to_string());
+            let permission = Self::organization_role_permission(&role);
+
+            access
+                .entry(user_id)
+                .or_insert_with(|| UserAccess {
+                    user_id,
+                    name,
+                    email,
+                    permission,
+                    sources: Vec::new(),
+                })
+                .sources
+                .push(AccessSource::Organization { role, permission });
+        }
+
+        for (user_id, name, email, team_id, team_name, permission) in
+            RepoRepository::find_team_members_access(conn, repo_id)?
+        {
+            let permission: RepoPermission = permission.parse()?;
+
+            let entry = access.entry(user_id).or_insert_with(|| UserAccess {
+                user_id,
+                name,
+                email,
+                permission,
+                sources: Vec::new(),
+            });
+            entry.permission = entry.permission.max(permission);
+            entry.sources.push(AccessSource::Team {
+                team_id,
+                team_name,
+                permission,
+            });
+        }
+
+        Ok(access.into_values().collect())
     }
 
-    pub fn delete(conn: &mut PgConnection, repo_id: Uuid) -> Result<(), Box<dyn Error>> {
-        RepoRepository::delete(conn, repo_id)
+    fn organization_role_permission(role: &str) -> RepoPermission {
+        match role {
+            "owner" | "admin" => RepoPermission::Admin,
+            _ => RepoPermission::Read,
+        }
     }
 }
@@ -0,0 +1,24 @@
+/// Kind of resource a slug redirect points to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlugResource {
+    Organization,
+    Team,
+    Repository,
+}
+
+impl SlugResource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SlugResource::Organization => "organization",
+            SlugResource::Team => "team",
+            SlugResource::Repository => "repository",
+        }
+    }
+}
+
+/// Outcome of resolving a slug path: either the resource itself, or the
+/// canonical path to redirect to when an old slug was used.
+pub enum SlugResolution<T> {
+    Found(T),
+    Moved(String),
+}
@@ -0,0 +1,86 @@
+use crate::db;
+use crate::modules::slug::dto::SlugResolution;
+use crate::modules::slug::service::SlugService;
+use crate::utils::response::{success, ApiError};
+use actix_web::http::{header, StatusCode};
+use actix_web::{web, HttpResponse, ResponseError};
+use diesel::r2d2::{self, ConnectionManager};
+use diesel::PgConnection;
+use serde::Serialize;
+use tracing::instrument;
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+// Answer with the resource, or a permanent redirect when an old slug was used
+fn respond<T: Serialize>(resolution: SlugResolution<T>) -> HttpResponse {
+    match resolution {
+        SlugResolution::Found(resource) => {
+            HttpResponse::Ok().json(success(StatusCode::OK, Some(resource)))
+        }
+        SlugResolution::Moved(location) => HttpResponse::MovedPermanently()
+            .insert_header((header::LOCATION, location))
+            .finish(),
+    }
+}
+
+#[instrument(name = "slug::handler::get_organization", skip_all)]
+pub async fn get_organization(path: web::Path<String>, pool: web::Data<DbPool>) -> HttpResponse {
+    let org_slug = path.into_inner();
+
+    // Resolve organization slug
+    let result = db::block(&pool, move |conn| {
+        SlugService::resolve_organization(conn, &org_slug).map_err(|e| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                format!("Organization not found: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(resolution) => respond(resolution),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "slug::handler::get_team", skip_all)]
+pub async fn get_team(path: web::Path<(String, String)>, pool: web::Data<DbPool>) -> HttpResponse {
+    let (org_slug, team_slug) = path.into_inner();
+
+    // Resolve team slug
+    let result = db::block(&pool, move |conn| {
+        SlugService::resolve_team(conn, &org_slug, &team_slug)
+            .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, format!("Team not found: {}", e)))
+    })
+    .await;
+
+    match result {
+        Ok(resolution) => respond(resolution),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "slug::handler::get_repository", skip_all)]
+pub async fn get_repository(
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    let (org_slug, repo_slug) = path.into_inner();
+
+    // Resolve repository slug
+    let result = db::block(&pool, move |conn| {
+        SlugService::resolve_repository(conn, &org_slug, &repo_slug).map_err(|e| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                format!("Repository not found: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(resolution) => respond(resolution),
+        Err(e) => e.error_response(),
+    }
+}
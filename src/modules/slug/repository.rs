@@ -0,0 +1,73 @@
+use crate::modules::slug::dto::SlugResource;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use std::error::Error;
+use tracing::instrument;
+use uuid::Uuid;
+
+pub struct SlugRepository;
+
+impl SlugRepository {
+    #[instrument(name = "SlugRepository::find_redirect", skip_all)]
+    pub fn find_redirect(
+        conn: &mut PgConnection,
+        resource: SlugResource,
+        scope: Option<Uuid>,
+        slug: &str,
+    ) -> Result<Option<Uuid>, Box<dyn Error>> {
+        use crate::schema::slug_redirects::dsl::*;
+
+        let mut query = slug_redirects
+            .filter(resource_type.eq(resource.as_str()))
+            .filter(old_slug.eq(slug))
+            .into_boxed();
+
+        query = match scope {
+            Some(scope) => query.filter(scope_id.eq(scope)),
+            None => query.filter(scope_id.is_null()),
+        };
+
+        let target = query
+            .order(created_at.desc())
+            .select(resource_id)
+            .first::<Uuid>(conn)
+            .optional()?;
+
+        Ok(target)
+    }
+
+    #[instrument(name = "SlugRepository::record_redirect", skip_all)]
+    pub fn record_redirect(
+        conn: &mut PgConnection,
+        resource: SlugResource,
+        scope: Option<Uuid>,
+        slug: &str,
+        target_id: Uuid,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::schema::slug_redirects::dsl::*;
+
+        // An old slug only ever points to the last resource that used it
+        let mut stale = diesel::delete(slug_redirects)
+            .filter(resource_type.eq(resource.as_str()))
+            .filter(old_slug.eq(slug))
+            .into_boxed();
+
+        stale = match scope {
+            Some(scope) => stale.filter(scope_id.eq(scope)),
+            None => stale.filter(scope_id.is_null()),
+        };
+
+        stale.execute(conn)?;
+
+        diesel::insert_into(slug_redirects)
+            .values((
+                resource_type.eq(resource.as_str()),
+                scope_id.eq(scope),
+                old_slug.eq(slug),
+                resource_id.eq(target_id),
+            ))
+            .execute(conn)?;
+
+        Ok(())
+    }
+}
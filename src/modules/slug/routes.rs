@@ -0,0 +1,14 @@
+use crate::modules::slug::handler::{get_organization, get_repository, get_team};
+use actix_web::web;
+
+pub fn config_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/orgs")
+            .route("/{org_slug}", web::get().to(get_organization))
+            .route("/{org_slug}/teams/{team_slug}", web::get().to(get_team))
+            .route(
+                "/{org_slug}/repos/{repo_slug}",
+                web::get().to(get_repository),
+            ),
+    );
+}
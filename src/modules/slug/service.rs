@@ -0,0 +1,121 @@
+use crate::models::{Organization, Repo, Team};
+use crate::modules::organization::repository::OrganizationRepository;
+use crate::modules::repo::repository::RepoRepository;
+use crate::modules::slug::dto::{SlugResolution, SlugResource};
+use crate::modules::slug::repository::SlugRepository;
+use crate::modules::team::repository::TeamRepository;
+use diesel::PgConnection;
+use std::error::Error;
+use tracing::instrument;
+
+pub struct SlugService;
+
+impl SlugService {
+    #[instrument(name = "SlugService::resolve_organization", skip_all)]
+    pub fn resolve_organization(
+        conn: &mut PgConnection,
+        org_slug: &str,
+    ) -> Result<SlugResolution<Organization>, Box<dyn Error>> {
+        let (organization, moved) = Self::find_organization(conn, org_slug)?;
+
+        if moved {
+            return Ok(SlugResolution::Moved(format!(
+                "/api/orgs/{}",
+                organization.slug
+            )));
+        }
+
+        Ok(SlugResolution::Found(organization))
+    }
+
+    #[instrument(name = "SlugService::resolve_team", skip_all)]
+    pub fn resolve_team(
+        conn: &mut PgConnection,
+        org_slug: &str,
+        team_slug: &str,
+    ) -> Result<SlugResolution<Team>, Box<dyn Error>> {
+        let (organization, org_moved) = Self::find_organization(conn, org_slug)?;
+
+        let (team, team_moved) =
+            match TeamRepository::find_by_slug(conn, organization.id, team_slug)? {
+                Some(team) => (team, false),
+                None => {
+                    let team_id = SlugRepository::find_redirect(
+                        conn,
+                        SlugResource::Team,
+                        Some(organization.id),
+                        team_slug,
+                    )?
+                    .ok_or("Team not found")?;
+
+                    (TeamRepository::find_by_id(conn, team_id)?, true)
+                }
+            };
+
+        // A team moved to another organization redirects there as well
+        if org_moved || team_moved {
+            let owner = OrganizationRepository::find_by_id(conn, team.organization_id)?;
+            return Ok(SlugResolution::Moved(format!(
+                "/api/orgs/{}/teams/{}",
+                owner.slug, team.slug
+            )));
+        }
+
+        Ok(SlugResolution::Found(team))
+    }
+
+    #[instrument(name = "SlugService::resolve_repository", skip_all)]
+    pub fn resolve_repository(
+        conn: &mut PgConnection,
+        org_slug: &str,
+        repo_slug: &str,
+    ) -> Result<SlugResolution<Repo>, Box<dyn Error>> {
+        let (organization, org_moved) = Self::find_organization(conn, org_slug)?;
+
+        let (repo, repo_moved) =
+            match RepoRepository::find_by_slug(conn, organization.id, repo_slug)? {
+                Some(repo) => (repo, false),
+                None => {
+                    let repo_id = SlugRepository::find_redirect(
+                        conn,
+                        SlugResource::Repository,
+                        Some(organization.id),
+                        repo_slug,
+                    )?
+                    .ok_or("Repository not found")?;
+
+                    (RepoRepository::find_by_id(conn, repo_id)?, true)
+                }
+            };
+
+        // A repository moved to another organization redirects there as well
+        if org_moved || repo_moved {
+            let owner = OrganizationRepository::find_by_id(conn, repo.organization_id)?;
+            return Ok(SlugResolution::Moved(format!(
+                "/api/orgs/{}/repos/{}",
+                owner.slug, repo.slug
+            )));
+        }
+
+        Ok(SlugResolution::Found(repo))
+    }
+
+    // Look up an organization by its current slug, then through old slugs
+    fn find_organization(
+        conn: &mut PgConnection,
+        org_slug: &str,
+    ) -> Result<(Organization, bool), Box<dyn Error>> {
+        if let Some(organization) = OrganizationRepository::find_by_slug(conn, org_slug)? {
+            return Ok((organization, false));
+        }
+
+        let organization_id =
+            SlugRepository::find_redirect(conn, SlugResource::Organization, None, org_slug)?
+                .ok_or("Organization not found")?;
+
+        Ok((
+            OrganizationRepository::find_by_id(conn, organization_id)?,
+            true,
+        ))
+    }
+}
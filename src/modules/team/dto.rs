@@ -17,6 +17,37 @@ pub struct TeamCreateQuery {
 
     #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
     pub organization_id: Uuid,
+
+    /// Generated from `name` when omitted
+    #[schema(example = "backend")]
+    #[diesel(skip_insertion)]
+    pub slug: Option<String>,
+}
+
+/// Body of `POST /organizations/{org_id}/teams`, the organization comes from the path.
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct OrganizationTeamCreateQuery {
+    #[schema(example = "Backend")]
+    #[validate(length(min = 3, max = 100))]
+    pub name: String,
+
+    #[schema(example = "Owns the API and the workers")]
+    pub description: Option<String>,
+
+    /// Generated from `name` when omitted
+    #[schema(example = "backend")]
+    pub slug: Option<String>,
+}
+
+impl OrganizationTeamCreateQuery {
+    pub fn into_create_query(self, organization_id: Uuid) -> TeamCreateQuery {
+        TeamCreateQuery {
+            name: self.name,
+            description: self.description,
+            organization_id,
+            slug: self.slug,
+        }
+    }
 }
 
 #[derive(AsChangeset, Deserialize, Validate, ToSchema)]
@@ -29,8 +60,8 @@ pub struct TeamUpdateQuery {
     #[schema(example = "A leading provider of innovative solutions worldwide")]
     pub description: Option<String>,
 
-    #[schema(example = "550e8400-e29b-41d4-a716-446655440000")]
-    pub organization_id: Option<Uuid>,
+    #[schema(example = "backend-platform")]
+    pub slug: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, ToSchema)]
@@ -1,63 +1,68 @@
-use crate::modules::team::dto::{AddUserToTeamQuery, TeamCreateQuery, TeamUpdateQuery};
+use crate::db;
+use crate::modules::audit::dto::AuditContext;
+use crate::modules::auth::handler::session_user;
+use crate::modules::organization::handler::require_member;
+use crate::modules::team::dto::{
+    AddUserToTeamQuery, OrganizationTeamCreateQuery, TeamCreateQuery, TeamUpdateQuery,
+};
 use crate::modules::team::service::TeamService;
-use crate::utils::response::{error, success};
+use crate::utils::response::{error, success, ApiError};
+use actix_identity::Identity;
 use actix_web::http::StatusCode;
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpResponse, ResponseError};
 use diesel::r2d2::{self, ConnectionManager};
 use diesel::PgConnection;
+use tracing::instrument;
 use validator::Validate;
 
 type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 
+#[instrument(name = "team::handler::get_all", skip_all)]
 pub async fn get_all(pool: web::Data<DbPool>) -> HttpResponse {
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
     // Get all teams
-    match TeamService::get_all(&mut conn) {
+    let result = db::block(&pool, |conn| {
+        TeamService::get_all(conn).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve teams: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(teams) => HttpResponse::Ok().json(success(StatusCode::OK, Some(teams))),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to retrieve teams: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
+#[instrument(name = "team::handler::get_by_id", skip_all)]
 pub async fn get_by_id(path: web::Path<uuid::Uuid>, pool: web::Data<DbPool>) -> HttpResponse {
     let id = path.into_inner();
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
     // Get team by ID
-    match TeamService::get_by_id(&mut conn, id) {
+    let result = db::block(&pool, move |conn| {
+        TeamService::get_by_id(conn, id).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve team: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(team) => HttpResponse::Ok().json(success(StatusCode::OK, Some(team))),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to retrieve team: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
+#[instrument(name = "team::handler::create", skip_all)]
 pub async fn create(
+    id: Identity,
     pool: web::Data<DbPool>,
     team_data: web::Json<TeamCreateQuery>,
+    audit: AuditContext,
 ) -> HttpResponse {
     // Validate team data
     if let Err(errors) = team_data.validate() {
@@ -67,31 +72,108 @@ pub async fn create(
         ));
     }
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
     };
 
     // Create team
-    match TeamService::create(&mut conn, &team_data) {
+    let result = db::block(&pool, move |conn| {
+        require_member(conn, user_id, team_data.organization_id)?;
+        TeamService::create(conn, &team_data, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Failed to create team: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(team) => HttpResponse::Created().json(success(StatusCode::CREATED, Some(team))),
-        Err(e) => HttpResponse::BadRequest().json(error(
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "team::handler::get_by_organization", skip_all)]
+pub async fn get_by_organization(
+    id: Identity,
+    path: web::Path<uuid::Uuid>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    let organization_id = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Get organization teams
+    let result = db::block(&pool, move |conn| {
+        require_member(conn, user_id, organization_id)?;
+        TeamService::get_by_organization(conn, organization_id).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve teams: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(teams) => HttpResponse::Ok().json(success(StatusCode::OK, Some(teams))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "team::handler::create_in_organization", skip_all)]
+pub async fn create_in_organization(
+    id: Identity,
+    path: web::Path<uuid::Uuid>,
+    pool: web::Data<DbPool>,
+    team_data: web::Json<OrganizationTeamCreateQuery>,
+    audit: AuditContext,
+) -> HttpResponse {
+    let organization_id = path.into_inner();
+
+    // Validate team data
+    if let Err(errors) = team_data.validate() {
+        return HttpResponse::BadRequest().json(error(
             StatusCode::BAD_REQUEST,
-            format!("Failed to create team: {}", e),
-        )),
+            format!("Validation error: {:?}", errors),
+        ));
+    }
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Create team in the organization
+    let team_data = team_data.into_inner().into_create_query(organization_id);
+    let result = db::block(&pool, move |conn| {
+        require_member(conn, user_id, organization_id)?;
+        TeamService::create(conn, &team_data, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Failed to create team: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(team) => HttpResponse::Created().json(success(StatusCode::CREATED, Some(team))),
+        Err(e) => e.error_response(),
     }
 }
 
+#[instrument(name = "team::handler::update", skip_all)]
 pub async fn update(
     path: web::Path<uuid::Uuid>,
     pool: web::Data<DbPool>,
     team_data: web::Json<TeamUpdateQuery>,
+    audit: AuditContext,
 ) -> HttpResponse {
     let id = path.into_inner();
 
@@ -103,75 +185,70 @@ pub async fn update(
         ));
     }
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
     // Update team
-    match TeamService::update(&mut conn, id, &team_data) {
+    let result = db::block(&pool, move |conn| {
+        TeamService::update(conn, id, &team_data, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to update team: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(team) => HttpResponse::Ok().json(success(StatusCode::OK, Some(team))),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to update team: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
-pub async fn delete(path: web::Path<uuid::Uuid>, pool: web::Data<DbPool>) -> HttpResponse {
+#[instrument(name = "team::handler::delete", skip_all)]
+pub async fn delete(
+    path: web::Path<uuid::Uuid>,
+    pool: web::Data<DbPool>,
+    audit: AuditContext,
+) -> HttpResponse {
     let id = path.into_inner();
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
     // Delete team
-    match TeamService::delete(&mut conn, id) {
+    let result = db::block(&pool, move |conn| {
+        TeamService::delete(conn, id, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to delete team: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(_) => HttpResponse::Ok().json(success::<()>(StatusCode::OK, None)),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to delete team: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
+#[instrument(name = "team::handler::add_user", skip_all)]
 pub async fn add_user(
     path: web::Path<uuid::Uuid>,
     pool: web::Data<DbPool>,
     user_data: web::Json<AddUserToTeamQuery>,
+    audit: AuditContext,
 ) -> HttpResponse {
     let id = path.into_inner();
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
     // Add user to team
-    match TeamService::add_user(&mut conn, id, &user_data) {
+    let result = db::block(&pool, move |conn| {
+        TeamService::add_user(conn, id, &user_data, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to add user to team: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(_) => HttpResponse::Ok().json(success::<()>(StatusCode::OK, None)),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to add user to team: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
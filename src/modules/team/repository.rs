@@ -1,9 +1,12 @@
 use crate::models::Team;
+use crate::modules::slug::dto::SlugResource;
+use crate::modules::slug::repository::SlugRepository;
 use crate::modules::team::dto::{TeamCreateQuery, TeamUpdateQuery};
 use chrono::Utc;
 use diesel::prelude::*;
 use diesel::PgConnection;
 use std::error::Error;
+use tracing::instrument;
 use uuid::Uuid;
 
 use super::dto::AddUserToTeamQuery;
@@ -11,6 +14,7 @@ use super::dto::AddUserToTeamQuery;
 pub struct TeamRepository;
 
 impl TeamRepository {
+    #[instrument(name = "TeamRepository::find_by_id", skip_all)]
     pub fn find_by_id(conn: &mut PgConnection, team_id: Uuid) -> Result<Team, Box<dyn Error>> {
         use crate::schema::teams::dsl::*;
 
@@ -22,6 +26,49 @@ impl TeamRepository {
         Ok(team)
     }
 
+    #[instrument(name = "TeamRepository::find_by_slug", skip_all)]
+    pub fn find_by_slug(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        team_slug: &str,
+    ) -> Result<Option<Team>, Box<dyn Error>> {
+        use crate::schema::teams::dsl::*;
+
+        let team = teams
+            .filter(organization_id.eq(org_id))
+            .filter(slug.eq(team_slug))
+            .filter(deleted_at.is_null())
+            .first::<Team>(conn)
+            .optional()?;
+
+        Ok(team)
+    }
+
+    #[instrument(name = "TeamRepository::slug_taken", skip_all)]
+    pub fn slug_taken(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        team_slug: &str,
+        exclude_id: Option<Uuid>,
+    ) -> Result<bool, Box<dyn Error>> {
+        use crate::schema::teams::dsl::*;
+
+        let mut query = teams
+            .filter(organization_id.eq(org_id))
+            .filter(slug.eq(team_slug))
+            .filter(deleted_at.is_null())
+            .into_boxed();
+
+        if let Some(exclude_id) = exclude_id {
+            query = query.filter(id.ne(exclude_id));
+        }
+
+        let taken = diesel::select(diesel::dsl::exists(query)).get_result::<bool>(conn)?;
+
+        Ok(taken)
+    }
+
+    #[instrument(name = "TeamRepository::find_all", skip_all)]
     pub fn find_all(conn: &mut PgConnection) -> Result<Vec<Team>, Box<dyn Error>> {
         use crate::schema::teams::dsl::*;
 
@@ -30,18 +77,36 @@ impl TeamRepository {
         Ok(all_teams)
     }
 
+    #[instrument(name = "TeamRepository::find_by_organization", skip_all)]
+    pub fn find_by_organization(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+    ) -> Result<Vec<Team>, Box<dyn Error>> {
+        use crate::schema::teams::dsl::*;
+
+        let organization_teams = teams
+            .filter(organization_id.eq(org_id))
+            .filter(deleted_at.is_null())
+            .load::<Team>(conn)?;
+
+        Ok(organization_teams)
+    }
+
+    #[instrument(name = "TeamRepository::create", skip_all)]
     pub fn create(
         conn: &mut PgConnection,
         new_team: &TeamCreateQuery,
+        new_slug: &str,
     ) -> Result<Team, Box<dyn Error>> {
         use crate::schema::teams::dsl::*;
 
         diesel::insert_into(teams)
-            .values(new_team)
+            .values((new_team, slug.eq(new_slug)))
             .get_result::<Team>(conn)
             .map_err(|e| e.into())
     }
 
+    #[instrument(name = "TeamRepository::update", skip_all)]
     pub fn update(
         conn: &mut PgConnection,
         team_id: Uuid,
@@ -49,14 +114,31 @@ impl TeamRepository {
     ) -> Result<Team, Box<dyn Error>> {
         use crate::schema::teams::dsl::*;
 
-        diesel::update(teams)
-            .filter(id.eq(team_id))
-            .filter(deleted_at.is_null())
-            .set(update_data)
-            .get_result::<Team>(conn)
-            .map_err(|e| e.into())
+        conn.transaction(|conn| {
+            let current = Self::find_by_id(conn, team_id)?;
+
+            let team = diesel::update(teams)
+                .filter(id.eq(team_id))
+                .filter(deleted_at.is_null())
+                .set(update_data)
+                .get_result::<Team>(conn)?;
+
+            // Keep the previous slug resolving to this team
+            if team.slug != current.slug {
+                SlugRepository::record_redirect(
+                    conn,
+                    SlugResource::Team,
+                    Some(current.organization_id),
+                    &current.slug,
+                    team.id,
+                )?;
+            }
+
+            Ok(team)
+        })
     }
 
+    #[instrument(name = "TeamRepository::delete", skip_all)]
     pub fn delete(conn: &mut PgConnection, team_id: Uuid) -> Result<(), Box<dyn Error>> {
         use crate::schema::teams::dsl::*;
 
@@ -69,6 +151,7 @@ impl TeamRepository {
         Ok(())
     }
 
+    #[instrument(name = "TeamRepository::add_user", skip_all)]
     pub fn add_user(
         conn: &mut PgConnection,
         team_id: Uuid,
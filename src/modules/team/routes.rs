@@ -1,4 +1,7 @@
-use crate::modules::team::handler::{add_user, create, delete, get_all, get_by_id, update};
+use crate::modules::team::handler::{
+    add_user, create, create_in_organization, delete, get_all, get_by_id, get_by_organization,
+    update,
+};
 use actix_web::web;
 
 pub fn config_routes(cfg: &mut web::ServiceConfig) {
@@ -12,3 +15,9 @@ pub fn config_routes(cfg: &mut web::ServiceConfig) {
             .route("/{id}/users", web::post().to(add_user)),
     );
 }
+
+// Mounted inside the `/organizations` scope
+pub fn config_organization_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/{org_id}/teams", web::get().to(get_by_organization))
+        .route("/{org_id}/teams", web::post().to(create_in_organization));
+}
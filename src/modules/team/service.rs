@@ -1,42 +1,166 @@
 use crate::models::Team;
+use crate::modules::audit::dto::{AuditAction, AuditContext};
+use crate::modules::audit::service::AuditService;
+use crate::modules::organization::repository::OrganizationRepository;
+use crate::modules::outbox::dto::DomainEvent;
+use crate::modules::outbox::service::OutboxService;
 use crate::modules::team::dto::{AddUserToTeamQuery, TeamCreateQuery, TeamUpdateQuery};
 use crate::modules::team::repository::TeamRepository;
-use diesel::PgConnection;
+use crate::utils::slug::{unique_slug, validate_slug};
+use diesel::{Connection, PgConnection};
+use serde_json::json;
 use std::error::Error;
+use tracing::instrument;
 use uuid::Uuid;
 
 pub struct TeamService;
 
 impl TeamService {
+    #[instrument(name = "TeamService::get_all", skip_all)]
     pub fn get_all(conn: &mut PgConnection) -> Result<Vec<Team>, Box<dyn Error>> {
         TeamRepository::find_all(conn)
     }
 
+    #[instrument(name = "TeamService::get_by_id", skip_all)]
     pub fn get_by_id(conn: &mut PgConnection, team_id: Uuid) -> Result<Team, Box<dyn Error>> {
         TeamRepository::find_by_id(conn, team_id)
     }
 
-    pub fn create(conn: &mut PgConnection, data: &TeamCreateQuery) -> Result<Team, Box<dyn Error>> {
-        TeamRepository::create(conn, data)
+    #[instrument(name = "TeamService::get_by_organization", skip_all)]
+    pub fn get_by_organization(
+        conn: &mut PgConnection,
+        organization_id: Uuid,
+    ) -> Result<Vec<Team>, Box<dyn Error>> {
+        TeamRepository::find_by_organization(conn, organization_id)
+    }
+
+    #[instrument(name = "TeamService::create", skip_all)]
+    pub fn create(
+        conn: &mut PgConnection,
+        data: &TeamCreateQuery,
+        audit: &AuditContext,
+    ) -> Result<Team, Box<dyn Error>> {
+        // The owning organization must exist and not be deleted
+        OrganizationRepository::find_by_id(conn, data.organization_id)
+            .map_err(|_| "Organization not found")?;
+
+        let slug = match &data.slug {
+            Some(slug) => {
+                validate_slug(slug)?;
+                if TeamRepository::slug_taken(conn, data.organization_id, slug, None)? {
+                    return Err(format!("Slug '{}' is already taken", slug).into());
+                }
+                slug.clone()
+            }
+            None => unique_slug(&data.name, "team", |candidate| {
+                TeamRepository::slug_taken(conn, data.organization_id, candidate, None)
+            })?,
+        };
+
+        conn.transaction(|conn| {
+            let team = TeamRepository::create(conn, data, &slug)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::TeamCreated,
+                Some(team.organization_id),
+                Some(team.id),
+                AuditService::diff(None, Some(&team))?,
+            )?;
+            OutboxService::record(conn, &DomainEvent::TeamCreated { team: team.clone() })?;
+
+            Ok(team)
+        })
     }
 
+    #[instrument(name = "TeamService::update", skip_all)]
     pub fn update(
         conn: &mut PgConnection,
         team_id: Uuid,
         data: &TeamUpdateQuery,
+        audit: &AuditContext,
     ) -> Result<Team, Box<dyn Error>> {
-        TeamRepository::update(conn, team_id, data)
+        // Slugs are unique per organization
+        if let Some(slug) = &data.slug {
+            validate_slug(slug)?;
+            let current = TeamRepository::find_by_id(conn, team_id)?;
+            if TeamRepository::slug_taken(conn, current.organization_id, slug, Some(team_id))? {
+                return Err(format!("Slug '{}' is already taken", slug).into());
+            }
+        }
+
+        conn.transaction(|conn| {
+            let before = TeamRepository::find_by_id(conn, team_id)?;
+            let team = TeamRepository::update(conn, team_id, data)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::TeamUpdated,
+                Some(team.organization_id),
+                Some(team.id),
+                AuditService::diff(Some(&before), Some(&team))?,
+            )?;
+            OutboxService::record(conn, &DomainEvent::TeamUpdated { team: team.clone() })?;
+
+            Ok(team)
+        })
     }
 
-    pub fn delete(conn: &mut PgConnection, team_id: Uuid) -> Result<(), Box<dyn Error>> {
-        TeamRepository::delete(conn, team_id)
+    #[instrument(name = "TeamService::delete", skip_all)]
+    pub fn delete(
+        conn: &mut PgConnection,
+        team_id: Uuid,
+        audit: &AuditContext,
+    ) -> Result<(), Box<dyn Error>> {
+        conn.transaction(|conn| {
+            let team = TeamRepository::find_by_id(conn, team_id)?;
+            TeamRepository::delete(conn, team_id)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::TeamDeleted,
+                Some(team.organization_id),
+                Some(team.id),
+                AuditService::diff(Some(&team), None)?,
+            )?;
+            OutboxService::record(conn, &DomainEvent::TeamDeleted { team })?;
+
+            Ok(())
+        })
     }
 
+    #[instrument(name = "TeamService::add_user", skip_all)]
     pub fn add_user(
         conn: &mut PgConnection,
         team_id: Uuid,
         user_data: &AddUserToTeamQuery,
+        audit: &AuditContext,
     ) -> Result<(), Box<dyn Error>> {
-        TeamRepository::add_user(conn, team_id, user_data)
+        conn.transaction(|conn| {
+            let team = TeamRepository::find_by_id(conn, team_id)?;
+            TeamRepository::add_user(conn, team_id, user_data)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::TeamMemberAdded,
+                Some(team.organization_id),
+                Some(user_data.user_id),
+                AuditService::diff(
+                    None,
+                    Some(&json!({ "team_id": team.id, "role": user_data.role })),
+                )?,
+            )?;
+            OutboxService::record(
+                conn,
+                &DomainEvent::TeamMemberAdded {
+                    organization_id: team.organization_id,
+                    team_id: team.id,
+                    user_id: user_data.user_id,
+                    role: user_data.role.clone(),
+                },
+            )?;
+
+            Ok(())
+        })
     }
 }
@@ -1,16 +1,20 @@
-use crate::modules::user::service::UserService;
+use crate::db;
+use crate::modules::audit::dto::AuditContext;
 use crate::modules::user::dto::UserUpdateQuery;
-use crate::utils::response::{error, success};
+use crate::modules::user::service::UserService;
+use crate::utils::response::{error, success, ApiError};
 use actix_identity::Identity;
 use actix_web::http::StatusCode;
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpResponse, ResponseError};
 use diesel::r2d2::{self, ConnectionManager};
 use diesel::PgConnection;
+use tracing::instrument;
 use uuid::Uuid;
 use validator::Validate;
 
 type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 
+#[instrument(name = "user::handler::get_me", skip_all)]
 pub async fn get_me(id: Identity, pool: web::Data<DbPool>) -> HttpResponse {
     // Get user ID from session
     let user_id = match id.id() {
@@ -32,31 +36,25 @@ pub async fn get_me(id: Identity, pool: web::Data<DbPool>) -> HttpResponse {
         }
     };
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
     // Get user
-    match UserService::get_by_id(&mut conn, uuid) {
+    let result = db::block(&pool, move |conn| {
+        UserService::get_by_id(conn, uuid)
+            .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, format!("User not found: {}", e)))
+    })
+    .await;
+
+    match result {
         Ok(user) => HttpResponse::Ok().json(success(StatusCode::OK, Some(user))),
-        Err(e) => HttpResponse::NotFound().json(error(
-            StatusCode::NOT_FOUND,
-            format!("User not found: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
+#[instrument(name = "user::handler::update_me", skip_all)]
 pub async fn update_me(
     id: Identity,
     pool: web::Data<DbPool>,
     user_data: web::Json<UserUpdateQuery>,
+    audit: AuditContext,
 ) -> HttpResponse {
     // Validate update data
     if let Err(errors) = user_data.validate() {
@@ -86,69 +84,56 @@ pub async fn update_me(
         }
     };
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
     // Update user
-    match UserService::update_user(&mut conn, uuid, &user_data) {
+    let user_data = user_data.into_inner();
+    let result = db::block(&pool, move |conn| {
+        UserService::update_user(conn, uuid, &user_data, &audit).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to update user: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(user) => HttpResponse::Ok().json(success(StatusCode::OK, Some(user))),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to update user: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
+#[instrument(name = "user::handler::get_all", skip_all)]
 pub async fn get_all(pool: web::Data<DbPool>) -> HttpResponse {
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
     // Get all users
-    match UserService::get_all(&mut conn) {
+    let result = db::block(&pool, |conn| {
+        UserService::get_all(conn).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve users: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
         Ok(users) => HttpResponse::Ok().json(success(StatusCode::OK, Some(users))),
-        Err(e) => HttpResponse::InternalServerError().json(error(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to retrieve users: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
 
+#[instrument(name = "user::handler::get_by_id", skip_all)]
 pub async fn get_by_id(path: web::Path<uuid::Uuid>, pool: web::Data<DbPool>) -> HttpResponse {
     let id = path.into_inner();
 
-    // Get DB connection
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            return HttpResponse::ServiceUnavailable().json(error(
-                StatusCode::SERVICE_UNAVAILABLE,
-                "Database connection error".into(),
-            ));
-        }
-    };
-
     // Get user
-    match UserService::get_by_id(&mut conn, id) {
+    let result = db::block(&pool, move |conn| {
+        UserService::get_by_id(conn, id)
+            .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, format!("User not found: {}", e)))
+    })
+    .await;
+
+    match result {
         Ok(user) => HttpResponse::Ok().json(success(StatusCode::OK, Some(user))),
-        Err(e) => HttpResponse::NotFound().json(error(
-            StatusCode::NOT_FOUND,
-            format!("User not found: {}", e),
-        )),
+        Err(e) => e.error_response(),
     }
 }
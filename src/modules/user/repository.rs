@@ -3,11 +3,13 @@ use crate::modules::user::dto::UserUpdateQuery;
 use diesel::prelude::*;
 use diesel::PgConnection;
 use std::error::Error;
+use tracing::instrument;
 use uuid::Uuid;
 
 pub struct UserRepository;
 
 impl UserRepository {
+    #[instrument(name = "UserRepository::find_by_id", skip_all)]
     pub fn find_by_id(conn: &mut PgConnection, user_id: Uuid) -> Result<User, Box<dyn Error>> {
         use crate::schema::users::dsl::*;
 
@@ -19,6 +21,7 @@ impl UserRepository {
         Ok(user)
     }
 
+    #[instrument(name = "UserRepository::find_all", skip_all)]
     pub fn find_all(conn: &mut PgConnection) -> Result<Vec<User>, Box<dyn Error>> {
         use crate::schema::users::dsl::*;
 
@@ -27,6 +30,7 @@ impl UserRepository {
         Ok(all_users)
     }
 
+    #[instrument(name = "UserRepository::update", skip_all)]
     pub fn update(
         conn: &mut PgConnection,
         user_id: Uuid,
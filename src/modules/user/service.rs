@@ -1,26 +1,46 @@
 use crate::models::User;
-use crate::modules::user::repository::UserRepository;
+use crate::modules::audit::dto::{AuditAction, AuditContext};
+use crate::modules::audit::service::AuditService;
 use crate::modules::user::dto::UserUpdateQuery;
-use diesel::PgConnection;
+use crate::modules::user::repository::UserRepository;
+use diesel::{Connection, PgConnection};
 use std::error::Error;
+use tracing::instrument;
 use uuid::Uuid;
 
 pub struct UserService;
 
 impl UserService {
+    #[instrument(name = "UserService::get_by_id", skip_all)]
     pub fn get_by_id(conn: &mut PgConnection, user_id: Uuid) -> Result<User, Box<dyn Error>> {
         UserRepository::find_by_id(conn, user_id)
     }
 
+    #[instrument(name = "UserService::get_all", skip_all)]
     pub fn get_all(conn: &mut PgConnection) -> Result<Vec<User>, Box<dyn Error>> {
         UserRepository::find_all(conn)
     }
 
+    #[instrument(name = "UserService::update_user", skip_all)]
     pub fn update_user(
         conn: &mut PgConnection,
         user_id: Uuid,
         data: &UserUpdateQuery,
+        audit: &AuditContext,
     ) -> Result<User, Box<dyn Error>> {
-        UserRepository::update(conn, user_id, data)
+        conn.transaction(|conn| {
+            let before = UserRepository::find_by_id(conn, user_id)?;
+            let user = UserRepository::update(conn, user_id, data)?;
+            AuditService::record(
+                conn,
+                audit,
+                AuditAction::UserUpdated,
+                None,
+                Some(user.id),
+                AuditService::diff(Some(&before), Some(&user))?,
+            )?;
+
+            Ok(user)
+        })
     }
 }
@@ -0,0 +1,274 @@
+use crate::schema::{organization_webhook_deliveries, organization_webhooks};
+use crate::utils::envelope::Envelope;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+/// Changes integrators can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    OrganizationUpdated,
+    OrganizationDeleted,
+    OrganizationRestored,
+    MemberAdded,
+    TeamCreated,
+    TeamUpdated,
+    TeamDeleted,
+    TeamMemberAdded,
+    RepositoryCreated,
+    RepositoryUpdated,
+    RepositoryDeleted,
+    RepositoryTransferred,
+    HostKeyChanged,
+    /// Sent on demand to check a receiver, whatever its filter
+    Ping,
+}
+
+impl WebhookEvent {
+    pub const ALL: &'static [WebhookEvent] = &[
+        WebhookEvent::OrganizationUpdated,
+        WebhookEvent::OrganizationDeleted,
+        WebhookEvent::OrganizationRestored,
+        WebhookEvent::MemberAdded,
+        WebhookEvent::TeamCreated,
+        WebhookEvent::TeamUpdated,
+        WebhookEvent::TeamDeleted,
+        WebhookEvent::TeamMemberAdded,
+        WebhookEvent::RepositoryCreated,
+        WebhookEvent::RepositoryUpdated,
+        WebhookEvent::RepositoryDeleted,
+        WebhookEvent::RepositoryTransferred,
+        WebhookEvent::HostKeyChanged,
+        WebhookEvent::Ping,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::OrganizationUpdated => "organization.updated",
+            WebhookEvent::OrganizationDeleted => "organization.deleted",
+            WebhookEvent::OrganizationRestored => "organization.restored",
+            WebhookEvent::MemberAdded => "member.added",
+            WebhookEvent::TeamCreated => "team.created",
+            WebhookEvent::TeamUpdated => "team.updated",
+            WebhookEvent::TeamDeleted => "team.deleted",
+            WebhookEvent::TeamMemberAdded => "team.member_added",
+            WebhookEvent::RepositoryCreated => "repository.created",
+            WebhookEvent::RepositoryUpdated => "repository.updated",
+            WebhookEvent::RepositoryDeleted => "repository.deleted",
+            WebhookEvent::RepositoryTransferred => "repository.transferred",
+            WebhookEvent::HostKeyChanged => "host_key.changed",
+            WebhookEvent::Ping => "ping",
+        }
+    }
+
+    /// Whether a subscription filter entry, an event type or a `prefix.*`
+    /// pattern, selects this event.
+    pub fn matches(&self, filter: &str) -> bool {
+        match filter.strip_suffix(".*") {
+            Some(prefix) => self
+                .as_str()
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with('.')),
+            None => filter == self.as_str(),
+        }
+    }
+}
+
+impl fmt::Display for WebhookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    /// Waiting for its first attempt or a retry
+    Pending,
+    Succeeded,
+    /// Gave up after the last attempt
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Succeeded => "succeeded",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+impl fmt::Display for DeliveryStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+fn validate_events(events: &[String]) -> Result<(), ValidationError> {
+    for filter in events {
+        if !WebhookEvent::ALL.iter().any(|event| event.matches(filter)) {
+            let mut error = ValidationError::new("unknown_event");
+            error.message = Some(format!("Unknown event: {}", filter).into());
+            return Err(error);
+        }
+    }
+
+    Ok(())
+}
+
+// Deliveries are only sent over HTTP(S)
+fn validate_scheme(url: &str) -> Result<(), ValidationError> {
+    let scheme = url.split("://").next().unwrap_or_default();
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        let mut error = ValidationError::new("scheme");
+        error.message = Some("Webhook URL must use http or https".into());
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct WebhookCreateQuery {
+    #[schema(example = "https://ci.example.com/hooks/scylla")]
+    #[validate(url, length(max = 2048), custom(function = "validate_scheme"))]
+    pub url: String,
+
+    /// Event types or `prefix.*` patterns, every event when empty
+    #[schema(example = json!(["repository.*", "team.created"]))]
+    #[validate(custom(function = "validate_events"))]
+    pub events: Option<Vec<String>>,
+
+    /// Generated when omitted
+    #[validate(length(min = 16, max = 256))]
+    pub secret: Option<String>,
+
+    pub active: Option<bool>,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct WebhookUpdateQuery {
+    #[validate(url, length(max = 2048), custom(function = "validate_scheme"))]
+    pub url: Option<String>,
+
+    #[validate(custom(function = "validate_events"))]
+    pub events: Option<Vec<String>>,
+
+    /// Replaces the signing secret
+    #[validate(length(min = 16, max = 256))]
+    pub secret: Option<String>,
+
+    pub active: Option<bool>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = organization_webhooks)]
+pub struct NewWebhook {
+    pub organization_id: Uuid,
+    pub url: String,
+    pub events: Vec<String>,
+    pub active: bool,
+    pub encrypted_secret: Vec<u8>,
+    pub secret_nonce: Vec<u8>,
+    pub encrypted_data_key: Vec<u8>,
+    pub data_key_nonce: Vec<u8>,
+    pub created_by: Option<Uuid>,
+}
+
+#[derive(AsChangeset)]
+#[diesel(table_name = organization_webhooks)]
+pub struct WebhookChanges {
+    pub url: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub active: Option<bool>,
+    pub encrypted_secret: Option<Vec<u8>>,
+    pub secret_nonce: Option<Vec<u8>>,
+    pub encrypted_data_key: Option<Vec<u8>>,
+    pub data_key_nonce: Option<Vec<u8>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl WebhookChanges {
+    pub fn new(data: &WebhookUpdateQuery, secret: Option<Envelope>) -> Self {
+        let (encrypted_secret, secret_nonce, encrypted_data_key, data_key_nonce) = match secret {
+            Some(envelope) => (
+                Some(envelope.ciphertext),
+                Some(envelope.nonce),
+                Some(envelope.encrypted_data_key),
+                Some(envelope.data_key_nonce),
+            ),
+            None => (None, None, None, None),
+        };
+
+        WebhookChanges {
+            url: data.url.clone(),
+            events: data.events.clone(),
+            active: data.active,
+            encrypted_secret,
+            secret_nonce,
+            encrypted_data_key,
+            data_key_nonce,
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+/// A new webhook with its secret, which is only ever returned here.
+#[derive(Serialize, ToSchema, Debug)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: crate::models::OrganizationWebhook,
+    pub secret: String,
+}
+
+/// Body of every delivery.
+#[derive(Serialize, ToSchema, Debug)]
+pub struct WebhookEnvelope<T: Serialize> {
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub organization_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub data: T,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = organization_webhook_deliveries)]
+pub struct NewDelivery {
+    pub webhook_id: Uuid,
+    pub event_id: Uuid,
+    pub event_type: String,
+    pub payload: String,
+    pub status: String,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+}
+
+/// Outcome of one attempt.
+#[derive(AsChangeset)]
+#[diesel(table_name = organization_webhook_deliveries)]
+#[diesel(treat_none_as_null = true)]
+pub struct AttemptResult {
+    pub status: String,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub request_excerpt: Option<String>,
+    pub response_status: Option<i32>,
+    pub response_excerpt: Option<String>,
+    pub error: Option<String>,
+    pub duration_ms: Option<i64>,
+}
+
+/// A claimed delivery, ready to be signed and sent.
+pub struct OutgoingDelivery {
+    pub id: Uuid,
+    pub url: String,
+    pub event_type: String,
+    pub payload: String,
+    pub attempts: i32,
+    pub secret: zeroize::Zeroizing<Vec<u8>>,
+}
@@ -0,0 +1,309 @@
+use crate::config::Config;
+use crate::db;
+use crate::modules::auth::handler::session_user;
+use crate::modules::organization::handler::{require_manager, require_member};
+use crate::modules::webhook::dto::{WebhookCreateQuery, WebhookUpdateQuery};
+use crate::modules::webhook::service::WebhookService;
+use crate::utils::envelope::MasterKey;
+use crate::utils::response::{error, success, ApiError};
+use actix_identity::Identity;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use diesel::r2d2::{self, ConnectionManager};
+use diesel::PgConnection;
+use tracing::instrument;
+use uuid::Uuid;
+use validator::Validate;
+
+type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+
+// Webhook secrets are encrypted with the deploy key master key
+fn master_key(config: &Config) -> Result<MasterKey, HttpResponse> {
+    MasterKey::from_base64(&config.deploy_keys.master_key).map_err(|e| {
+        HttpResponse::ServiceUnavailable().json(error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("Webhook secrets are not configured: {}", e),
+        ))
+    })
+}
+
+#[instrument(name = "webhook::handler::get_all", skip_all)]
+pub async fn get_all(id: Identity, path: web::Path<Uuid>, pool: web::Data<DbPool>) -> HttpResponse {
+    let org_id = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Get webhooks
+    let result = db::block(&pool, move |conn| {
+        require_member(conn, user_id, org_id)?;
+        WebhookService::get_by_organization(conn, org_id).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to retrieve webhooks: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(webhooks) => HttpResponse::Ok().json(success(StatusCode::OK, Some(webhooks))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "webhook::handler::get_by_id", skip_all)]
+pub async fn get_by_id(
+    id: Identity,
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    let (org_id, webhook_id) = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Get webhook
+    let result = db::block(&pool, move |conn| {
+        require_member(conn, user_id, org_id)?;
+        WebhookService::get_by_id(conn, org_id, webhook_id)
+            .map_err(|e| ApiError::new(StatusCode::NOT_FOUND, format!("Webhook not found: {}", e)))
+    })
+    .await;
+
+    match result {
+        Ok(webhook) => HttpResponse::Ok().json(success(StatusCode::OK, Some(webhook))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "webhook::handler::create", skip_all)]
+pub async fn create(
+    id: Identity,
+    path: web::Path<Uuid>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    webhook_data: web::Json<WebhookCreateQuery>,
+) -> HttpResponse {
+    let org_id = path.into_inner();
+
+    // Validate webhook data
+    if let Err(errors) = webhook_data.validate() {
+        return HttpResponse::BadRequest().json(error(
+            StatusCode::BAD_REQUEST,
+            format!("Validation error: {:?}", errors),
+        ));
+    }
+
+    let master_key = match master_key(&config) {
+        Ok(master_key) => master_key,
+        Err(response) => return response,
+    };
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Create webhook, returning its secret once
+    let result = db::block(&pool, move |conn| {
+        require_manager(conn, user_id, org_id)?;
+        WebhookService::create(conn, org_id, &webhook_data, &master_key, user_id).map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to create webhook: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(webhook) => HttpResponse::Created().json(success(StatusCode::CREATED, Some(webhook))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "webhook::handler::update", skip_all)]
+pub async fn update(
+    id: Identity,
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    webhook_data: web::Json<WebhookUpdateQuery>,
+) -> HttpResponse {
+    let (org_id, webhook_id) = path.into_inner();
+
+    // Validate webhook data
+    if let Err(errors) = webhook_data.validate() {
+        return HttpResponse::BadRequest().json(error(
+            StatusCode::BAD_REQUEST,
+            format!("Validation error: {:?}", errors),
+        ));
+    }
+
+    // Only a new secret needs the master key
+    let master_key = match &webhook_data.secret {
+        Some(_) => match master_key(&config) {
+            Ok(master_key) => Some(master_key),
+            Err(response) => return response,
+        },
+        None => None,
+    };
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Update webhook
+    let result = db::block(&pool, move |conn| {
+        require_manager(conn, user_id, org_id)?;
+        WebhookService::update(conn, org_id, webhook_id, &webhook_data, master_key.as_ref())
+            .map_err(|e| {
+                ApiError::new(
+                    StatusCode::NOT_FOUND,
+                    format!("Failed to update webhook: {}", e),
+                )
+            })
+    })
+    .await;
+
+    match result {
+        Ok(webhook) => HttpResponse::Ok().json(success(StatusCode::OK, Some(webhook))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "webhook::handler::delete", skip_all)]
+pub async fn delete(
+    id: Identity,
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    let (org_id, webhook_id) = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Delete webhook along with its deliveries
+    let result = db::block(&pool, move |conn| {
+        require_manager(conn, user_id, org_id)?;
+        WebhookService::delete(conn, org_id, webhook_id).map_err(|e| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                format!("Failed to delete webhook: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(success::<()>(StatusCode::OK, None)),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "webhook::handler::ping", skip_all)]
+pub async fn ping(
+    id: Identity,
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    let (org_id, webhook_id) = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    let result = db::block(&pool, move |conn| {
+        require_manager(conn, user_id, org_id)?;
+
+        WebhookService::get_by_id(conn, org_id, webhook_id).map_err(|e| {
+            ApiError::new(StatusCode::NOT_FOUND, format!("Webhook not found: {}", e))
+        })?;
+
+        // Queue a ping delivery
+        WebhookService::ping(conn, org_id, webhook_id).map_err(|e| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Failed to ping webhook: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(delivery) => {
+            HttpResponse::Accepted().json(success(StatusCode::ACCEPTED, Some(delivery)))
+        }
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "webhook::handler::get_deliveries", skip_all)]
+pub async fn get_deliveries(
+    id: Identity,
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    let (org_id, webhook_id) = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Get latest deliveries
+    let result = db::block(&pool, move |conn| {
+        require_manager(conn, user_id, org_id)?;
+        WebhookService::get_deliveries(conn, org_id, webhook_id).map_err(|e| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                format!("Failed to retrieve deliveries: {}", e),
+            )
+        })
+    })
+    .await;
+
+    match result {
+        Ok(deliveries) => HttpResponse::Ok().json(success(StatusCode::OK, Some(deliveries))),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[instrument(name = "webhook::handler::redeliver", skip_all)]
+pub async fn redeliver(
+    id: Identity,
+    path: web::Path<(Uuid, Uuid, Uuid)>,
+    pool: web::Data<DbPool>,
+) -> HttpResponse {
+    let (org_id, webhook_id, delivery_id) = path.into_inner();
+
+    let user_id = match session_user(&id) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.error_response(),
+    };
+
+    // Queue the stored payload again
+    let result = db::block(&pool, move |conn| {
+        require_manager(conn, user_id, org_id)?;
+        WebhookService::redeliver(conn, org_id, webhook_id, delivery_id).map_err(|e| {
+            ApiError::new(StatusCode::NOT_FOUND, format!("Failed to redeliver: {}", e))
+        })
+    })
+    .await;
+
+    match result {
+        Ok(delivery) => {
+            HttpResponse::Accepted().json(success(StatusCode::ACCEPTED, Some(delivery)))
+        }
+        Err(e) => e.error_response(),
+    }
+}
@@ -0,0 +1,224 @@
+use crate::models::{OrganizationWebhook, OrganizationWebhookDelivery};
+use crate::modules::webhook::dto::{
+    AttemptResult, DeliveryStatus, NewDelivery, NewWebhook, WebhookChanges,
+};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use std::error::Error;
+use tracing::instrument;
+use uuid::Uuid;
+
+pub struct WebhookRepository;
+
+impl WebhookRepository {
+    #[instrument(name = "WebhookRepository::find_by_organization", skip_all)]
+    pub fn find_by_organization(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+    ) -> Result<Vec<OrganizationWebhook>, Box<dyn Error>> {
+        use crate::schema::organization_webhooks::dsl::*;
+
+        let webhooks = organization_webhooks
+            .filter(organization_id.eq(org_id))
+            .order(created_at.asc())
+            .load::<OrganizationWebhook>(conn)?;
+
+        Ok(webhooks)
+    }
+
+    #[instrument(name = "WebhookRepository::find_active", skip_all)]
+    pub fn find_active(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+    ) -> Result<Vec<OrganizationWebhook>, Box<dyn Error>> {
+        use crate::schema::organization_webhooks::dsl::*;
+
+        let webhooks = organization_webhooks
+            .filter(organization_id.eq(org_id))
+            .filter(active.eq(true))
+            .load::<OrganizationWebhook>(conn)?;
+
+        Ok(webhooks)
+    }
+
+    #[instrument(name = "WebhookRepository::find_by_id", skip_all)]
+    pub fn find_by_id(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<OrganizationWebhook, Box<dyn Error>> {
+        use crate::schema::organization_webhooks::dsl::*;
+
+        organization_webhooks
+            .filter(id.eq(webhook_id))
+            .filter(organization_id.eq(org_id))
+            .first::<OrganizationWebhook>(conn)
+            .optional()?
+            .ok_or_else(|| "Webhook not found".into())
+    }
+
+    #[instrument(name = "WebhookRepository::create", skip_all)]
+    pub fn create(
+        conn: &mut PgConnection,
+        new_webhook: &NewWebhook,
+    ) -> Result<OrganizationWebhook, Box<dyn Error>> {
+        use crate::schema::organization_webhooks::dsl::*;
+
+        diesel::insert_into(organization_webhooks)
+            .values(new_webhook)
+            .get_result::<OrganizationWebhook>(conn)
+            .map_err(|e| e.into())
+    }
+
+    #[instrument(name = "WebhookRepository::update", skip_all)]
+    pub fn update(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        webhook_id: Uuid,
+        changes: &WebhookChanges,
+    ) -> Result<OrganizationWebhook, Box<dyn Error>> {
+        use crate::schema::organization_webhooks::dsl::*;
+
+        diesel::update(organization_webhooks)
+            .filter(id.eq(webhook_id))
+            .filter(organization_id.eq(org_id))
+            .set(changes)
+            .get_result::<OrganizationWebhook>(conn)
+            .optional()?
+            .ok_or_else(|| "Webhook not found".into())
+    }
+
+    #[instrument(name = "WebhookRepository::delete", skip_all)]
+    pub fn delete(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<(), Box<dyn Error>> {
+        use crate::schema::organization_webhooks::dsl::*;
+
+        let deleted = diesel::delete(organization_webhooks)
+            .filter(id.eq(webhook_id))
+            .filter(organization_id.eq(org_id))
+            .execute(conn)?;
+        if deleted == 0 {
+            return Err("Webhook not found".into());
+        }
+
+        Ok(())
+    }
+
+    #[instrument(name = "WebhookRepository::find_deliveries", skip_all)]
+    pub fn find_deliveries(
+        conn: &mut PgConnection,
+        hook_id: Uuid,
+        limit: i64,
+    ) -> Result<Vec<OrganizationWebhookDelivery>, Box<dyn Error>> {
+        use crate::schema::organization_webhook_deliveries::dsl::*;
+
+        let deliveries = organization_webhook_deliveries
+            .filter(webhook_id.eq(hook_id))
+            .order(created_at.desc())
+            .limit(limit)
+            .load::<OrganizationWebhookDelivery>(conn)?;
+
+        Ok(deliveries)
+    }
+
+    #[instrument(name = "WebhookRepository::find_delivery", skip_all)]
+    pub fn find_delivery(
+        conn: &mut PgConnection,
+        hook_id: Uuid,
+        delivery_id: Uuid,
+    ) -> Result<OrganizationWebhookDelivery, Box<dyn Error>> {
+        use crate::schema::organization_webhook_deliveries::dsl::*;
+
+        organization_webhook_deliveries
+            .filter(id.eq(delivery_id))
+            .filter(webhook_id.eq(hook_id))
+            .first::<OrganizationWebhookDelivery>(conn)
+            .optional()?
+            .ok_or_else(|| "Delivery not found".into())
+    }
+
+    #[instrument(name = "WebhookRepository::create_deliveries", skip_all)]
+    pub fn create_deliveries(
+        conn: &mut PgConnection,
+        new_deliveries: &[NewDelivery],
+    ) -> Result<Vec<OrganizationWebhookDelivery>, Box<dyn Error>> {
+        use crate::schema::organization_webhook_deliveries::dsl::*;
+
+        diesel::insert_into(organization_webhook_deliveries)
+            .values(new_deliveries)
+            .get_results::<OrganizationWebhookDelivery>(conn)
+            .map_err(|e| e.into())
+    }
+
+    /// Deliveries still to be attempted, due or not.
+    #[instrument(name = "WebhookRepository::count_pending", skip_all)]
+    pub fn count_pending(conn: &mut PgConnection) -> Result<i64, Box<dyn Error>> {
+        use crate::schema::organization_webhook_deliveries::dsl::*;
+
+        organization_webhook_deliveries
+            .filter(status.eq(DeliveryStatus::Pending.as_str()))
+            .count()
+            .get_result(conn)
+            .map_err(|e| e.into())
+    }
+
+    /// Takes up to `limit` due deliveries of active webhooks and pushes
+    /// their next attempt to `lease_until`, so no other worker picks them
+    /// up while they are being sent.
+    #[instrument(name = "WebhookRepository::claim_due", skip_all)]
+    pub fn claim_due(
+        conn: &mut PgConnection,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<(OrganizationWebhookDelivery, OrganizationWebhook)>, Box<dyn Error>> {
+        use crate::schema::organization_webhook_deliveries::dsl::*;
+        use crate::schema::organization_webhooks;
+
+        conn.transaction(|conn| {
+            let due = organization_webhook_deliveries
+                .inner_join(organization_webhooks::table)
+                .filter(status.eq(DeliveryStatus::Pending.as_str()))
+                .filter(next_attempt_at.le(now))
+                .filter(organization_webhooks::active.eq(true))
+                .order(next_attempt_at.asc())
+                .limit(limit)
+                .select(id)
+                .for_update()
+                .skip_locked()
+                .load::<Uuid>(conn)?;
+
+            diesel::update(organization_webhook_deliveries.filter(id.eq_any(&due)))
+                .set(next_attempt_at.eq(lease_until))
+                .execute(conn)?;
+
+            organization_webhook_deliveries
+                .inner_join(organization_webhooks::table)
+                .filter(id.eq_any(&due))
+                .select((
+                    OrganizationWebhookDelivery::as_select(),
+                    OrganizationWebhook::as_select(),
+                ))
+                .load::<(OrganizationWebhookDelivery, OrganizationWebhook)>(conn)
+        })
+        .map_err(|e| e.into())
+    }
+
+    #[instrument(name = "WebhookRepository::record_attempt", skip_all)]
+    pub fn record_attempt(
+        conn: &mut PgConnection,
+        delivery_id: Uuid,
+        result: &AttemptResult,
+    ) -> Result<OrganizationWebhookDelivery, Box<dyn Error>> {
+        use crate::schema::organization_webhook_deliveries::dsl::*;
+
+        diesel::update(organization_webhook_deliveries.filter(id.eq(delivery_id)))
+            .set((result, attempts.eq(attempts + 1)))
+            .get_result::<OrganizationWebhookDelivery>(conn)
+            .map_err(|e| e.into())
+    }
+}
@@ -0,0 +1,22 @@
+use crate::modules::webhook::handler::{
+    create, delete, get_all, get_by_id, get_deliveries, ping, redeliver, update,
+};
+use actix_web::web;
+
+// Mounted inside the `/organizations` scope
+pub fn config_organization_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/{id}/webhooks", web::get().to(get_all))
+        .route("/{id}/webhooks", web::post().to(create))
+        .route("/{id}/webhooks/{webhook_id}", web::get().to(get_by_id))
+        .route("/{id}/webhooks/{webhook_id}", web::put().to(update))
+        .route("/{id}/webhooks/{webhook_id}", web::delete().to(delete))
+        .route("/{id}/webhooks/{webhook_id}/ping", web::post().to(ping))
+        .route(
+            "/{id}/webhooks/{webhook_id}/deliveries",
+            web::get().to(get_deliveries),
+        )
+        .route(
+            "/{id}/webhooks/{webhook_id}/deliveries/{delivery_id}/redeliver",
+            web::post().to(redeliver),
+        );
+}
@@ -0,0 +1,421 @@
+use crate::config::WebhookConfig;
+use crate::models::{OrganizationWebhook, OrganizationWebhookDelivery, OutboxEvent};
+use crate::modules::outbox::dto::DomainEvent;
+use crate::modules::webhook::dto::{
+    AttemptResult, CreatedWebhook, DeliveryStatus, NewDelivery, NewWebhook, OutgoingDelivery,
+    WebhookChanges, WebhookCreateQuery, WebhookEnvelope, WebhookEvent, WebhookUpdateQuery,
+};
+use crate::modules::webhook::repository::WebhookRepository;
+use crate::utils::envelope::{Envelope, MasterKey};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use chrono::{DateTime, Duration, Utc};
+use diesel::PgConnection;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json::json;
+use sha2::Sha256;
+use std::error::Error;
+use tracing::instrument;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const EVENT_HEADER: &str = "X-Scylla-Event";
+pub const DELIVERY_HEADER: &str = "X-Scylla-Delivery";
+pub const TIMESTAMP_HEADER: &str = "X-Scylla-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-Scylla-Signature";
+
+const SECRET_BYTES: usize = 32;
+const DELIVERY_LIST_LIMIT: i64 = 100;
+pub const EXCERPT_BYTES: usize = 2048;
+const MAX_BACKOFF_SECONDS: i64 = 6 * 60 * 60;
+
+pub struct WebhookService;
+
+impl WebhookService {
+    #[instrument(name = "WebhookService::get_by_organization", skip_all)]
+    pub fn get_by_organization(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+    ) -> Result<Vec<OrganizationWebhook>, Box<dyn Error>> {
+        WebhookRepository::find_by_organization(conn, org_id)
+    }
+
+    #[instrument(name = "WebhookService::get_by_id", skip_all)]
+    pub fn get_by_id(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<OrganizationWebhook, Box<dyn Error>> {
+        WebhookRepository::find_by_id(conn, org_id, webhook_id)
+    }
+
+    /// Creates a webhook, generating its secret when none is given. The
+    /// secret is only ever returned here.
+    #[instrument(name = "WebhookService::create", skip_all)]
+    pub fn create(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        data: &WebhookCreateQuery,
+        master_key: &MasterKey,
+        created_by: Uuid,
+    ) -> Result<CreatedWebhook, Box<dyn Error>> {
+        let secret = match &data.secret {
+            Some(secret) => secret.clone(),
+            None => {
+                let mut bytes = Zeroizing::new([0u8; SECRET_BYTES]);
+                OsRng.fill_bytes(bytes.as_mut_slice());
+                hex::encode(bytes.as_slice())
+            }
+        };
+        let envelope = master_key.seal(secret.as_bytes(), org_id.as_bytes())?;
+
+        let new_webhook = NewWebhook {
+            organization_id: org_id,
+            url: data.url.clone(),
+            events: data.events.clone().unwrap_or_default(),
+            active: data.active.unwrap_or(true),
+            encrypted_secret: envelope.ciphertext,
+            secret_nonce: envelope.nonce,
+            encrypted_data_key: envelope.encrypted_data_key,
+            data_key_nonce: envelope.data_key_nonce,
+            created_by: Some(created_by),
+        };
+        let webhook = WebhookRepository::create(conn, &new_webhook)?;
+
+        Ok(CreatedWebhook { webhook, secret })
+    }
+
+    /// Updates a webhook. The master key is only needed to replace the
+    /// secret.
+    #[instrument(name = "WebhookService::update", skip_all)]
+    pub fn update(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        webhook_id: Uuid,
+        data: &WebhookUpdateQuery,
+        master_key: Option<&MasterKey>,
+    ) -> Result<OrganizationWebhook, Box<dyn Error>> {
+        let envelope = match (&data.secret, master_key) {
+            (Some(secret), Some(master_key)) => {
+                Some(master_key.seal(secret.as_bytes(), org_id.as_bytes())?)
+            }
+            (Some(_), None) => return Err("Webhook secrets are not configured".into()),
+            (None, _) => None,
+        };
+
+        WebhookRepository::update(
+            conn,
+            org_id,
+            webhook_id,
+            &WebhookChanges::new(data, envelope),
+        )
+    }
+
+    #[instrument(name = "WebhookService::delete", skip_all)]
+    pub fn delete(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<(), Box<dyn Error>> {
+        WebhookRepository::delete(conn, org_id, webhook_id)
+    }
+
+    #[instrument(name = "WebhookService::get_deliveries", skip_all)]
+    pub fn get_deliveries(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<Vec<OrganizationWebhookDelivery>, Box<dyn Error>> {
+        let webhook = WebhookRepository::find_by_id(conn, org_id, webhook_id)?;
+        WebhookRepository::find_deliveries(conn, webhook.id, DELIVERY_LIST_LIMIT)
+    }
+
+    /// Queues the payload of a past delivery again, as a new delivery
+    /// with the same event id.
+    #[instrument(name = "WebhookService::redeliver", skip_all)]
+    pub fn redeliver(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        webhook_id: Uuid,
+        delivery_id: Uuid,
+    ) -> Result<OrganizationWebhookDelivery, Box<dyn Error>> {
+        let webhook = WebhookRepository::find_by_id(conn, org_id, webhook_id)?;
+        let delivery = WebhookRepository::find_delivery(conn, webhook.id, delivery_id)?;
+
+        let new_delivery = NewDelivery {
+            webhook_id: webhook.id,
+            event_id: delivery.event_id,
+            event_type: delivery.event_type,
+            payload: delivery.payload,
+            status: DeliveryStatus::Pending.to_string(),
+            next_attempt_at: Some(Utc::now()),
+        };
+
+        WebhookRepository::create_deliveries(conn, &[new_delivery])?
+            .pop()
+            .ok_or_else(|| "Failed to queue delivery".into())
+    }
+
+    /// Queues a `ping` event for one webhook, whatever its filter.
+    #[instrument(name = "WebhookService::ping", skip_all)]
+    pub fn ping(
+        conn: &mut PgConnection,
+        org_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<OrganizationWebhookDelivery, Box<dyn Error>> {
+        let webhook = WebhookRepository::find_by_id(conn, org_id, webhook_id)?;
+        if !webhook.active {
+            return Err("Webhook is not active".into());
+        }
+
+        let envelope = WebhookEnvelope {
+            id: Uuid::new_v4(),
+            event_type: WebhookEvent::Ping.to_string(),
+            organization_id: org_id,
+            created_at: Utc::now(),
+            data: json!({ "webhook_id": webhook.id, "url": webhook.url }),
+        };
+        Self::enqueue(conn, &[webhook], &envelope)?
+            .pop()
+            .ok_or_else(|| "Failed to queue delivery".into())
+    }
+
+    /// Queues a domain event for the active webhooks of its organizations
+    /// that subscribe to it. The outbox event id is the envelope id, so
+    /// receivers can deduplicate.
+    #[instrument(name = "WebhookService::publish", skip_all)]
+    pub fn publish(
+        conn: &mut PgConnection,
+        outbox_event: &OutboxEvent,
+        event: &DomainEvent,
+    ) -> Result<(), Box<dyn Error>> {
+        let (webhook_event, org_ids) = match event {
+            DomainEvent::OrganizationUpdated { organization } => {
+                (WebhookEvent::OrganizationUpdated, vec![organization.id])
+            }
+            DomainEvent::OrganizationDeleted { organization } => {
+                (WebhookEvent::OrganizationDeleted, vec![organization.id])
+            }
+            DomainEvent::OrganizationRestored { organization } => {
+                (WebhookEvent::OrganizationRestored, vec![organization.id])
+            }
+            DomainEvent::MemberAdded {
+                organization_id, ..
+            } => (WebhookEvent::MemberAdded, vec![*organization_id]),
+            DomainEvent::TeamCreated { team } => {
+                (WebhookEvent::TeamCreated, vec![team.organization_id])
+            }
+            DomainEvent::TeamUpdated { team } => {
+                (WebhookEvent::TeamUpdated, vec![team.organization_id])
+            }
+            DomainEvent::TeamDeleted { team } => {
+                (WebhookEvent::TeamDeleted, vec![team.organization_id])
+            }
+            DomainEvent::TeamMemberAdded {
+                organization_id, ..
+            } => (WebhookEvent::TeamMemberAdded, vec![*organization_id]),
+            DomainEvent::RepositoryCreated { repository } => (
+                WebhookEvent::RepositoryCreated,
+                vec![repository.organization_id],
+            ),
+            DomainEvent::RepositoryUpdated { repository } => (
+                WebhookEvent::RepositoryUpdated,
+                vec![repository.organization_id],
+            ),
+            DomainEvent::RepositoryDeleted { repository } => (
+                WebhookEvent::RepositoryDeleted,
+                vec![repository.organization_id],
+            ),
+            // Both organizations hear about a transfer
+            DomainEvent::RepositoryTransferred {
+                repository,
+                previous_organization_id,
+            } => (
+                WebhookEvent::RepositoryTransferred,
+                vec![*previous_organization_id, repository.organization_id],
+            ),
+            DomainEvent::HostKeyChanged {
+                organization_id, ..
+            } => (WebhookEvent::HostKeyChanged, vec![*organization_id]),
+            // A new organization has no webhooks yet, users belong to none
+            DomainEvent::OrganizationCreated { .. } | DomainEvent::UserVerified { .. } => {
+                return Ok(())
+            }
+        };
+
+        let data = event.data()?;
+        for org_id in org_ids {
+            let subscribed: Vec<OrganizationWebhook> =
+                WebhookRepository::find_active(conn, org_id)?
+                    .into_iter()
+                    .filter(|webhook| {
+                        webhook.events.is_empty()
+                            || webhook
+                                .events
+                                .iter()
+                                .any(|filter| webhook_event.matches(filter))
+                    })
+                    .collect();
+
+            let envelope = WebhookEnvelope {
+                id: outbox_event.id,
+                event_type: webhook_event.to_string(),
+                organization_id: org_id,
+                created_at: outbox_event.created_at,
+                data: &data,
+            };
+            Self::enqueue(conn, &subscribed, &envelope)?;
+        }
+
+        Ok(())
+    }
+
+    /// Claims due deliveries and decrypts the secrets to sign them with.
+    /// A delivery whose secret cannot be decrypted fails right away.
+    #[instrument(name = "WebhookService::claim", skip_all)]
+    pub fn claim(
+        conn: &mut PgConnection,
+        master_key: &MasterKey,
+        config: &WebhookConfig,
+    ) -> Result<Vec<OutgoingDelivery>, Box<dyn Error>> {
+        let now = Utc::now();
+        // Outlive the request timeout, so a delivery is not sent twice
+        let lease_until = now + Duration::seconds(config.timeout_seconds as i64 * 2 + 30);
+
+        let claimed = WebhookRepository::claim_due(conn, now, lease_until, config.batch_size)?;
+
+        let mut outgoing = Vec::with_capacity(claimed.len());
+        for (delivery, webhook) in claimed {
+            let secret = master_key.open(
+                &Envelope {
+                    ciphertext: webhook.encrypted_secret,
+                    nonce: webhook.secret_nonce,
+                    encrypted_data_key: webhook.encrypted_data_key,
+                    data_key_nonce: webhook.data_key_nonce,
+                },
+                webhook.organization_id.as_bytes(),
+            );
+
+            match secret {
+                Ok(secret) => outgoing.push(OutgoingDelivery {
+                    id: delivery.id,
+                    url: webhook.url,
+                    event_type: delivery.event_type,
+                    payload: delivery.payload,
+                    attempts: delivery.attempts,
+                    secret,
+                }),
+                Err(e) => {
+                    let result = AttemptResult {
+                        status: DeliveryStatus::Failed.to_string(),
+                        next_attempt_at: None,
+                        last_attempt_at: Some(now),
+                        request_excerpt: None,
+                        response_status: None,
+                        response_excerpt: None,
+                        error: Some(format!("Failed to decrypt webhook secret: {}", e)),
+                        duration_ms: None,
+                    };
+                    WebhookRepository::record_attempt(conn, delivery.id, &result)?;
+                }
+            }
+        }
+
+        Ok(outgoing)
+    }
+
+    /// Stores the outcome of an attempt. Anything but a 2xx response is
+    /// retried with exponential backoff until `max_attempts` is reached.
+    #[instrument(name = "WebhookService::record_attempt", skip_all)]
+    pub fn record_attempt(
+        conn: &mut PgConnection,
+        delivery: &OutgoingDelivery,
+        mut result: AttemptResult,
+        config: &WebhookConfig,
+    ) -> Result<OrganizationWebhookDelivery, Box<dyn Error>> {
+        let attempt = delivery.attempts + 1;
+        let succeeded = result
+            .response_status
+            .is_some_and(|code| (200..300).contains(&code));
+
+        let (status, next_attempt_at) = if succeeded {
+            (DeliveryStatus::Succeeded, None)
+        } else if attempt >= config.max_attempts {
+            (DeliveryStatus::Failed, None)
+        } else {
+            let retry_at = Self::retry_at(
+                result.last_attempt_at.unwrap_or_else(Utc::now),
+                config.backoff_seconds,
+                attempt,
+            );
+            (DeliveryStatus::Pending, Some(retry_at))
+        };
+
+        result.status = status.to_string();
+        result.next_attempt_at = next_attempt_at;
+
+        WebhookRepository::record_attempt(conn, delivery.id, &result)
+    }
+
+    /// Hex HMAC-SHA256 of `"{timestamp}.{payload}"`. Signing the timestamp
+    /// lets receivers reject replayed deliveries.
+    #[instrument(name = "WebhookService::sign", skip_all)]
+    pub fn sign(secret: &[u8], timestamp: i64, payload: &str) -> String {
+        let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts any key length");
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(payload.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Start of a request or response body, kept in the delivery log.
+    #[instrument(name = "WebhookService::excerpt", skip_all)]
+    pub fn excerpt(bytes: &[u8]) -> String {
+        let end = bytes.len().min(EXCERPT_BYTES);
+        String::from_utf8_lossy(&bytes[..end]).into_owned()
+    }
+
+    fn enqueue<T: Serialize>(
+        conn: &mut PgConnection,
+        webhooks: &[OrganizationWebhook],
+        envelope: &WebhookEnvelope<T>,
+    ) -> Result<Vec<OrganizationWebhookDelivery>, Box<dyn Error>> {
+        if webhooks.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let payload = serde_json::to_string(envelope)?;
+        let now = Utc::now();
+
+        let new_deliveries: Vec<NewDelivery> = webhooks
+            .iter()
+            .map(|webhook| NewDelivery {
+                webhook_id: webhook.id,
+                event_id: envelope.id,
+                event_type: envelope.event_type.clone(),
+                payload: payload.clone(),
+                status: DeliveryStatus::Pending.to_string(),
+                next_attempt_at: Some(now),
+            })
+            .collect();
+
+        WebhookRepository::create_deliveries(conn, &new_deliveries)
+    }
+
+    /// When to retry after failed attempt number `attempt`, counted from 1.
+    /// The delay doubles with each attempt, up to six hours.
+    pub fn retry_at(
+        last_attempt: DateTime<Utc>,
+        backoff_seconds: i64,
+        attempt: i32,
+    ) -> DateTime<Utc> {
+        let factor = 1i64 << (attempt - 1).clamp(0, 20);
+        let delay = backoff_seconds
+            .saturating_mul(factor)
+            .min(MAX_BACKOFF_SECONDS);
+        last_attempt + Duration::seconds(delay)
+    }
+}
@@ -27,12 +27,109 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    audit_events (id) {
+        id -> Uuid,
+        seq -> Int8,
+        organization_id -> Nullable<Uuid>,
+        actor_id -> Nullable<Uuid>,
+        #[max_length = 100]
+        action -> Varchar,
+        #[max_length = 50]
+        target_type -> Varchar,
+        target_id -> Nullable<Uuid>,
+        changes -> Nullable<Jsonb>,
+        #[max_length = 45]
+        ip -> Nullable<Varchar>,
+        user_agent -> Nullable<Text>,
+        #[max_length = 100]
+        request_id -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    deploy_keys (id) {
+        id -> Uuid,
+        repository_id -> Uuid,
+        public_key -> Text,
+        #[max_length = 100]
+        fingerprint -> Varchar,
+        encrypted_private_key -> Bytea,
+        private_key_nonce -> Bytea,
+        encrypted_data_key -> Bytea,
+        data_key_nonce -> Bytea,
+        created_at -> Timestamptz,
+        expires_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    known_hosts (id) {
+        id -> Uuid,
+        organization_id -> Uuid,
+        #[max_length = 255]
+        host -> Varchar,
+        #[max_length = 50]
+        key_type -> Varchar,
+        public_key -> Text,
+        #[max_length = 100]
+        fingerprint -> Varchar,
+        #[max_length = 20]
+        status -> Varchar,
+        created_at -> Timestamptz,
+        confirmed_at -> Nullable<Timestamptz>,
+        confirmed_by -> Nullable<Uuid>,
+    }
+}
+
 diesel::table! {
     organization_users (organization_id, user_id) {
         organization_id -> Uuid,
         user_id -> Uuid,
         #[max_length = 50]
         role -> Nullable<Varchar>,
+        deleted_at -> Nullable<Timestamptz>,
+        deletion_batch_id -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    organization_webhook_deliveries (id) {
+        id -> Uuid,
+        webhook_id -> Uuid,
+        event_id -> Uuid,
+        #[max_length = 100]
+        event_type -> Varchar,
+        payload -> Text,
+        #[max_length = 20]
+        status -> Varchar,
+        attempts -> Int4,
+        next_attempt_at -> Nullable<Timestamptz>,
+        last_attempt_at -> Nullable<Timestamptz>,
+        request_excerpt -> Nullable<Text>,
+        response_status -> Nullable<Int4>,
+        response_excerpt -> Nullable<Text>,
+        error -> Nullable<Text>,
+        duration_ms -> Nullable<Int8>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    organization_webhooks (id) {
+        id -> Uuid,
+        organization_id -> Uuid,
+        url -> Text,
+        events -> Array<Text>,
+        active -> Bool,
+        encrypted_secret -> Bytea,
+        secret_nonce -> Bytea,
+        encrypted_data_key -> Bytea,
+        data_key_nonce -> Bytea,
+        created_by -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
     }
 }
 
@@ -45,6 +142,34 @@ diesel::table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         deleted_at -> Nullable<Timestamptz>,
+        deletion_batch_id -> Nullable<Uuid>,
+        #[max_length = 100]
+        slug -> Varchar,
+    }
+}
+
+diesel::table! {
+    outbox_events (id) {
+        id -> Uuid,
+        #[max_length = 100]
+        event_type -> Varchar,
+        organization_id -> Nullable<Uuid>,
+        payload -> Jsonb,
+        attempts -> Int4,
+        next_attempt_at -> Timestamptz,
+        last_error -> Nullable<Text>,
+        dispatched_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        dead_lettered_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    outbox_receipts (event_id, subscriber) {
+        event_id -> Uuid,
+        #[max_length = 50]
+        subscriber -> Varchar,
+        handled_at -> Timestamptz,
     }
 }
 
@@ -63,6 +188,41 @@ diesel::table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         deleted_at -> Nullable<Timestamptz>,
+        deletion_batch_id -> Nullable<Uuid>,
+        #[max_length = 100]
+        slug -> Varchar,
+        #[max_length = 10]
+        protocol -> Nullable<Varchar>,
+        #[max_length = 255]
+        owner_path -> Nullable<Varchar>,
+        #[max_length = 255]
+        remote_key -> Nullable<Varchar>,
+        reachable -> Nullable<Bool>,
+        #[max_length = 255]
+        default_branch -> Nullable<Varchar>,
+        ref_count -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        last_checked_at -> Nullable<Timestamptz>,
+        sync_interval_minutes -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    repository_mirrors (repository_id) {
+        repository_id -> Uuid,
+        #[max_length = 20]
+        status -> Varchar,
+        path -> Text,
+        #[max_length = 255]
+        default_branch -> Nullable<Varchar>,
+        size_bytes -> Nullable<Int8>,
+        last_error -> Nullable<Text>,
+        sync_started_at -> Timestamptz,
+        last_synced_at -> Nullable<Timestamptz>,
+        last_duration_ms -> Nullable<Int8>,
+        next_sync_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
     }
 }
 
@@ -78,12 +238,36 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    slug_redirects (id) {
+        id -> Uuid,
+        #[max_length = 50]
+        resource_type -> Varchar,
+        scope_id -> Nullable<Uuid>,
+        #[max_length = 100]
+        old_slug -> Varchar,
+        resource_id -> Uuid,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    team_repositories (team_id, repository_id) {
+        team_id -> Uuid,
+        repository_id -> Uuid,
+        #[max_length = 50]
+        permission -> Varchar,
+    }
+}
+
 diesel::table! {
     team_users (team_id, user_id) {
         team_id -> Uuid,
         user_id -> Uuid,
         #[max_length = 50]
         role -> Nullable<Varchar>,
+        deleted_at -> Nullable<Timestamptz>,
+        deletion_batch_id -> Nullable<Uuid>,
     }
 }
 
@@ -97,6 +281,9 @@ diesel::table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         deleted_at -> Nullable<Timestamptz>,
+        deletion_batch_id -> Nullable<Uuid>,
+        #[max_length = 100]
+        slug -> Varchar,
     }
 }
 
@@ -132,24 +319,80 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    webhook_deliveries (id) {
+        id -> Uuid,
+        repository_id -> Uuid,
+        #[max_length = 20]
+        provider -> Varchar,
+        #[max_length = 255]
+        delivery_id -> Nullable<Varchar>,
+        #[max_length = 100]
+        event_name -> Varchar,
+        event -> Nullable<Jsonb>,
+        payload -> Text,
+        #[max_length = 20]
+        status -> Varchar,
+        error -> Nullable<Text>,
+        attempts -> Int4,
+        received_at -> Timestamptz,
+        processed_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    webhook_secrets (repository_id) {
+        repository_id -> Uuid,
+        encrypted_secret -> Bytea,
+        secret_nonce -> Bytea,
+        encrypted_data_key -> Bytea,
+        data_key_nonce -> Bytea,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::joinable!(accounts -> users (user_id));
+diesel::joinable!(deploy_keys -> repositories (repository_id));
+diesel::joinable!(known_hosts -> organizations (organization_id));
+diesel::joinable!(known_hosts -> users (confirmed_by));
 diesel::joinable!(organization_users -> organizations (organization_id));
 diesel::joinable!(organization_users -> users (user_id));
+diesel::joinable!(organization_webhook_deliveries -> organization_webhooks (webhook_id));
+diesel::joinable!(organization_webhooks -> organizations (organization_id));
+diesel::joinable!(organization_webhooks -> users (created_by));
+diesel::joinable!(outbox_receipts -> outbox_events (event_id));
 diesel::joinable!(repositories -> organizations (organization_id));
+diesel::joinable!(repository_mirrors -> repositories (repository_id));
 diesel::joinable!(reset_password_tokens -> users (user_id));
+diesel::joinable!(team_repositories -> repositories (repository_id));
+diesel::joinable!(team_repositories -> teams (team_id));
 diesel::joinable!(team_users -> teams (team_id));
 diesel::joinable!(team_users -> users (user_id));
 diesel::joinable!(teams -> organizations (organization_id));
 diesel::joinable!(verification_tokens -> users (user_id));
+diesel::joinable!(webhook_deliveries -> repositories (repository_id));
+diesel::joinable!(webhook_secrets -> repositories (repository_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     accounts,
+    audit_events,
+    deploy_keys,
+    known_hosts,
     organization_users,
+    organization_webhook_deliveries,
+    organization_webhooks,
     organizations,
+    outbox_events,
+    outbox_receipts,
     repositories,
+    repository_mirrors,
     reset_password_tokens,
+    slug_redirects,
+    team_repositories,
     team_users,
     teams,
     users,
     verification_tokens,
+    webhook_deliveries,
+    webhook_secrets,
 );
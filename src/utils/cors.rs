@@ -0,0 +1,112 @@
+use crate::config::CorsConfig;
+use actix_cors::Cors;
+use url::Url;
+
+/// An entry of `cors.allowed_origins`: an exact origin such as
+/// `https://app.example.com`, or `https://*.example.com` matching any
+/// subdomain of `example.com` but not `example.com` itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AllowedOrigin {
+    Exact(String),
+    Subdomains {
+        scheme: String,
+        /// `.example.com`
+        suffix: String,
+        port: Option<u16>,
+    },
+}
+
+impl AllowedOrigin {
+    pub fn parse(origin: &str) -> Result<Self, String> {
+        if origin == "*" {
+            return Err("`*` would let any site make authenticated requests".to_string());
+        }
+
+        let (scheme, authority) = origin
+            .split_once("://")
+            .ok_or_else(|| format!("'{}' is not an origin like https://example.com", origin))?;
+        if scheme != "http" && scheme != "https" {
+            return Err(format!("'{}' must use http or https", origin));
+        }
+
+        let (wildcard, authority) = match authority.strip_prefix("*.") {
+            Some(authority) => (true, authority),
+            None => (false, authority),
+        };
+        if authority.contains('*') {
+            return Err(format!(
+                "'{}' may only have a wildcard as its first label, like https://*.example.com",
+                origin
+            ));
+        }
+
+        // Browsers send the serialized origin, anything else never matches
+        let written = format!("{}://{}", scheme, authority);
+        let url = Url::parse(&written).map_err(|e| format!("'{}': {}", origin, e))?;
+        let normalized = url.origin().ascii_serialization();
+        if normalized != written {
+            return Err(format!(
+                "'{}' is not an origin, did you mean '{}'?",
+                origin,
+                normalized.replacen("://", if wildcard { "://*." } else { "://" }, 1)
+            ));
+        }
+
+        match (wildcard, url.host_str()) {
+            (true, Some(host)) => Ok(AllowedOrigin::Subdomains {
+                scheme: scheme.to_string(),
+                suffix: format!(".{}", host),
+                port: url.port(),
+            }),
+            _ => Ok(AllowedOrigin::Exact(normalized)),
+        }
+    }
+
+    pub fn is_https(&self) -> bool {
+        match self {
+            AllowedOrigin::Exact(origin) => origin.starts_with("https://"),
+            AllowedOrigin::Subdomains { scheme, .. } => scheme == "https",
+        }
+    }
+
+    /// Whether the `Origin` header of a request is allowed.
+    pub fn matches(&self, origin: &str) -> bool {
+        match self {
+            AllowedOrigin::Exact(allowed) => allowed == origin,
+            AllowedOrigin::Subdomains {
+                scheme,
+                suffix,
+                port,
+            } => Url::parse(origin).is_ok_and(|url| {
+                url.scheme() == scheme
+                    && url.port() == *port
+                    && url.origin().ascii_serialization() == origin
+                    && url
+                        .host_str()
+                        .is_some_and(|host| host.len() > suffix.len() && host.ends_with(suffix))
+            }),
+        }
+    }
+}
+
+/// CORS middleware for the configured origins, methods and headers.
+/// Credentials are always allowed, since the session lives in a cookie.
+pub fn cors(config: &CorsConfig) -> Cors {
+    // Entries were validated with the configuration
+    let origins: Vec<AllowedOrigin> = config
+        .allowed_origins
+        .iter()
+        .filter_map(|origin| AllowedOrigin::parse(origin).ok())
+        .collect();
+
+    Cors::default()
+        .allowed_origin_fn(move |origin, _| {
+            origin
+                .to_str()
+                .is_ok_and(|origin| origins.iter().any(|allowed| allowed.matches(origin)))
+        })
+        .allowed_methods(config.allowed_methods.iter().map(String::as_str))
+        .allowed_headers(config.allowed_headers.iter().map(String::as_str))
+        .supports_credentials()
+        .max_age(config.max_age_seconds as usize)
+}
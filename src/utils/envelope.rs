@@ -0,0 +1,112 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::error::Error;
+use zeroize::Zeroizing;
+
+/// A secret encrypted with its own data key, the data key itself being
+/// encrypted with the server-side master key. Both are bound to the
+/// associated data given when sealing, typically the id of the owning row, so
+/// an envelope copied to another row no longer opens.
+pub struct Envelope {
+    pub ciphertext: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub encrypted_data_key: Vec<u8>,
+    pub data_key_nonce: Vec<u8>,
+}
+
+/// Server-side key encryption key, configured as 32 base64-encoded bytes.
+pub struct MasterKey(Key<Aes256Gcm>);
+
+impl MasterKey {
+    pub fn from_base64(encoded: &str) -> Result<Self, Box<dyn Error>> {
+        let bytes = Zeroizing::new(
+            STANDARD
+                .decode(encoded.trim())
+                .map_err(|_| "Master key is not valid base64")?,
+        );
+        if bytes.len() != 32 {
+            return Err("Master key must be 32 bytes".into());
+        }
+
+        Ok(MasterKey(*Key::<Aes256Gcm>::from_slice(&bytes)))
+    }
+
+    pub fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Result<Envelope, Box<dyn Error>> {
+        let mut data_key = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(data_key.as_mut_slice());
+
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(data_key.as_slice()))
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .map_err(|_| "Failed to encrypt secret")?;
+
+        let data_key_nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let encrypted_data_key = Aes256Gcm::new(&self.0)
+            .encrypt(
+                &data_key_nonce,
+                Payload {
+                    msg: data_key.as_slice(),
+                    aad,
+                },
+            )
+            .map_err(|_| "Failed to encrypt data key")?;
+
+        Ok(Envelope {
+            ciphertext,
+            nonce: nonce.to_vec(),
+            encrypted_data_key,
+            data_key_nonce: data_key_nonce.to_vec(),
+        })
+    }
+
+    /// Fails unless `aad` is the associated data the envelope was sealed with.
+    pub fn open(
+        &self,
+        envelope: &Envelope,
+        aad: &[u8],
+    ) -> Result<Zeroizing<Vec<u8>>, Box<dyn Error>> {
+        let data_key = Zeroizing::new(
+            Aes256Gcm::new(&self.0)
+                .decrypt(
+                    nonce(&envelope.data_key_nonce)?,
+                    Payload {
+                        msg: envelope.encrypted_data_key.as_slice(),
+                        aad,
+                    },
+                )
+                .map_err(|_| "Failed to decrypt data key")?,
+        );
+        if data_key.len() != 32 {
+            return Err("Data key must be 32 bytes".into());
+        }
+
+        let plaintext = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&data_key))
+            .decrypt(
+                nonce(&envelope.nonce)?,
+                Payload {
+                    msg: envelope.ciphertext.as_slice(),
+                    aad,
+                },
+            )
+            .map_err(|_| "Failed to decrypt secret")?;
+
+        Ok(Zeroizing::new(plaintext))
+    }
+}
+
+fn nonce(bytes: &[u8]) -> Result<&Nonce<<Aes256Gcm as AeadCore>::NonceSize>, Box<dyn Error>> {
+    if bytes.len() != 12 {
+        return Err("Nonce must be 12 bytes".into());
+    }
+
+    Ok(Nonce::from_slice(bytes))
+}
@@ -0,0 +1,200 @@
+use regex::Regex;
+use std::error::Error;
+use std::fmt;
+use std::sync::LazyLock;
+use url::Url;
+
+// `[user@]host:path`, the scp-like syntax understood by git for SSH remotes
+static SCP_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:(?P<user>[^@/:]+)@)?(?P<host>[^@/:]+):(?P<path>[^/].*)$").unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitProtocol {
+    Https,
+    Http,
+    Ssh,
+    File,
+}
+
+impl GitProtocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GitProtocol::Https => "https",
+            GitProtocol::Http => "http",
+            GitProtocol::Ssh => "ssh",
+            GitProtocol::File => "file",
+        }
+    }
+
+    fn default_port(&self) -> Option<u16> {
+        match self {
+            GitProtocol::Https => Some(443),
+            GitProtocol::Http => Some(80),
+            GitProtocol::Ssh => Some(22),
+            GitProtocol::File => None,
+        }
+    }
+}
+
+/// A parsed git remote, as accepted by `git clone`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitUrl {
+    pub protocol: GitProtocol,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub user: Option<String>,
+    /// Repository path on the host, without leading slash (absolute for `file://`)
+    pub path: String,
+    /// Everything before the repository name, e.g. `acme` or `group/subgroup`
+    pub owner: Option<String>,
+    /// Repository name without the `.git` suffix
+    pub name: String,
+}
+
+impl GitUrl {
+    /// Parses `https://`, `http://`, `ssh://`, `file://` and scp-style
+    /// `git@host:owner/repo.git` remotes, rejecting any other scheme.
+    pub fn parse(input: &str) -> Result<Self, Box<dyn Error>> {
+        let input = input.trim();
+
+        if input.contains("://") {
+            return Self::parse_url(input);
+        }
+
+        if let Some(captures) = SCP_REGEX.captures(input) {
+            return Self::build(
+                GitProtocol::Ssh,
+                Some(captures["host"].to_lowercase()),
+                None,
+                captures.name("user").map(|user| user.as_str().to_string()),
+                captures["path"].trim_start_matches('/'),
+            );
+        }
+
+        Err(format!("Unsupported git URL: {}", input).into())
+    }
+
+    fn parse_url(input: &str) -> Result<Self, Box<dyn Error>> {
+        let url = Url::parse(input).map_err(|e| format!("Invalid git URL: {}", e))?;
+
+        let protocol = match url.scheme() {
+            "https" => GitProtocol::Https,
+            "http" => GitProtocol::Http,
+            "ssh" | "git+ssh" | "ssh+git" => GitProtocol::Ssh,
+            "file" => GitProtocol::File,
+            other => return Err(format!("Unsupported git URL scheme: {}", other).into()),
+        };
+
+        if url.password().is_some() {
+            return Err("Credentials must not be embedded in the git URL".into());
+        }
+
+        if url.query().is_some() || url.fragment().is_some() {
+            return Err("Git URL must not have a query or fragment".into());
+        }
+
+        let user = Some(url.username().to_string()).filter(|user| !user.is_empty());
+
+        if protocol == GitProtocol::File {
+            return Self::build(protocol, None, None, user, url.path());
+        }
+
+        let host = url
+            .host_str()
+            .filter(|host| !host.is_empty())
+            .ok_or("Git URL is missing a host")?
+            .to_lowercase();
+
+        // Drop ports that are the protocol default
+        let port = url
+            .port()
+            .filter(|port| Some(*port) != protocol.default_port());
+
+        Self::build(
+            protocol,
+            Some(host),
+            port,
+            user,
+            url.path().trim_start_matches('/'),
+        )
+    }
+
+    fn build(
+        protocol: GitProtocol,
+        host: Option<String>,
+        port: Option<u16>,
+        user: Option<String>,
+        path: &str,
+    ) -> Result<Self, Box<dyn Error>> {
+        let path = path.trim_end_matches('/').to_string();
+
+        let (owner, name) = match path.rsplit_once('/') {
+            Some((owner, name)) => (Some(owner.trim_start_matches('/')), name),
+            None => (None, path.as_str()),
+        };
+
+        let name = name.strip_suffix(".git").unwrap_or(name).to_string();
+        if name.is_empty() {
+            return Err("Git URL is missing a repository path".into());
+        }
+
+        Ok(GitUrl {
+            protocol,
+            host,
+            port,
+            user,
+            owner: owner.filter(|owner| !owner.is_empty()).map(str::to_string),
+            path,
+            name,
+        })
+    }
+
+    /// Identifies the remote regardless of protocol, user or `.git` suffix, so
+    /// the HTTPS and SSH URLs of one repository are detected as duplicates.
+    /// A non-default port is kept, it may be another server on the same host.
+    pub fn remote_key(&self) -> String {
+        let path = self.path.strip_suffix(".git").unwrap_or(&self.path);
+
+        match (&self.host, self.port) {
+            (Some(host), Some(port)) => format!("{}:{}/{}", host, port, path),
+            (Some(host), None) => format!("{}/{}", host, path),
+            (None, _) => format!("file://{}", path),
+        }
+    }
+
+    /// Host as written in `known_hosts`, `[host]:port` off the default port.
+    /// Only SSH remotes have one.
+    pub fn known_host(&self) -> Option<String> {
+        if self.protocol != GitProtocol::Ssh {
+            return None;
+        }
+
+        let host = self.host.as_ref()?;
+        match self.port {
+            Some(port) => Some(format!("[{}]:{}", host, port)),
+            None => Some(host.clone()),
+        }
+    }
+}
+
+impl fmt::Display for GitUrl {
+    /// Normalized form, scp-style remotes are written as `ssh://` URLs.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.protocol == GitProtocol::File {
+            return write!(f, "file://{}", self.path);
+        }
+
+        write!(f, "{}://", self.protocol.as_str())?;
+        if let Some(user) = &self.user {
+            write!(f, "{}@", user)?;
+        }
+        if let Some(host) = &self.host {
+            write!(f, "{}", host)?;
+        }
+        if let Some(port) = self.port {
+            write!(f, ":{}", port)?;
+        }
+        write!(f, "/{}", self.path)
+    }
+}
@@ -0,0 +1,68 @@
+use crate::config::SmtpConfig;
+use crate::utils::metrics::METRICS;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::{Message, SmtpTransport, Transport};
+use std::error::Error;
+use tracing::field::Empty;
+use tracing::Span;
+
+/// SMTP transport for the configured server, with TLS according to
+/// `tls_mode`: `none`, `required`, or `opportunistic` by default.
+pub fn mailer(smtp: &SmtpConfig) -> Result<SmtpTransport, Box<dyn Error>> {
+    let mut builder = SmtpTransport::builder_dangerous(&smtp.server).port(smtp.port);
+    // Servers without authentication take an empty username
+    if !smtp.username.is_empty() {
+        builder = builder.credentials(Credentials::new(
+            smtp.username.clone(),
+            smtp.password.clone(),
+        ));
+    }
+
+    // Build different transports based on TLS mode
+    let mailer = match smtp.tls_mode.to_lowercase().as_str() {
+        "none" => builder.build(),
+        "required" => {
+            let tls_parameters = TlsParameters::new(smtp.server.clone())
+                .map_err(|e| format!("TLS error: {}", e))?;
+
+            builder.tls(Tls::Required(tls_parameters)).build()
+        }
+        _ => {
+            let tls_parameters = TlsParameters::new(smtp.server.clone())
+                .map_err(|e| format!("TLS error: {}", e))?;
+
+            builder.tls(Tls::Opportunistic(tls_parameters)).build()
+        }
+    };
+
+    Ok(mailer)
+}
+
+/// Sends `email` through the configured server, counting the outcome under
+/// `kind`, e.g. `verification`.
+#[tracing::instrument(
+    name = "smtp.send",
+    skip_all,
+    fields(
+        otel.kind = "client",
+        otel.status_code = Empty,
+        email.kind = kind,
+        server.address = %smtp.server,
+        server.port = smtp.port,
+    )
+)]
+pub fn send(smtp: &SmtpConfig, kind: &str, email: &Message) -> Result<(), Box<dyn Error>> {
+    let result = mailer(smtp).and_then(|mailer| {
+        mailer.send(email)?;
+        Ok(())
+    });
+
+    let outcome = if result.is_ok() { "sent" } else { "failed" };
+    METRICS.emails.with_label_values(&[kind, outcome]).inc();
+    if result.is_err() {
+        Span::current().record("otel.status_code", "ERROR");
+    }
+
+    result
+}
@@ -0,0 +1,183 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::Error;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::sync::LazyLock;
+use std::time::Instant;
+
+/// Every metric the API exports, registered on first use.
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests: IntCounterVec,
+    pub http_request_duration: HistogramVec,
+    pub db_pool_connections: IntGaugeVec,
+    pub db_pool_max_connections: IntGauge,
+    pub db_pool_checkouts: IntCounter,
+    pub db_pool_waits: IntCounter,
+    pub db_pool_timeouts: IntCounter,
+    pub db_pool_wait_duration: Histogram,
+    pub logins: IntCounterVec,
+    pub registrations: IntCounterVec,
+    pub emails: IntCounterVec,
+    pub queue_depth: IntGaugeVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry =
+            Registry::new_custom(Some("scylla".to_string()), None).expect("metric prefix is valid");
+        let metrics = Metrics {
+            http_requests: IntCounterVec::new(
+                Opts::new("http_requests_total", "HTTP requests handled"),
+                &["method", "route", "status"],
+            )
+            .unwrap(),
+            http_request_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "http_request_duration_seconds",
+                    "Time to produce an HTTP response",
+                ),
+                &["method", "route", "status"],
+            )
+            .unwrap(),
+            db_pool_connections: IntGaugeVec::new(
+                Opts::new("db_pool_connections", "Open database connections"),
+                &["state"],
+            )
+            .unwrap(),
+            db_pool_max_connections: IntGauge::new(
+                "db_pool_max_connections",
+                "Size of the database pool",
+            )
+            .unwrap(),
+            db_pool_checkouts: IntCounter::new(
+                "db_pool_checkouts_total",
+                "Connections taken from the pool",
+            )
+            .unwrap(),
+            db_pool_waits: IntCounter::new(
+                "db_pool_waits_total",
+                "Checkouts that found no idle connection",
+            )
+            .unwrap(),
+            db_pool_timeouts: IntCounter::new(
+                "db_pool_timeouts_total",
+                "Checkouts that gave up waiting for a connection",
+            )
+            .unwrap(),
+            db_pool_wait_duration: Histogram::with_opts(
+                HistogramOpts::new(
+                    "db_pool_wait_seconds",
+                    "Time spent waiting for a connection",
+                )
+                .buckets(vec![0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 30.0]),
+            )
+            .unwrap(),
+            logins: IntCounterVec::new(
+                Opts::new("auth_logins_total", "Password logins"),
+                &["outcome"],
+            )
+            .unwrap(),
+            registrations: IntCounterVec::new(
+                Opts::new("auth_registrations_total", "Account registrations"),
+                &["outcome"],
+            )
+            .unwrap(),
+            emails: IntCounterVec::new(
+                Opts::new("emails_total", "Emails handed to the SMTP server"),
+                &["kind", "outcome"],
+            )
+            .unwrap(),
+            queue_depth: IntGaugeVec::new(
+                Opts::new("job_queue_depth", "Items waiting for a background job"),
+                &["queue"],
+            )
+            .unwrap(),
+            registry,
+        };
+
+        let collectors: [Box<dyn prometheus::core::Collector>; 12] = [
+            Box::new(metrics.http_requests.clone()),
+            Box::new(metrics.http_request_duration.clone()),
+            Box::new(metrics.db_pool_connections.clone()),
+            Box::new(metrics.db_pool_max_connections.clone()),
+            Box::new(metrics.db_pool_checkouts.clone()),
+            Box::new(metrics.db_pool_waits.clone()),
+            Box::new(metrics.db_pool_timeouts.clone()),
+            Box::new(metrics.db_pool_wait_duration.clone()),
+            Box::new(metrics.logins.clone()),
+            Box::new(metrics.registrations.clone()),
+            Box::new(metrics.emails.clone()),
+            Box::new(metrics.queue_depth.clone()),
+        ];
+        for collector in collectors {
+            metrics
+                .registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+
+        metrics
+    }
+
+    /// Everything registered, in the Prometheus text format.
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        String::from_utf8(buffer).map_err(|e| prometheus::Error::Msg(e.to_string()))
+    }
+}
+
+/// Counts requests and their latency by route template and status.
+/// Unmatched paths and non-standard methods share a label, so scanners
+/// cannot blow up the series.
+pub async fn track(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let method = method_label(req.method());
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let started = Instant::now();
+    let result = next.call(req).await;
+    let status = match &result {
+        Ok(res) => res.status().as_u16(),
+        Err(e) => e.as_response_error().status_code().as_u16(),
+    }
+    .to_string();
+
+    let labels = [method, route.as_str(), status.as_str()];
+    METRICS.http_requests.with_label_values(&labels).inc();
+    METRICS
+        .http_request_duration
+        .with_label_values(&labels)
+        .observe(started.elapsed().as_secs_f64());
+
+    result
+}
+
+/// The method as a label, `other` for extension methods.
+pub fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::HEAD => "HEAD",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::PATCH => "PATCH",
+        Method::DELETE => "DELETE",
+        Method::OPTIONS => "OPTIONS",
+        Method::CONNECT => "CONNECT",
+        Method::TRACE => "TRACE",
+        _ => "other",
+    }
+}
@@ -1,2 +1,13 @@
+pub mod cors;
+pub mod envelope;
+pub mod git_url;
+pub mod mail;
+pub mod metrics;
+pub mod network;
 pub mod password;
-pub mod response;
\ No newline at end of file
+pub mod request_id;
+pub mod response;
+pub mod retry;
+pub mod shutdown;
+pub mod slug;
+pub mod telemetry;
@@ -0,0 +1,102 @@
+use crate::utils::telemetry;
+use actix_identity::IdentityExt;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::Error;
+use std::time::Instant;
+use tracing::field::Empty;
+use tracing::Instrument;
+use uuid::Uuid;
+
+pub const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+// Longer ids from clients are replaced rather than logged
+const MAX_LEN: usize = 128;
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+/// Id of the request being handled, `None` outside of a request.
+pub fn current() -> Option<String> {
+    CURRENT.try_with(|id| id.clone()).ok()
+}
+
+// Ids from upstream proxies are kept so logs can be correlated across them
+fn accept(header: Option<&HeaderValue>) -> Option<String> {
+    let id = header?.to_str().ok()?;
+    let valid = !id.is_empty()
+        && id.len() <= MAX_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c));
+
+    valid.then(|| id.to_string())
+}
+
+/// Runs the request in a span holding its id, route template and user,
+/// continuing the trace of its `traceparent` header. Logs its outcome and
+/// latency, and returns the id in `X-Request-Id`.
+pub async fn request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let id = accept(req.headers().get(&REQUEST_ID)).unwrap_or_else(|| Uuid::new_v4().to_string());
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+    let span = tracing::info_span!(
+        "request",
+        otel.name = %format!("{} {}", req.method(), route),
+        otel.kind = "server",
+        otel.status_code = Empty,
+        request_id = %id,
+        method = %req.method(),
+        route,
+        user_id = Empty,
+        status = Empty,
+        latency_ms = Empty,
+    );
+    telemetry::continue_trace(&span, req.headers());
+    if let Ok(user_id) = req.get_identity().and_then(|identity| identity.id()) {
+        span.record("user_id", user_id);
+    }
+
+    let started = Instant::now();
+    let result = CURRENT
+        .scope(id.clone(), next.call(req))
+        .instrument(span.clone())
+        .await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+    let _entered = span.enter();
+
+    match result {
+        Ok(mut res) => {
+            let status = res.status();
+            span.record("status", status.as_u16());
+            span.record("latency_ms", latency_ms);
+            if status.is_server_error() {
+                span.record("otel.status_code", "ERROR");
+            }
+            // Handlers build most error responses themselves, without an `Error`
+            match res.response().error() {
+                _ if !status.is_server_error() => tracing::info!("Request completed"),
+                Some(e) => tracing::error!(error = %e, "Request failed"),
+                None => tracing::error!("Request failed"),
+            }
+
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                res.headers_mut().insert(REQUEST_ID, value);
+            }
+            Ok(res)
+        }
+        Err(e) => {
+            span.record("latency_ms", latency_ms);
+            span.record("otel.status_code", "ERROR");
+            tracing::error!(error = %e, "Request failed");
+            Err(e)
+        }
+    }
+}
@@ -1,5 +1,8 @@
+use crate::utils::request_id;
 use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Response<T> {
@@ -8,6 +11,9 @@ pub struct Response<T> {
     pub message: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<T>,
+    /// Set on errors, to quote when reporting them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 pub fn success<T>(status: StatusCode, data: Option<T>) -> Response<T> {
@@ -15,6 +21,7 @@ pub fn success<T>(status: StatusCode, data: Option<T>) -> Response<T> {
         status: status.as_u16(),
         message: None,
         data,
+        request_id: None,
     }
 }
 
@@ -23,5 +30,36 @@ pub fn error(status: StatusCode, message: String) -> Response<()> {
         status: status.as_u16(),
         message: Some(message),
         data: None,
+        request_id: request_id::current(),
+    }
+}
+
+/// An error response that can cross threads, for work running on the
+/// blocking pool where an `HttpResponse` cannot be built.
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: String) -> Self {
+        ApiError { status, message }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status).json(error(self.status, self.message.clone()))
     }
 }
@@ -0,0 +1,44 @@
+use crate::config::StartupConfig;
+use actix_web::rt::time;
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+
+/// Calls `connect` until it succeeds, doubling the delay between two
+/// attempts. Dependencies started alongside the server may take a moment
+/// to accept connections. Returns the last error once attempts run out.
+pub async fn with_backoff<T, E, F, Fut>(
+    name: &str,
+    config: &StartupConfig,
+    mut connect: F,
+) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let max_attempts = config.max_attempts.max(1);
+    let mut delay = Duration::from_secs(config.backoff_seconds);
+    let max_delay = Duration::from_secs(config.max_backoff_seconds);
+
+    let mut attempt = 1;
+    loop {
+        match connect().await {
+            Ok(connected) => return Ok(connected),
+            Err(e) if attempt >= max_attempts => return Err(e),
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to connect to {} (attempt {}/{}), retrying in {:?}: {}",
+                    name,
+                    attempt,
+                    max_attempts,
+                    delay,
+                    e
+                );
+                time::sleep(delay).await;
+                delay = (delay * 2).min(max_delay);
+                attempt += 1;
+            }
+        }
+    }
+}
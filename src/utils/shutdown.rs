@@ -0,0 +1,136 @@
+use actix_web::dev::ServerHandle;
+use actix_web::rt::{self, signal, time};
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+// Tasks interrupted at the timeout get this long to save their progress
+const CHECKPOINT_GRACE: Duration = Duration::from_secs(5);
+
+/// Coordinates the graceful shutdown of the server and its background
+/// tasks. Clones share the same state.
+#[derive(Clone)]
+pub struct Shutdown {
+    timeout: Duration,
+    begun_at: Arc<OnceLock<Instant>>,
+    begun: CancellationToken,
+    overdue: CancellationToken,
+    tasks: TaskTracker,
+}
+
+impl Shutdown {
+    pub fn new(timeout: Duration) -> Self {
+        Shutdown {
+            timeout,
+            begun_at: Arc::new(OnceLock::new()),
+            begun: CancellationToken::new(),
+            overdue: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+        }
+    }
+
+    /// Begins the shutdown on SIGTERM, or Ctrl-C in a terminal.
+    pub fn on_signal(&self) {
+        let shutdown = self.clone();
+        rt::spawn(async move {
+            if signal::ctrl_c().await.is_ok() {
+                shutdown.begin();
+            }
+        });
+
+        #[cfg(unix)]
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                let shutdown = self.clone();
+                rt::spawn(async move {
+                    if terminate.recv().await.is_some() {
+                        shutdown.begin();
+                    }
+                });
+            }
+            Err(e) => tracing::error!("Failed to listen for SIGTERM: {}", e),
+        }
+    }
+
+    /// From then on readiness fails, servers stop accepting connections
+    /// and workers take no new task. Calling it again does nothing.
+    pub fn begin(&self) {
+        if self.begun_at.set(Instant::now()).is_ok() {
+            tracing::info!(
+                "Shutting down, waiting up to {:?} for in-flight work",
+                self.timeout
+            );
+        }
+        self.begun.cancel();
+    }
+
+    pub fn has_begun(&self) -> bool {
+        self.begun.is_cancelled()
+    }
+
+    /// Whether the timeout elapsed. Tasks still running should stop and
+    /// save their progress. Can be checked from the blocking pool.
+    pub fn is_overdue(&self) -> bool {
+        self.overdue.is_cancelled()
+    }
+
+    /// Waits for the next tick of `interval`. Returns `false` once the
+    /// shutdown has begun, so worker loops end between two tasks.
+    pub async fn tick(&self, interval: &mut time::Interval) -> bool {
+        !self.has_begun()
+            && self
+                .begun
+                .run_until_cancelled(interval.tick())
+                .await
+                .is_some()
+    }
+
+    /// Runs `task` in the background. `drain` waits for it.
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.spawn(task);
+    }
+
+    /// Stops `server` once the shutdown begins. It closes its listeners
+    /// right away and gives in-flight requests its own shutdown timeout.
+    pub fn stop_server(&self, server: ServerHandle) {
+        let begun = self.begun.clone();
+        rt::spawn(async move {
+            begun.cancelled().await;
+            server.stop(true).await;
+        });
+    }
+
+    /// Waits for background tasks until the timeout, counted from the
+    /// beginning of the shutdown. Tasks still running are then told to
+    /// checkpoint. Returns whether every task finished in time.
+    pub async fn drain(&self) -> bool {
+        self.begin();
+        self.tasks.close();
+
+        let began = *self.begun_at.get().expect("shutdown has begun");
+        let remaining = self.timeout.saturating_sub(began.elapsed());
+        if time::timeout(remaining, self.tasks.wait()).await.is_ok() {
+            return true;
+        }
+
+        tracing::warn!(
+            "{} background task(s) still running after {:?}, interrupting them",
+            self.tasks.len(),
+            self.timeout
+        );
+        self.overdue.cancel();
+        if time::timeout(CHECKPOINT_GRACE, self.tasks.wait())
+            .await
+            .is_err()
+        {
+            tracing::error!("Abandoning {} background task(s)", self.tasks.len());
+        }
+
+        false
+    }
+}
@@ -0,0 +1,98 @@
+use regex::Regex;
+use std::error::Error;
+use std::sync::LazyLock;
+use uuid::Uuid;
+
+pub const MAX_SLUG_LENGTH: usize = 100;
+
+static SLUG_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[a-z0-9]+(?:-[a-z0-9]+)*$").unwrap());
+
+// Words that would shadow API routes or are confusing as resource names
+const RESERVED_SLUGS: &[&str] = &[
+    "access",
+    "admin",
+    "api",
+    "edit",
+    "health",
+    "login",
+    "logout",
+    "me",
+    "new",
+    "organizations",
+    "orgs",
+    "repos",
+    "repositories",
+    "restore",
+    "settings",
+    "teams",
+    "users",
+];
+
+// Convert a display name into a lowercase, dash-separated slug
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len());
+
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    slug.truncate(MAX_SLUG_LENGTH);
+    slug.trim_end_matches('-').to_string()
+}
+
+// Check a user-provided slug: 1 to 100 lowercase ASCII letters and digits,
+// in groups joined by single dashes, so never starting or ending with one.
+// Reserved words and anything parsing as a UUID are refused.
+pub fn validate_slug(slug: &str) -> Result<(), Box<dyn Error>> {
+    if slug.is_empty() || slug.len() > MAX_SLUG_LENGTH {
+        return Err(format!("Slug must be between 1 and {} characters", MAX_SLUG_LENGTH).into());
+    }
+
+    if !SLUG_REGEX.is_match(slug) {
+        return Err("Slug may only contain lowercase letters, digits and single dashes".into());
+    }
+
+    if RESERVED_SLUGS.contains(&slug) {
+        return Err(format!("Slug '{}' is reserved", slug).into());
+    }
+
+    // Slugs share routes with UUIDs, so they must never parse as one
+    if Uuid::parse_str(slug).is_ok() {
+        return Err("Slug cannot be a UUID".into());
+    }
+
+    Ok(())
+}
+
+// Derive a free slug from a name, appending a counter until `is_taken` says no
+pub fn unique_slug<F>(name: &str, fallback: &str, mut is_taken: F) -> Result<String, Box<dyn Error>>
+where
+    F: FnMut(&str) -> Result<bool, Box<dyn Error>>,
+{
+    let mut base = slugify(name);
+    if base.is_empty() {
+        base = fallback.to_string();
+    }
+
+    // Leave room for the counter suffix
+    base.truncate(MAX_SLUG_LENGTH - 10);
+    let base = base.trim_end_matches('-').to_string();
+
+    if validate_slug(&base).is_ok() && !is_taken(&base)? {
+        return Ok(base);
+    }
+
+    let mut counter = 2;
+    loop {
+        let candidate = format!("{}-{}", base, counter);
+        if validate_slug(&candidate).is_ok() && !is_taken(&candidate)? {
+            return Ok(candidate);
+        }
+        counter += 1;
+    }
+}
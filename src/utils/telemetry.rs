@@ -0,0 +1,143 @@
+use crate::config::{LogConfig, TracingConfig};
+use actix_web::http::header::HeaderMap;
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, SdkTracer, SdkTracerProvider, SpanExporter};
+use opentelemetry_sdk::Resource;
+use std::error::Error;
+use tracing::{Span, Subscriber};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+/// Keeps the span exporter running. Shut it down last, so the spans of
+/// the shutdown itself are exported.
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush traces: {}", e);
+            }
+        }
+    }
+}
+
+/// Installs the global subscriber, writing logs to stdout and exporting
+/// spans when tracing is enabled. `log` records from dependencies are
+/// forwarded to it.
+pub fn init(log: &LogConfig, tracing: &TracingConfig) -> Result<Telemetry, Box<dyn Error>> {
+    let provider = match tracing.enabled {
+        true => {
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_http()
+                .with_endpoint(&tracing.endpoint)
+                .build()?;
+            Some(tracer_provider(tracing, exporter))
+        }
+        false => None,
+    };
+
+    let (json, text) = match log.format.as_str() {
+        "json" => (Some(json_layer(std::io::stdout)), None),
+        _ => (None, Some(fmt::layer())),
+    };
+
+    tracing_subscriber::registry()
+        .with(EnvFilter::new(&log.filter))
+        .with(json)
+        .with(text)
+        .with(provider.as_ref().map(layer))
+        .try_init()?;
+
+    Ok(Telemetry { provider })
+}
+
+/// Logs events as JSON lines to `writer`. Each event lists every span it
+/// happens in, so events of nested spans still carry the request id and
+/// user of the request span.
+pub fn json_layer<S, W>(writer: W) -> impl Layer<S>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    fmt::layer()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_writer(writer)
+}
+
+/// Batches spans to `exporter`, sampling new traces at `sample_ratio` and
+/// following the decision of the caller for propagated ones.
+pub fn tracer_provider(
+    config: &TracingConfig,
+    exporter: impl SpanExporter + 'static,
+) -> SdkTracerProvider {
+    SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            config.sample_ratio,
+        ))))
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build(),
+        )
+        .build()
+}
+
+/// Turns `tracing` spans into OpenTelemetry spans of `provider`.
+pub fn layer<S>(provider: &SdkTracerProvider) -> OpenTelemetryLayer<S, SdkTracer>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer(env!("CARGO_PKG_NAME")))
+}
+
+/// Makes `span` continue the trace of a W3C `traceparent` header, if the
+/// request has a valid one.
+pub fn continue_trace(span: &Span, headers: &HeaderMap) {
+    let context = TraceContextPropagator::new().extract(&RequestHeaders(headers));
+    // Fails when tracing is disabled, which leaves nothing to continue
+    let _ = span.set_parent(context);
+}
+
+/// Adds `traceparent` for the current span to an outgoing request.
+pub fn propagate(headers: &mut reqwest::header::HeaderMap) {
+    TraceContextPropagator::new()
+        .inject_context(&Span::current().context(), &mut OutgoingHeaders(headers));
+}
+
+struct RequestHeaders<'a>(&'a HeaderMap);
+
+impl Extractor for RequestHeaders<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+struct OutgoingHeaders<'a>(&'a mut reqwest::header::HeaderMap);
+
+impl Injector for OutgoingHeaders<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            reqwest::header::HeaderName::from_bytes(key.as_bytes()),
+            reqwest::header::HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
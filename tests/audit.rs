@@ -0,0 +1,57 @@
+use actix_poc_scylla::models::AuditEvent;
+use actix_poc_scylla::modules::audit::service::AuditService;
+use chrono::Utc;
+use serde_json::json;
+use uuid::Uuid;
+
+fn event(user_agent: &str) -> AuditEvent {
+    AuditEvent {
+        id: Uuid::nil(),
+        seq: 1,
+        organization_id: None,
+        actor_id: None,
+        action: "auth.login_failed".into(),
+        target_type: "user".into(),
+        target_id: None,
+        changes: Some(json!({ "name": { "before": "-1", "after": "=1" } })),
+        ip: Some("203.0.113.7".into()),
+        user_agent: Some(user_agent.into()),
+        request_id: None,
+        created_at: Utc::now(),
+    }
+}
+
+#[test]
+fn escapes_formulas_in_cells() {
+    for value in [
+        "=HYPERLINK(\"http://x\")",
+        "+1",
+        "-1",
+        "@SUM(A1)",
+        "\tx",
+        "\rx",
+    ] {
+        assert_eq!(AuditService::csv_cell(value), format!("'{}", value));
+    }
+}
+
+#[test]
+fn leaves_other_cells_alone() {
+    for value in ["curl/8.0", "203.0.113.7", "{\"a\":1}", "", "a=b"] {
+        assert_eq!(AuditService::csv_cell(value), value);
+    }
+}
+
+#[test]
+fn exports_escaped_rows() {
+    let csv = AuditService::to_csv(&[event("=cmd|' /C calc'!A0")]).unwrap();
+    let mut reader = csv::Reader::from_reader(csv.as_slice());
+    let headers = reader.headers().unwrap().clone();
+    let row = reader.records().next().unwrap().unwrap();
+    let cell = |name: &str| &row[headers.iter().position(|h| h == name).unwrap()];
+
+    assert_eq!(cell("user_agent"), "'=cmd|' /C calc'!A0");
+    assert_eq!(cell("ip"), "203.0.113.7");
+    assert_eq!(cell("action"), "auth.login_failed");
+    assert!(cell("changes").starts_with('{'));
+}
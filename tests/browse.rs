@@ -0,0 +1,229 @@
+use actix_poc_scylla::modules::browse::dto::BrowseError;
+use actix_poc_scylla::modules::browse::service::BrowseService;
+use git2::{IndexEntry, IndexTime, Oid, Repository, Signature};
+use tempfile::TempDir;
+
+const MAX_FILE_BYTES: usize = 1024;
+
+struct Fixture {
+    _dir: TempDir,
+    git: Repository,
+    commits: Vec<Oid>,
+}
+
+// One commit on `main` per list of files, oldest first
+fn repository(changes: &[&[(&str, &str)]]) -> Fixture {
+    let dir = TempDir::new().unwrap();
+    let git = Repository::init_bare(dir.path()).unwrap();
+    let author = Signature::now("Test", "test@example.com").unwrap();
+
+    let mut commits = Vec::new();
+    for files in changes {
+        let parent = commits.last().map(|id| git.find_commit(*id).unwrap());
+        let mut index = git.index().unwrap();
+        if let Some(parent) = &parent {
+            index.read_tree(&parent.tree().unwrap()).unwrap();
+        }
+        for (path, content) in *files {
+            index
+                .add(&IndexEntry {
+                    ctime: IndexTime::new(0, 0),
+                    mtime: IndexTime::new(0, 0),
+                    dev: 0,
+                    ino: 0,
+                    mode: 0o100644,
+                    uid: 0,
+                    gid: 0,
+                    file_size: content.len() as u32,
+                    id: git.blob(content.as_bytes()).unwrap(),
+                    flags: 0,
+                    flags_extended: 0,
+                    path: path.as_bytes().to_vec(),
+                })
+                .unwrap();
+        }
+        let tree = git.find_tree(index.write_tree_to(&git).unwrap()).unwrap();
+        let parents = parent.iter().collect::<Vec<_>>();
+        let commit = git
+            .commit(
+                Some("refs/heads/main"),
+                &author,
+                &author,
+                &format!("Commit {}", commits.len() + 1),
+                &tree,
+                &parents,
+            )
+            .unwrap();
+        commits.push(commit);
+    }
+
+    let head = *commits.last().unwrap();
+    git.reference("refs/heads/feature/login", commits[0], false, "branch")
+        .unwrap();
+    git.reference("refs/tags/v1", head, false, "tag").unwrap();
+    git.set_head("refs/heads/main").unwrap();
+
+    Fixture {
+        _dir: dir,
+        git,
+        commits,
+    }
+}
+
+fn history() -> Fixture {
+    repository(&[
+        &[("README.md", "# Demo\n")],
+        &[("src/lib.rs", "pub fn one() {}\n")],
+        &[("src/lib.rs", "pub fn one() {}\npub fn two() {}\n")],
+        &[("README.md", "# Demo\n\nUpdated\n")],
+    ])
+}
+
+fn listed(fixture: &Fixture, rev: &str) -> Result<Vec<String>, BrowseError> {
+    BrowseService::commits(&fixture.git, Some(rev), None, 1, 100, 100)
+        .map(|page| page.commits.into_iter().map(|commit| commit.sha).collect())
+}
+
+#[test]
+fn resolves_branches_tags_refs_and_full_shas() {
+    let fixture = history();
+    let head = fixture.commits[3].to_string();
+    let first = fixture.commits[0].to_string();
+
+    assert_eq!(listed(&fixture, "HEAD").unwrap()[0], head);
+    assert_eq!(listed(&fixture, "main").unwrap()[0], head);
+    assert_eq!(listed(&fixture, "v1").unwrap()[0], head);
+    assert_eq!(listed(&fixture, "refs/heads/main").unwrap()[0], head);
+    assert_eq!(
+        listed(&fixture, "feature/login").unwrap(),
+        vec![first.clone()]
+    );
+    assert_eq!(listed(&fixture, &first).unwrap(), vec![first]);
+}
+
+#[test]
+fn refuses_revision_expressions() {
+    let fixture = history();
+    let short_sha = &fixture.commits[0].to_string()[..7];
+
+    for rev in [
+        "main~1", "HEAD^", "main@{0}", "main..v1", ":/Commit", short_sha,
+    ] {
+        assert!(
+            matches!(listed(&fixture, rev), Err(BrowseError::NotFound(_))),
+            "{} should not resolve",
+            rev
+        );
+    }
+}
+
+#[test]
+fn lists_commits_touching_a_path() {
+    let fixture = history();
+
+    let page = BrowseService::commits(&fixture.git, None, Some("/src/"), 1, 10, 100).unwrap();
+
+    let shas = page
+        .commits
+        .iter()
+        .map(|c| c.sha.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        shas,
+        vec![
+            fixture.commits[2].to_string(),
+            fixture.commits[1].to_string()
+        ]
+    );
+    assert!(!page.has_more);
+    assert!(!page.truncated);
+}
+
+#[test]
+fn stops_the_walk_after_the_limit() {
+    let fixture = history();
+
+    let page = BrowseService::commits(&fixture.git, None, Some("README.md"), 1, 10, 2).unwrap();
+
+    assert_eq!(page.commits.len(), 1);
+    assert_eq!(page.commits[0].sha, fixture.commits[3].to_string());
+    assert!(page.truncated);
+}
+
+#[test]
+fn paginates_commits() {
+    let fixture = history();
+
+    let page = BrowseService::commits(&fixture.git, None, None, 2, 3, 100).unwrap();
+
+    assert_eq!(page.commits.len(), 1);
+    assert_eq!(page.commits[0].sha, fixture.commits[0].to_string());
+    assert!(!page.has_more);
+}
+
+#[test]
+fn diffs_a_commit_against_its_parent() {
+    let fixture = history();
+
+    let details = BrowseService::commit(
+        &fixture.git,
+        &fixture.commits[2].to_string(),
+        10,
+        MAX_FILE_BYTES,
+    )
+    .unwrap();
+
+    assert_eq!(details.files_changed, 1);
+    assert_eq!(details.files[0].path, "src/lib.rs");
+    assert_eq!(details.files[0].status, "modified");
+    assert_eq!((details.insertions, details.deletions), (1, 0));
+    assert!(!details.truncated);
+}
+
+#[test]
+fn truncates_large_diffs() {
+    let fixture = repository(&[&[("a.txt", "a\n"), ("b.txt", "b\n"), ("c.txt", "c\n")]]);
+    let root = fixture.commits[0].to_string();
+
+    let details = BrowseService::commit(&fixture.git, &root, 2, MAX_FILE_BYTES).unwrap();
+
+    assert_eq!(details.files_changed, 3);
+    assert_eq!(details.files.len(), 2);
+    assert_eq!((details.insertions, details.deletions), (2, 0));
+    assert!(details.truncated);
+}
+
+#[test]
+fn counts_files_over_the_size_limit_as_binary() {
+    let large = "line\n".repeat(MAX_FILE_BYTES);
+    let fixture = repository(&[&[("large.txt", &large)]]);
+
+    let details = BrowseService::commit(
+        &fixture.git,
+        &fixture.commits[0].to_string(),
+        10,
+        MAX_FILE_BYTES,
+    )
+    .unwrap();
+
+    assert!(details.files[0].binary);
+    assert_eq!(details.insertions, 0);
+}
+
+#[test]
+fn reads_files_on_branches_with_slashes() {
+    let fixture = history();
+
+    let blob =
+        BrowseService::blob(&fixture.git, "feature/login/README.md", MAX_FILE_BYTES).unwrap();
+    assert_eq!(blob.content, "# Demo\n");
+
+    let tree = BrowseService::tree(&fixture.git, "main/src", 100).unwrap();
+    assert_eq!(tree.entries.len(), 1);
+    assert_eq!(tree.entries[0].path, "src/lib.rs");
+
+    assert!(matches!(
+        BrowseService::blob(&fixture.git, "main~1/README.md", MAX_FILE_BYTES),
+        Err(BrowseError::NotFound(_))
+    ));
+}
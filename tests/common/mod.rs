@@ -0,0 +1,138 @@
+// Shared by the tests needing Postgres. They are skipped unless
+// `TEST_DATABASE_URL` points at a database they may write to.
+#![allow(dead_code, unused_macros)]
+
+use actix_identity::Identity;
+use actix_poc_scylla::db;
+use actix_poc_scylla::models::{Organization, User};
+use actix_poc_scylla::modules::audit::dto::AuditContext;
+use actix_poc_scylla::modules::auth::dto::RegisterQuery;
+use actix_poc_scylla::modules::auth::service::AuthService;
+use actix_poc_scylla::modules::organization::dto::{
+    AddUserToOrganizationQuery, OrganizationCreateQuery,
+};
+use actix_poc_scylla::modules::organization::service::OrganizationService;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::{Connection, PgConnection};
+use std::env;
+use std::sync::Once;
+use uuid::Uuid;
+
+pub type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Test service mounting `routes` like the server, the user read from the
+/// session, plus `POST /login/{user_id}` to log a session in.
+macro_rules! app {
+    ($pool:expr, $routes:expr) => {
+        actix_web::test::init_service(
+            actix_web::App::new()
+                .wrap(actix_identity::IdentityMiddleware::default())
+                .wrap(actix_session::SessionMiddleware::new(
+                    actix_session::storage::CookieSessionStore::default(),
+                    actix_web::cookie::Key::generate(),
+                ))
+                .app_data(actix_web::web::Data::new($pool))
+                .route(
+                    "/login/{user_id}",
+                    actix_web::web::post().to($crate::common::login),
+                )
+                .configure($routes),
+        )
+        .await
+    };
+}
+
+/// Session cookie of `user_id` on an `app!` service.
+macro_rules! session {
+    ($app:expr, $user_id:expr) => {
+        actix_web::test::call_service(
+            &$app,
+            actix_web::test::TestRequest::post()
+                .uri(&format!("/login/{}", $user_id))
+                .to_request(),
+        )
+        .await
+        .response()
+        .cookies()
+        .next()
+        .map(actix_web::cookie::Cookie::into_owned)
+        .unwrap()
+    };
+}
+
+pub async fn login(req: HttpRequest, path: web::Path<Uuid>) -> HttpResponse {
+    Identity::login(&req.extensions(), path.into_inner().to_string()).unwrap();
+    HttpResponse::Ok().finish()
+}
+
+static MIGRATE: Once = Once::new();
+
+/// A pool on the test database, migrated, or `None` when there is none.
+pub fn database() -> Option<DbPool> {
+    let Ok(url) = env::var("TEST_DATABASE_URL") else {
+        eprintln!("TEST_DATABASE_URL is not set, skipping");
+        return None;
+    };
+
+    MIGRATE.call_once(|| {
+        let mut conn = PgConnection::establish(&url).expect("test database is reachable");
+        db::run_migrations(&mut conn).expect("migrations apply");
+    });
+
+    Some(
+        Pool::builder()
+            .max_size(2)
+            .build(ConnectionManager::new(url))
+            .unwrap(),
+    )
+}
+
+pub fn audit() -> AuditContext {
+    AuditContext::background("test")
+}
+
+/// A user with a unique email.
+pub fn user(conn: &mut PgConnection) -> User {
+    let name = Uuid::new_v4().simple().to_string();
+    AuthService::register(
+        conn,
+        &RegisterQuery {
+            name: format!("User {}", &name[..8]),
+            email: format!("{}@example.com", name),
+            password: "correct horse battery".to_string(),
+        },
+        &audit(),
+    )
+    .unwrap()
+}
+
+/// An organization whose members are given with their role.
+pub fn organization(conn: &mut PgConnection, members: &[(&User, &str)]) -> Organization {
+    let name = Uuid::new_v4().simple().to_string();
+    let organization = OrganizationService::create(
+        conn,
+        &OrganizationCreateQuery {
+            name: format!("Organization {}", &name[..8]),
+            description: None,
+            slug: Some(format!("org-{}", name)),
+        },
+        &audit(),
+    )
+    .unwrap();
+
+    for (user, role) in members {
+        OrganizationService::add_user(
+            conn,
+            organization.id,
+            &AddUserToOrganizationQuery {
+                user_id: user.id,
+                role: Some(role.to_string()),
+            },
+            &audit(),
+        )
+        .unwrap();
+    }
+
+    organization
+}
@@ -0,0 +1,384 @@
+use actix_poc_scylla::config::{Config, ConfigArgs, ConfigError, Settings};
+use actix_poc_scylla::utils::cors::AllowedOrigin;
+use actix_web::cookie::SameSite;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+// Settings every profile requires, as they would come from the environment
+fn required() -> HashMap<&'static str, String> {
+    HashMap::from([
+        (
+            "DATABASE_URL",
+            "postgres://app@localhost/scylla".to_string(),
+        ),
+        ("REDIS_URL", "redis://localhost:6379".to_string()),
+        ("SESSION_SECRET", "s".repeat(64)),
+    ])
+}
+
+fn settings(file: Option<&Path>, env: &[(&'static str, &str)], overrides: &[&str]) -> Settings {
+    let mut vars = required();
+    for (name, value) in env {
+        vars.insert(name, value.to_string());
+    }
+    let args = ConfigArgs {
+        config: file.map(Path::to_path_buf),
+        overrides: overrides.iter().map(|set| set.to_string()).collect(),
+    };
+
+    Settings::from_env(&args, |name| vars.get(name).cloned())
+}
+
+fn load(env: &[(&'static str, &str)], overrides: &[&str]) -> Result<Config, ConfigError> {
+    settings(None, env, overrides).resolve()
+}
+
+// Writes `content` to a file in `dir`, returning its path as a string
+fn write(dir: &TempDir, name: &str, content: &str) -> String {
+    let path = dir.path().join(name);
+    fs::write(&path, content).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+fn problems(env: &[(&'static str, &str)], overrides: &[&str]) -> Vec<String> {
+    match load(env, overrides) {
+        Ok(_) => panic!("configuration should have been rejected"),
+        Err(ConfigError(problems)) => problems,
+    }
+}
+
+fn rejects(env: &[(&'static str, &str)], overrides: &[&str], needle: &str) {
+    let problems = problems(env, overrides);
+    assert!(
+        problems.iter().any(|problem| problem.contains(needle)),
+        "expected a problem mentioning '{}', got {:?}",
+        needle,
+        problems
+    );
+}
+
+const PROD_ORIGINS: (&str, &str) = ("CORS_ALLOWED_ORIGINS", "https://app.example.com");
+
+#[test]
+fn dev_profile_binds_loopback_with_plain_cookies() {
+    let config = load(&[], &[]).unwrap();
+
+    assert_eq!(config.env, "dev");
+    assert_eq!(config.host, "127.0.0.1");
+    assert_eq!(config.cors.allowed_origins, ["http://localhost:3000"]);
+    assert!(!config.cookie.secure);
+    assert_eq!(config.cookie.same_site(), SameSite::Lax);
+}
+
+#[test]
+fn prod_profile_requires_secure_cookies() {
+    let config = load(&[("ENV", "prod"), PROD_ORIGINS], &[]).unwrap();
+
+    assert_eq!(config.host, "0.0.0.0");
+    assert!(config.cookie.secure);
+    assert_eq!(config.cors.allowed_origins, ["https://app.example.com"]);
+
+    rejects(
+        &[("ENV", "prod"), PROD_ORIGINS, ("COOKIE_SECURE", "false")],
+        &[],
+        "cookie.secure must be true in prod",
+    );
+    rejects(
+        &[("ENV", "staging")],
+        &["cookie.secure=false"],
+        "cookie.secure must be true in staging",
+    );
+}
+
+#[test]
+fn explicit_settings_win_over_the_profile() {
+    let config = load(&[("ENV", "dev"), ("HOST", "0.0.0.0")], &[]).unwrap();
+
+    assert_eq!(config.host, "0.0.0.0");
+}
+
+#[test]
+fn rejects_unknown_profile() {
+    rejects(
+        &[("ENV", "production")],
+        &[],
+        "unknown profile 'production'",
+    );
+}
+
+#[test]
+fn rejects_invalid_origins() {
+    rejects(&[("CORS_ALLOWED_ORIGINS", "*")], &[], "`*`");
+    rejects(
+        &[("CORS_ALLOWED_ORIGINS", "https://app.example.com/")],
+        &[],
+        "did you mean 'https://app.example.com'",
+    );
+    rejects(
+        &[("CORS_ALLOWED_ORIGINS", "https://app.*.example.com")],
+        &[],
+        "wildcard",
+    );
+    rejects(
+        &[("CORS_ALLOWED_ORIGINS", "ftp://example.com")],
+        &[],
+        "http or https",
+    );
+    rejects(
+        &[
+            ("ENV", "prod"),
+            ("CORS_ALLOWED_ORIGINS", "http://app.example.com"),
+        ],
+        &[],
+        "must use https in prod",
+    );
+}
+
+#[test]
+fn rejects_invalid_methods_and_headers() {
+    rejects(&[("CORS_ALLOWED_METHODS", "GET,FETCH")], &[], "'FETCH'");
+    rejects(
+        &[("CORS_ALLOWED_HEADERS", "Content Type")],
+        &[],
+        "not a header name",
+    );
+}
+
+#[test]
+fn rejects_invalid_bind_host() {
+    rejects(&[("HOST", "localhost")], &[], "not an IP address");
+}
+
+#[test]
+fn rejects_zero_purge_retention() {
+    rejects(
+        &[("PURGE_RETENTION_DAYS", "0")],
+        &[],
+        "purge.retention_days must be at least 1",
+    );
+}
+
+#[test]
+fn rejects_invalid_cookie_policy() {
+    rejects(
+        &[("COOKIE_SAME_SITE", "none")],
+        &[],
+        "cookie.same_site = none requires cookie.secure",
+    );
+    rejects(
+        &[("COOKIE_SAME_SITE", "loose")],
+        &[],
+        "must be strict, lax or none",
+    );
+    rejects(
+        &[("COOKIE_DOMAIN", "https://example.com")],
+        &[],
+        "bare domain",
+    );
+    rejects(&[("COOKIE_NAME", "session id")], &[], "cookie.name");
+}
+
+#[test]
+fn reports_every_problem_together() {
+    let problems = problems(
+        &[
+            ("ENV", "prod"),
+            ("HOST", "example.com"),
+            ("CORS_ALLOWED_ORIGINS", "*"),
+            ("COOKIE_SECURE", "false"),
+        ],
+        &[],
+    );
+
+    assert!(problems.len() >= 3, "{:?}", problems);
+}
+
+#[test]
+fn wildcard_origin_matches_subdomains_only() {
+    let allowed = AllowedOrigin::parse("https://*.example.com").unwrap();
+
+    assert!(allowed.matches("https://app.example.com"));
+    assert!(allowed.matches("https://a.b.example.com"));
+    assert!(!allowed.matches("https://example.com"));
+    assert!(!allowed.matches("https://evil-example.com"));
+    assert!(!allowed.matches("https://example.com.evil.com"));
+    assert!(!allowed.matches("http://app.example.com"));
+    assert!(!allowed.matches("https://app.example.com:8443"));
+}
+
+#[test]
+fn exact_origin_matches_itself_only() {
+    let allowed = AllowedOrigin::parse("http://localhost:3000").unwrap();
+
+    assert!(allowed.matches("http://localhost:3000"));
+    assert!(!allowed.matches("http://localhost:3001"));
+    assert!(!allowed.matches("https://localhost:3000"));
+}
+
+#[test]
+fn metrics_on_the_api_listener_require_a_token() {
+    rejects(
+        &[],
+        &["metrics.bind="],
+        "metrics.token is required without metrics.bind",
+    );
+
+    let config = load(&[("METRICS_TOKEN", "scrape")], &["metrics.bind="]).unwrap();
+    assert!(config.metrics.bind.is_empty());
+}
+
+#[test]
+fn rejects_invalid_tracing_settings() {
+    rejects(
+        &[
+            ("TRACING_ENABLED", "true"),
+            ("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT", "collector:4318"),
+        ],
+        &[],
+        "tracing.endpoint 'collector:4318'",
+    );
+    rejects(
+        &[("TRACING_SAMPLE_RATIO", "1.5")],
+        &[],
+        "tracing.sample_ratio must be between 0 and 1",
+    );
+
+    let config = load(&[("TRACING_SAMPLE_RATIO", "0.25")], &[]).unwrap();
+    assert_eq!(config.tracing.sample_ratio, 0.25);
+}
+
+#[test]
+fn layers_file_environment_and_flags_in_order() {
+    let dir = TempDir::new().unwrap();
+    let file = write(
+        &dir,
+        "scylla.toml",
+        "port = 9000\n[database]\nmax_connections = 20\n[smtp]\nport = 2525\n",
+    );
+    let file = Path::new(&file);
+
+    let config = settings(Some(file), &[], &[]).resolve().unwrap();
+    assert_eq!(config.port, 9000);
+    assert_eq!(config.database.max_connections, 20);
+
+    let config = settings(Some(file), &[("PORT", "9001")], &[])
+        .resolve()
+        .unwrap();
+    assert_eq!(config.port, 9001);
+    assert_eq!(config.database.max_connections, 20);
+
+    let config = settings(
+        Some(file),
+        &[("PORT", "9001")],
+        &["port=9002", "smtp.port=587"],
+    )
+    .resolve()
+    .unwrap();
+    assert_eq!(config.port, 9002);
+    assert_eq!(config.smtp.port, 587);
+}
+
+#[test]
+fn profile_only_replaces_defaults() {
+    let dir = TempDir::new().unwrap();
+    let file = write(&dir, "scylla.toml", "[log]\nformat = \"json\"\n");
+
+    let config = load(&[], &[]).unwrap();
+    assert_eq!(config.log.format, "text");
+
+    let config = settings(Some(Path::new(&file)), &[], &[])
+        .resolve()
+        .unwrap();
+    assert_eq!(config.log.format, "json");
+}
+
+#[test]
+fn rejects_unreadable_and_invalid_files() {
+    let dir = TempDir::new().unwrap();
+    let invalid = write(&dir, "invalid.toml", "port = \n");
+    let unknown = write(&dir, "unknown.toml", "[database]\npool = 5\n");
+    let missing = dir.path().join("missing.toml");
+
+    for (file, needle) in [
+        (Path::new(&invalid), "invalid.toml"),
+        (Path::new(&unknown), "database.pool"),
+        (missing.as_path(), "missing.toml"),
+    ] {
+        let problems = match settings(Some(file), &[], &[]).resolve() {
+            Ok(_) => panic!("{} should have been rejected", file.display()),
+            Err(ConfigError(problems)) => problems,
+        };
+        assert!(
+            problems.iter().any(|problem| problem.contains(needle)),
+            "expected a problem mentioning '{}', got {:?}",
+            needle,
+            problems
+        );
+    }
+}
+
+#[test]
+fn reads_secrets_from_files() {
+    let dir = TempDir::new().unwrap();
+    let secret = "f".repeat(64);
+    let path = write(&dir, "session_secret", &format!("{}\r\n", secret));
+
+    let config = settings(
+        None,
+        &[("SESSION_SECRET", ""), ("SESSION_SECRET_FILE", &path)],
+        &[],
+    )
+    .resolve()
+    .unwrap();
+
+    assert_eq!(config.session_secret, secret);
+}
+
+#[test]
+fn rejects_conflicting_and_missing_secret_files() {
+    let dir = TempDir::new().unwrap();
+    let path = write(&dir, "session_secret", &"f".repeat(64));
+    let missing = dir.path().join("missing").to_string_lossy().into_owned();
+
+    rejects(
+        &[("SESSION_SECRET_FILE", &path)],
+        &[],
+        "$SESSION_SECRET and $SESSION_SECRET_FILE are both set",
+    );
+    rejects(
+        &[("SESSION_SECRET", ""), ("SESSION_SECRET_FILE", &missing)],
+        &[],
+        "$SESSION_SECRET_FILE: cannot read",
+    );
+}
+
+#[test]
+fn redacts_secrets_and_url_passwords() {
+    let redacted = settings(
+        None,
+        &[
+            ("DATABASE_URL", "postgres://app:hunter2@db/scylla"),
+            ("SMTP_PASSWORD", "smtp-secret"),
+        ],
+        &["metrics.token=scrape-secret"],
+    )
+    .redacted();
+
+    for secret in ["hunter2", "smtp-secret", "scrape-secret", &"s".repeat(64)] {
+        assert!(
+            !redacted.contains(secret),
+            "{} leaked:\n{}",
+            secret,
+            redacted
+        );
+    }
+    assert!(
+        redacted.contains("database_url = \"postgres://app:redacted@db/scylla\"  # $DATABASE_URL")
+    );
+    assert!(redacted.contains("session_secret = \"<redacted>\"  # $SESSION_SECRET"));
+    assert!(redacted.contains("metrics.token = \"<redacted>\"  # --set"));
+    assert!(redacted.contains("redis_url = \"redis://localhost:6379\"  # $REDIS_URL"));
+    assert!(redacted.contains("log.format = \"text\"  # profile dev"));
+}
@@ -0,0 +1,82 @@
+use actix_poc_scylla::utils::envelope::{Envelope, MasterKey};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use uuid::Uuid;
+
+fn master_key(byte: u8) -> MasterKey {
+    MasterKey::from_base64(&STANDARD.encode([byte; 32])).unwrap()
+}
+
+fn copy(envelope: &Envelope) -> Envelope {
+    Envelope {
+        ciphertext: envelope.ciphertext.clone(),
+        nonce: envelope.nonce.clone(),
+        encrypted_data_key: envelope.encrypted_data_key.clone(),
+        data_key_nonce: envelope.data_key_nonce.clone(),
+    }
+}
+
+#[test]
+fn opens_what_it_sealed() {
+    let key = master_key(7);
+    let repo_id = Uuid::new_v4();
+
+    let envelope = key.seal(b"deploy key", repo_id.as_bytes()).unwrap();
+
+    assert_ne!(envelope.ciphertext, b"deploy key");
+    assert_eq!(
+        key.open(&envelope, repo_id.as_bytes()).unwrap().as_slice(),
+        b"deploy key"
+    );
+}
+
+#[test]
+fn does_not_open_for_another_repository() {
+    let key = master_key(7);
+    let (repo_id, other_repo_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+    let envelope = key.seal(b"deploy key", repo_id.as_bytes()).unwrap();
+
+    // As if the row had been copied to the other repository
+    assert!(key.open(&envelope, other_repo_id.as_bytes()).is_err());
+    assert!(key.open(&envelope, b"").is_err());
+}
+
+#[test]
+fn does_not_open_with_another_master_key() {
+    let repo_id = Uuid::new_v4();
+    let envelope = master_key(7)
+        .seal(b"deploy key", repo_id.as_bytes())
+        .unwrap();
+
+    assert!(master_key(8).open(&envelope, repo_id.as_bytes()).is_err());
+}
+
+#[test]
+fn detects_tampering() {
+    let key = master_key(7);
+    let repo_id = Uuid::new_v4();
+    let envelope = key.seal(b"deploy key", repo_id.as_bytes()).unwrap();
+
+    let mut tampered = copy(&envelope);
+    tampered.ciphertext[0] ^= 1;
+    assert!(key.open(&tampered, repo_id.as_bytes()).is_err());
+
+    let mut tampered = copy(&envelope);
+    tampered.encrypted_data_key[0] ^= 1;
+    assert!(key.open(&tampered, repo_id.as_bytes()).is_err());
+
+    let mut tampered = copy(&envelope);
+    tampered.nonce[0] ^= 1;
+    assert!(key.open(&tampered, repo_id.as_bytes()).is_err());
+
+    let mut tampered = copy(&envelope);
+    tampered.data_key_nonce.pop();
+    assert!(key.open(&tampered, repo_id.as_bytes()).is_err());
+}
+
+#[test]
+fn rejects_malformed_master_keys() {
+    assert!(MasterKey::from_base64("not base64!").is_err());
+    assert!(MasterKey::from_base64(&STANDARD.encode([0u8; 16])).is_err());
+}
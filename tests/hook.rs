@@ -0,0 +1,233 @@
+use actix_poc_scylla::modules::hook::dto::{Provider, RefAction, RepoEvent};
+use actix_poc_scylla::modules::hook::service::HookService;
+use serde_json::json;
+
+// Example from GitHub's documentation on validating webhook deliveries
+const SECRET: &[u8] = b"It's a Secret to Everybody";
+const PAYLOAD: &[u8] = b"Hello, World!";
+const SIGNATURE: &str = "757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+const ZERO_SHA: &str = "0000000000000000000000000000000000000000";
+const BEFORE: &str = "6113728f27ae82c7b1a177c8d03f9e96e0adf246";
+const AFTER: &str = "59b20b8d5c6ff8d09518454d4dd8b7b30f095ab5";
+
+#[test]
+fn accepts_github_signatures() {
+    let header = format!("sha256={}", SIGNATURE);
+
+    assert!(HookService::verify(
+        Provider::GitHub,
+        Some(&header),
+        SECRET,
+        PAYLOAD
+    ));
+}
+
+#[test]
+fn rejects_invalid_github_signatures() {
+    let valid = format!("sha256={}", SIGNATURE);
+    let other_secret = b"It's not a secret".as_slice();
+
+    // Missing prefix, other algorithm, no header, other secret or body
+    for (signature, secret, payload) in [
+        (Some(SIGNATURE), SECRET, PAYLOAD),
+        (Some(&*format!("sha1={}", SIGNATURE)), SECRET, PAYLOAD),
+        (Some("sha256=not-hex"), SECRET, PAYLOAD),
+        (None, SECRET, PAYLOAD),
+        (Some(valid.as_str()), other_secret, PAYLOAD),
+        (Some(valid.as_str()), SECRET, b"Hello, World?".as_slice()),
+    ] {
+        assert!(!HookService::verify(
+            Provider::GitHub,
+            signature,
+            secret,
+            payload
+        ));
+    }
+}
+
+#[test]
+fn accepts_gitea_signatures_without_prefix() {
+    assert!(HookService::verify(
+        Provider::Gitea,
+        Some(SIGNATURE),
+        SECRET,
+        PAYLOAD
+    ));
+    assert!(!HookService::verify(
+        Provider::Gitea,
+        Some(&format!("sha256={}", SIGNATURE)),
+        SECRET,
+        PAYLOAD
+    ));
+}
+
+#[test]
+fn compares_the_gitlab_token_to_the_secret() {
+    assert!(HookService::verify(
+        Provider::GitLab,
+        Some("It's a Secret to Everybody"),
+        SECRET,
+        PAYLOAD
+    ));
+
+    for token in [
+        Some("It's a Secret to Everybod"),
+        Some("It's a Secret to Everybody!"),
+        Some(""),
+        Some(SIGNATURE),
+        None,
+    ] {
+        assert!(!HookService::verify(
+            Provider::GitLab,
+            token,
+            SECRET,
+            PAYLOAD
+        ));
+    }
+}
+
+#[test]
+fn parses_github_pushes() {
+    let payload = json!({
+        "ref": "refs/heads/main",
+        "before": BEFORE,
+        "after": AFTER,
+        "commits": [{ "id": AFTER }, { "id": BEFORE }],
+        "pusher": { "name": "octocat" },
+    });
+
+    let event = HookService::parse(Provider::GitHub, "push", &payload.to_string()).unwrap();
+
+    assert_eq!(
+        event,
+        Some(RepoEvent::Push {
+            branch: "main".into(),
+            action: RefAction::Updated,
+            before: BEFORE.into(),
+            after: AFTER.into(),
+            commit_count: 2,
+            pusher: Some("octocat".into()),
+        })
+    );
+}
+
+#[test]
+fn parses_gitlab_pushes() {
+    let payload = json!({
+        "ref": "refs/heads/feature/login",
+        "before": ZERO_SHA,
+        "after": AFTER,
+        "total_commits_count": 3,
+        "commits": [],
+        "user_username": "jsmith",
+    });
+
+    let event = HookService::parse(Provider::GitLab, "Push Hook", &payload.to_string()).unwrap();
+
+    assert_eq!(
+        event,
+        Some(RepoEvent::Push {
+            branch: "feature/login".into(),
+            action: RefAction::Created,
+            before: ZERO_SHA.into(),
+            after: AFTER.into(),
+            commit_count: 3,
+            pusher: Some("jsmith".into()),
+        })
+    );
+}
+
+#[test]
+fn parses_tag_pushes() {
+    let payload = json!({
+        "ref": "refs/tags/v1.0.0",
+        "before": BEFORE,
+        "after": ZERO_SHA,
+    });
+
+    let event = HookService::parse(Provider::Gitea, "push", &payload.to_string()).unwrap();
+
+    assert_eq!(
+        event,
+        Some(RepoEvent::Tag {
+            name: "v1.0.0".into(),
+            action: RefAction::Deleted,
+            sha: ZERO_SHA.into(),
+        })
+    );
+}
+
+#[test]
+fn parses_merged_pull_requests() {
+    let payload = json!({
+        "action": "closed",
+        "number": 42,
+        "pull_request": {
+            "title": "Add login",
+            "merged": true,
+            "head": { "ref": "feature/login", "sha": AFTER },
+            "base": { "ref": "main" },
+        },
+    });
+
+    let event = HookService::parse(Provider::GitHub, "pull_request", &payload.to_string()).unwrap();
+
+    assert_eq!(
+        event,
+        Some(RepoEvent::PullRequest {
+            number: 42,
+            action: "merged".into(),
+            title: "Add login".into(),
+            source_branch: "feature/login".into(),
+            target_branch: "main".into(),
+            head_sha: AFTER.into(),
+        })
+    );
+}
+
+#[test]
+fn parses_gitlab_merge_requests() {
+    let payload = json!({
+        "object_attributes": {
+            "action": "update",
+            "iid": 7,
+            "title": "Add login",
+            "source_branch": "feature/login",
+            "target_branch": "main",
+            "last_commit": { "id": AFTER },
+        },
+    });
+
+    let event =
+        HookService::parse(Provider::GitLab, "Merge Request Hook", &payload.to_string()).unwrap();
+
+    assert_eq!(
+        event,
+        Some(RepoEvent::PullRequest {
+            number: 7,
+            action: "updated".into(),
+            title: "Add login".into(),
+            source_branch: "feature/login".into(),
+            target_branch: "main".into(),
+            head_sha: AFTER.into(),
+        })
+    );
+}
+
+#[test]
+fn ignores_other_events() {
+    let event = HookService::parse(Provider::GitHub, "issues", "{}").unwrap();
+
+    assert_eq!(event, None);
+}
+
+#[test]
+fn rejects_malformed_payloads() {
+    let missing_after = json!({ "ref": "refs/heads/main", "before": BEFORE });
+    let other_ref = json!({ "ref": "refs/notes/commits", "before": BEFORE, "after": AFTER });
+
+    assert!(HookService::parse(Provider::GitHub, "push", "not json").is_err());
+    assert!(HookService::parse(Provider::GitHub, "push", &missing_after.to_string()).is_err());
+    assert!(HookService::parse(Provider::GitHub, "push", &other_ref.to_string()).is_err());
+}
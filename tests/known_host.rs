@@ -0,0 +1,71 @@
+#[macro_use]
+mod common;
+
+use actix_poc_scylla::modules::known_host::routes::config_organization_routes;
+use actix_web::http::{Method, StatusCode};
+use actix_web::test::{call_and_read_body_json, call_service, TestRequest};
+use actix_web::web;
+use serde_json::{json, Value};
+
+const GITHUB: &str =
+    "github.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl";
+
+fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/organizations").configure(config_organization_routes));
+}
+
+#[actix_web::test]
+async fn only_managers_change_known_hosts() {
+    let Some(pool) = common::database() else {
+        return;
+    };
+    let mut conn = pool.get().unwrap();
+    let owner = common::user(&mut conn);
+    let member = common::user(&mut conn);
+    let organization = common::organization(&mut conn, &[(&owner, "owner"), (&member, "member")]);
+    drop(conn);
+    let app = app!(pool, routes);
+    let owner_cookie = session!(app, owner.id);
+    let member_cookie = session!(app, member.id);
+    let base = format!("/organizations/{}/known-hosts", organization.id);
+
+    let import = TestRequest::post()
+        .uri(&base)
+        .set_json(json!({ "lines": GITHUB }));
+    let res = call_service(&app, import.cookie(member_cookie.clone()).to_request()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let import = TestRequest::post()
+        .uri(&base)
+        .set_json(json!({ "lines": GITHUB }));
+    let res = call_service(&app, import.cookie(owner_cookie.clone()).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Members still see the pinned keys
+    let list = TestRequest::get().uri(&base).cookie(member_cookie.clone());
+    let body: Value = call_and_read_body_json(&app, list.to_request()).await;
+    let key = &body["data"][0];
+    let key_uri = format!("{}/{}", base, key["id"].as_str().unwrap());
+
+    for (method, uri, body) in [
+        (
+            Method::POST,
+            format!("{}/confirm", key_uri),
+            Some(json!({ "fingerprint": key["fingerprint"] })),
+        ),
+        (Method::DELETE, key_uri.clone(), None),
+    ] {
+        let mut req = TestRequest::default()
+            .method(method.clone())
+            .uri(&uri)
+            .cookie(member_cookie.clone());
+        if let Some(body) = body {
+            req = req.set_json(body);
+        }
+        let res = call_service(&app, req.to_request()).await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{} {}", method, uri);
+    }
+
+    let delete = TestRequest::delete().uri(&key_uri).cookie(owner_cookie);
+    let res = call_service(&app, delete.to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
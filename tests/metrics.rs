@@ -0,0 +1,40 @@
+use actix_poc_scylla::utils::metrics::{self, METRICS};
+use actix_web::http::Method;
+use actix_web::middleware::from_fn;
+use actix_web::test::{call_service, init_service, TestRequest};
+use actix_web::{web, App, HttpResponse};
+
+#[test]
+fn labels_extension_methods_as_other() {
+    for (method, label) in [
+        (Method::GET, "GET"),
+        (Method::DELETE, "DELETE"),
+        (Method::from_bytes(b"PROPFIND").unwrap(), "other"),
+        (Method::from_bytes(b"X-SCAN-1").unwrap(), "other"),
+    ] {
+        assert_eq!(metrics::method_label(&method), label);
+    }
+}
+
+#[actix_web::test]
+async fn tracks_requests_without_a_series_per_method() {
+    let app = init_service(
+        App::new()
+            .wrap(from_fn(metrics::track))
+            .route("/ping", web::to(HttpResponse::Ok)),
+    )
+    .await;
+
+    for method in ["BREW", "X-RANDOM-42"] {
+        let req = TestRequest::default()
+            .method(Method::from_bytes(method.as_bytes()).unwrap())
+            .uri("/ping")
+            .to_request();
+        call_service(&app, req).await;
+    }
+
+    let rendered = METRICS.render().unwrap();
+    assert!(rendered.contains(r#"method="other",route="/ping",status="200""#));
+    assert!(!rendered.contains("BREW"));
+    assert!(!rendered.contains("X-RANDOM-42"));
+}
@@ -0,0 +1,100 @@
+use actix_poc_scylla::git::mirror;
+use actix_poc_scylla::git::remote::RemoteAuth;
+use git2::{Oid, Repository, Signature};
+use tempfile::TempDir;
+
+// Bare repository with one commit on `main` and a `feature` branch
+fn bare_repo() -> (TempDir, String, Oid) {
+    let dir = TempDir::new().unwrap();
+    let repo = Repository::init_bare(dir.path()).unwrap();
+
+    let tree_id = repo.treebuilder(None).unwrap().write().unwrap();
+    let tree = repo.find_tree(tree_id).unwrap();
+    let author = Signature::now("Test", "test@example.com").unwrap();
+    let commit = repo
+        .commit(
+            Some("refs/heads/main"),
+            &author,
+            &author,
+            "Initial commit",
+            &tree,
+            &[],
+        )
+        .unwrap();
+    repo.reference("refs/heads/feature", commit, false, "branch")
+        .unwrap();
+    repo.set_head("refs/heads/main").unwrap();
+
+    let url = format!("file://{}", dir.path().display());
+    (dir, url, commit)
+}
+
+fn sync(url: &str, target: &TempDir) -> mirror::MirrorStats {
+    mirror::sync(
+        url,
+        &target.path().join("mirror.git"),
+        &RemoteAuth::default(),
+        &|| false,
+    )
+    .unwrap()
+}
+
+#[test]
+fn clones_a_local_bare_repository() {
+    let (_origin, url, commit) = bare_repo();
+    let target = TempDir::new().unwrap();
+
+    let stats = sync(&url, &target);
+
+    assert_eq!(stats.default_branch.as_deref(), Some("main"));
+    assert!(stats.size_bytes > 0);
+    let mirror = Repository::open_bare(target.path().join("mirror.git")).unwrap();
+    assert_eq!(mirror.refname_to_id("refs/heads/main").unwrap(), commit);
+    assert_eq!(mirror.refname_to_id("refs/heads/feature").unwrap(), commit);
+    assert_eq!(mirror.head().unwrap().name(), Some("refs/heads/main"));
+}
+
+#[test]
+fn fetch_prunes_refs_deleted_on_the_remote() {
+    let (origin, url, commit) = bare_repo();
+    let target = TempDir::new().unwrap();
+    sync(&url, &target);
+
+    let remote = Repository::open_bare(origin.path()).unwrap();
+    remote
+        .find_reference("refs/heads/feature")
+        .unwrap()
+        .delete()
+        .unwrap();
+    remote
+        .reference("refs/heads/release", commit, false, "branch")
+        .unwrap();
+    sync(&url, &target);
+
+    let mirror = Repository::open_bare(target.path().join("mirror.git")).unwrap();
+    assert!(mirror.find_reference("refs/heads/feature").is_err());
+    assert_eq!(mirror.refname_to_id("refs/heads/release").unwrap(), commit);
+    assert_eq!(mirror.refname_to_id("refs/heads/main").unwrap(), commit);
+}
+
+#[test]
+fn fetch_follows_forced_updates() {
+    let (origin, url, commit) = bare_repo();
+    let target = TempDir::new().unwrap();
+    sync(&url, &target);
+
+    // Rewrite `main` with an unrelated commit
+    let remote = Repository::open_bare(origin.path()).unwrap();
+    let tree = remote.find_commit(commit).unwrap().tree().unwrap();
+    let author = Signature::now("Test", "test@example.com").unwrap();
+    let rewritten = remote
+        .commit(None, &author, &author, "Rewritten", &tree, &[])
+        .unwrap();
+    remote
+        .reference("refs/heads/main", rewritten, true, "force push")
+        .unwrap();
+    sync(&url, &target);
+
+    let mirror = Repository::open_bare(target.path().join("mirror.git")).unwrap();
+    assert_eq!(mirror.refname_to_id("refs/heads/main").unwrap(), rewritten);
+}
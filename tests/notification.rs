@@ -0,0 +1,136 @@
+mod common;
+
+use actix_poc_scylla::config::SmtpConfig;
+use actix_poc_scylla::modules::notification::service::NotificationService;
+use actix_poc_scylla::modules::outbox::dto::DomainEvent;
+use actix_poc_scylla::schema::users;
+use diesel::prelude::*;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use uuid::Uuid;
+
+// SMTP server accepting every message, keeping their recipients
+fn smtp_server() -> (u16, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let recipients = Arc::new(Mutex::new(Vec::new()));
+
+    let received = recipients.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            stream.write_all(b"220 localhost\r\n").unwrap();
+
+            let mut in_data = false;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                let reply: &[u8] = match line.trim_end() {
+                    "." if in_data => {
+                        in_data = false;
+                        b"250 OK\r\n"
+                    }
+                    _ if in_data => b"",
+                    command if command.starts_with("RCPT TO:") => {
+                        received.lock().unwrap().push(command[8..].to_string());
+                        b"250 OK\r\n"
+                    }
+                    "DATA" => {
+                        in_data = true;
+                        b"354 Go ahead\r\n"
+                    }
+                    "QUIT" => {
+                        let _ = stream.write_all(b"221 Bye\r\n");
+                        break;
+                    }
+                    _ => b"250 OK\r\n",
+                };
+                if stream.write_all(reply).is_err() {
+                    break;
+                }
+                line.clear();
+            }
+        }
+    });
+
+    (port, recipients)
+}
+
+fn smtp(port: u16) -> SmtpConfig {
+    SmtpConfig {
+        server: "127.0.0.1".to_string(),
+        port,
+        username: String::new(),
+        password: String::new(),
+        email_from: "noreply@example.com".to_string(),
+        frontend_url: "http://localhost:3000".to_string(),
+        tls_mode: "none".to_string(),
+    }
+}
+
+fn host_key_changed(organization_id: Uuid) -> DomainEvent {
+    DomainEvent::HostKeyChanged {
+        organization_id,
+        host: "github.com".to_string(),
+        key_type: "ssh-ed25519".to_string(),
+        fingerprint: "SHA256:+DiY3wvvV6TuJJhbpZisF/zLDA0zPMSvHdkr4UvCOqU".to_string(),
+    }
+}
+
+#[test]
+fn skips_managers_with_invalid_emails() {
+    let Some(pool) = common::database() else {
+        return;
+    };
+    let mut conn = pool.get().unwrap();
+    let owner = common::user(&mut conn);
+    let admin = common::user(&mut conn);
+    let broken = common::user(&mut conn);
+    let member = common::user(&mut conn);
+    diesel::update(users::table.find(broken.id))
+        .set(users::email.eq(format!("not an email {}", broken.id)))
+        .execute(&mut conn)
+        .unwrap();
+    let organization = common::organization(
+        &mut conn,
+        &[
+            (&owner, "owner"),
+            (&broken, "admin"),
+            (&admin, "admin"),
+            (&member, "member"),
+        ],
+    );
+    let (port, recipients) = smtp_server();
+
+    NotificationService::publish(&mut conn, &smtp(port), &host_key_changed(organization.id))
+        .unwrap();
+
+    let mut recipients = recipients.lock().unwrap().clone();
+    recipients.sort();
+    let mut expected = vec![format!("<{}>", owner.email), format!("<{}>", admin.email)];
+    expected.sort();
+    assert_eq!(recipients, expected);
+}
+
+#[test]
+fn fails_when_nobody_could_be_emailed() {
+    let Some(pool) = common::database() else {
+        return;
+    };
+    let mut conn = pool.get().unwrap();
+    let owner = common::user(&mut conn);
+    let organization = common::organization(&mut conn, &[(&owner, "owner")]);
+    // Nothing listens there once the listener is dropped
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let result =
+        NotificationService::publish(&mut conn, &smtp(port), &host_key_changed(organization.id));
+
+    assert!(result.is_err());
+}
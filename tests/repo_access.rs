@@ -0,0 +1,160 @@
+#[macro_use]
+mod common;
+
+use actix_poc_scylla::config::GitConfig;
+use actix_poc_scylla::models::{Repo, Team};
+use actix_poc_scylla::modules::repo::dto::RepoCreateQuery;
+use actix_poc_scylla::modules::repo::routes::config_routes;
+use actix_poc_scylla::modules::repo::service::RepoService;
+use actix_poc_scylla::modules::team::dto::TeamCreateQuery;
+use actix_poc_scylla::modules::team::service::TeamService;
+use actix_web::http::{Method, StatusCode};
+use actix_web::test::{call_service, TestRequest};
+use common::DbPool;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+// Every team access route, with the body it expects
+fn requests(repo_id: Uuid, team_id: Uuid) -> Vec<(Method, String, Option<Value>)> {
+    vec![
+        (
+            Method::POST,
+            format!("/repositories/{}/teams", repo_id),
+            Some(json!({ "team_id": team_id, "permission": "admin" })),
+        ),
+        (
+            Method::PUT,
+            format!("/repositories/{}/teams/{}", repo_id, team_id),
+            Some(json!({ "permission": "admin" })),
+        ),
+        (
+            Method::DELETE,
+            format!("/repositories/{}/teams/{}", repo_id, team_id),
+            None,
+        ),
+        (
+            Method::GET,
+            format!("/repositories/{}/access", repo_id),
+            None,
+        ),
+    ]
+}
+
+fn request(method: &Method, uri: &str, body: &Option<Value>) -> TestRequest {
+    let req = TestRequest::default().method(method.clone()).uri(uri);
+    match body {
+        Some(body) => req.set_json(body),
+        None => req,
+    }
+}
+
+fn repository(conn: &mut PgConnection, organization_id: Uuid) -> (Repo, Team) {
+    let git = GitConfig {
+        timeout_seconds: 5,
+        allow_file_remotes: false,
+        allow_http_remotes: false,
+    };
+    let repo = RepoService::create(
+        conn,
+        &RepoCreateQuery {
+            name: "Hello World".to_string(),
+            url: "https://github.com/octocat/Hello-World.git".to_string(),
+            organization_id,
+            slug: None,
+            sync_interval_minutes: Some(0),
+        },
+        &git,
+        &common::audit(),
+    )
+    .unwrap();
+    let team = TeamService::create(
+        conn,
+        &TeamCreateQuery {
+            name: "Core".to_string(),
+            description: None,
+            organization_id,
+            slug: None,
+        },
+        &common::audit(),
+    )
+    .unwrap();
+
+    (repo, team)
+}
+
+#[actix_web::test]
+async fn anonymous_requests_are_refused() {
+    // Never connected to, the session is checked first
+    let pool: DbPool = Pool::builder()
+        .build_unchecked(ConnectionManager::new("postgres://localhost:1/unreachable"));
+    let app = app!(pool, config_routes);
+
+    for (method, uri, body) in requests(Uuid::new_v4(), Uuid::new_v4()) {
+        let res = call_service(&app, request(&method, &uri, &body).to_request()).await;
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{} {}", method, uri);
+    }
+}
+
+#[actix_web::test]
+async fn only_managers_change_team_access() {
+    let Some(pool) = common::database() else {
+        return;
+    };
+    let mut conn = pool.get().unwrap();
+    let owner = common::user(&mut conn);
+    let member = common::user(&mut conn);
+    let outsider = common::user(&mut conn);
+    let organization = common::organization(&mut conn, &[(&owner, "owner"), (&member, "member")]);
+    let (repo, team) = repository(&mut conn, organization.id);
+    drop(conn);
+    let app = app!(pool.clone(), config_routes);
+
+    let member_cookie = session!(app, member.id);
+    let outsider_cookie = session!(app, outsider.id);
+    let owner_cookie = session!(app, owner.id);
+
+    // Members may list access but not change it, outsiders may do neither
+    for (method, uri, body) in requests(repo.id, team.id) {
+        let expected = match method {
+            Method::GET => StatusCode::OK,
+            _ => StatusCode::FORBIDDEN,
+        };
+        let req = request(&method, &uri, &body).cookie(member_cookie.clone());
+        let res = call_service(&app, req.to_request()).await;
+        assert_eq!(res.status(), expected, "member {} {}", method, uri);
+
+        let req = request(&method, &uri, &body).cookie(outsider_cookie.clone());
+        let res = call_service(&app, req.to_request()).await;
+        assert_eq!(
+            res.status(),
+            StatusCode::FORBIDDEN,
+            "outsider {} {}",
+            method,
+            uri
+        );
+    }
+
+    // Owners may, with 404 once there is no grant left and 409 for a
+    // second one
+    let [grant, update, revoke, _] = &requests(repo.id, team.id)[..] else {
+        unreachable!();
+    };
+    for ((method, uri, body), expected) in [
+        (grant, StatusCode::CREATED),
+        (grant, StatusCode::CONFLICT),
+        (update, StatusCode::OK),
+        (revoke, StatusCode::OK),
+        (revoke, StatusCode::NOT_FOUND),
+        (update, StatusCode::NOT_FOUND),
+    ] {
+        let req = request(method, uri, body).cookie(owner_cookie.clone());
+        let res = call_service(&app, req.to_request()).await;
+        assert_eq!(res.status(), expected, "owner {} {}", method, uri);
+    }
+
+    let grants = RepoService::find_grant(&mut pool.get().unwrap(), repo.id, team.id).unwrap();
+    assert!(grants.is_none());
+}
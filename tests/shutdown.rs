@@ -0,0 +1,167 @@
+use actix_poc_scylla::config::{Config, ConfigArgs, Settings};
+use actix_poc_scylla::db::DbPool;
+use actix_poc_scylla::modules::health::dto::{
+    CheckResult, CheckStatus, Readiness, ReadinessChecks,
+};
+use actix_poc_scylla::modules::health::handler::ready;
+use actix_poc_scylla::modules::health::service::ReadinessCache;
+use actix_poc_scylla::utils::shutdown::Shutdown;
+use actix_web::rt::{self, time};
+use actix_web::test::{call_service, init_service, TestRequest};
+use actix_web::{web, App, HttpResponse, HttpServer};
+use chrono::Utc;
+use diesel::r2d2::{ConnectionManager, Pool};
+use std::collections::HashMap;
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+fn config() -> Config {
+    let vars = HashMap::from([
+        (
+            "DATABASE_URL",
+            "postgres://app@localhost/scylla".to_string(),
+        ),
+        ("REDIS_URL", "redis://localhost:6379".to_string()),
+        ("SESSION_SECRET", "s".repeat(64)),
+    ]);
+
+    Settings::from_env(&ConfigArgs::default(), |name| vars.get(name).cloned())
+        .resolve()
+        .unwrap()
+}
+
+// Readiness as last seen by the cache, before anything went wrong
+fn up() -> Readiness {
+    Readiness {
+        status: CheckStatus::Up,
+        checked_at: Utc::now(),
+        checks: ReadinessChecks {
+            database: CheckResult::up(Duration::ZERO),
+            migrations: CheckResult::up(Duration::ZERO),
+            redis: CheckResult::up(Duration::ZERO),
+            smtp: CheckResult::skipped(),
+        },
+    }
+}
+
+#[actix_web::test]
+async fn readiness_fails_as_soon_as_shutdown_begins() {
+    let config = config();
+    let pool: DbPool =
+        Pool::builder().build_unchecked(ConnectionManager::new(config.database_url.clone()));
+    let redis = redis::Client::open(config.redis_url.as_str()).unwrap();
+    let cache = ReadinessCache::new(Duration::from_secs(60));
+    cache.put(&up());
+    let shutdown = Shutdown::new(Duration::from_secs(5));
+
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(pool))
+            .app_data(web::Data::new(redis))
+            .app_data(web::Data::new(config))
+            .app_data(web::Data::new(cache))
+            .app_data(web::Data::new(shutdown.clone()))
+            .route("/health/ready", web::get().to(ready)),
+    )
+    .await;
+
+    let res = call_service(&app, TestRequest::get().uri("/health/ready").to_request()).await;
+    assert_eq!(res.status(), 200);
+
+    shutdown.begin();
+    let res = call_service(&app, TestRequest::get().uri("/health/ready").to_request()).await;
+    assert_eq!(res.status(), 503);
+}
+
+#[actix_web::test]
+async fn drains_in_flight_requests_and_workers() {
+    let shutdown = Shutdown::new(Duration::from_secs(5));
+
+    // A worker polling often, with tasks longer than its interval
+    let started = Arc::new(AtomicUsize::new(0));
+    let finished = Arc::new(AtomicUsize::new(0));
+    let worker = shutdown.clone();
+    let (worker_started, worker_finished) = (started.clone(), finished.clone());
+    shutdown.spawn(async move {
+        let mut interval = time::interval(Duration::from_millis(20));
+        while worker.tick(&mut interval).await {
+            worker_started.fetch_add(1, Ordering::SeqCst);
+            time::sleep(Duration::from_millis(300)).await;
+            worker_finished.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    let arrived = web::Data::new(AtomicBool::new(false));
+    let handler_arrived = arrived.clone();
+    let server = HttpServer::new(move || {
+        App::new().app_data(handler_arrived.clone()).route(
+            "/slow",
+            web::get().to(|arrived: web::Data<AtomicBool>| async move {
+                arrived.store(true, Ordering::SeqCst);
+                time::sleep(Duration::from_millis(500)).await;
+                HttpResponse::Ok().body("done")
+            }),
+        )
+    })
+    .workers(1)
+    .disable_signals()
+    .shutdown_timeout(5)
+    .bind("127.0.0.1:0")
+    .unwrap();
+    let addr = server.addrs()[0];
+    let server = server.run();
+    shutdown.stop_server(server.handle());
+    let server = rt::spawn(server);
+
+    let in_flight = rt::spawn(reqwest::get(format!("http://{}/slow", addr)));
+    while !arrived.load(Ordering::SeqCst) {
+        time::sleep(Duration::from_millis(10)).await;
+    }
+
+    shutdown.begin();
+    let started_before = started.load(Ordering::SeqCst);
+    time::sleep(Duration::from_millis(100)).await;
+
+    // New connections are refused while the request is still running
+    assert!(TcpStream::connect(addr).is_err());
+    let res = in_flight.await.unwrap().unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.text().await.unwrap(), "done");
+    server.await.unwrap().unwrap();
+
+    // The task running at the time was finished, no other was started
+    assert!(shutdown.drain().await);
+    assert!(started_before > 0);
+    assert_eq!(started.load(Ordering::SeqCst), started_before);
+    assert_eq!(finished.load(Ordering::SeqCst), started_before);
+}
+
+#[actix_web::test]
+async fn interrupts_tasks_past_the_timeout() {
+    let timeout = Duration::from_millis(200);
+    let shutdown = Shutdown::new(timeout);
+
+    // Blocking work that only stops when told to, like a mirror fetch
+    let checkpointed = Arc::new(AtomicBool::new(false));
+    let task = shutdown.clone();
+    let task_checkpointed = checkpointed.clone();
+    shutdown.spawn(async move {
+        web::block(move || {
+            while !task.is_overdue() {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            task_checkpointed.store(true, Ordering::SeqCst);
+        })
+        .await
+        .unwrap();
+    });
+
+    let began = Instant::now();
+    shutdown.begin();
+    assert!(!shutdown.drain().await);
+    assert!(checkpointed.load(Ordering::SeqCst));
+    assert!(began.elapsed() >= timeout);
+    assert!(began.elapsed() < Duration::from_secs(2));
+}
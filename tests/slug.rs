@@ -0,0 +1,90 @@
+use actix_poc_scylla::utils::slug::{slugify, unique_slug, validate_slug, MAX_SLUG_LENGTH};
+
+#[test]
+fn slugify_lowercases_and_collapses_separators() {
+    assert_eq!(slugify("Acme Corporation"), "acme-corporation");
+    assert_eq!(slugify("  Acme -- Corp!  "), "acme-corp");
+    assert_eq!(slugify("Café API"), "caf-api");
+    assert_eq!(slugify("!!!"), "");
+}
+
+#[test]
+fn slugify_truncates_without_trailing_dash() {
+    let name = format!("{} tail", "a".repeat(MAX_SLUG_LENGTH - 1));
+    let slug = slugify(&name);
+
+    assert!(slug.len() <= MAX_SLUG_LENGTH);
+    assert!(!slug.ends_with('-'));
+}
+
+#[test]
+fn accepts_dash_separated_groups() {
+    for slug in [
+        "a",
+        "acme",
+        "acme-corp",
+        "team-2",
+        "0-9",
+        &"a".repeat(MAX_SLUG_LENGTH),
+    ] {
+        assert!(validate_slug(slug).is_ok(), "{} should be accepted", slug);
+    }
+}
+
+#[test]
+fn rejects_edge_characters() {
+    let too_long = "a".repeat(MAX_SLUG_LENGTH + 1);
+    for slug in [
+        "",
+        "-acme",
+        "acme-",
+        "acme--corp",
+        "Acme",
+        "acme_corp",
+        "acme.corp",
+        "acme corp",
+        "acmé",
+        too_long.as_str(),
+    ] {
+        assert!(
+            validate_slug(slug).is_err(),
+            "{:?} should be rejected",
+            slug
+        );
+    }
+}
+
+#[test]
+fn rejects_reserved_names() {
+    for slug in ["admin", "api", "new", "teams", "repos", "restore"] {
+        let err = validate_slug(slug).unwrap_err();
+        assert!(err.to_string().contains("reserved"), "{}: {}", slug, err);
+    }
+}
+
+#[test]
+fn rejects_uuids() {
+    let err = validate_slug("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap_err();
+
+    assert!(err.to_string().contains("UUID"));
+}
+
+#[test]
+fn unique_slug_appends_a_counter_until_free() {
+    let taken = ["acme", "acme-2"];
+    let slug = unique_slug("Acme", "organization", |candidate| {
+        Ok(taken.contains(&candidate))
+    })
+    .unwrap();
+
+    assert_eq!(slug, "acme-3");
+}
+
+#[test]
+fn unique_slug_avoids_reserved_and_empty_names() {
+    let slug = unique_slug("Admin", "organization", |_| Ok(false)).unwrap();
+    assert_eq!(slug, "admin-2");
+
+    let slug = unique_slug("???", "team", |_| Ok(false)).unwrap();
+    assert_eq!(slug, "team");
+}
@@ -0,0 +1,218 @@
+use actix_identity::IdentityMiddleware;
+use actix_poc_scylla::config::TracingConfig;
+use actix_poc_scylla::db::describe_query;
+use actix_poc_scylla::utils::request_id::request_id;
+use actix_poc_scylla::utils::telemetry;
+use actix_session::storage::CookieSessionStore;
+use actix_session::SessionMiddleware;
+use actix_web::cookie::Key;
+use actix_web::middleware::from_fn;
+use actix_web::test::{call_service, init_service, TestRequest};
+use actix_web::{web, App, HttpResponse};
+use opentelemetry::trace::{SpanId, SpanKind, TraceId};
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+use serde_json::Value;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use tracing::instrument;
+use tracing_subscriber::layer::SubscriberExt;
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const PARENT_ID: &str = "00f067aa0ba902b7";
+
+fn config(sample_ratio: f64) -> TracingConfig {
+    TracingConfig {
+        enabled: true,
+        endpoint: "http://localhost:4318/v1/traces".to_string(),
+        service_name: "scylla-test".to_string(),
+        sample_ratio,
+    }
+}
+
+async fn get_thing() -> HttpResponse {
+    tracing::info_span!("thing.load").in_scope(|| HttpResponse::Ok().finish())
+}
+
+#[instrument(name = "thing::handler::update", skip_all)]
+async fn update_thing() -> HttpResponse {
+    tracing::info!("Thing updated");
+    HttpResponse::Ok().finish()
+}
+
+// Collects the JSON log lines
+#[derive(Clone, Default)]
+struct Logs(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Logs {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Sends one request through the request id middleware and returns the
+// exported spans
+async fn spans(sample_ratio: f64, traceparent: Option<String>) -> Vec<SpanData> {
+    let exporter = InMemorySpanExporter::default();
+    let provider: SdkTracerProvider =
+        telemetry::tracer_provider(&config(sample_ratio), exporter.clone());
+    let subscriber = tracing_subscriber::registry().with(telemetry::layer(&provider));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    // Laid out like the server, the user is read from the session
+    let app = init_service(
+        App::new()
+            .wrap(from_fn(request_id))
+            .wrap(IdentityMiddleware::default())
+            .wrap(SessionMiddleware::new(
+                CookieSessionStore::default(),
+                Key::generate(),
+            ))
+            .route("/things/{id}", web::get().to(get_thing)),
+    )
+    .await;
+    let mut req = TestRequest::get().uri("/things/42");
+    if let Some(traceparent) = traceparent {
+        req = req.insert_header(("traceparent", traceparent));
+    }
+    let res = call_service(&app, req.to_request()).await;
+    assert!(res.status().is_success());
+    assert!(res.headers().contains_key("x-request-id"));
+
+    provider.force_flush().unwrap();
+    exporter.get_finished_spans().unwrap()
+}
+
+fn find<'a>(spans: &'a [SpanData], name: &str) -> &'a SpanData {
+    spans
+        .iter()
+        .find(|span| span.name == name)
+        .unwrap_or_else(|| panic!("no span named {}", name))
+}
+
+#[actix_web::test]
+async fn continues_incoming_trace() {
+    let spans = spans(1.0, Some(format!("00-{}-{}-01", TRACE_ID, PARENT_ID))).await;
+
+    let request = find(&spans, "GET /things/{id}");
+    assert_eq!(request.span_kind, SpanKind::Server);
+    assert_eq!(
+        request.span_context.trace_id(),
+        TraceId::from_hex(TRACE_ID).unwrap()
+    );
+    assert_eq!(request.parent_span_id, SpanId::from_hex(PARENT_ID).unwrap());
+    assert!(request
+        .attributes
+        .iter()
+        .any(|attribute| attribute.key.as_str() == "request_id"));
+
+    let child = find(&spans, "thing.load");
+    assert_eq!(
+        child.span_context.trace_id(),
+        request.span_context.trace_id()
+    );
+    assert_eq!(child.parent_span_id, request.span_context.span_id());
+}
+
+#[actix_web::test]
+async fn starts_a_trace_without_traceparent() {
+    let spans = spans(1.0, None).await;
+
+    let request = find(&spans, "GET /things/{id}");
+    assert_ne!(
+        request.span_context.trace_id(),
+        TraceId::from_hex(TRACE_ID).unwrap()
+    );
+    assert_eq!(request.parent_span_id, SpanId::INVALID);
+}
+
+#[actix_web::test]
+async fn sampling_follows_the_caller() {
+    assert!(spans(0.0, None).await.is_empty());
+
+    let sampled = spans(0.0, Some(format!("00-{}-{}-01", TRACE_ID, PARENT_ID))).await;
+    assert!(!sampled.is_empty());
+
+    let unsampled = spans(1.0, Some(format!("00-{}-{}-00", TRACE_ID, PARENT_ID))).await;
+    assert!(unsampled.is_empty());
+}
+
+#[actix_web::test]
+async fn events_in_handlers_log_the_request_id() {
+    let logs = Logs::default();
+    let writer = logs.clone();
+    let subscriber =
+        tracing_subscriber::registry().with(telemetry::json_layer(move || writer.clone()));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let app = init_service(
+        App::new()
+            .wrap(from_fn(request_id))
+            .wrap(IdentityMiddleware::default())
+            .wrap(SessionMiddleware::new(
+                CookieSessionStore::default(),
+                Key::generate(),
+            ))
+            .route("/things/{id}", web::put().to(update_thing)),
+    )
+    .await;
+    let req = TestRequest::put()
+        .uri("/things/42")
+        .insert_header(("x-request-id", "req-123"))
+        .to_request();
+    call_service(&app, req).await;
+
+    let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    let event = output
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .find(|event| event["fields"]["message"] == "Thing updated")
+        .unwrap_or_else(|| panic!("event not logged:\n{}", output));
+
+    assert_eq!(event["span"]["name"], "thing::handler::update");
+    assert_eq!(event["spans"][0]["name"], "request");
+    assert_eq!(event["spans"][0]["request_id"], "req-123");
+    assert_eq!(event["spans"][0]["route"], "/things/{id}");
+}
+
+#[test]
+fn describes_diesel_queries() {
+    let cases = [
+        (
+            r#"SELECT "users"."id", "users"."email" FROM "users" WHERE "users"."email" = $1"#,
+            "SELECT",
+            Some("users"),
+        ),
+        (
+            r#"INSERT INTO "audit_events" ("id", "action") VALUES ($1, $2)"#,
+            "INSERT",
+            Some("audit_events"),
+        ),
+        (
+            r#"UPDATE "outbox_events" SET "next_attempt_at" = $1 WHERE "id" = ANY($2)"#,
+            "UPDATE",
+            Some("outbox_events"),
+        ),
+        (
+            r#"DELETE FROM "verification_tokens" WHERE "expires_at" < $1"#,
+            "DELETE",
+            Some("verification_tokens"),
+        ),
+        ("SELECT 1", "SELECT", None),
+        ("SELECT COUNT(*) FROM (SELECT 1) AS t", "SELECT", None),
+        ("BEGIN", "TRANSACTION", None),
+        ("SET statement_timeout = 0", "OTHER", None),
+    ];
+
+    for (sql, kind, table) in cases {
+        assert_eq!(
+            describe_query(sql),
+            (kind, table.map(str::to_string)),
+            "{}",
+            sql
+        );
+    }
+}
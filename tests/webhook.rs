@@ -0,0 +1,121 @@
+use actix_poc_scylla::modules::webhook::service::WebhookService;
+use actix_poc_scylla::utils::network::{self, PublicResolver};
+use chrono::{DateTime, Duration, Utc};
+use reqwest::dns::{Name, Resolve};
+use std::net::IpAddr;
+use std::str::FromStr;
+
+const SECRET: &[u8] = b"whsec_test";
+const PAYLOAD: &str = r#"{"id":1}"#;
+const TIMESTAMP: i64 = 1_700_000_000;
+
+fn last_attempt() -> DateTime<Utc> {
+    DateTime::from_timestamp(TIMESTAMP, 0).unwrap()
+}
+
+#[test]
+fn signs_the_timestamp_and_payload() {
+    let signature = WebhookService::sign(SECRET, TIMESTAMP, PAYLOAD);
+
+    // HMAC-SHA256 of `1700000000.{"id":1}`, as lowercase hex
+    assert_eq!(
+        signature,
+        "2f441ba4b3b2d50d28a9ab9d9fd8880376ecd1eb5d0435401553f5d8d0a5dcf8"
+    );
+}
+
+#[test]
+fn signature_depends_on_every_input() {
+    let signature = WebhookService::sign(SECRET, TIMESTAMP, PAYLOAD);
+
+    assert_ne!(
+        WebhookService::sign(b"other", TIMESTAMP, PAYLOAD),
+        signature
+    );
+    assert_ne!(
+        WebhookService::sign(SECRET, TIMESTAMP + 1, PAYLOAD),
+        signature
+    );
+    assert_ne!(
+        WebhookService::sign(SECRET, TIMESTAMP, r#"{"id":2}"#),
+        signature
+    );
+}
+
+#[test]
+fn doubles_the_delay_after_each_attempt() {
+    let delays = (1..=5)
+        .map(|attempt| WebhookService::retry_at(last_attempt(), 30, attempt) - last_attempt())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        delays,
+        [30, 60, 120, 240, 480].map(Duration::seconds).to_vec()
+    );
+}
+
+#[test]
+fn caps_the_delay_at_six_hours() {
+    let cap = Duration::hours(6);
+
+    assert_eq!(
+        WebhookService::retry_at(last_attempt(), 30, 12) - last_attempt(),
+        cap
+    );
+    assert_eq!(
+        WebhookService::retry_at(last_attempt(), 30, 100) - last_attempt(),
+        cap
+    );
+    assert_eq!(
+        WebhookService::retry_at(last_attempt(), i64::MAX, 1) - last_attempt(),
+        cap
+    );
+}
+
+#[test]
+fn refuses_local_addresses() {
+    for ip in [
+        "0.0.0.0",
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "255.255.255.255",
+        "::",
+        "::1",
+        "fe80::1",
+        "fd00::1",
+        "::ffff:127.0.0.1",
+        "::ffff:169.254.169.254",
+    ] {
+        assert!(!network::is_public(IpAddr::from_str(ip).unwrap()), "{}", ip);
+    }
+}
+
+#[test]
+fn accepts_public_addresses() {
+    for ip in ["1.1.1.1", "93.184.216.34", "2606:4700:4700::1111"] {
+        assert!(network::is_public(IpAddr::from_str(ip).unwrap()), "{}", ip);
+    }
+}
+
+#[test]
+fn checks_ip_literals_in_urls() {
+    assert!(network::ensure_public_url("http://127.0.0.1:8080/hook").is_err());
+    assert!(network::ensure_public_url("http://[::1]/hook").is_err());
+    assert!(network::ensure_public_url("http://169.254.169.254/latest/meta-data").is_err());
+    assert!(network::ensure_public_url("https://1.1.1.1/hook").is_ok());
+    // Left to the resolver
+    assert!(network::ensure_public_url("https://hooks.example.com/scylla").is_ok());
+}
+
+#[actix_web::test]
+async fn resolver_refuses_hosts_resolving_to_loopback() {
+    let name = Name::from_str("localhost").unwrap();
+
+    let resolved = PublicResolver.resolve(name).await;
+
+    assert!(resolved.is_err());
+}